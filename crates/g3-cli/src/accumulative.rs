@@ -5,10 +5,12 @@ use crossterm::style::{Color, ResetColor, SetForegroundColor};
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tracing::error;
 
 use g3_core::project::Project;
-use g3_core::Agent;
+use g3_core::{Agent, CostLedger};
 
 use crate::autonomous::run_autonomous;
 use crate::cli_args::Cli;
@@ -59,6 +61,10 @@ pub async fn run_accumulative_mode(
     let mut accumulated_requirements = Vec::new();
     let mut turn_number = 0;
 
+    // Spend and wall time accumulate across runs, so budgets cover the whole session
+    let session_ledger = Arc::new(Mutex::new(CostLedger::new()));
+    let session_started = Instant::now();
+
     loop {
         output.print(&format!("\n{}", "=".repeat(60)));
         if accumulated_requirements.is_empty() {
@@ -153,13 +159,15 @@ pub async fn run_accumulative_mode(
 
                 // Create agent for this autonomous run
                 let ui_writer = ConsoleUiWriter::new();
-                let agent = Agent::new_autonomous_with_readme_and_quiet(
+                let mut agent = Agent::new_autonomous_with_readme_and_quiet(
                     config.clone(),
                     ui_writer,
                     combined_content.clone(),
                     cli.quiet,
                 )
                 .await?;
+                agent.set_cost_ledger(session_ledger.clone());
+                agent.set_budget_started(session_started);
 
                 // Run autonomous mode with the accumulated requirements
                 let autonomous_result = tokio::select! {
@@ -179,8 +187,12 @@ pub async fn run_accumulative_mode(
                 };
 
                 match autonomous_result {
-                    Ok(Some(_returned_agent)) => {
+                    Ok(Some(mut returned_agent)) => {
                         output.print("");
+                        if let Err(e) = returned_agent.check_budget() {
+                            output.print(&format!("🛑 {}. No further runs will be started.", e));
+                            break;
+                        }
                         output.print("✅ Autonomous run completed");
                     }
                     Ok(None) => {
@@ -300,6 +312,7 @@ async fn handle_command(
                 chat_combined_content,
                 workspace_dir,
                 cli.new_session,
+                cli.print_diff,
             )
             .await?;
 
@@ -12,8 +12,10 @@ use crate::language_prompts::{get_language_prompts_for_workspace, get_agent_lang
 use crate::simple_output::SimpleOutput;
 use crate::embedded_agents::load_agent_prompt;
 use crate::ui_writer_impl::ConsoleUiWriter;
+use crate::utils::print_session_diff;
 
 /// Run agent mode - loads a specialized agent prompt and executes a single task.
+#[allow(clippy::too_many_arguments)]
 pub async fn run_agent_mode(
     agent_name: &str,
     workspace: Option<PathBuf>,
@@ -23,6 +25,7 @@ pub async fn run_agent_mode(
     task: Option<String>,
     chrome_headless: bool,
     safari: bool,
+    print_diff: bool,
 ) -> Result<()> {
     use g3_core::find_incomplete_agent_session;
     use g3_core::get_agent_system_prompt;
@@ -249,6 +252,11 @@ pub async fn run_agent_mode(
 
     // Save session continuation for resume capability
     agent.save_session_continuation(None);
+    agent.end_session().await;
+
+    if print_diff {
+        print_session_diff(&agent, &output);
+    }
 
     // Don't print completion message for scout agent - it needs the last line
     // to be the report file path for the research tool to read
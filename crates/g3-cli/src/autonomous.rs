@@ -8,7 +8,7 @@ use tracing::debug;
 
 use g3_core::error_handling::{classify_error, ErrorType, RecoverableError};
 use g3_core::project::Project;
-use g3_core::{Agent, DiscoveryOptions};
+use g3_core::{is_budget_exceeded, Agent, AgentRole, DiscoveryOptions};
 
 use crate::coach_feedback;
 use crate::metrics::{format_elapsed_time, generate_turn_histogram, TurnMetrics};
@@ -84,6 +84,7 @@ pub async fn run_autonomous(
     let mut turn = 1;
     let mut coach_feedback_text = String::new();
     let mut implementation_approved = false;
+    let mut budget_exhausted = false;
 
     loop {
         let turn_start_time = Instant::now();
@@ -143,6 +144,11 @@ pub async fn run_autonomous(
         let player_failed = match player_result {
             PlayerTurnResult::Success => false,
             PlayerTurnResult::Failed => true,
+            PlayerTurnResult::BudgetExceeded => {
+                output.print("\n=== SESSION STOPPED - BUDGET EXHAUSTED ===");
+                budget_exhausted = true;
+                break;
+            }
             PlayerTurnResult::Panic(e) => return Err(e),
         };
 
@@ -212,6 +218,11 @@ pub async fn run_autonomous(
                 ));
                 coach_feedback_text = "The implementation needs review. Please ensure all requirements are met and the code compiles without errors.".to_string();
             }
+            CoachTurnResult::BudgetExceeded => {
+                output.print("\n=== SESSION STOPPED - BUDGET EXHAUSTED ===");
+                budget_exhausted = true;
+                break;
+            }
             CoachTurnResult::Panic(e) => return Err(e),
         }
 
@@ -242,7 +253,13 @@ pub async fn run_autonomous(
         start_time,
         turn,
         max_turns,
-        implementation_approved,
+        if implementation_approved {
+            RunOutcome::Approved
+        } else if budget_exhausted {
+            RunOutcome::BudgetExhausted
+        } else {
+            RunOutcome::Unfinished
+        },
     );
 
     if implementation_approved {
@@ -250,6 +267,11 @@ pub async fn run_autonomous(
             "\n🎉 Autonomous mode completed successfully (total loop time: {})",
             format_elapsed_time(loop_start.elapsed())
         ));
+    } else if budget_exhausted {
+        output.print(&format!(
+            "\n🛑 Autonomous mode stopped (budget exhausted) (total loop time: {})",
+            format_elapsed_time(loop_start.elapsed())
+        ));
     } else {
         output.print(&format!(
             "\n🔄 Autonomous mode terminated (max iterations) (total loop time: {})",
@@ -268,13 +290,22 @@ pub async fn run_autonomous(
 enum PlayerTurnResult {
     Success,
     Failed,
+    BudgetExceeded,
     Panic(anyhow::Error),
 }
 
+/// How an autonomous run ended, for the final report
+enum RunOutcome {
+    Approved,
+    BudgetExhausted,
+    Unfinished,
+}
+
 enum CoachTurnResult {
     Approved,
     Feedback(String),
     Failed,
+    BudgetExceeded,
     Panic(anyhow::Error),
 }
 
@@ -425,6 +456,11 @@ async fn execute_player_turn(
                 return PlayerTurnResult::Success;
             }
             Err(e) => {
+                if is_budget_exceeded(&e) {
+                    output.print(&format!("🛑 {}", e));
+                    return PlayerTurnResult::BudgetExceeded;
+                }
+
                 let error_type = classify_error(&e);
 
                 if matches!(
@@ -506,6 +542,12 @@ async fn execute_coach_turn(
             Err(e) => return CoachTurnResult::Panic(e),
         };
 
+    // Book coach spend in the player's ledger so the final report covers the whole run,
+    // and hold the coach to the run's remaining budget
+    coach_agent.set_agent_role(AgentRole::Coach);
+    coach_agent.set_cost_ledger(player_agent.cost_ledger_handle());
+    coach_agent.set_budget_started(player_agent.budget_started());
+
     coach_agent.print_provider_banner("Coach");
 
     if let Err(e) = project.enter_workspace() {
@@ -575,6 +617,11 @@ async fn execute_coach_turn(
                 return CoachTurnResult::Feedback(feedback_text);
             }
             Err(e) => {
+                if is_budget_exceeded(&e) {
+                    output.print(&format!("🛑 {}", e));
+                    return CoachTurnResult::BudgetExceeded;
+                }
+
                 let error_type = classify_error(&e);
 
                 if matches!(
@@ -651,7 +698,7 @@ fn print_no_requirements_error(
     output.print("      g3 --autonomous --requirements \"Your requirements here\"");
     output.print("");
 
-    print_final_report(output, agent, turn_metrics, start_time, 0, max_turns, false);
+    print_final_report(output, agent, turn_metrics, start_time, 0, max_turns, RunOutcome::Unfinished);
 }
 
 fn print_cannot_read_requirements_error(
@@ -662,7 +709,7 @@ fn print_cannot_read_requirements_error(
     max_turns: usize,
 ) {
     output.print("❌ Error: Could not read requirements (neither --requirements flag nor requirements.md file provided)");
-    print_final_report(output, agent, turn_metrics, start_time, 0, max_turns, false);
+    print_final_report(output, agent, turn_metrics, start_time, 0, max_turns, RunOutcome::Unfinished);
 }
 
 fn print_panic_report(
@@ -690,6 +737,7 @@ fn print_panic_report(
     output.print(&format!("   • Total Available: {}", context_window.total_tokens));
     output.print(&format!("   • Cumulative Tokens: {}", context_window.cumulative_tokens));
     output.print(&format!("   • Usage Percentage: {:.1}%", context_window.percentage_used()));
+    print_spend(output, agent);
     output.print(&generate_turn_histogram(turn_metrics));
     output.print(&"=".repeat(60));
 }
@@ -701,7 +749,7 @@ fn print_final_report(
     start_time: Instant,
     turn: usize,
     max_turns: usize,
-    implementation_approved: bool,
+    outcome: RunOutcome,
 ) {
     let elapsed = start_time.elapsed();
     let context_window = agent.get_context_window();
@@ -714,12 +762,11 @@ fn print_final_report(
     output.print(&format!("🔄 Turns Taken: {}/{}", turn, max_turns));
     output.print(&format!(
         "📝 Final Status: {}",
-        if implementation_approved {
-            "✅ APPROVED"
-        } else if turn >= max_turns {
-            "⏰ MAX TURNS REACHED"
-        } else {
-            "⚠️ INCOMPLETE"
+        match outcome {
+            RunOutcome::Approved => "✅ APPROVED",
+            RunOutcome::BudgetExhausted => "🛑 BUDGET EXHAUSTED",
+            RunOutcome::Unfinished if turn >= max_turns => "⏰ MAX TURNS REACHED",
+            RunOutcome::Unfinished => "⚠️ INCOMPLETE",
         }
     ));
 
@@ -728,6 +775,12 @@ fn print_final_report(
     output.print(&format!("   • Total Available: {}", context_window.total_tokens));
     output.print(&format!("   • Cumulative Tokens: {}", context_window.cumulative_tokens));
     output.print(&format!("   • Usage Percentage: {:.1}%", context_window.percentage_used()));
+    print_spend(output, agent);
     output.print(&generate_turn_histogram(turn_metrics));
     output.print(&"=".repeat(60));
 }
+
+fn print_spend(output: &SimpleOutput, agent: &Agent<ConsoleUiWriter>) {
+    output.print("\n💰 Spend:");
+    output.print(agent.get_cost_ledger().format_breakdown("   ").trim_end());
+}
@@ -1,6 +1,6 @@
 //! CLI argument parsing for G3.
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser, Clone)]
@@ -8,6 +8,9 @@ use std::path::PathBuf;
 #[command(about = "A modular, composable AI coding agent")]
 #[command(version)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Enable verbose logging
     #[arg(short, long)]
     pub verbose: bool,
@@ -114,4 +117,157 @@ pub struct Cli {
     /// Enable aggressive context dehydration (save context to disk on compaction)
     #[arg(long)]
     pub acd: bool,
+
+    /// Stop the run once provider spend reaches this many USD
+    #[arg(long, value_name = "USD")]
+    pub max_cost: Option<f64>,
+
+    /// Stop the run once this many tokens have been used across all requests
+    #[arg(long, value_name = "TOKENS")]
+    pub max_tokens_total: Option<u64>,
+
+    /// Stop the run after this much wall-clock time (seconds, or with an s/m/h suffix, e.g. 90m, 8h)
+    #[arg(long, value_name = "DURATION", value_parser = parse_wall_time)]
+    pub max_wall_time: Option<u64>,
+
+    /// Run shell and background_process commands in a Linux namespace sandbox
+    /// (read-only filesystem except the workspace)
+    #[arg(long)]
+    pub sandbox: bool,
+
+    /// Deny network access to sandboxed commands (implies --sandbox)
+    #[arg(long)]
+    pub sandbox_no_network: bool,
+
+    /// Print a diff of every file the agent changed when the run ends
+    #[arg(long)]
+    pub print_diff: bool,
+
+    /// Output format for single-shot runs: `text` for people, `stream-json` for
+    /// newline-delimited JSON events
+    #[arg(long, value_enum, default_value = "text")]
+    pub output_format: OutputFormat,
+}
+
+/// How a single-shot run reports its progress
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    StreamJson,
+}
+
+/// Modes that replace the agent run
+#[derive(Subcommand, Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Serve g3's built-in tools to other agents and editors over MCP (stdio)
+    McpServe,
+    /// Host agent sessions behind a local HTTP API
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Port to listen on
+        #[arg(long, default_value_t = 8787)]
+        port: u16,
+        /// Token clients must send; generated and printed when not given
+        #[arg(long, env = "G3_SERVE_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
+}
+
+/// Parse a wall-time budget into seconds. Accepts plain seconds or a number with
+/// an `s`, `m` or `h` suffix.
+fn parse_wall_time(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((i, 's')) => (&value[..i], 1),
+        Some((i, 'm')) => (&value[..i], 60),
+        Some((i, 'h')) => (&value[..i], 3600),
+        _ => (value, 1),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .map(|n| n * multiplier)
+        .map_err(|_| format!("invalid duration '{}' (expected e.g. 3600, 90m or 8h)", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wall_time() {
+        assert_eq!(parse_wall_time("3600"), Ok(3600));
+        assert_eq!(parse_wall_time("45s"), Ok(45));
+        assert_eq!(parse_wall_time("90m"), Ok(5400));
+        assert_eq!(parse_wall_time("8h"), Ok(28800));
+        assert!(parse_wall_time("").is_err());
+        assert!(parse_wall_time("2d").is_err());
+        assert!(parse_wall_time("h").is_err());
+    }
+
+    #[test]
+    fn test_budget_flags() {
+        let cli = Cli::parse_from([
+            "g3",
+            "--autonomous",
+            "--max-cost",
+            "12.5",
+            "--max-tokens-total",
+            "2000000",
+            "--max-wall-time",
+            "8h",
+        ]);
+        assert_eq!(cli.max_cost, Some(12.5));
+        assert_eq!(cli.max_tokens_total, Some(2_000_000));
+        assert_eq!(cli.max_wall_time, Some(28800));
+    }
+
+    #[test]
+    fn test_sandbox_flags() {
+        let cli = Cli::parse_from(["g3", "--autonomous", "--sandbox-no-network"]);
+        assert!(cli.sandbox_no_network);
+        assert!(!cli.sandbox);
+    }
+
+    #[test]
+    fn test_output_format_flag() {
+        let cli = Cli::parse_from(["g3", "fix the build"]);
+        assert_eq!(cli.output_format, OutputFormat::Text);
+        let cli = Cli::parse_from(["g3", "--output-format", "stream-json", "fix the build"]);
+        assert_eq!(cli.output_format, OutputFormat::StreamJson);
+        assert!(Cli::try_parse_from(["g3", "--output-format", "xml", "fix the build"]).is_err());
+    }
+
+    #[test]
+    fn test_subcommands() {
+        let cli = Cli::parse_from(["g3", "--config", "g3.toml", "mcp-serve"]);
+        assert_eq!(cli.command, Some(Command::McpServe));
+        assert_eq!(cli.config.as_deref(), Some("g3.toml"));
+
+        let cli = Cli::parse_from(["g3", "serve", "--port", "9000"]);
+        assert_eq!(
+            cli.command,
+            Some(Command::Serve {
+                host: "127.0.0.1".to_string(),
+                port: 9000,
+                token: None
+            })
+        );
+        let cli = Cli::parse_from(["g3", "serve", "--token", "s3cret"]);
+        assert!(matches!(cli.command, Some(Command::Serve { token: Some(token), .. }) if token == "s3cret"));
+
+        // Anything else is still a task
+        let cli = Cli::parse_from(["g3", "serve the docs"]);
+        assert_eq!(cli.command, None);
+        assert_eq!(cli.task.as_deref(), Some("serve the docs"));
+    }
+
+    #[test]
+    fn test_print_diff_flag() {
+        let cli = Cli::parse_from(["g3", "--print-diff", "fix the build"]);
+        assert!(cli.print_diff);
+        assert_eq!(cli.task.as_deref(), Some("fix the build"));
+    }
 }
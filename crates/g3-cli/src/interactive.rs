@@ -13,7 +13,7 @@ use g3_core::Agent;
 use crate::project_files::extract_readme_heading;
 use crate::simple_output::SimpleOutput;
 use crate::task_execution::execute_task_with_retry;
-use crate::utils::display_context_progress;
+use crate::utils::{display_context_progress, print_session_diff};
 
 /// Run interactive mode with console output.
 pub async fn run_interactive<W: UiWriter>(
@@ -23,6 +23,7 @@ pub async fn run_interactive<W: UiWriter>(
     combined_content: Option<String>,
     workspace_path: &Path,
     new_session: bool,
+    print_diff: bool,
 ) -> Result<()> {
     let output = SimpleOutput::new();
 
@@ -278,6 +279,11 @@ pub async fn run_interactive<W: UiWriter>(
 
     // Save session continuation for resume capability
     agent.save_session_continuation(None);
+    agent.end_session().await;
+
+    if print_diff {
+        print_session_diff(&agent, &output);
+    }
 
     output.print("👋 Goodbye!");
     Ok(())
@@ -301,6 +307,9 @@ async fn handle_command<W: UiWriter>(
             output.print("  /fragments - List dehydrated context fragments (ACD)");
             output.print("  /rehydrate - Restore a dehydrated fragment by ID");
             output.print("  /resume    - List and switch to a previous session");
+            output.print("  /checkpoints - List turns whose file edits can be undone");
+            output.print("  /undo [n]  - Undo the last turn's file edits (or turn n and everything after it)");
+            output.print("  /diff      - Show a diff of every file changed this session");
             output.print("  /dump      - Dump entire context window to file for debugging");
             output.print("  /readme    - Reload README.md and AGENTS.md from disk");
             output.print("  /stats     - Show detailed context and performance statistics");
@@ -390,6 +399,75 @@ async fn handle_command<W: UiWriter>(
             }
             Ok(true)
         }
+        "/checkpoints" => {
+            match agent.checkpoints() {
+                Ok(checkpoints) if checkpoints.is_empty() => {
+                    output.print("No checkpoints - no files have been edited in this session.");
+                }
+                Ok(checkpoints) => {
+                    output.print(&format!("💾 {} checkpoint(s):", checkpoints.len()));
+                    for checkpoint in &checkpoints {
+                        let time_str = g3_core::format_session_time(&checkpoint.created_at);
+                        output.print(&format!(
+                            "  Turn {} [{}] {}",
+                            checkpoint.turn,
+                            time_str,
+                            g3_core::utils::truncate_to_word_boundary(
+                                checkpoint.prompt.lines().next().unwrap_or_default(),
+                                60
+                            )
+                        ));
+                        for file in &checkpoint.files {
+                            let marker = if file.saved_as.is_some() { "~" } else { "+" };
+                            output.print(&format!("      {} {}", marker, file.path.display()));
+                        }
+                    }
+                    output.print("");
+                    output.print("Use /undo to roll back the last turn, or /undo <turn> to roll back to before that turn.");
+                }
+                Err(e) => output.print(&format!("❌ Error listing checkpoints: {}", e)),
+            }
+            Ok(true)
+        }
+        cmd if cmd == "/undo" || cmd.starts_with("/undo ") => {
+            let arg = cmd["/undo".len()..].trim();
+            let result = if arg.is_empty() {
+                agent.undo_last_turn().map(|checkpoint| checkpoint.into_iter().collect())
+            } else {
+                match arg.parse::<u32>() {
+                    Ok(turn) => agent.undo_to_turn(turn),
+                    Err(_) => {
+                        output.print("Usage: /undo [turn]");
+                        output.print("Use /checkpoints to list turns with file edits.");
+                        return Ok(true);
+                    }
+                }
+            };
+            match result {
+                Ok(restored) if restored.is_empty() => {
+                    output.print("Nothing to undo.");
+                }
+                Ok(restored) => {
+                    for checkpoint in &restored {
+                        output.print(&format!(
+                            "⏪ Undid turn {} ({} file(s)):",
+                            checkpoint.turn,
+                            checkpoint.files.len()
+                        ));
+                        for file in &checkpoint.files {
+                            let action = if file.saved_as.is_some() { "restored" } else { "removed" };
+                            output.print(&format!("      {} {}", action, file.path.display()));
+                        }
+                    }
+                }
+                Err(e) => output.print(&format!("❌ Error undoing edits: {}", e)),
+            }
+            Ok(true)
+        }
+        "/diff" => {
+            print_session_diff(agent, output);
+            Ok(true)
+        }
         "/dump" => {
             // Dump entire context window to a file for debugging
             let dump_dir = std::path::Path::new("tmp");
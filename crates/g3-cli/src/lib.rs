@@ -13,7 +13,10 @@ mod autonomous;
 mod cli_args;
 mod coach_feedback;
 mod interactive;
+mod mcp_serve;
+mod serve;
 mod simple_output;
+mod stream_json;
 mod task_execution;
 mod ui_writer_impl;
 mod utils;
@@ -26,23 +29,44 @@ use g3_config::Config;
 use g3_core::project::Project;
 use g3_core::Agent;
 
-pub use cli_args::Cli;
+pub use cli_args::{Cli, Command, OutputFormat};
 use clap::Parser;
 
 use accumulative::run_accumulative_mode;
 use agent_mode::run_agent_mode;
 use autonomous::run_autonomous;
 use interactive::run_interactive;
+use mcp_serve::run_mcp_serve;
+use serve::run_serve;
 use project_files::{combine_project_content, read_agents_config, read_project_memory, read_project_readme};
 use simple_output::SimpleOutput;
+use stream_json::run_stream_json_mode;
 use ui_writer_impl::ConsoleUiWriter;
-use utils::{initialize_logging, load_config_with_cli_overrides, setup_workspace_directory};
+use utils::{initialize_logging, load_config_with_cli_overrides, print_session_diff, setup_workspace_directory};
 
 pub async fn run() -> Result<()> {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
 
     // Initialize logging FIRST (before any mode checks)
-    initialize_logging(cli.verbose);
+    let mcp_serve = cli.command == Some(Command::McpServe);
+    let stream_json = cli.output_format == OutputFormat::StreamJson;
+    initialize_logging(cli.verbose, mcp_serve || stream_json);
+
+    if mcp_serve {
+        return run_mcp_serve(&cli).await;
+    }
+
+    if stream_json {
+        if cli.task.is_none() || cli.autonomous || cli.auto || cli.agent.is_some() || cli.planning {
+            anyhow::bail!(
+                "--output-format stream-json runs a single task: pass the task as an argument, without --autonomous, --auto, --agent or --planning"
+            );
+        }
+        // --webdriver and --chrome-headless are on by default, and the Chrome diagnostics
+        // print to stdout, so WebDriver tools follow the config file in this mode
+        cli.webdriver = false;
+        cli.chrome_headless = false;
+    }
 
     if cli.codebase_fast_start.is_some() {
         print!("codebase_fast_start is temporarily disabled.");
@@ -88,6 +112,7 @@ pub async fn run() -> Result<()> {
             cli.task.clone(),
             cli.chrome_headless,
             cli.safari,
+            cli.print_diff,
         )
         .await;
     }
@@ -114,6 +139,14 @@ pub async fn run() -> Result<()> {
     // Combine AGENTS.md, README, and memory content
     let combined_content = combine_project_content(agents_content, readme_content, memory_content, language_content, &workspace_dir);
 
+    if let Some(Command::Serve { host, port, token }) = &cli.command {
+        return run_serve(host, *port, token.clone(), config, combined_content, cli.quiet).await;
+    }
+
+    if stream_json {
+        return run_stream_json_mode(cli, config, combined_content).await;
+    }
+
     run_console_mode(cli, config, project, combined_content, workspace_dir).await
 }
 
@@ -182,7 +215,7 @@ async fn run_console_mode(
     }
 
     if cli.autonomous {
-        let _agent = run_autonomous(
+        let agent = run_autonomous(
             agent,
             project,
             cli.show_prompt,
@@ -192,6 +225,10 @@ async fn run_console_mode(
             cli.codebase_fast_start.clone(),
         )
         .await?;
+        agent.end_session().await;
+        if cli.print_diff {
+            print_session_diff(&agent, &SimpleOutput::new());
+        }
         Ok(())
     } else if let Some(task) = cli.task {
         // Single-shot mode
@@ -208,6 +245,10 @@ async fn run_console_mode(
             debug!("Auto-memory reminder failed: {}", e);
         }
         agent.save_session_continuation(Some(result.response.clone()));
+        agent.end_session().await;
+        if cli.print_diff {
+            print_session_diff(&agent, &output);
+        }
         Ok(())
     } else {
         run_interactive(
@@ -217,6 +258,7 @@ async fn run_console_mode(
             combined_content,
             project.workspace(),
             cli.new_session,
+            cli.print_diff,
         )
         .await
     }
@@ -0,0 +1,31 @@
+//! MCP server mode (`g3 mcp-serve`): exposes g3's tools over stdio.
+
+use anyhow::Result;
+use tracing::info;
+
+use g3_core::mcp_server::McpToolServer;
+
+use crate::cli_args::Cli;
+use crate::utils::load_config_with_cli_overrides;
+
+/// Serve the built-in tools over MCP until the client closes stdin.
+/// Nothing but protocol messages may be written to stdout in this mode.
+pub async fn run_mcp_serve(cli: &Cli) -> Result<()> {
+    if let Some(workspace) = &cli.workspace {
+        std::env::set_current_dir(workspace)?;
+    }
+    // --webdriver and --chrome-headless are on by default, and the Chrome diagnostics
+    // print to stdout, so WebDriver tools follow the config file in this mode
+    let mut cli = cli.clone();
+    cli.webdriver = false;
+    cli.chrome_headless = false;
+    let config = load_config_with_cli_overrides(&cli)?;
+
+    let server = McpToolServer::new(config);
+    info!(
+        "Serving {} tools over MCP (session {})",
+        server.tool_definitions().len(),
+        server.session_id()
+    );
+    server.serve_stdio().await
+}
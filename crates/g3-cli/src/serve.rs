@@ -0,0 +1,731 @@
+//! HTTP server mode (`g3 serve`): hosts agent sessions behind a local HTTP API.
+//!
+//! | Method | Path | |
+//! |--------|------|-|
+//! | `GET` | `/sessions` | Hosted sessions, and saved sessions that can be resumed |
+//! | `POST` | `/sessions` | Start a session with `{}`; `{"resume": "<session id>"}` resumes a saved one |
+//! | `POST` | `/sessions/{id}/messages` | Send `{"content": "..."}`; runs in the background |
+//! | `GET` | `/sessions/{id}/events` | Server-sent events, in the `stream-json` format |
+//! | `GET` | `/sessions/{id}/ws` | WebSocket with the same events, taking messages and cancels |
+//! | `POST` | `/sessions/{id}/cancel` | Cancel the running message |
+//! | `DELETE` | `/sessions/{id}` | Save the session and stop hosting it |
+//!
+//! Every request needs the server's token, as `Authorization: Bearer <token>` or, for
+//! clients that can't set headers (`EventSource`, browser WebSockets), `?token=<token>`.
+//! Bodies must be JSON, so a web page can't post to the API without a CORS preflight.
+
+use anyhow::Result;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures_util::stream::{self, Stream};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+use g3_config::Config;
+use g3_core::Agent;
+
+use crate::stream_json::{result_event, JsonUiWriter};
+
+/// Events buffered per session for slow subscribers
+const EVENT_BUFFER: usize = 1024;
+
+type ApiError = (StatusCode, Json<Value>);
+type ApiResult<T> = Result<T, ApiError>;
+
+fn api_error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (status, Json(json!({ "error": message.into() })))
+}
+
+/// One agent hosted by the server
+struct HostedSession {
+    agent: tokio::sync::Mutex<Agent<JsonUiWriter>>,
+    events: broadcast::Sender<Value>,
+    /// Cancels the message being processed, if any
+    running: Mutex<Option<CancellationToken>>,
+    /// g3 session id, once the agent has one
+    session_id: Mutex<Option<String>>,
+    /// Cancelled once the session is no longer hosted, which ends its event streams
+    closed: CancellationToken,
+}
+
+impl HostedSession {
+    fn is_busy(&self) -> bool {
+        self.running.lock().unwrap().is_some()
+    }
+
+    /// Cancel the running message; false if there was none
+    fn cancel_running(&self) -> bool {
+        let running = self.running.lock().unwrap().clone();
+        if let Some(token) = &running {
+            token.cancel();
+        }
+        running.is_some()
+    }
+}
+
+/// Marks a session idle when its message finishes, including when the agent panics
+struct BusyGuard<'a>(&'a HostedSession);
+
+impl Drop for BusyGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut running) = self.0.running.lock() {
+            *running = None;
+        }
+        // Clients wait for `result`, so they still get one
+        if std::thread::panicking() {
+            let _ = self.0.events.send(json!({
+                "type": "result",
+                "success": false,
+                "error": "g3 panicked while working on the message",
+            }));
+        }
+    }
+}
+
+struct ServerState {
+    config: Config,
+    combined_content: Option<String>,
+    quiet: bool,
+    /// Token every request must carry
+    token: String,
+    sessions: Mutex<HashMap<String, Arc<HostedSession>>>,
+    next_id: AtomicU64,
+    /// Cancelled when the server starts shutting down; no sessions start after that
+    shutdown: CancellationToken,
+}
+
+impl ServerState {
+    fn new(config: Config, combined_content: Option<String>, quiet: bool, token: String) -> Self {
+        Self {
+            config,
+            combined_content,
+            quiet,
+            token,
+            sessions: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    fn session(&self, id: &str) -> ApiResult<Arc<HostedSession>> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| api_error(StatusCode::NOT_FOUND, format!("No session {}", id)))
+    }
+}
+
+#[derive(Deserialize)]
+struct CreateSessionRequest {
+    /// Saved session to resume
+    resume: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MessageRequest {
+    content: String,
+}
+
+/// What a WebSocket client can send
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SocketRequest {
+    Message { content: String },
+    Cancel,
+}
+
+/// Serve the HTTP API until interrupted, then save every hosted session. Without a
+/// `token`, one is generated and printed.
+pub async fn run_serve(
+    host: &str,
+    port: u16,
+    token: Option<String>,
+    config: Config,
+    combined_content: Option<String>,
+    quiet: bool,
+) -> Result<()> {
+    let generated = token.is_none();
+    let token = token.unwrap_or_else(|| hex::encode(rand::random::<[u8; 16]>()));
+    let state = Arc::new(ServerState::new(config, combined_content, quiet, token));
+
+    let listener = tokio::net::TcpListener::bind((host, port)).await?;
+    println!("🌐 g3 serving on http://{}", listener.local_addr()?);
+    if generated {
+        println!("🔑 token: {}", state.token);
+    }
+    let shutdown_state = state.clone();
+    axum::serve(listener, router(state.clone()))
+        .with_graceful_shutdown(async move {
+            let _ = tokio::signal::ctrl_c().await;
+            // Open event streams hold the server up until their sessions end
+            shut_down(&shutdown_state).await;
+        })
+        .await?;
+    Ok(())
+}
+
+fn router(state: Arc<ServerState>) -> Router {
+    Router::new()
+        .route("/sessions", get(list_sessions).post(create_session))
+        .route("/sessions/:id", axum::routing::delete(close_session))
+        .route("/sessions/:id/messages", post(post_message))
+        .route("/sessions/:id/events", get(session_events))
+        .route("/sessions/:id/ws", get(session_socket))
+        .route("/sessions/:id/cancel", post(cancel_message))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .with_state(state)
+}
+
+/// Stop accepting sessions, then save and stop hosting every session
+async fn shut_down(state: &ServerState) {
+    let sessions: Vec<Arc<HostedSession>> = {
+        let mut sessions = state.sessions.lock().unwrap();
+        state.shutdown.cancel();
+        sessions.drain().map(|(_, session)| session).collect()
+    };
+    for session in sessions {
+        end_hosted_session(&session).await;
+    }
+}
+
+async fn require_token(
+    State(state): State<Arc<ServerState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let query = request.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+    });
+    match bearer.or(query) {
+        Some(token) if same_token(token, &state.token) => next.run(request).await,
+        _ => api_error(StatusCode::UNAUTHORIZED, "Missing or wrong token").into_response(),
+    }
+}
+
+/// Compare tokens in time that doesn't depend on where they differ
+fn same_token(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn list_sessions(State(state): State<Arc<ServerState>>) -> ApiResult<Json<Value>> {
+    let hosted: Vec<Value> = {
+        let sessions = state.sessions.lock().unwrap();
+        let mut hosted: Vec<Value> = sessions
+            .iter()
+            .map(|(id, session)| {
+                json!({
+                    "id": id,
+                    "session_id": *session.session_id.lock().unwrap(),
+                    "busy": session.is_busy(),
+                })
+            })
+            .collect();
+        hosted.sort_by_key(|session| session["id"].as_str().and_then(|id| id.parse::<u64>().ok()));
+        hosted
+    };
+
+    let saved: Vec<Value> = g3_core::list_sessions_for_directory()
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .iter()
+        .map(|session| {
+            json!({
+                "session_id": session.session_id,
+                "description": session.description,
+                "created_at": session.created_at,
+                "context_percentage": session.context_percentage,
+                "agent_name": session.agent_name,
+                "has_incomplete_todos": session.has_incomplete_todos(),
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({ "hosted": hosted, "saved": saved })))
+}
+
+async fn create_session(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<CreateSessionRequest>,
+) -> ApiResult<(StatusCode, Json<Value>)> {
+    let continuation = match &request.resume {
+        Some(session_id) => Some(
+            g3_core::list_sessions_for_directory()
+                .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                .into_iter()
+                .find(|session| &session.session_id == session_id)
+                .ok_or_else(|| {
+                    api_error(
+                        StatusCode::NOT_FOUND,
+                        format!("No saved session {}", session_id),
+                    )
+                })?,
+        ),
+        None => None,
+    };
+
+    let (events, _) = broadcast::channel(EVENT_BUFFER);
+    let mut agent = Agent::new_with_readme_and_quiet(
+        state.config.clone(),
+        JsonUiWriter::channel(events.clone()),
+        state.combined_content.clone(),
+        state.quiet,
+    )
+    .await
+    .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+    let full_context = match &continuation {
+        Some(continuation) => Some(
+            agent
+                .switch_to_session(continuation)
+                .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?,
+        ),
+        None => None,
+    };
+
+    let id = state.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+    let session_id = agent.get_session_id().map(str::to_string);
+    let session = Arc::new(HostedSession {
+        agent: tokio::sync::Mutex::new(agent),
+        events,
+        running: Mutex::new(None),
+        session_id: Mutex::new(session_id.clone()),
+        closed: CancellationToken::new(),
+    });
+    {
+        // Checked under the lock, so shutdown can't miss a session
+        let mut sessions = state.sessions.lock().unwrap();
+        if state.shutdown.is_cancelled() {
+            return Err(api_error(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "The server is shutting down",
+            ));
+        }
+        sessions.insert(id.clone(), session);
+    }
+    debug!("Hosting session {} (resumed: {:?})", id, request.resume);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "id": id,
+            "session_id": session_id,
+            "full_context_restored": full_context,
+        })),
+    ))
+}
+
+async fn post_message(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+    Json(request): Json<MessageRequest>,
+) -> ApiResult<(StatusCode, Json<Value>)> {
+    start_message(state.session(&id)?, request.content)?;
+    Ok((StatusCode::ACCEPTED, Json(json!({ "accepted": true }))))
+}
+
+/// Start working on `content` in the background, unless the session is busy
+fn start_message(session: Arc<HostedSession>, content: String) -> ApiResult<()> {
+    let token = CancellationToken::new();
+    {
+        let mut running = session.running.lock().unwrap();
+        if running.is_some() {
+            return Err(api_error(
+                StatusCode::CONFLICT,
+                "The session is still working on the previous message",
+            ));
+        }
+        *running = Some(token.clone());
+    }
+    tokio::spawn(run_message(session, content, token));
+    Ok(())
+}
+
+async fn run_message(session: Arc<HostedSession>, content: String, token: CancellationToken) {
+    let busy = BusyGuard(&session);
+    let start = Instant::now();
+    let mut agent = session.agent.lock().await;
+    let _ = session
+        .events
+        .send(json!({ "type": "user_message", "content": content }));
+    let result = agent
+        .execute_task_with_timing_cancellable(
+            &content, None, false, false, false, false, token, None,
+        )
+        .await;
+    let event = match &result {
+        Ok(result) => {
+            agent.save_session_continuation(Some(result.response.clone()));
+            result_event(&agent, Ok(&result.response), start.elapsed())
+        }
+        Err(e) => result_event(&agent, Err(e), start.elapsed()),
+    };
+    *session.session_id.lock().unwrap() = agent.get_session_id().map(str::to_string);
+    // Idle before `result` goes out, so a client can send the next message right away
+    drop(busy);
+    let _ = session.events.send(event);
+}
+
+async fn session_events(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let session = state.session(&id)?;
+    let receiver = session.events.subscribe();
+    let events = stream::unfold(
+        (receiver, session.closed.clone()),
+        |(mut receiver, closed)| async move {
+            let event = next_event(&mut receiver, &closed).await?;
+            let name = event["type"].as_str().unwrap_or("message").to_string();
+            let sse = Event::default().event(name).data(event.to_string());
+            Some((Ok(sse), (receiver, closed)))
+        },
+    );
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// The next event for a subscriber, or `None` once the session is closed. Events
+/// sent before it closed are still delivered.
+async fn next_event(
+    receiver: &mut broadcast::Receiver<Value>,
+    closed: &CancellationToken,
+) -> Option<Value> {
+    loop {
+        tokio::select! {
+            biased;
+            received = receiver.recv() => match received {
+                Ok(event) => return Some(event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Event subscriber fell behind, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            },
+            _ = closed.cancelled() => return None,
+        }
+    }
+}
+
+async fn session_socket(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+    upgrade: WebSocketUpgrade,
+) -> ApiResult<Response> {
+    let session = state.session(&id)?;
+    Ok(upgrade.on_upgrade(move |socket| serve_socket(socket, session)))
+}
+
+/// Forward the session's events to the socket, and take messages and cancels from it
+async fn serve_socket(mut socket: WebSocket, session: Arc<HostedSession>) {
+    let mut receiver = session.events.subscribe();
+    loop {
+        tokio::select! {
+            event = next_event(&mut receiver, &session.closed) => {
+                let Some(event) = event else { break };
+                if socket.send(Message::Text(event.to_string())).await.is_err() {
+                    return;
+                }
+            }
+            incoming = socket.recv() => {
+                let reply = match incoming {
+                    Some(Ok(Message::Text(text))) => socket_reply(&session, &text),
+                    // Pings are answered by axum, and there is nothing to do with binary
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                    Some(Ok(_)) => continue,
+                };
+                if socket.send(Message::Text(reply.to_string())).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+/// Act on a WebSocket request and say how it went
+fn socket_reply(session: &Arc<HostedSession>, text: &str) -> Value {
+    let request = match serde_json::from_str::<SocketRequest>(text) {
+        Ok(request) => request,
+        Err(e) => return json!({ "type": "error", "error": e.to_string() }),
+    };
+    match request {
+        SocketRequest::Message { content } => match start_message(session.clone(), content) {
+            Ok(()) => json!({ "type": "accepted" }),
+            Err((_, Json(body))) => json!({ "type": "error", "error": body["error"] }),
+        },
+        SocketRequest::Cancel => {
+            json!({ "type": "cancelled", "cancelled": session.cancel_running() })
+        }
+    }
+}
+
+async fn cancel_message(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<Value>> {
+    let session = state.session(&id)?;
+    Ok(Json(json!({ "cancelled": session.cancel_running() })))
+}
+
+async fn close_session(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<Value>> {
+    let session = state
+        .sessions
+        .lock()
+        .unwrap()
+        .remove(&id)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, format!("No session {}", id)))?;
+    end_hosted_session(&session).await;
+    let session_id = session.session_id.lock().unwrap().clone();
+    Ok(Json(json!({ "closed": id, "session_id": session_id })))
+}
+
+/// Cancel any running message, save the session so it can be resumed, and end its
+/// event streams
+async fn end_hosted_session(session: &HostedSession) {
+    session.cancel_running();
+    let agent = session.agent.lock().await;
+    agent.save_session_continuation(None);
+    agent.end_session().await;
+    session.closed.cancel();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    const TOKEN: &str = "test-token";
+
+    fn test_state() -> Arc<ServerState> {
+        Arc::new(ServerState::new(
+            Config::default(),
+            None,
+            true,
+            TOKEN.to_string(),
+        ))
+    }
+
+    fn request(method: &str, uri: &str, body: Option<Value>) -> Request<Body> {
+        let builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", TOKEN));
+        match body {
+            Some(body) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    }
+
+    async fn send(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_requests_need_the_token() {
+        let app = router(test_state());
+        let unauthenticated = Request::builder()
+            .uri("/sessions")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            send(&app, unauthenticated).await.0,
+            StatusCode::UNAUTHORIZED
+        );
+        let wrong = Request::builder()
+            .uri("/sessions")
+            .header(header::AUTHORIZATION, "Bearer test-tokem")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(send(&app, wrong).await.0, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = send(&app, request("GET", "/sessions", None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["hosted"], json!([]));
+        let query = Request::builder()
+            .uri(format!("/sessions?token={}", TOKEN))
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(send(&app, query).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_create_session_needs_a_json_body() {
+        let app = router(test_state());
+        let (status, _) = send(&app, request("POST", "/sessions", None)).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let (status, body) = send(&app, request("POST", "/sessions", Some(json!({})))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["id"], "1");
+        let (_, body) = send(&app, request("GET", "/sessions", None)).await;
+        assert_eq!(body["hosted"][0]["id"], "1");
+        assert_eq!(body["hosted"][0]["busy"], false);
+
+        let (status, _) = send(
+            &app,
+            request(
+                "POST",
+                "/sessions/7/messages",
+                Some(json!({ "content": "hi" })),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, body) = send(&app, request("POST", "/sessions/1/cancel", None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["cancelled"], false);
+    }
+
+    #[tokio::test]
+    async fn test_busy_session_refuses_messages_until_idle_even_after_a_panic() {
+        let state = test_state();
+        let app = router(state.clone());
+        send(&app, request("POST", "/sessions", Some(json!({})))).await;
+        let session = state.session("1").unwrap();
+        let mut events = session.events.subscribe();
+
+        *session.running.lock().unwrap() = Some(CancellationToken::new());
+        let (status, _) = send(
+            &app,
+            request(
+                "POST",
+                "/sessions/1/messages",
+                Some(json!({ "content": "hi" })),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _busy = BusyGuard(&session);
+            panic!("agent bug");
+        }));
+        assert!(panicked.is_err());
+        assert!(!session.is_busy());
+        let event = events.try_recv().unwrap();
+        assert_eq!(event["type"], "result");
+        assert_eq!(event["success"], false);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_ends_event_streams() {
+        let state = test_state();
+        let app = router(state.clone());
+        send(&app, request("POST", "/sessions", Some(json!({})))).await;
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/sessions/1/events", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let stream = tokio::spawn(axum::body::to_bytes(response.into_body(), usize::MAX));
+
+        shut_down(&state).await;
+        let ended = tokio::time::timeout(Duration::from_secs(5), stream).await;
+        assert!(ended.is_ok(), "the event stream outlived the shutdown");
+        let (status, _) = send(&app, request("POST", "/sessions", Some(json!({})))).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_websocket_sends_events_and_takes_requests() {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+        let state = test_state();
+        let app = router(state.clone());
+        send(&app, request("POST", "/sessions", Some(json!({})))).await;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let url = format!("ws://{}/sessions/1/ws", address);
+        assert!(tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .is_err());
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("{}?token={}", url, TOKEN))
+            .await
+            .unwrap();
+
+        socket
+            .send(ClientMessage::Text(r#"{"type": "cancel"}"#.into()))
+            .await
+            .unwrap();
+        let reply = read_json(&mut socket).await;
+        assert_eq!(reply, json!({ "type": "cancelled", "cancelled": false }));
+        socket
+            .send(ClientMessage::Text(r#"{"type": "bogus"}"#.into()))
+            .await
+            .unwrap();
+        assert_eq!(read_json(&mut socket).await["type"], "error");
+
+        let session = state.session("1").unwrap();
+        session
+            .events
+            .send(json!({ "type": "status", "message": "hello" }))
+            .unwrap();
+        assert_eq!(read_json(&mut socket).await["message"], "hello");
+
+        shut_down(&state).await;
+        let closed = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .unwrap();
+        assert!(
+            matches!(closed, Some(Ok(ClientMessage::Close(_))) | None),
+            "{:?}",
+            closed
+        );
+    }
+
+    type ClientSocket = tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >;
+
+    async fn read_json(socket: &mut ClientSocket) -> Value {
+        use futures_util::StreamExt;
+        use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+        match socket.next().await {
+            Some(Ok(ClientMessage::Text(text))) => serde_json::from_str(&text).unwrap(),
+            other => panic!("expected a text message, got {:?}", other),
+        }
+    }
+}
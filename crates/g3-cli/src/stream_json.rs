@@ -0,0 +1,292 @@
+//! Machine-readable output (`--output-format stream-json`).
+//!
+//! A single-shot run reports everything as newline-delimited JSON events on stdout,
+//! one object per line with a `type` field, so other tools can drive g3 without
+//! scraping terminal output. The last event is always `result`. `g3 serve` sends
+//! the same events to its clients.
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+use g3_config::Config;
+use g3_core::session_diff::ChangeKind;
+use g3_core::ui_writer::UiWriter;
+use g3_core::Agent;
+
+use crate::cli_args::Cli;
+use crate::filter_json::{filter_json_tool_calls, reset_json_tool_state};
+
+/// Write one event as a line on stdout
+fn emit(event: Value) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{}", event);
+    let _ = stdout.flush();
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+/// The `result` event that ends a task: the response, or the error it failed with
+pub fn result_event<W: UiWriter>(
+    agent: &Agent<W>,
+    outcome: Result<&str, &anyhow::Error>,
+    duration: Duration,
+) -> Value {
+    let ledger = agent.get_cost_ledger();
+    let mut event = json!({
+        "type": "result",
+        "success": outcome.is_ok(),
+        "session_id": agent.get_session_id(),
+        "duration_ms": millis(duration),
+        "context_percentage": agent.get_context_window().percentage_used(),
+        "total_tokens": ledger.total_tokens(),
+        "cost_usd": ledger.total_cost(),
+    });
+    match outcome {
+        Ok(response) => event["response"] = json!(response),
+        Err(e) => event["error"] = json!(format!("{:#}", e)),
+    }
+    event
+}
+
+/// `UiWriter` that turns everything the agent reports into JSON events
+pub struct JsonUiWriter {
+    /// Where events go; stdout when unset
+    channel: Option<broadcast::Sender<Value>>,
+}
+
+impl JsonUiWriter {
+    /// Write events to stdout, one per line
+    pub fn stdout() -> Self {
+        Self { channel: None }
+    }
+
+    /// Send events to a channel (events sent while nobody listens are dropped)
+    pub fn channel(sender: broadcast::Sender<Value>) -> Self {
+        Self {
+            channel: Some(sender),
+        }
+    }
+
+    fn emit(&self, event: Value) {
+        match &self.channel {
+            Some(sender) => {
+                let _ = sender.send(event);
+            }
+            None => emit(event),
+        }
+    }
+}
+
+impl UiWriter for JsonUiWriter {
+    fn print(&self, message: &str) {
+        self.println(message);
+    }
+
+    fn println(&self, message: &str) {
+        if !message.trim().is_empty() {
+            self.emit(json!({ "type": "status", "message": message.trim() }));
+        }
+    }
+
+    fn print_inline(&self, message: &str) {
+        self.println(message);
+    }
+
+    fn print_system_prompt(&self, prompt: &str) {
+        self.emit(json!({ "type": "system_prompt", "prompt": prompt }));
+    }
+
+    fn print_context_status(&self, message: &str) {
+        self.println(message);
+    }
+
+    fn print_context_thinning(&self, message: &str) {
+        self.emit(json!({ "type": "thinning", "message": message }));
+    }
+
+    fn print_tool_header(&self, tool_name: &str, tool_args: Option<&Value>) {
+        self.emit(json!({
+            "type": "tool_call_args",
+            "tool": tool_name,
+            "args": tool_args.cloned().unwrap_or_else(|| json!({})),
+        }));
+    }
+
+    fn print_tool_arg(&self, _key: &str, _value: &str) {}
+    fn print_tool_output_header(&self) {}
+    fn update_tool_output_line(&self, _line: &str) {}
+    fn print_tool_output_line(&self, _line: &str) {}
+    fn print_tool_output_summary(&self, _hidden_count: usize) {}
+
+    fn print_tool_timing(&self, _duration_str: &str, tokens_delta: u32, context_percentage: f32) {
+        self.emit(json!({
+            "type": "context",
+            "tokens_delta": tokens_delta,
+            "percentage": context_percentage,
+        }));
+    }
+
+    fn print_tool_compact(
+        &self,
+        _tool_name: &str,
+        _summary: &str,
+        duration_str: &str,
+        tokens_delta: u32,
+        context_percentage: f32,
+    ) -> bool {
+        self.print_tool_timing(duration_str, tokens_delta, context_percentage);
+        true
+    }
+
+    fn print_todo_compact(&self, _content: Option<&str>, _is_write: bool) -> bool {
+        // The full result is reported by notify_tool_result
+        true
+    }
+
+    fn print_agent_prompt(&self) {}
+
+    fn print_agent_response(&self, content: &str) {
+        if !content.is_empty() {
+            self.emit(json!({ "type": "text_delta", "text": content }));
+        }
+    }
+
+    fn notify_sse_received(&self) {}
+
+    fn notify_tool_result(&self, tool_name: &str, result: &str, duration: Duration) {
+        self.emit(json!({
+            "type": "tool_call_result",
+            "tool": tool_name,
+            "result": result,
+            "success": !result.starts_with('❌'),
+            "duration_ms": millis(duration),
+        }));
+    }
+
+    fn notify_turn_timing(
+        &self,
+        elapsed: Duration,
+        time_to_first_token: Duration,
+        turn_tokens: Option<u32>,
+        context_percentage: f32,
+    ) {
+        self.emit(json!({
+            "type": "timing",
+            "elapsed_ms": millis(elapsed),
+            "time_to_first_token_ms": millis(time_to_first_token),
+            "turn_tokens": turn_tokens,
+            "context_percentage": context_percentage,
+        }));
+    }
+
+    fn notify_compaction(&self, chars_saved: usize, context_percentage: f32) {
+        self.emit(json!({
+            "type": "compaction",
+            "chars_saved": chars_saved,
+            "context_percentage": context_percentage,
+        }));
+    }
+
+    fn print_tool_streaming_hint(&self, tool_name: &str) {
+        self.emit(json!({ "type": "tool_call_start", "tool": tool_name }));
+    }
+
+    fn print_tool_streaming_active(&self) {}
+
+    fn flush(&self) {}
+
+    fn wants_full_output(&self) -> bool {
+        true
+    }
+
+    fn prompt_user_yes_no(&self, message: &str) -> bool {
+        // Programs reading the events have no way to answer
+        self.emit(json!({ "type": "prompt_declined", "message": message }));
+        false
+    }
+
+    fn prompt_user_choice(&self, message: &str, options: &[&str]) -> usize {
+        self.emit(json!({ "type": "prompt_declined", "message": message, "choice": options.first() }));
+        0
+    }
+
+    fn filter_json_tool_calls(&self, content: &str) -> String {
+        filter_json_tool_calls(content)
+    }
+
+    fn reset_json_filter(&self) {
+        reset_json_tool_state();
+    }
+}
+
+/// Run the task given on the command line, reporting it as JSON events
+pub async fn run_stream_json_mode(
+    cli: Cli,
+    config: Config,
+    combined_content: Option<String>,
+) -> Result<()> {
+    let Some(task) = cli.task.clone() else {
+        anyhow::bail!("--output-format stream-json needs a task");
+    };
+    let start = Instant::now();
+
+    let mut agent = Agent::new_with_readme_and_quiet(
+        config,
+        JsonUiWriter::stdout(),
+        combined_content,
+        cli.quiet,
+    )
+    .await?;
+    if cli.auto_memory {
+        agent.set_auto_memory(true);
+    }
+    if cli.acd {
+        agent.set_acd_enabled(true);
+    }
+
+    let result = agent
+        .execute_task_with_timing(&task, None, false, cli.show_prompt, cli.show_code, false, None)
+        .await;
+    let response = match result {
+        Ok(result) => result.response,
+        Err(e) => {
+            emit(result_event(&agent, Err(&e), start.elapsed()));
+            return Err(e);
+        }
+    };
+
+    if let Err(e) = agent.send_auto_memory_reminder().await {
+        tracing::debug!("Auto-memory reminder failed: {}", e);
+    }
+    agent.save_session_continuation(Some(response.clone()));
+    agent.end_session().await;
+
+    if cli.print_diff {
+        match agent.session_diff() {
+            Ok(diffs) => {
+                for diff in diffs {
+                    let kind = match diff.kind {
+                        ChangeKind::Added => "added",
+                        ChangeKind::Modified => "modified",
+                        ChangeKind::Deleted => "deleted",
+                    };
+                    emit(json!({
+                        "type": "file_diff",
+                        "path": diff.path,
+                        "kind": kind,
+                        "diff": diff.diff,
+                    }));
+                }
+            }
+            Err(e) => tracing::warn!("Error building session diff: {}", e),
+        }
+    }
+
+    emit(result_event(&agent, Ok(&response), start.elapsed()));
+    Ok(())
+}
@@ -529,6 +529,20 @@ impl UiWriter for ConsoleUiWriter {
                     .map(|(_, v)| v.as_str())
                     .unwrap_or("end");
                 format!(" [{}..{}]", start_val, end_val)
+            } else if let Some((_, symbol)) = args.iter().find(|(k, _)| k == "symbol") {
+                format!(" [{}]", symbol)
+            } else if args.iter().any(|(k, _)| k == "start_line" || k == "end_line") {
+                let start_val = args
+                    .iter()
+                    .find(|(k, _)| k == "start_line")
+                    .map(|(_, v)| v.as_str())
+                    .unwrap_or("1");
+                let end_val = args
+                    .iter()
+                    .find(|(k, _)| k == "end_line")
+                    .map(|(_, v)| v.as_str())
+                    .unwrap_or("end");
+                format!(" [L{}-{}]", start_val, end_val)
             } else {
                 String::new()
             }
@@ -67,6 +67,37 @@ pub fn display_context_progress<W: UiWriter>(agent: &Agent<W>, _output: &SimpleO
     );
 }
 
+/// Print every file the session changed as a colored unified diff.
+pub fn print_session_diff<W: UiWriter>(agent: &Agent<W>, output: &SimpleOutput) {
+    let diffs = match agent.session_diff() {
+        Ok(diffs) => diffs,
+        Err(e) => {
+            output.print(&format!("❌ Error building session diff: {}", e));
+            return;
+        }
+    };
+    if diffs.is_empty() {
+        output.print("No file changes in this session.");
+        return;
+    }
+
+    output.print(&format!("📝 {} file(s) changed this session:", diffs.len()));
+    for line in g3_core::session_diff::format_session_diff(&diffs).lines() {
+        let color = if line.starts_with("+++") || line.starts_with("---") {
+            Color::White
+        } else if line.starts_with('+') {
+            Color::Green
+        } else if line.starts_with('-') {
+            Color::Red
+        } else if line.starts_with("@@") {
+            Color::Cyan
+        } else {
+            Color::Reset
+        };
+        println!("{}{}{}", SetForegroundColor(color), line, ResetColor);
+    }
+}
+
 /// Set up the workspace directory for autonomous mode.
 /// Uses G3_WORKSPACE environment variable or defaults to ~/tmp/workspace.
 pub fn setup_workspace_directory() -> Result<PathBuf> {
@@ -132,9 +163,33 @@ pub fn load_config_with_cli_overrides(cli: &Cli) -> Result<Config> {
         config.agent.auto_compact = false;
     }
 
+    // Apply budget overrides
+    if cli.max_cost.is_some() {
+        config.agent.max_cost = cli.max_cost;
+    }
+    if cli.max_tokens_total.is_some() {
+        config.agent.max_tokens_total = cli.max_tokens_total;
+    }
+    if cli.max_wall_time.is_some() {
+        config.agent.max_wall_time_seconds = cli.max_wall_time;
+    }
+
+    // Apply sandbox overrides; refuse to start rather than run commands unsandboxed
+    if cli.sandbox || cli.sandbox_no_network {
+        config.sandbox.enabled = true;
+    }
+    if cli.sandbox_no_network {
+        config.sandbox.network = false;
+    }
+    if config.sandbox.enabled && !g3_core::Sandbox::is_supported() {
+        return Err(anyhow::anyhow!(
+            "The command sandbox is enabled but unavailable: it needs Linux with unprivileged user namespaces"
+        ));
+    }
+
     // Validate provider if specified
     if let Some(ref provider) = cli.provider {
-        let valid_providers = ["anthropic", "databricks", "embedded", "openai"];
+        let valid_providers = ["anthropic", "databricks", "embedded", "gemini", "ollama", "openai"];
         if !valid_providers.contains(&provider.as_str()) {
             return Err(anyhow::anyhow!(
                 "Invalid provider '{}'. Valid options: {:?}",
@@ -148,7 +203,7 @@ pub fn load_config_with_cli_overrides(cli: &Cli) -> Result<Config> {
 }
 
 /// Initialize logging based on CLI verbosity settings.
-pub fn initialize_logging(verbose: bool) {
+pub fn initialize_logging(verbose: bool, to_stderr: bool) {
     use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
     let filter = if verbose {
@@ -169,8 +224,15 @@ pub fn initialize_logging(verbose: bool) {
             .add_directive("llama=off".parse().unwrap())
     };
 
+    // Modes that speak a protocol on stdout log to stderr instead
+    let (stdout_layer, stderr_layer) = if to_stderr {
+        (None, Some(tracing_subscriber::fmt::layer().with_writer(std::io::stderr)))
+    } else {
+        (Some(tracing_subscriber::fmt::layer()), None)
+    };
     let _ = tracing_subscriber::registry()
-        .with(tracing_subscriber::fmt::layer())
+        .with(stdout_layer)
+        .with(stderr_layer)
         .with(filter)
         .try_init();
 }
@@ -243,3 +243,24 @@ fn test_quiet_option_accepted() {
         "--quiet option should be recognized"
     );
 }
+
+// =============================================================================
+// Test: stream-json output needs a task
+// =============================================================================
+
+#[test]
+fn test_stream_json_without_task_fails_cleanly() {
+    let output = Command::new(get_g3_binary())
+        .args(["--output-format", "stream-json"])
+        .output()
+        .expect("Failed to execute g3 with stream-json output");
+
+    assert!(
+        !output.status.success(),
+        "stream-json without a task should fail"
+    );
+    assert!(
+        output.stdout.is_empty(),
+        "stream-json errors should not write to stdout"
+    );
+}
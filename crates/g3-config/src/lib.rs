@@ -13,22 +13,41 @@ pub struct Config {
     pub computer_control: ComputerControlConfig,
     #[serde(default)]
     pub webdriver: WebDriverConfig,
+    /// Per-model prices, keyed by model name. Merged over the built-in table.
+    #[serde(default)]
+    pub pricing: HashMap<String, ModelPricing>,
+    /// Policy checked before every tool call
+    #[serde(default)]
+    pub permissions: PermissionsConfig,
+    /// Namespace sandbox for shell commands (Linux only)
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+    /// External MCP tool servers, keyed by server name
+    #[serde(default)]
+    pub mcp_servers: HashMap<String, McpServerConfig>,
+    /// Custom tools backed by executables, keyed by tool name
+    #[serde(default)]
+    pub plugin_tools: HashMap<String, PluginToolConfig>,
+    /// Scripts run on tool calls, turns, compaction and session end
+    #[serde(default)]
+    pub hooks: HooksConfig,
 }
 
 /// Provider configuration with named configs per provider type
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProvidersConfig {
-    /// Default provider in format "<provider_type>.<config_name>"
-    pub default_provider: String,
+    /// Default provider in format "<provider_type>.<config_name>", or an ordered
+    /// failover list of such references
+    pub default_provider: ProviderChain,
 
     /// Provider for planner mode (optional, falls back to default_provider)
-    pub planner: Option<String>,
+    pub planner: Option<ProviderChain>,
 
     /// Provider for coach in autonomous mode (optional, falls back to default_provider)
-    pub coach: Option<String>,
+    pub coach: Option<ProviderChain>,
 
     /// Provider for player in autonomous mode (optional, falls back to default_provider)
-    pub player: Option<String>,
+    pub player: Option<ProviderChain>,
 
     /// Named Anthropic provider configs
     #[serde(default)]
@@ -49,6 +68,101 @@ pub struct ProvidersConfig {
     /// Multiple named OpenAI-compatible providers (e.g., openrouter, groq, etc.)
     #[serde(default)]
     pub openai_compatible: HashMap<String, OpenAIConfig>,
+
+    /// Named Google Gemini provider configs
+    #[serde(default)]
+    pub gemini: HashMap<String, GeminiConfig>,
+
+    /// Named Ollama provider configs
+    #[serde(default)]
+    pub ollama: HashMap<String, OllamaConfig>,
+
+    /// Named record/replay (cassette) provider configs
+    #[serde(default)]
+    pub replay: HashMap<String, ReplayConfig>,
+}
+
+/// An ordered list of provider references: the primary provider followed by failovers.
+///
+/// In TOML this is either a single reference (`"anthropic.default"`) or a list
+/// (`["anthropic.default", "databricks.default"]`). Code that only cares about the active
+/// provider uses `primary()`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawProviderChain", into = "RawProviderChain")]
+pub struct ProviderChain(Vec<String>);
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawProviderChain {
+    Single(String),
+    List(Vec<String>),
+}
+
+impl ProviderChain {
+    /// The first (preferred) provider reference
+    pub fn primary(&self) -> &str {
+        &self.0[0]
+    }
+
+    /// Providers to fall over to, in order, after the primary fails
+    pub fn fallbacks(&self) -> &[String] {
+        &self.0[1..]
+    }
+
+    /// All provider references, primary first
+    pub fn providers(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl TryFrom<Vec<String>> for ProviderChain {
+    type Error = String;
+
+    fn try_from(providers: Vec<String>) -> std::result::Result<Self, Self::Error> {
+        if providers.is_empty() {
+            return Err("provider list must contain at least one provider".to_string());
+        }
+        Ok(Self(providers))
+    }
+}
+
+impl TryFrom<RawProviderChain> for ProviderChain {
+    type Error = String;
+
+    fn try_from(raw: RawProviderChain) -> std::result::Result<Self, Self::Error> {
+        match raw {
+            RawProviderChain::Single(provider) => Ok(Self(vec![provider])),
+            RawProviderChain::List(providers) => providers.try_into(),
+        }
+    }
+}
+
+impl From<ProviderChain> for RawProviderChain {
+    fn from(chain: ProviderChain) -> Self {
+        if chain.0.len() == 1 {
+            RawProviderChain::Single(chain.0.into_iter().next().unwrap())
+        } else {
+            RawProviderChain::List(chain.0)
+        }
+    }
+}
+
+impl From<String> for ProviderChain {
+    fn from(provider: String) -> Self {
+        Self(vec![provider])
+    }
+}
+
+impl From<&str> for ProviderChain {
+    fn from(provider: &str) -> Self {
+        Self(vec![provider.to_string()])
+    }
+}
+
+impl std::fmt::Display for ProviderChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.join(" -> "))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -58,6 +172,8 @@ pub struct OpenAIConfig {
     pub base_url: Option<String>,
     pub max_tokens: Option<u32>,
     pub temperature: Option<f32>,
+    /// Whether the model accepts images; detected from the model name when unset
+    pub vision: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,6 +208,54 @@ pub struct EmbeddedConfig {
     pub threads: Option<u32>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeminiConfig {
+    pub api_key: String,
+    pub model: String,
+    /// API base URL (default: https://generativelanguage.googleapis.com/v1beta)
+    pub base_url: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaConfig {
+    /// Model tag (e.g., "qwen2.5-coder:14b"). If omitted, the first model
+    /// reported by the server's `/api/tags` is used.
+    pub model: Option<String>,
+    /// Server address (default: http://localhost:11434)
+    pub base_url: Option<String>,
+    /// Context size sent to Ollama as `num_ctx`; also sizes g3's context window.
+    /// Defaults to the model's context length as reported by the server.
+    pub num_ctx: Option<u32>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+}
+
+/// Whether a replay provider records a new cassette or plays back an existing one
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayMode {
+    #[default]
+    Replay,
+    Record,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayConfig {
+    /// Path to the cassette JSON file
+    pub cassette: String,
+    #[serde(default)]
+    pub mode: ReplayMode,
+    /// Provider to record from in record mode (e.g., "anthropic.default")
+    pub record_from: Option<String>,
+    /// Fail on requests that are not in the cassette. When false, unmatched
+    /// requests are answered with the next interaction in recording order.
+    #[serde(default = "default_true")]
+    pub strict: bool,
+    pub context_length: Option<u32>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentConfig {
     pub max_context_length: Option<u32>,
@@ -109,6 +273,23 @@ pub struct AgentConfig {
     pub autonomous_max_retry_attempts: u32,
     #[serde(default = "default_check_todo_staleness")]
     pub check_todo_staleness: bool,
+    /// Stop the run once provider spend reaches this many USD
+    pub max_cost: Option<f64>,
+    /// Stop the run once this many tokens have been used across all requests
+    pub max_tokens_total: Option<u64>,
+    /// Stop the run after this many seconds of wall-clock time
+    pub max_wall_time_seconds: Option<u64>,
+    /// Kill `shell` commands after this many seconds (the tool's `timeout_secs` overrides it)
+    #[serde(default = "default_shell_timeout_seconds")]
+    pub shell_timeout_seconds: u64,
+    /// Lines of `shell` output returned to the model, split between the start and the end.
+    /// Longer output is saved in full to the session directory.
+    #[serde(default = "default_shell_output_max_lines")]
+    pub shell_output_max_lines: usize,
+    /// Run `shell` commands in one long-lived bash session by default, so `cd`, `export`
+    /// and `source` carry over (the tool's `persistent` argument overrides it)
+    #[serde(default = "default_false")]
+    pub persistent_shell: bool,
 }
 
 fn default_fallback_max_tokens() -> usize {
@@ -123,6 +304,12 @@ fn default_false() -> bool {
 fn default_timeout_seconds() -> u64 {
     120
 }
+fn default_shell_timeout_seconds() -> u64 {
+    600
+}
+fn default_shell_output_max_lines() -> usize {
+    400
+}
 fn default_max_retry_attempts() -> u32 {
     3
 }
@@ -181,6 +368,239 @@ pub struct WebDriverConfig {
     pub browser: WebDriverBrowser,
 }
 
+/// What the permission policy does with a tool call
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionMode {
+    #[default]
+    Allow,
+    /// Ask the user; refused in autonomous mode
+    Ask,
+    Deny,
+}
+
+/// Tool permission policy (`[permissions]`). Everything is allowed by default.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PermissionsConfig {
+    /// Decision per tool name, for tools without more specific rules
+    #[serde(default)]
+    pub tools: HashMap<String, PermissionMode>,
+    /// Glob rules for commands run by `shell` and `background_process`
+    #[serde(default)]
+    pub shell: ShellPermissions,
+    /// Where `write_file` and `str_replace` may write
+    #[serde(default)]
+    pub paths: PathPermissions,
+}
+
+/// Glob rules (`*` matches anything, `?` one character) for shell commands.
+/// Compound commands are checked part by part; deny wins over ask, and ask over allow.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ShellPermissions {
+    /// Commands that run without asking, even when the tool itself is set to ask
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub ask: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PathPermissions {
+    /// Decision for writes outside the workspace
+    #[serde(default)]
+    pub outside_workspace: PermissionMode,
+    /// Globs for paths outside the workspace that may always be written
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+/// Sandbox for commands run by `shell` and `background_process` (`[sandbox]`).
+/// The filesystem is read-only apart from the workspace and `writable_paths`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Allow network access from sandboxed commands
+    #[serde(default = "default_true")]
+    pub network: bool,
+    /// Directories besides the workspace that stay writable (e.g. "~/.cargo")
+    #[serde(default)]
+    pub writable_paths: Vec<String>,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            network: true,
+            writable_paths: Vec::new(),
+        }
+    }
+}
+
+/// An MCP server launched over stdio (`[mcp_servers.<name>]`). Its tools are offered
+/// to the model next to the built-in ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    /// Program to run
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra environment variables for the server
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Working directory for the server (defaults to the current directory)
+    #[serde(default)]
+    pub cwd: Option<String>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// How long to wait for the server to answer a request
+    #[serde(default = "default_mcp_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+fn default_mcp_timeout_seconds() -> u64 {
+    60
+}
+
+/// A custom tool backed by an executable (`[plugin_tools.<name>]`). The tool call's
+/// arguments are written to its stdin as JSON, and its stdout is the tool result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginToolConfig {
+    /// Description shown to the model
+    pub description: String,
+    /// Program to run
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// JSON schema of the tool's arguments (defaults to an object with no properties)
+    #[serde(default = "default_plugin_input_schema")]
+    pub input_schema: serde_json::Value,
+    /// Kill the program after this many seconds (0 disables the timeout)
+    #[serde(default = "default_plugin_timeout_seconds")]
+    pub timeout_seconds: u64,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_plugin_input_schema() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+fn default_plugin_timeout_seconds() -> u64 {
+    120
+}
+
+/// Scripts run on agent lifecycle events (`[[hooks.<event>]]`). Each receives a JSON
+/// payload describing the event on stdin. A `pre_tool` hook that exits non-zero
+/// blocks the tool call.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HooksConfig {
+    /// Before a tool runs; a non-zero exit blocks the call
+    #[serde(default)]
+    pub pre_tool: Vec<HookConfig>,
+    /// After a tool has run, with its result
+    #[serde(default)]
+    pub post_tool: Vec<HookConfig>,
+    /// After the agent has finished responding to a prompt
+    #[serde(default)]
+    pub post_turn: Vec<HookConfig>,
+    /// After the context window has been compacted
+    #[serde(default)]
+    pub on_compaction: Vec<HookConfig>,
+    /// When g3 exits
+    #[serde(default)]
+    pub on_session_end: Vec<HookConfig>,
+}
+
+/// A script registered for a hook event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookConfig {
+    /// Shell command to run (with `sh -c`)
+    pub command: String,
+    /// Tools the hook applies to, for `pre_tool` and `post_tool` (empty means all)
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Kill the command after this many seconds (0 disables the timeout)
+    #[serde(default = "default_hook_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+fn default_hook_timeout_seconds() -> u64 {
+    60
+}
+
+/// Token prices for a model, in USD per million tokens
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    #[serde(default)]
+    pub cache_read: f64,
+    #[serde(default)]
+    pub cache_write: f64,
+}
+
+impl ModelPricing {
+    const fn new(input: f64, output: f64, cache_read: f64, cache_write: f64) -> Self {
+        Self {
+            input,
+            output,
+            cache_read,
+            cache_write,
+        }
+    }
+
+    /// Cost in USD of the given token counts
+    pub fn cost(&self, input: u64, output: u64, cache_read: u64, cache_write: u64) -> f64 {
+        (input as f64 * self.input
+            + output as f64 * self.output
+            + cache_read as f64 * self.cache_read
+            + cache_write as f64 * self.cache_write)
+            / 1_000_000.0
+    }
+}
+
+/// Built-in list prices, used when `[pricing]` has no entry for a model.
+/// Local providers (embedded, ollama) are intentionally absent.
+const DEFAULT_MODEL_PRICING: &[(&str, ModelPricing)] = &[
+    ("claude-opus-4-5", ModelPricing::new(5.0, 25.0, 0.5, 6.25)),
+    ("claude-opus-4", ModelPricing::new(15.0, 75.0, 1.5, 18.75)),
+    ("claude-sonnet-4", ModelPricing::new(3.0, 15.0, 0.3, 3.75)),
+    ("claude-3-7-sonnet", ModelPricing::new(3.0, 15.0, 0.3, 3.75)),
+    ("claude-3-5-sonnet", ModelPricing::new(3.0, 15.0, 0.3, 3.75)),
+    ("claude-haiku-4-5", ModelPricing::new(1.0, 5.0, 0.1, 1.25)),
+    ("claude-3-5-haiku", ModelPricing::new(0.8, 4.0, 0.08, 1.0)),
+    ("gpt-4o-mini", ModelPricing::new(0.15, 0.6, 0.075, 0.0)),
+    ("gpt-4o", ModelPricing::new(2.5, 10.0, 1.25, 0.0)),
+    ("gpt-4.1-mini", ModelPricing::new(0.4, 1.6, 0.1, 0.0)),
+    ("gpt-4.1", ModelPricing::new(2.0, 8.0, 0.5, 0.0)),
+    ("gpt-5-mini", ModelPricing::new(0.25, 2.0, 0.025, 0.0)),
+    ("gpt-5", ModelPricing::new(1.25, 10.0, 0.125, 0.0)),
+    ("gemini-2.5-pro", ModelPricing::new(1.25, 10.0, 0.31, 0.0)),
+    ("gemini-2.5-flash", ModelPricing::new(0.3, 2.5, 0.075, 0.0)),
+];
+
+/// Find the price for `model` in `table`: an exact key match wins, otherwise the
+/// longest key contained in the model name (so "databricks-claude-sonnet-4" and
+/// "claude-sonnet-4-5-20250929" both resolve to "claude-sonnet-4").
+fn lookup_pricing<'a, I>(table: I, model: &str) -> Option<ModelPricing>
+where
+    I: IntoIterator<Item = (&'a str, &'a ModelPricing)>,
+{
+    let mut best: Option<(&str, &ModelPricing)> = None;
+    for (key, pricing) in table {
+        if key == model {
+            return Some(*pricing);
+        }
+        if model.contains(key) && best.is_none_or(|(best_key, _)| key.len() > best_key.len()) {
+            best = Some((key, pricing));
+        }
+    }
+    best.map(|(_, pricing)| *pricing)
+}
+
 impl Default for AgentConfig {
     fn default() -> Self {
         Self {
@@ -192,6 +612,12 @@ impl Default for AgentConfig {
             max_retry_attempts: 3,
             autonomous_max_retry_attempts: 6,
             check_todo_staleness: true,
+            max_cost: None,
+            max_tokens_total: None,
+            max_wall_time_seconds: None,
+            shell_timeout_seconds: 600,
+            shell_output_max_lines: 400,
+            persistent_shell: false,
         }
     }
 }
@@ -222,7 +648,7 @@ impl Default for Config {
 
         Self {
             providers: ProvidersConfig {
-                default_provider: "databricks.default".to_string(),
+                default_provider: "databricks.default".into(),
                 planner: None,
                 coach: None,
                 player: None,
@@ -231,6 +657,9 @@ impl Default for Config {
                 databricks: databricks_configs,
                 embedded: HashMap::new(),
                 openai_compatible: HashMap::new(),
+                gemini: HashMap::new(),
+                ollama: HashMap::new(),
+                replay: HashMap::new(),
             },
             agent: AgentConfig {
                 max_context_length: None,
@@ -241,9 +670,21 @@ impl Default for Config {
                 max_retry_attempts: 3,
                 autonomous_max_retry_attempts: 6,
                 check_todo_staleness: true,
+                max_cost: None,
+                max_tokens_total: None,
+                max_wall_time_seconds: None,
+                shell_timeout_seconds: 600,
+                shell_output_max_lines: 400,
+                persistent_shell: false,
             },
             computer_control: ComputerControlConfig::default(),
             webdriver: WebDriverConfig::default(),
+            pricing: HashMap::new(),
+            permissions: PermissionsConfig::default(),
+            sandbox: SandboxConfig::default(),
+            mcp_servers: HashMap::new(),
+            plugin_tools: HashMap::new(),
+            hooks: HooksConfig::default(),
         }
     }
 }
@@ -345,8 +786,10 @@ impl Config {
 
             let config: Config = toml::from_str(&config_content)?;
 
-            // Validate the default_provider format
-            config.validate_provider_reference(&config.providers.default_provider)?;
+            // Validate every provider in the default failover chain
+            for provider in config.providers.default_provider.providers() {
+                config.validate_provider_reference(provider)?;
+            }
 
             return Ok(config);
         }
@@ -445,11 +888,38 @@ impl Config {
                     );
                 }
             }
+            "gemini" => {
+                if !self.providers.gemini.contains_key(config_name) {
+                    anyhow::bail!(
+                        "Provider config 'gemini.{}' not found. Available: {:?}",
+                        config_name,
+                        self.providers.gemini.keys().collect::<Vec<_>>()
+                    );
+                }
+            }
+            "ollama" => {
+                if !self.providers.ollama.contains_key(config_name) {
+                    anyhow::bail!(
+                        "Provider config 'ollama.{}' not found. Available: {:?}",
+                        config_name,
+                        self.providers.ollama.keys().collect::<Vec<_>>()
+                    );
+                }
+            }
+            "replay" => {
+                if !self.providers.replay.contains_key(config_name) {
+                    anyhow::bail!(
+                        "Provider config 'replay.{}' not found. Available: {:?}",
+                        config_name,
+                        self.providers.replay.keys().collect::<Vec<_>>()
+                    );
+                }
+            }
             _ => {
                 // Check openai_compatible providers
                 if !self.providers.openai_compatible.contains_key(provider_type) {
                     anyhow::bail!(
-                        "Unknown provider type '{}'. Valid types: anthropic, openai, databricks, embedded, or openai_compatible names",
+                        "Unknown provider type '{}'. Valid types: anthropic, openai, databricks, embedded, gemini, ollama, replay, or openai_compatible names",
                         provider_type
                     );
                 }
@@ -493,13 +963,13 @@ impl Config {
                 format!("{}.default", provider)
             };
             config.validate_provider_reference(&provider)?;
-            config.providers.default_provider = provider;
+            config.providers.default_provider = provider.into();
         }
 
         // Apply model override to the active provider
         if let Some(model) = model_override {
             let (provider_type, config_name) =
-                Self::parse_provider_reference(&config.providers.default_provider)?;
+                Self::parse_provider_reference(config.providers.default_provider.primary())?;
 
             match provider_type.as_str() {
                 "anthropic" => {
@@ -550,6 +1020,35 @@ impl Config {
                         ));
                     }
                 }
+                "gemini" => {
+                    if let Some(ref mut gemini_config) =
+                        config.providers.gemini.get_mut(&config_name)
+                    {
+                        gemini_config.model = model;
+                    } else {
+                        return Err(anyhow::anyhow!(
+                            "Provider config 'gemini.{}' not found.",
+                            config_name
+                        ));
+                    }
+                }
+                "ollama" => {
+                    if let Some(ref mut ollama_config) =
+                        config.providers.ollama.get_mut(&config_name)
+                    {
+                        ollama_config.model = Some(model);
+                    } else {
+                        return Err(anyhow::anyhow!(
+                            "Provider config 'ollama.{}' not found.",
+                            config_name
+                        ));
+                    }
+                }
+                "replay" => {
+                    return Err(anyhow::anyhow!(
+                        "Model override is not supported for replay providers; the model comes from the cassette."
+                    ));
+                }
                 _ => {
                     // Check openai_compatible
                     if let Some(ref mut compat_config) =
@@ -568,51 +1067,73 @@ impl Config {
 
     /// Get the provider reference for planner mode
     pub fn get_planner_provider(&self) -> &str {
+        self.get_planner_chain().primary()
+    }
+
+    /// Get the provider reference for coach mode in autonomous execution
+    pub fn get_coach_provider(&self) -> &str {
+        self.get_coach_chain().primary()
+    }
+
+    /// Get the provider reference for player mode in autonomous execution
+    pub fn get_player_provider(&self) -> &str {
+        self.get_player_chain().primary()
+    }
+
+    /// Get the provider failover chain for planner mode
+    pub fn get_planner_chain(&self) -> &ProviderChain {
         self.providers
             .planner
-            .as_deref()
+            .as_ref()
             .unwrap_or(&self.providers.default_provider)
     }
 
-    /// Get the provider reference for coach mode in autonomous execution
-    pub fn get_coach_provider(&self) -> &str {
+    /// Get the provider failover chain for coach mode in autonomous execution
+    pub fn get_coach_chain(&self) -> &ProviderChain {
         self.providers
             .coach
-            .as_deref()
+            .as_ref()
             .unwrap_or(&self.providers.default_provider)
     }
 
-    /// Get the provider reference for player mode in autonomous execution
-    pub fn get_player_provider(&self) -> &str {
+    /// Get the provider failover chain for player mode in autonomous execution
+    pub fn get_player_chain(&self) -> &ProviderChain {
         self.providers
             .player
-            .as_deref()
+            .as_ref()
             .unwrap_or(&self.providers.default_provider)
     }
 
     /// Create a copy of the config with a different default provider
     pub fn with_provider_override(&self, provider_ref: &str) -> Result<Self> {
-        // Validate that the provider is configured
-        self.validate_provider_reference(provider_ref)?;
+        self.with_provider_chain(&ProviderChain::from(provider_ref))
+    }
+
+    /// Create a copy of the config with a different default provider failover chain
+    pub fn with_provider_chain(&self, chain: &ProviderChain) -> Result<Self> {
+        // Validate that every provider in the chain is configured
+        for provider_ref in chain.providers() {
+            self.validate_provider_reference(provider_ref)?;
+        }
 
         let mut config = self.clone();
-        config.providers.default_provider = provider_ref.to_string();
+        config.providers.default_provider = chain.clone();
         Ok(config)
     }
 
     /// Create a copy of the config for planner mode
     pub fn for_planner(&self) -> Result<Self> {
-        self.with_provider_override(self.get_planner_provider())
+        self.with_provider_chain(self.get_planner_chain())
     }
 
     /// Create a copy of the config for coach mode in autonomous execution
     pub fn for_coach(&self) -> Result<Self> {
-        self.with_provider_override(self.get_coach_provider())
+        self.with_provider_chain(self.get_coach_chain())
     }
 
     /// Create a copy of the config for player mode in autonomous execution
     pub fn for_player(&self) -> Result<Self> {
-        self.with_provider_override(self.get_player_provider())
+        self.with_provider_chain(self.get_player_chain())
     }
 
     /// Get Anthropic config by name
@@ -635,10 +1156,42 @@ impl Config {
         self.providers.embedded.get(name)
     }
 
+    /// Get Gemini config by name
+    pub fn get_gemini_config(&self, name: &str) -> Option<&GeminiConfig> {
+        self.providers.gemini.get(name)
+    }
+
+    /// Get Ollama config by name
+    pub fn get_ollama_config(&self, name: &str) -> Option<&OllamaConfig> {
+        self.providers.ollama.get(name)
+    }
+
+    /// Get Replay config by name
+    pub fn get_replay_config(&self, name: &str) -> Option<&ReplayConfig> {
+        self.providers.replay.get(name)
+    }
+
+    /// Get the price of a model, preferring `[pricing]` entries over the built-in table.
+    /// Returns None for models with no known price (e.g. local models).
+    pub fn pricing_for(&self, model: &str) -> Option<ModelPricing> {
+        lookup_pricing(
+            self.pricing.iter().map(|(key, pricing)| (key.as_str(), pricing)),
+            model,
+        )
+        .or_else(|| {
+            lookup_pricing(
+                DEFAULT_MODEL_PRICING
+                    .iter()
+                    .map(|(key, pricing)| (*key, pricing)),
+                model,
+            )
+        })
+    }
+
     /// Get the current default provider's config
     pub fn get_default_provider_config(&self) -> Result<ProviderConfigRef<'_>> {
         let (provider_type, config_name) =
-            Self::parse_provider_reference(&self.providers.default_provider)?;
+            Self::parse_provider_reference(self.providers.default_provider.primary())?;
 
         match provider_type.as_str() {
             "anthropic" => self
@@ -665,6 +1218,24 @@ impl Config {
                 .get(&config_name)
                 .map(ProviderConfigRef::Embedded)
                 .ok_or_else(|| anyhow::anyhow!("Embedded config '{}' not found", config_name)),
+            "gemini" => self
+                .providers
+                .gemini
+                .get(&config_name)
+                .map(ProviderConfigRef::Gemini)
+                .ok_or_else(|| anyhow::anyhow!("Gemini config '{}' not found", config_name)),
+            "ollama" => self
+                .providers
+                .ollama
+                .get(&config_name)
+                .map(ProviderConfigRef::Ollama)
+                .ok_or_else(|| anyhow::anyhow!("Ollama config '{}' not found", config_name)),
+            "replay" => self
+                .providers
+                .replay
+                .get(&config_name)
+                .map(ProviderConfigRef::Replay)
+                .ok_or_else(|| anyhow::anyhow!("Replay config '{}' not found", config_name)),
             _ => self
                 .providers
                 .openai_compatible
@@ -684,6 +1255,9 @@ pub enum ProviderConfigRef<'a> {
     OpenAI(&'a OpenAIConfig),
     Databricks(&'a DatabricksConfig),
     Embedded(&'a EmbeddedConfig),
+    Gemini(&'a GeminiConfig),
+    Ollama(&'a OllamaConfig),
+    Replay(&'a ReplayConfig),
     OpenAICompatible(&'a OpenAIConfig),
 }
 
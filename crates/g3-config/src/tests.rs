@@ -58,17 +58,17 @@ autonomous_max_retry_attempts = 6
         let config = Config::load(Some(config_path.to_str().unwrap())).unwrap();
 
         // Test that the providers are correctly identified
-        assert_eq!(config.providers.default_provider, "databricks.default");
+        assert_eq!(config.providers.default_provider.providers(), ["databricks.default"]);
         assert_eq!(config.get_coach_provider(), "anthropic.default");
         assert_eq!(config.get_player_provider(), "embedded.local");
 
         // Test creating coach config
         let coach_config = config.for_coach().unwrap();
-        assert_eq!(coach_config.providers.default_provider, "anthropic.default");
+        assert_eq!(coach_config.providers.default_provider.providers(), ["anthropic.default"]);
 
         // Test creating player config
         let player_config = config.for_player().unwrap();
-        assert_eq!(player_config.providers.default_provider, "embedded.local");
+        assert_eq!(player_config.providers.default_provider.providers(), ["embedded.local"]);
     }
 
     #[test]
@@ -107,11 +107,11 @@ autonomous_max_retry_attempts = 6
 
         // Test creating coach config (should use default)
         let coach_config = config.for_coach().unwrap();
-        assert_eq!(coach_config.providers.default_provider, "databricks.default");
+        assert_eq!(coach_config.providers.default_provider.providers(), ["databricks.default"]);
 
         // Test creating player config (should use default)
         let player_config = config.for_player().unwrap();
-        assert_eq!(player_config.providers.default_provider, "databricks.default");
+        assert_eq!(player_config.providers.default_provider.providers(), ["databricks.default"]);
     }
 
     #[test]
@@ -228,7 +228,7 @@ autonomous_max_retry_attempts = 6
 
         // Test creating planner config
         let planner_config = config.for_planner().unwrap();
-        assert_eq!(planner_config.providers.default_provider, "anthropic.planner");
+        assert_eq!(planner_config.providers.default_provider.providers(), ["anthropic.planner"]);
     }
 
     #[test]
@@ -264,4 +264,437 @@ autonomous_max_retry_attempts = 6
         // Test that planner falls back to default provider
         assert_eq!(config.get_planner_provider(), "databricks.default");
     }
+
+    #[test]
+    fn test_replay_provider_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+
+        let config_content = format!(r#"
+[providers]
+default_provider = "replay.ci"
+
+[providers.replay.ci]
+cassette = "tests/cassettes/session.json"
+
+[providers.replay.capture]
+cassette = "tests/cassettes/capture.json"
+mode = "record"
+record_from = "anthropic.default"
+strict = false
+
+[providers.anthropic.default]
+api_key = "test-key"
+model = "claude-3"
+{}"#, test_config_footer());
+
+        fs::write(&config_path, config_content).unwrap();
+
+        let config = Config::load(Some(config_path.to_str().unwrap())).unwrap();
+
+        let ci = config.get_replay_config("ci").unwrap();
+        assert_eq!(ci.mode, crate::ReplayMode::Replay);
+        assert!(ci.strict, "strict should default to true");
+        assert!(ci.record_from.is_none());
+
+        let capture = config.get_replay_config("capture").unwrap();
+        assert_eq!(capture.mode, crate::ReplayMode::Record);
+        assert_eq!(capture.record_from.as_deref(), Some("anthropic.default"));
+        assert!(!capture.strict);
+
+        assert!(matches!(
+            config.get_default_provider_config().unwrap(),
+            crate::ProviderConfigRef::Replay(_)
+        ));
+
+        // Replay providers take their model from the cassette
+        let result = Config::load_with_overrides(
+            Some(config_path.to_str().unwrap()),
+            None,
+            Some("other-model".to_string()),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_ollama_provider_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+
+        let config_content = format!(r#"
+[providers]
+default_provider = "ollama.default"
+
+[providers.ollama.default]
+model = "qwen2.5-coder:14b"
+num_ctx = 32768
+
+[providers.ollama.auto]
+base_url = "http://gpu-box:11434"
+{}"#, test_config_footer());
+
+        fs::write(&config_path, config_content).unwrap();
+
+        let config = Config::load(Some(config_path.to_str().unwrap())).unwrap();
+
+        let default = config.get_ollama_config("default").unwrap();
+        assert_eq!(default.model.as_deref(), Some("qwen2.5-coder:14b"));
+        assert_eq!(default.num_ctx, Some(32768));
+        assert!(default.base_url.is_none());
+
+        let auto = config.get_ollama_config("auto").unwrap();
+        assert!(auto.model.is_none(), "model is optional and discovered at startup");
+        assert!(config.with_provider_override("ollama.auto").is_ok());
+        assert!(config.with_provider_override("ollama.missing").is_err());
+
+        let config = Config::load_with_overrides(
+            Some(config_path.to_str().unwrap()),
+            None,
+            Some("llama3.1:8b".to_string()),
+        )
+        .unwrap();
+        match config.get_default_provider_config().unwrap() {
+            crate::ProviderConfigRef::Ollama(ollama) => {
+                assert_eq!(ollama.model.as_deref(), Some("llama3.1:8b"));
+            }
+            other => panic!("Expected Ollama config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_gemini_coach_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+
+        let config_content = format!(r#"
+[providers]
+default_provider = "anthropic.default"
+coach = "gemini.default"
+
+[providers.anthropic.default]
+api_key = "test-key"
+model = "claude-3"
+
+[providers.gemini.default]
+api_key = "gemini-key"
+model = "gemini-2.5-pro"
+max_tokens = 8192
+{}"#, test_config_footer());
+
+        fs::write(&config_path, config_content).unwrap();
+
+        let config = Config::load(Some(config_path.to_str().unwrap())).unwrap();
+
+        let gemini = config.get_gemini_config("default").unwrap();
+        assert_eq!(gemini.model, "gemini-2.5-pro");
+        assert_eq!(gemini.max_tokens, Some(8192));
+        assert!(gemini.base_url.is_none());
+
+        let coach_config = config.for_coach().unwrap();
+        assert_eq!(coach_config.providers.default_provider.providers(), ["gemini.default"]);
+        assert!(matches!(
+            coach_config.get_default_provider_config().unwrap(),
+            crate::ProviderConfigRef::Gemini(_)
+        ));
+    }
+
+    #[test]
+    fn test_provider_failover_chains() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+
+        let config_content = format!(r#"
+[providers]
+default_provider = ["anthropic.default", "databricks.default", "openai.default"]
+coach = ["databricks.default", "anthropic.default"]
+player = "anthropic.default"
+
+[providers.anthropic.default]
+api_key = "test-key"
+model = "claude-3"
+
+[providers.databricks.default]
+host = "https://test.databricks.com"
+model = "test-model"
+
+[providers.openai.default]
+api_key = "test-key"
+model = "gpt-4o"
+{}"#, test_config_footer());
+
+        fs::write(&config_path, config_content).unwrap();
+
+        let config = Config::load(Some(config_path.to_str().unwrap())).unwrap();
+
+        let chain = &config.providers.default_provider;
+        assert_eq!(chain.primary(), "anthropic.default");
+        assert_eq!(chain.fallbacks(), ["databricks.default", "openai.default"]);
+        assert_eq!(config.get_coach_provider(), "databricks.default");
+        assert_eq!(config.get_player_provider(), "anthropic.default");
+        assert_eq!(config.get_planner_chain(), chain, "planner falls back to the default chain");
+
+        // Role configs carry the whole chain as their default
+        let coach_config = config.for_coach().unwrap();
+        assert_eq!(
+            coach_config.providers.default_provider.providers(),
+            ["databricks.default", "anthropic.default"]
+        );
+        let player_config = config.for_player().unwrap();
+        assert_eq!(player_config.providers.default_provider.providers(), ["anthropic.default"]);
+
+        // The chain round-trips through TOML in both forms
+        let saved = toml::to_string(&config).unwrap();
+        assert!(saved.contains(r#"default_provider = ["anthropic.default", "databricks.default", "openai.default"]"#));
+        assert!(saved.contains(r#"player = "anthropic.default""#));
+
+        // A --provider override replaces the chain with a single provider
+        let config = Config::load_with_overrides(
+            Some(config_path.to_str().unwrap()),
+            Some("openai".to_string()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(config.providers.default_provider.providers(), ["openai.default"]);
+    }
+
+    #[test]
+    fn test_provider_failover_chain_validation() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+
+        let config_content = format!(r#"
+[providers]
+default_provider = ["anthropic.default", "databricks.missing"]
+
+[providers.anthropic.default]
+api_key = "test-key"
+model = "claude-3"
+{}"#, test_config_footer());
+        fs::write(&config_path, config_content).unwrap();
+        let err = Config::load(Some(config_path.to_str().unwrap())).unwrap_err();
+        assert!(err.to_string().contains("databricks.missing"));
+
+        let config_content = format!(r#"
+[providers]
+default_provider = []
+
+[providers.anthropic.default]
+api_key = "test-key"
+model = "claude-3"
+{}"#, test_config_footer());
+        fs::write(&config_path, config_content).unwrap();
+        assert!(Config::load(Some(config_path.to_str().unwrap())).is_err());
+    }
+
+    #[test]
+    fn test_model_pricing() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+
+        let config_content = format!(r#"
+[providers]
+default_provider = "anthropic.default"
+
+[providers.anthropic.default]
+api_key = "test-key"
+model = "claude-sonnet-4-5"
+
+[pricing."claude-sonnet-4-5"]
+input = 2.0
+output = 10.0
+cache_read = 0.2
+
+[pricing."my-finetune"]
+input = 1.0
+output = 1.0
+{}"#, test_config_footer());
+        fs::write(&config_path, config_content).unwrap();
+        let config = Config::load(Some(config_path.to_str().unwrap())).unwrap();
+
+        // Configured prices override the built-in table
+        let sonnet = config.pricing_for("claude-sonnet-4-5").unwrap();
+        assert_eq!(sonnet.input, 2.0);
+        assert_eq!(sonnet.cache_write, 0.0);
+        assert_eq!(config.pricing_for("my-finetune").unwrap().output, 1.0);
+
+        // Built-in prices match on the longest contained model name
+        assert_eq!(config.pricing_for("claude-opus-4-5-20251101").unwrap().input, 5.0);
+        assert_eq!(config.pricing_for("databricks-claude-opus-4").unwrap().input, 15.0);
+        assert_eq!(config.pricing_for("gpt-4o-mini").unwrap().input, 0.15);
+
+        // Local models have no price
+        assert!(config.pricing_for("qwen2.5-coder:7b").is_none());
+
+        // 1M input + 1M output + 1M cache reads at the configured sonnet price
+        let cost = sonnet.cost(1_000_000, 1_000_000, 1_000_000, 0);
+        assert!((cost - 12.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_permissions_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+
+        let config_content = format!(r#"
+[providers]
+default_provider = "anthropic.default"
+
+[providers.anthropic.default]
+api_key = "test-key"
+model = "claude-sonnet-4-5"
+
+[permissions.tools]
+shell = "ask"
+webdriver_start = "deny"
+
+[permissions.shell]
+allow = ["cargo *", "ls*"]
+ask = ["git push*"]
+deny = ["rm -rf *"]
+
+[permissions.paths]
+outside_workspace = "ask"
+allow = ["/tmp/*"]
+{}"#, test_config_footer());
+        fs::write(&config_path, config_content).unwrap();
+        let config = Config::load(Some(config_path.to_str().unwrap())).unwrap();
+
+        let permissions = &config.permissions;
+        assert_eq!(permissions.tools["shell"], crate::PermissionMode::Ask);
+        assert_eq!(permissions.tools["webdriver_start"], crate::PermissionMode::Deny);
+        assert_eq!(permissions.shell.allow, vec!["cargo *", "ls*"]);
+        assert_eq!(permissions.shell.deny, vec!["rm -rf *"]);
+        assert_eq!(permissions.paths.outside_workspace, crate::PermissionMode::Ask);
+
+        // Everything is allowed when the section is missing
+        let default = Config::default().permissions;
+        assert!(default.tools.is_empty());
+        assert_eq!(default.paths.outside_workspace, crate::PermissionMode::Allow);
+    }
+
+    #[test]
+    fn test_mcp_servers_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+
+        let config_content = format!(r#"
+[providers]
+default_provider = "anthropic.default"
+
+[providers.anthropic.default]
+api_key = "test-key"
+model = "claude-sonnet-4-5"
+
+[mcp_servers.tickets]
+command = "npx"
+args = ["-y", "@acme/tickets-mcp"]
+env = {{ TICKETS_TOKEN = "secret" }}
+
+[mcp_servers.db]
+command = "/usr/local/bin/db-mcp"
+enabled = false
+timeout_seconds = 10
+{}"#, test_config_footer());
+        fs::write(&config_path, config_content).unwrap();
+        let config = Config::load(Some(config_path.to_str().unwrap())).unwrap();
+
+        let tickets = &config.mcp_servers["tickets"];
+        assert_eq!(tickets.command, "npx");
+        assert_eq!(tickets.args, vec!["-y", "@acme/tickets-mcp"]);
+        assert_eq!(tickets.env["TICKETS_TOKEN"], "secret");
+        assert!(tickets.enabled);
+        assert_eq!(tickets.timeout_seconds, 60);
+
+        let db = &config.mcp_servers["db"];
+        assert!(!db.enabled);
+        assert_eq!(db.timeout_seconds, 10);
+        assert!(Config::default().mcp_servers.is_empty());
+    }
+
+    #[test]
+    fn test_plugin_tools_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+
+        let config_content = format!(r#"
+[providers]
+default_provider = "anthropic.default"
+
+[providers.anthropic.default]
+api_key = "test-key"
+model = "claude-sonnet-4-5"
+
+[plugin_tools.ticket_lookup]
+description = "Look up a ticket by key"
+command = "~/bin/ticket-lookup"
+args = ["--json"]
+timeout_seconds = 30
+
+[plugin_tools.ticket_lookup.input_schema]
+type = "object"
+required = ["key"]
+
+[plugin_tools.ticket_lookup.input_schema.properties.key]
+type = "string"
+description = "Ticket key, e.g. OPS-123"
+
+[plugin_tools.deploy_status]
+description = "Show the current deploy status"
+command = "deploy-status"
+{}"#, test_config_footer());
+        fs::write(&config_path, config_content).unwrap();
+        let config = Config::load(Some(config_path.to_str().unwrap())).unwrap();
+
+        let lookup = &config.plugin_tools["ticket_lookup"];
+        assert_eq!(lookup.command, "~/bin/ticket-lookup");
+        assert_eq!(lookup.args, vec!["--json"]);
+        assert_eq!(lookup.timeout_seconds, 30);
+        assert!(lookup.enabled);
+        assert_eq!(lookup.input_schema["required"][0], "key");
+        assert_eq!(lookup.input_schema["properties"]["key"]["type"], "string");
+
+        // Schema and timeout defaults
+        let status = &config.plugin_tools["deploy_status"];
+        assert_eq!(status.input_schema, serde_json::json!({ "type": "object", "properties": {} }));
+        assert_eq!(status.timeout_seconds, 120);
+    }
+
+    #[test]
+    fn test_hooks_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("test_config.toml");
+
+        let config_content = format!(r#"
+[providers]
+default_provider = "anthropic.default"
+
+[providers.anthropic.default]
+api_key = "test-key"
+model = "claude-sonnet-4-5"
+
+[[hooks.pre_tool]]
+command = "./scripts/check-policy.sh"
+tools = ["shell", "background_process"]
+timeout_seconds = 10
+
+[[hooks.post_tool]]
+command = "cargo fmt"
+tools = ["str_replace", "write_file"]
+
+[[hooks.on_session_end]]
+command = "notify-send 'g3 finished'"
+{}"#, test_config_footer());
+        fs::write(&config_path, config_content).unwrap();
+        let config = Config::load(Some(config_path.to_str().unwrap())).unwrap();
+
+        let hooks = &config.hooks;
+        assert_eq!(hooks.pre_tool.len(), 1);
+        assert_eq!(hooks.pre_tool[0].command, "./scripts/check-policy.sh");
+        assert_eq!(hooks.pre_tool[0].tools, vec!["shell", "background_process"]);
+        assert_eq!(hooks.pre_tool[0].timeout_seconds, 10);
+        assert_eq!(hooks.post_tool[0].timeout_seconds, 60);
+        assert!(hooks.post_turn.is_empty());
+        assert!(hooks.on_compaction.is_empty());
+        assert!(hooks.on_session_end[0].tools.is_empty());
+    }
 }
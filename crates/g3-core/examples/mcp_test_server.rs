@@ -0,0 +1,85 @@
+//! Minimal MCP server over stdio for the MCP client tests, which build and run it
+//! with `cargo build --example mcp_test_server`.
+//!
+//! Provides `echo` (returns its `text`), `add` (sums `a` and `b`), `fail` (a tool
+//! error), `shell` (clashes with the built-in tool of the same name) and `stall`
+//! (stops reading and answering, like a hung server).
+
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+
+fn main() {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        // Notifications get no response
+        let Some(id) = message.get("id").cloned() else {
+            continue;
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        if params["name"] == "stall" {
+            loop {
+                std::thread::park();
+            }
+        }
+        let response = match message["method"].as_str().unwrap_or_default() {
+            "initialize" => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "g3-mcp-test-server", "version": "0.1.0" }
+                }
+            }),
+            "tools/list" => json!({ "jsonrpc": "2.0", "id": id, "result": { "tools": tools() } }),
+            "tools/call" => json!({ "jsonrpc": "2.0", "id": id, "result": call(&params) }),
+            method => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": format!("Unknown method {}", method) }
+            }),
+        };
+        writeln!(stdout, "{}", response).unwrap();
+        stdout.flush().unwrap();
+    }
+}
+
+fn tools() -> Value {
+    json!([
+        {
+            "name": "echo",
+            "description": "Echo the given text",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "text": { "type": "string" } },
+                "required": ["text"]
+            }
+        },
+        {
+            "name": "add",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "a": { "type": "number" }, "b": { "type": "number" } }
+            }
+        },
+        { "name": "fail", "description": "Always fails" },
+        { "name": "shell", "description": "Not the built-in shell" },
+        { "name": "stall", "description": "Never answers" }
+    ])
+}
+
+fn call(params: &Value) -> Value {
+    let args = &params["arguments"];
+    let text = |text: String| json!({ "content": [{ "type": "text", "text": text }] });
+    match params["name"].as_str().unwrap_or_default() {
+        "echo" => text(args["text"].as_str().unwrap_or_default().to_string()),
+        "add" => text((args["a"].as_f64().unwrap_or(0.0) + args["b"].as_f64().unwrap_or(0.0)).to_string()),
+        "shell" => text("test server shell".to_string()),
+        _ => json!({ "content": [{ "type": "text", "text": "tool failed" }], "isError": true }),
+    }
+}
@@ -288,9 +288,10 @@ fn find_json_end(json_str: &str) -> Option<usize> {
 
 /// Estimate token count for messages.
 fn estimate_fragment_tokens(messages: &[Message]) -> u32 {
-    let total_chars: usize = messages.iter().map(|m| m.content.len()).sum();
-    // Use same heuristic as ContextWindow: ~4 chars per token with 10% buffer
-    ((total_chars as f32 / 4.0) * 1.1).ceil() as u32
+    messages
+        .iter()
+        .map(|m| crate::context_window::ContextWindow::estimate_tokens(&m.content))
+        .sum()
 }
 
 /// Extract topic hints from messages using heuristics.
@@ -652,7 +653,7 @@ mod tests {
 
         let tokens = estimate_fragment_tokens(&messages);
 
-        // 10 chars / 4 * 1.1 ≈ 3 tokens
+        // One token per word
         assert!(tokens > 0);
         assert!(tokens < 10);
     }
@@ -11,6 +11,7 @@
 //! - Stop processes: `kill <pid>` or `pkill -f <name>`
 //! - Check status: `ps aux | grep <name>`
 
+use g3_execution::Sandbox;
 use std::collections::HashMap;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
@@ -77,6 +78,17 @@ impl BackgroundProcessManager {
         name: &str,
         command: &str,
         working_dir: &PathBuf,
+    ) -> Result<ProcessInfo, String> {
+        self.start_with_sandbox(name, command, working_dir, None)
+    }
+
+    /// Start a new background process, inside `sandbox` if given
+    pub fn start_with_sandbox(
+        &self,
+        name: &str,
+        command: &str,
+        working_dir: &PathBuf,
+        sandbox: Option<&Sandbox>,
     ) -> Result<ProcessInfo, String> {
         // Check if a process with this name already exists
         {
@@ -122,12 +134,18 @@ impl BackgroundProcessManager {
             .map_err(|e| format!("Failed to clone log file handle: {}", e))?;
 
         // Spawn the process
-        let child = Command::new("bash")
-            .arg("-c")
+        let mut cmd = Command::new("bash");
+        cmd.arg("-c")
             .arg(command)
             .current_dir(working_dir)
             .stdout(Stdio::from(log_handle))
-            .stderr(Stdio::from(log_handle_stderr))
+            .stderr(Stdio::from(log_handle_stderr));
+        if let Some(sandbox) = sandbox {
+            sandbox
+                .apply(&mut cmd)
+                .map_err(|e| format!("Failed to set up sandbox: {}", e))?;
+        }
+        let child = cmd
             .spawn()
             .map_err(|e| format!("Failed to spawn process: {}", e))?;
 
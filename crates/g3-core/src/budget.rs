@@ -0,0 +1,315 @@
+//! Run budgets.
+//!
+//! Limits on spend, total tokens and wall-clock time for a run. The agent checks
+//! its budget before every LLM request; crossing 80% of a limit produces a single
+//! warning, and reaching a limit stops the run with a `BudgetExceeded` error. The
+//! wall-time limit is also a hard deadline: a stream or tool call still running when
+//! it passes is cancelled.
+
+use g3_config::{AgentConfig, Config};
+use std::time::{Duration, Instant};
+
+use crate::cost_ledger::CostLedger;
+
+/// Fraction of a budget at which a warning is shown
+pub const BUDGET_WARNING_THRESHOLD: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetKind {
+    Cost,
+    Tokens,
+    WallTime,
+}
+
+/// A budget that has been used up. Returned (via anyhow) from the agent so that
+/// callers can tell a budget stop apart from a failure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetExceeded {
+    pub kind: BudgetKind,
+    pub used: f64,
+    pub limit: f64,
+}
+
+impl std::fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Budget exhausted: {} of {} {}",
+            format_amount(self.kind, self.used),
+            format_amount(self.kind, self.limit),
+            budget_name(self.kind)
+        )
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
+/// Whether an error is a budget stop rather than a failure
+pub fn is_budget_exceeded(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<BudgetExceeded>().is_some()
+}
+
+fn budget_name(kind: BudgetKind) -> &'static str {
+    match kind {
+        BudgetKind::Cost => "cost budget",
+        BudgetKind::Tokens => "token budget",
+        BudgetKind::WallTime => "wall-time budget",
+    }
+}
+
+fn format_amount(kind: BudgetKind, amount: f64) -> String {
+    match kind {
+        BudgetKind::Cost => format!("${:.2}", amount),
+        BudgetKind::Tokens => format!("{}", amount as u64),
+        BudgetKind::WallTime => format!("{}s", amount as u64),
+    }
+}
+
+/// Result of a budget check
+#[derive(Debug, Clone, PartialEq)]
+pub enum BudgetStatus {
+    WithinBudget,
+    /// A budget crossed the warning threshold for the first time
+    Warning(String),
+    Exceeded(BudgetExceeded),
+}
+
+/// Tracks a run against its budget limits
+#[derive(Debug, Clone)]
+pub struct BudgetTracker {
+    max_cost: Option<f64>,
+    max_tokens_total: Option<u64>,
+    max_wall_time: Option<Duration>,
+    started: Instant,
+    warned: Vec<BudgetKind>,
+}
+
+impl BudgetTracker {
+    pub fn new(config: &AgentConfig) -> Self {
+        Self {
+            max_cost: config.max_cost,
+            max_tokens_total: config.max_tokens_total,
+            max_wall_time: config.max_wall_time_seconds.map(Duration::from_secs),
+            started: Instant::now(),
+            warned: Vec::new(),
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.max_cost.is_none() && self.max_tokens_total.is_none() && self.max_wall_time.is_none()
+    }
+
+    /// When the wall-time budget started counting
+    pub fn started(&self) -> Instant {
+        self.started
+    }
+
+    /// Count wall time from an earlier start, e.g. the start of a multi-agent run
+    pub fn set_started(&mut self, started: Instant) {
+        self.started = started;
+    }
+
+    /// Time left before the wall-time budget runs out, if there is one
+    pub fn remaining_wall_time(&self) -> Option<Duration> {
+        self.max_wall_time
+            .map(|limit| limit.saturating_sub(self.started.elapsed()))
+    }
+
+    /// The error for a run cut off at its wall-time deadline
+    pub fn wall_time_exceeded(&self) -> Option<BudgetExceeded> {
+        self.max_wall_time.map(|limit| BudgetExceeded {
+            kind: BudgetKind::WallTime,
+            used: self.started.elapsed().as_secs_f64(),
+            limit: limit.as_secs_f64(),
+        })
+    }
+
+    /// Check the ledger and elapsed time against the limits
+    pub fn check(&mut self, ledger: &CostLedger) -> BudgetStatus {
+        let usage = [
+            (BudgetKind::Cost, ledger.total_cost(), self.max_cost),
+            (
+                BudgetKind::Tokens,
+                ledger.total_tokens() as f64,
+                self.max_tokens_total.map(|t| t as f64),
+            ),
+            (
+                BudgetKind::WallTime,
+                self.started.elapsed().as_secs_f64(),
+                self.max_wall_time.map(|d| d.as_secs_f64()),
+            ),
+        ];
+
+        let mut warnings = Vec::new();
+        for (kind, used, limit) in usage {
+            let Some(limit) = limit else {
+                continue;
+            };
+            if used >= limit {
+                return BudgetStatus::Exceeded(BudgetExceeded { kind, used, limit });
+            }
+            if used >= limit * BUDGET_WARNING_THRESHOLD && !self.warned.contains(&kind) {
+                self.warned.push(kind);
+                warnings.push(format!(
+                    "{} of {} {} used",
+                    format_amount(kind, used),
+                    format_amount(kind, limit),
+                    budget_name(kind)
+                ));
+            }
+        }
+
+        if warnings.is_empty() {
+            BudgetStatus::WithinBudget
+        } else {
+            BudgetStatus::Warning(warnings.join(", "))
+        }
+    }
+}
+
+/// A warning when `max_cost` is set but `model` has no price, since its usage would
+/// never count towards the cost budget
+pub fn unpriced_cost_budget_warning(config: &Config, model: &str) -> Option<String> {
+    let max_cost = config.agent.max_cost?;
+    if config.pricing_for(model).is_some() {
+        return None;
+    }
+    Some(format!(
+        "max_cost is ${:.2} but model {} has no pricing, so its spend is not counted. Add a [pricing.\"{}\"] entry to enforce the cost budget.",
+        max_cost, model, model
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost_ledger::AgentRole;
+    use g3_config::ModelPricing;
+    use g3_providers::Usage;
+
+    fn ledger_with(input_tokens: u32) -> CostLedger {
+        let mut ledger = CostLedger::new();
+        ledger.record(
+            "anthropic.default",
+            "claude-sonnet-4-5",
+            AgentRole::Player,
+            &Usage {
+                prompt_tokens: input_tokens,
+                completion_tokens: 0,
+                total_tokens: input_tokens,
+                ..Default::default()
+            },
+            Some(ModelPricing {
+                input: 10.0,
+                output: 10.0,
+                ..Default::default()
+            }),
+        );
+        ledger
+    }
+
+    #[test]
+    fn test_unlimited_budget() {
+        let mut tracker = BudgetTracker::new(&AgentConfig::default());
+        assert!(tracker.is_unlimited());
+        assert_eq!(tracker.check(&ledger_with(10_000_000)), BudgetStatus::WithinBudget);
+    }
+
+    #[test]
+    fn test_cost_budget_warns_once_then_stops() {
+        let config = AgentConfig {
+            max_cost: Some(1.0),
+            ..Default::default()
+        };
+        let mut tracker = BudgetTracker::new(&config);
+
+        // $0.50 spent
+        assert_eq!(tracker.check(&ledger_with(50_000)), BudgetStatus::WithinBudget);
+
+        // $0.85 spent: warn, but only the first time
+        match tracker.check(&ledger_with(85_000)) {
+            BudgetStatus::Warning(msg) => assert_eq!(msg, "$0.85 of $1.00 cost budget used"),
+            other => panic!("expected warning, got {:?}", other),
+        }
+        assert_eq!(tracker.check(&ledger_with(85_000)), BudgetStatus::WithinBudget);
+
+        // $1.00 spent
+        match tracker.check(&ledger_with(100_000)) {
+            BudgetStatus::Exceeded(exceeded) => {
+                assert_eq!(exceeded.kind, BudgetKind::Cost);
+                assert_eq!(
+                    exceeded.to_string(),
+                    "Budget exhausted: $1.00 of $1.00 cost budget"
+                );
+            }
+            other => panic!("expected exceeded, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_token_and_wall_time_budgets() {
+        let config = AgentConfig {
+            max_tokens_total: Some(1000),
+            max_wall_time_seconds: Some(60),
+            ..Default::default()
+        };
+        let mut tracker = BudgetTracker::new(&config);
+        assert!(matches!(
+            tracker.check(&ledger_with(1500)),
+            BudgetStatus::Exceeded(BudgetExceeded {
+                kind: BudgetKind::Tokens,
+                ..
+            })
+        ));
+
+        tracker.set_started(Instant::now() - Duration::from_secs(61));
+        match tracker.check(&CostLedger::new()) {
+            BudgetStatus::Exceeded(exceeded) => {
+                assert_eq!(exceeded.kind, BudgetKind::WallTime);
+                assert!(exceeded.to_string().ends_with("of 60s wall-time budget"));
+            }
+            other => panic!("expected exceeded, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_wall_time_deadline() {
+        let mut tracker = BudgetTracker::new(&AgentConfig::default());
+        assert_eq!(tracker.remaining_wall_time(), None);
+        assert_eq!(tracker.wall_time_exceeded(), None);
+
+        let config = AgentConfig {
+            max_wall_time_seconds: Some(60),
+            ..Default::default()
+        };
+        tracker = BudgetTracker::new(&config);
+        assert!(tracker.remaining_wall_time().unwrap() > Duration::from_secs(59));
+
+        tracker.set_started(Instant::now() - Duration::from_secs(61));
+        assert_eq!(tracker.remaining_wall_time(), Some(Duration::ZERO));
+        assert_eq!(tracker.wall_time_exceeded().unwrap().kind, BudgetKind::WallTime);
+    }
+
+    #[test]
+    fn test_unpriced_cost_budget_warning() {
+        let mut config = Config::default();
+        assert_eq!(unpriced_cost_budget_warning(&config, "my-local-model"), None);
+
+        config.agent.max_cost = Some(5.0);
+        assert_eq!(unpriced_cost_budget_warning(&config, "claude-sonnet-4-5"), None);
+        let warning = unpriced_cost_budget_warning(&config, "my-local-model").unwrap();
+        assert!(warning.contains("my-local-model has no pricing"), "{}", warning);
+    }
+
+    #[test]
+    fn test_budget_error_is_detectable() {
+        let error: anyhow::Error = BudgetExceeded {
+            kind: BudgetKind::Tokens,
+            used: 2000.0,
+            limit: 1000.0,
+        }
+        .into();
+        assert!(is_budget_exceeded(&error));
+        assert!(!is_budget_exceeded(&anyhow::anyhow!("Budget exhausted")));
+    }
+}
@@ -0,0 +1,294 @@
+//! Builder for embedding an [`Agent`] in another program.
+//!
+//! The `Agent::new*` constructors used by the CLI are all shorthands for an
+//! `AgentBuilder`. Embedders can set everything the agent is built from:
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! use g3_core::{AgentBuilder, NullUiWriter};
+//!
+//! let config = g3_config::Config::load(None)?;
+//! let mut agent = AgentBuilder::new(config, NullUiWriter)
+//!     .tools(["read_file", "code_search"])
+//!     .working_dir("/path/to/project")
+//!     .build()
+//!     .await?;
+//! let result = agent.execute_task("Summarize the README", None, false).await?;
+//! println!("{}", result.response);
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::Result;
+use g3_config::Config;
+use g3_providers::{Message, MessageRole, ProviderRegistry};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tracing::warn;
+
+use crate::prompts::{get_system_prompt_for_native, SYSTEM_PROMPT_FOR_NON_NATIVE_TOOL_USE};
+use crate::ui_writer::UiWriter;
+use crate::{
+    background_process, budget, checkpoints, mcp, paths, provider_registration, tokenizer,
+    tool_definitions, Agent, AgentRole, ContextWindow, CostLedger, FileFingerprints,
+    SYSTEM_PROMPT_MARKER,
+};
+
+/// Configures and creates an [`Agent`]
+pub struct AgentBuilder<W: UiWriter> {
+    config: Config,
+    ui_writer: W,
+    providers: Option<ProviderRegistry>,
+    system_prompt: Option<String>,
+    project_context: Option<String>,
+    working_dir: Option<PathBuf>,
+    tools: Option<Vec<String>>,
+    autonomous: bool,
+    quiet: bool,
+}
+
+impl<W: UiWriter> AgentBuilder<W> {
+    /// Start from a config and the `UiWriter` that receives everything the agent reports
+    pub fn new(config: Config, ui_writer: W) -> Self {
+        Self {
+            config,
+            ui_writer,
+            providers: None,
+            system_prompt: None,
+            project_context: None,
+            working_dir: None,
+            tools: None,
+            autonomous: false,
+            quiet: false,
+        }
+    }
+
+    /// Use an already-populated provider registry instead of registering the
+    /// providers named in the config. Its default provider is the one used.
+    pub fn providers(mut self, providers: ProviderRegistry) -> Self {
+        self.providers = Some(providers);
+        self
+    }
+
+    /// Replace the default system prompt. It must keep g3's tool-use instructions;
+    /// [`crate::get_agent_system_prompt`] builds one from a custom identity.
+    pub fn system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Extra context (README, AGENTS.md, memory) added as a second system message
+    pub fn project_context(mut self, context: impl Into<String>) -> Self {
+        self.project_context = Some(context.into());
+        self
+    }
+
+    /// Directory that tools run in and resolve relative paths against; the process
+    /// working directory when unset
+    pub fn working_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(dir.into());
+        self
+    }
+
+    /// Only offer these tools to the model (built-in, plugin or MCP tool names).
+    /// Calls to any other tool are refused. All tools are offered when unset.
+    pub fn tools<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tools = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Autonomous mode: registers the coach and player providers as well
+    pub fn autonomous(mut self, autonomous: bool) -> Self {
+        self.autonomous = autonomous;
+        self
+    }
+
+    /// Suppress session logs
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Register providers, start MCP servers and create the agent
+    pub async fn build(self) -> Result<Agent<W>> {
+        let AgentBuilder {
+            config,
+            ui_writer,
+            providers,
+            system_prompt,
+            project_context,
+            working_dir,
+            tools,
+            autonomous: is_autonomous,
+            quiet,
+        } = self;
+
+        if let Some(prompt) = &system_prompt {
+            if !prompt.contains(SYSTEM_PROMPT_MARKER) {
+                anyhow::bail!(
+                    "Custom system prompt is missing g3's tool-use instructions \
+                     (use get_agent_system_prompt to build one)"
+                );
+            }
+        }
+        let working_dir = match working_dir {
+            Some(dir) if !dir.is_dir() => {
+                anyhow::bail!("Working directory {} does not exist", dir.display());
+            }
+            Some(dir) => Some(dir.to_string_lossy().into_owned()),
+            None => None,
+        };
+
+        let providers = match providers {
+            Some(providers) => providers,
+            None => {
+                let providers_to_register =
+                    provider_registration::determine_providers_to_register(&config, is_autonomous);
+                provider_registration::register_providers(&config, &providers_to_register).await?
+            }
+        };
+
+        // Determine context window size based on active provider
+        let mut context_warnings = Vec::new();
+        let context_length =
+            Agent::<W>::get_configured_context_length(&config, &providers, &mut context_warnings)?;
+        let mut context_window = ContextWindow::new(context_length);
+
+        // Surface any context warnings to the user via UI
+        for warning in context_warnings {
+            ui_writer.print_context_status(&format!("⚠️ {}", warning));
+        }
+
+        // Add system prompt as the FIRST message (before README)
+        // This ensures the agent always has proper tool usage instructions
+        let provider = providers.get(None)?;
+        let provider_has_native_tool_calling = provider.has_native_tool_calling();
+        context_window.set_tokenizer(tokenizer::for_provider(provider.name(), provider.model()));
+
+        let system_prompt = if let Some(custom_prompt) = system_prompt {
+            // Use custom system prompt (for agent mode)
+            custom_prompt
+        } else {
+            // Use default system prompt based on provider capabilities
+            if provider_has_native_tool_calling {
+                // For native tool calling providers, use a more explicit system prompt
+                get_system_prompt_for_native()
+            } else {
+                // For non-native providers (embedded models), use JSON format instructions
+                SYSTEM_PROMPT_FOR_NON_NATIVE_TOOL_USE.to_string()
+            }
+        };
+
+        let system_message = Message::new(MessageRole::System, system_prompt);
+        context_window.add_message(system_message);
+
+        // If README content is provided, add it as a second system message (after the main system prompt)
+        if let Some(readme) = project_context {
+            let readme_message = Message::new(MessageRole::System, readme);
+            context_window.add_message(readme_message);
+        }
+
+        // NOTE: TODO lists are now session-scoped and stored in .g3/sessions/<session_id>/todo.g3.md
+        // We don't load any TODO at initialization since we don't have a session_id yet.
+        // The agent will use todo_read to load the TODO once a session is established.
+
+        // Initialize computer controller if enabled
+        let computer_controller = if config.computer_control.enabled {
+            match g3_computer_control::create_controller() {
+                Ok(controller) => Some(controller),
+                Err(e) => {
+                    warn!("Failed to initialize computer control: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Start MCP servers; their tools must not shadow built-in or plugin ones
+        let mut builtin_tools: Vec<String> = tool_definitions::create_tool_definitions(
+            tool_definitions::ToolConfig::new(true, true),
+        )
+        .into_iter()
+        .map(|tool| tool.name)
+        .collect();
+        builtin_tools.extend(
+            tool_definitions::create_plugin_tool_definitions(&config.plugin_tools)
+                .into_iter()
+                .map(|tool| tool.name),
+        );
+        let (mcp_servers, mcp_warnings) =
+            mcp::McpServers::start(&config.mcp_servers, &builtin_tools).await;
+        for warning in mcp_warnings {
+            ui_writer.print_context_status(&format!("⚠️ {}", warning));
+        }
+
+        // An explicit tool set may only name tools that exist
+        let allowed_tools = match tools {
+            Some(names) => {
+                let known: HashSet<String> = builtin_tools
+                    .into_iter()
+                    .chain(mcp_servers.tool_definitions().into_iter().map(|tool| tool.name))
+                    .collect();
+                if let Some(unknown) = names.iter().find(|name| !known.contains(*name)) {
+                    anyhow::bail!("Unknown tool '{}' in the agent's tool set", unknown);
+                }
+                Some(names.into_iter().collect())
+            }
+            None => None,
+        };
+
+        let budget = budget::BudgetTracker::new(&config.agent);
+
+        let agent = Agent {
+            providers,
+            context_window,
+            auto_compact: config.agent.auto_compact,
+            pending_90_compaction: false,
+            thinning_events: Vec::new(),
+            compaction_events: Vec::new(),
+            first_token_times: Vec::new(),
+            config,
+            session_id: None,
+            tool_call_metrics: Vec::new(),
+            ui_writer,
+            // TODO content starts empty - session-scoped TODOs are loaded via todo_read
+            todo_content: std::sync::Arc::new(tokio::sync::RwLock::new(String::new())),
+            is_autonomous,
+            quiet,
+            computer_controller,
+            webdriver_session: std::sync::Arc::new(tokio::sync::RwLock::new(None)),
+            webdriver_process: std::sync::Arc::new(tokio::sync::RwLock::new(None)),
+            tool_call_count: 0,
+            tool_calls_this_turn: Vec::new(),
+            requirements_sha: None,
+            working_dir,
+            allowed_tools,
+            background_process_manager: std::sync::Arc::new(
+                background_process::BackgroundProcessManager::new(
+                    paths::get_background_processes_dir(),
+                ),
+            ),
+            shell_session: std::sync::Arc::new(tokio::sync::Mutex::new(None)),
+            mcp_servers: std::sync::Arc::new(mcp_servers),
+            turn: checkpoints::Turn::default(),
+            pending_undo_note: None,
+            pending_images: Vec::new(),
+            is_agent_mode: false,
+            agent_name: None,
+            auto_memory: false,
+            acd_enabled: false,
+            cost_ledger: std::sync::Arc::new(std::sync::Mutex::new(CostLedger::new())),
+            agent_role: AgentRole::default(),
+            budget,
+            file_fingerprints: std::sync::Arc::new(std::sync::Mutex::new(FileFingerprints::new())),
+            permission_log: std::sync::Arc::new(std::sync::Mutex::new(Vec::new())),
+        };
+        agent.warn_if_cost_budget_unpriced();
+        Ok(agent)
+    }
+}
@@ -0,0 +1,151 @@
+//! File checkpoints for undoing a turn's edits.
+//!
+//! Before `write_file`, `str_replace` or `apply_patch` changes a file, its current
+//! content is copied into `.g3/sessions/<session_id>/checkpoints/turn_<n>/`, once per
+//! file and turn.
+//! Restoring a turn puts every file it edited back the way it was before the turn,
+//! and deletes the files it created.
+//!
+//! Each turn directory holds `checkpoint.json` (what was saved) and the saved copies,
+//! named `file_<index>`.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::paths::get_checkpoints_dir;
+
+const MANIFEST_FILE: &str = "checkpoint.json";
+
+/// The user turn that tool calls currently belong to
+#[derive(Debug, Clone, Default)]
+pub struct Turn {
+    /// Turn number within the session, starting at 1 (0 for tool calls made outside a task)
+    pub number: u32,
+    /// The user's request, for listing checkpoints
+    pub prompt: String,
+}
+
+/// The files a turn edited and how to put them back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub turn: u32,
+    /// When the turn's first edit happened
+    pub created_at: String,
+    pub prompt: String,
+    pub files: Vec<FileSnapshot>,
+}
+
+/// A file's state before its first edit in a turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    /// Absolute path of the edited file
+    pub path: PathBuf,
+    /// Name of the saved copy in the turn's directory; `None` if the file didn't exist
+    pub saved_as: Option<String>,
+}
+
+/// Save `path` as it is now, unless this turn already saved it
+pub fn snapshot_file(session_id: &str, turn: &Turn, path: &Path) -> Result<()> {
+    let path = std::path::absolute(path)?;
+    let turn_dir = turn_dir(session_id, turn.number);
+    let mut checkpoint = match load_checkpoint(&turn_dir)? {
+        Some(checkpoint) => checkpoint,
+        None => Checkpoint {
+            turn: turn.number,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            prompt: turn.prompt.clone(),
+            files: Vec::new(),
+        },
+    };
+    if checkpoint.files.iter().any(|file| file.path == path) {
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(&turn_dir)?;
+    let saved_as = if path.is_file() {
+        let name = format!("file_{}", checkpoint.files.len());
+        std::fs::copy(&path, turn_dir.join(&name))
+            .with_context(|| format!("Failed to save {} before editing it", path.display()))?;
+        Some(name)
+    } else {
+        None
+    };
+    debug!("Checkpointed {} for turn {}", path.display(), turn.number);
+    checkpoint.files.push(FileSnapshot { path, saved_as });
+    std::fs::write(
+        turn_dir.join(MANIFEST_FILE),
+        serde_json::to_string_pretty(&checkpoint)?,
+    )?;
+    Ok(())
+}
+
+/// All of the session's checkpoints, oldest turn first
+pub fn list_checkpoints(session_id: &str) -> Result<Vec<Checkpoint>> {
+    let dir = get_checkpoints_dir(session_id);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut checkpoints = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        if let Some(checkpoint) = load_checkpoint(&entry?.path())? {
+            checkpoints.push(checkpoint);
+        }
+    }
+    checkpoints.sort_by_key(|checkpoint| checkpoint.turn);
+    Ok(checkpoints)
+}
+
+/// The highest turn number with a checkpoint (0 if there are none)
+pub fn latest_turn(session_id: &str) -> u32 {
+    list_checkpoints(session_id)
+        .ok()
+        .and_then(|checkpoints| checkpoints.last().map(|checkpoint| checkpoint.turn))
+        .unwrap_or(0)
+}
+
+/// Put back the files `turn` edited and remove its checkpoint.
+/// Returns the checkpoint that was restored, or `None` if the turn edited nothing.
+pub fn restore_checkpoint(session_id: &str, turn: u32) -> Result<Option<Checkpoint>> {
+    let turn_dir = turn_dir(session_id, turn);
+    let Some(checkpoint) = load_checkpoint(&turn_dir)? else {
+        return Ok(None);
+    };
+
+    for file in &checkpoint.files {
+        match &file.saved_as {
+            Some(name) => {
+                if let Some(parent) = file.path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::copy(turn_dir.join(name), &file.path)
+                    .with_context(|| format!("Failed to restore {}", file.path.display()))?;
+            }
+            None => {
+                if file.path.exists() {
+                    std::fs::remove_file(&file.path)
+                        .with_context(|| format!("Failed to remove {}", file.path.display()))?;
+                }
+            }
+        }
+    }
+    std::fs::remove_dir_all(&turn_dir)?;
+    debug!("Restored {} file(s) from turn {}", checkpoint.files.len(), turn);
+    Ok(Some(checkpoint))
+}
+
+fn turn_dir(session_id: &str, turn: u32) -> PathBuf {
+    get_checkpoints_dir(session_id).join(format!("turn_{}", turn))
+}
+
+fn load_checkpoint(turn_dir: &Path) -> Result<Option<Checkpoint>> {
+    let manifest = turn_dir.join(MANIFEST_FILE);
+    if !manifest.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&manifest)?;
+    let checkpoint = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid checkpoint manifest {}", manifest.display()))?;
+    Ok(Some(checkpoint))
+}
@@ -74,6 +74,17 @@ pub struct Match {
     pub context: Option<String>,
 }
 
+/// A definition found by [`TreeSitterSearcher::find_symbol`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolRange {
+    /// tree-sitter node kind, e.g. `function_item` or `class_definition`
+    pub kind: String,
+    /// First line (1-indexed), including leading doc comments, attributes and decorators
+    pub start_line: usize,
+    /// Last line (1-indexed, inclusive)
+    pub end_line: usize,
+}
+
 /// Main entry point for code search
 pub async fn execute_code_search(request: CodeSearchRequest) -> Result<CodeSearchResponse> {
     let mut searcher = TreeSitterSearcher::new()?;
@@ -1,12 +1,67 @@
-use super::{CodeSearchRequest, CodeSearchResponse, Match, SearchResult, SearchSpec};
+use super::{CodeSearchRequest, CodeSearchResponse, Match, SearchResult, SearchSpec, SymbolRange};
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use streaming_iterator::StreamingIterator;
-use tree_sitter::{Language, Parser, Query, QueryCursor};
+use tree_sitter::{Language, Node, Parser, Query, QueryCursor};
 use walkdir::WalkDir;
 
+/// Languages whose definitions `find_symbol` understands
+const SYMBOL_LANGUAGES: &[&str] = &["rust", "python", "javascript", "typescript", "go", "java", "c", "cpp"];
+
+/// Node kinds that define a named symbol
+const DEFINITION_KINDS: &[&str] = &[
+    // Rust
+    "function_item",
+    "function_signature_item",
+    "struct_item",
+    "enum_item",
+    "union_item",
+    "trait_item",
+    "mod_item",
+    "type_item",
+    "const_item",
+    "static_item",
+    "macro_definition",
+    // Python (and C/C++ functions)
+    "function_definition",
+    "class_definition",
+    // JavaScript / TypeScript
+    "function_declaration",
+    "generator_function_declaration",
+    "class_declaration",
+    "abstract_class_declaration",
+    "method_definition",
+    "interface_declaration",
+    "type_alias_declaration",
+    "enum_declaration",
+    "variable_declarator",
+    // Go
+    "method_declaration",
+    "type_spec",
+    // Java
+    "constructor_declaration",
+    "record_declaration",
+    // C / C++
+    "struct_specifier",
+    "class_specifier",
+    "enum_specifier",
+    "union_specifier",
+];
+
+/// Nodes that wrap a definition and belong to its text
+const DEFINITION_WRAPPERS: &[&str] = &[
+    "decorated_definition",
+    "export_statement",
+    "lexical_declaration",
+    "variable_declaration",
+    "template_declaration",
+];
+
+/// Nodes directly above a definition that belong to it
+const LEADING_KINDS: &[&str] = &["line_comment", "block_comment", "comment", "attribute_item"];
+
 pub struct TreeSitterSearcher {
     parsers: HashMap<String, Parser>,
     languages: HashMap<String, Language>,
@@ -324,6 +379,158 @@ impl TreeSitterSearcher {
         })
     }
 
+    /// Find the definitions named `symbol` in a source file. `Type::method` (or
+    /// `Type.method`) only matches definitions directly inside `Type`: an impl block,
+    /// class, trait or module.
+    pub fn find_symbol(
+        &mut self,
+        path: &Path,
+        source: &str,
+        symbol: &str,
+    ) -> Result<Vec<SymbolRange>> {
+        let language = Self::language_for_path(path)
+            .ok_or_else(|| anyhow!("No tree-sitter grammar for {}", path.display()))?;
+        if !SYMBOL_LANGUAGES.contains(&language) {
+            return Err(anyhow!("Symbol lookup is not supported for {} files", language));
+        }
+        let parser = self
+            .parsers
+            .get_mut(language)
+            .ok_or_else(|| anyhow!("Unsupported language: {}", language))?;
+        let tree = parser
+            .parse(source, None)
+            .ok_or_else(|| anyhow!("Failed to parse {}", path.display()))?;
+
+        let (parent, name) = match symbol.rsplit_once("::").or_else(|| symbol.rsplit_once('.')) {
+            Some((parent, name)) => (Some(parent.rsplit("::").next().unwrap_or(parent)), name),
+            None => (None, symbol),
+        };
+        let mut found = Vec::new();
+        Self::collect_definitions(tree.root_node(), source, parent, name, &mut Vec::new(), &mut found);
+        Ok(found)
+    }
+
+    /// The code search language for a file, from its extension
+    pub fn language_for_path(path: &Path) -> Option<&'static str> {
+        [
+            "rust", "python", "javascript", "typescript", "go", "java", "c", "cpp", "haskell",
+            "scheme", "racket",
+        ]
+        .into_iter()
+        .find(|language| Self::is_language_file(path, language))
+    }
+
+    fn collect_definitions(
+        node: Node,
+        source: &str,
+        parent: Option<&str>,
+        name: &str,
+        containers: &mut Vec<String>,
+        found: &mut Vec<SymbolRange>,
+    ) {
+        let definition_name = Self::definition_name(node, source);
+        let in_parent = match parent {
+            Some(parent) => containers.last().map(String::as_str) == Some(parent),
+            None => true,
+        };
+        if in_parent && definition_name.as_deref() == Some(name) {
+            found.push(SymbolRange {
+                kind: node.kind().to_string(),
+                start_line: Self::definition_start_row(node) + 1,
+                end_line: Self::last_row(node) + 1,
+            });
+        }
+
+        // impl blocks are containers named after their type
+        let container = if node.kind() == "impl_item" {
+            node.child_by_field_name("type").map(|ty| {
+                let text = &source[ty.byte_range()];
+                text.split('<').next().unwrap_or(text).trim().to_string()
+            })
+        } else {
+            definition_name
+        };
+        let pushed = container.is_some();
+        if let Some(container) = container {
+            containers.push(container);
+        }
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            Self::collect_definitions(child, source, parent, name, containers, found);
+        }
+        if pushed {
+            containers.pop();
+        }
+    }
+
+    /// Name of the symbol a node defines, if it is a definition
+    fn definition_name(node: Node, source: &str) -> Option<String> {
+        let kind = node.kind();
+        if !DEFINITION_KINDS.contains(&kind) {
+            return None;
+        }
+        // `struct foo x;` uses a struct; only the one with a body defines it
+        if kind.ends_with("_specifier") && node.child_by_field_name("body").is_none() {
+            return None;
+        }
+        // `const handler = () => ...` defines a function; other variables don't count
+        if kind == "variable_declarator" {
+            let value = node.child_by_field_name("value")?;
+            if !matches!(
+                value.kind(),
+                "arrow_function" | "function_expression" | "function" | "class" | "generator_function"
+            ) {
+                return None;
+            }
+        }
+
+        let name_node = match node.child_by_field_name("name") {
+            Some(name_node) => name_node,
+            None => {
+                // C/C++ functions: the name is at the bottom of the declarator chain
+                let mut declarator = node.child_by_field_name("declarator")?;
+                while let Some(inner) = declarator.child_by_field_name("declarator") {
+                    declarator = inner;
+                }
+                declarator
+            }
+        };
+        let text = &source[name_node.byte_range()];
+        Some(text.rsplit("::").next().unwrap_or(text).to_string())
+    }
+
+    /// Row where a definition's text starts: wrappers such as decorators and
+    /// `export`, then adjacent doc comments and attributes
+    fn definition_start_row(node: Node) -> usize {
+        let mut start = node;
+        while let Some(parent) = start.parent() {
+            if !DEFINITION_WRAPPERS.contains(&parent.kind()) {
+                break;
+            }
+            start = parent;
+        }
+        while let Some(previous) = start.prev_sibling() {
+            if !LEADING_KINDS.contains(&previous.kind())
+                || Self::last_row(previous) + 1 < start.start_position().row
+            {
+                break;
+            }
+            start = previous;
+        }
+        start.start_position().row
+    }
+
+    /// Last row a node has text on; one ending at column 0 ends with the previous
+    /// line's newline
+    fn last_row(node: Node) -> usize {
+        let end = node.end_position();
+        if end.column == 0 && end.row > node.start_position().row {
+            end.row - 1
+        } else {
+            end.row
+        }
+    }
+
     fn is_language_file(path: &Path, language: &str) -> bool {
         let ext = path.extension().and_then(|e| e.to_str());
         match (language, ext) {
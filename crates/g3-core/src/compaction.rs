@@ -24,6 +24,8 @@ pub struct CompactionResult {
     pub chars_saved: usize,
     /// Error message (if failed)
     pub error: Option<String>,
+    /// Usage reported for the summary request (if successful)
+    pub usage: Option<g3_providers::Usage>,
 }
 
 impl CompactionResult {
@@ -32,14 +34,21 @@ impl CompactionResult {
             success: true,
             chars_saved,
             error: None,
+            usage: None,
         }
     }
 
+    pub fn with_usage(mut self, usage: g3_providers::Usage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
     pub fn failure(error: String) -> Self {
         Self {
             success: false,
             chars_saved: 0,
             error: Some(error),
+            usage: None,
         }
     }
 }
@@ -197,7 +206,7 @@ pub async fn perform_compaction<W: UiWriter>(
                 summary_response.content,
                 compaction_config.latest_user_msg,
             );
-            Ok(CompactionResult::success(chars_saved))
+            Ok(CompactionResult::success(chars_saved).with_usage(summary_response.usage))
         }
         Err(e) => {
             error!("Failed to create summary: {}", e);
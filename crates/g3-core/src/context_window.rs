@@ -8,11 +8,23 @@
 
 use g3_providers::{Message, MessageRole, Usage};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use tracing::{debug, warn};
 
 use crate::paths::get_thinned_dir;
+use crate::tokenizer::{self, TokenCounter, Tokenizer};
 use crate::ToolCall;
 
+/// Minimum estimated context growth between two provider reports for the growth to be
+/// used to calibrate the tokenizer (smaller samples are dominated by noise)
+const MIN_CALIBRATION_SAMPLE_TOKENS: u32 = 500;
+
+/// How far each calibration sample moves the correction factor towards its own ratio
+const CALIBRATION_RATE: f32 = 0.3;
+
+/// Bounds for the tokenizer correction factor
+const CALIBRATION_RANGE: (f32, f32) = (0.5, 2.5);
+
 /// Scope for context thinning operations
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ThinScope {
@@ -66,6 +78,12 @@ pub struct ContextWindow {
     pub cumulative_tokens: u32, // Track cumulative tokens across all interactions
     pub conversation_history: Vec<Message>,
     pub last_thinning_percentage: u32, // Track the last percentage at which we thinned
+    /// Tokenizer for the active provider's model, calibrated against reported usage
+    #[serde(skip)]
+    token_counter: TokenCounter,
+    /// (reported prompt tokens, used_tokens) at the last provider usage report
+    #[serde(skip)]
+    last_usage_sample: Option<(u32, u32)>,
 }
 
 impl ContextWindow {
@@ -76,9 +94,30 @@ impl ContextWindow {
             cumulative_tokens: 0,
             conversation_history: Vec::new(),
             last_thinning_percentage: 0,
+            token_counter: TokenCounter::default(),
+            last_usage_sample: None,
         }
     }
 
+    /// Count tokens with a different tokenizer (e.g. after switching provider).
+    /// Resets the calibration and recounts the existing history.
+    pub fn set_tokenizer(&mut self, tokenizer: Arc<dyn Tokenizer>) {
+        debug!("Using {} tokenizer", tokenizer.name());
+        self.token_counter = TokenCounter::new(tokenizer);
+        self.recalculate_tokens();
+    }
+
+    /// The calibrated tokenizer this window counts with
+    pub fn token_counter(&self) -> &TokenCounter {
+        &self.token_counter
+    }
+
+    /// Count tokens in `text` with the active tokenizer, corrected by the calibration
+    /// learned from provider-reported usage
+    pub fn count_tokens(&self, text: &str) -> u32 {
+        self.token_counter.count(text)
+    }
+
     pub fn add_message(&mut self, message: Message) {
         self.add_message_with_tokens(message, None);
     }
@@ -92,7 +131,7 @@ impl ContextWindow {
         }
 
         // Use provided token count if available, otherwise estimate
-        let token_count = tokens.unwrap_or_else(|| Self::estimate_tokens(&message.content));
+        let token_count = tokens.unwrap_or_else(|| self.count_tokens(&message.content));
         self.used_tokens += token_count;
         self.cumulative_tokens += token_count;
         self.conversation_history.push(message);
@@ -104,15 +143,17 @@ impl ContextWindow {
     }
 
     /// Update token usage from provider response
-    /// NOTE: This only updates cumulative_tokens (total API usage tracking).
-    /// It does NOT update used_tokens because:
+    /// NOTE: This does not add the usage to used_tokens because:
     /// 1. prompt_tokens represents the ENTIRE context sent to API (already tracked via add_message)
     /// 2. completion_tokens will be tracked when the assistant message is added via add_message
     /// Adding total_tokens here would cause double/triple counting and break the 80% threshold check.
+    ///
+    /// The reported prompt size is used to calibrate the tokenizer instead (see `reconcile_usage`).
     pub fn update_usage_from_response(&mut self, usage: &Usage) {
         // Only update cumulative tokens for API usage tracking
         // Do NOT update used_tokens - that's tracked via add_message to avoid double counting
         self.cumulative_tokens += usage.total_tokens;
+        self.reconcile_usage(usage);
 
         debug!(
             "Updated cumulative tokens: {} (used: {}/{}, cumulative: {})",
@@ -120,18 +161,46 @@ impl ContextWindow {
         );
     }
 
-    /// More accurate token estimation
+    /// Calibrate the tokenizer against the prompt size the provider reported.
+    ///
+    /// The reported prompt also covers tool definitions and other request overhead that
+    /// the context window doesn't track, so absolute sizes can't be compared. Instead, the
+    /// growth of the reported prompt between two requests is compared with the estimated
+    /// growth of the context over the same period, and the correction factor is nudged
+    /// towards their ratio. `used_tokens` is rescaled to match.
+    fn reconcile_usage(&mut self, usage: &Usage) {
+        let reported = usage.prompt_tokens + usage.cache_read_tokens + usage.cache_creation_tokens;
+        if reported == 0 {
+            return;
+        }
+
+        if let Some((last_reported, last_used)) = self.last_usage_sample {
+            let estimated_growth = self.used_tokens.saturating_sub(last_used);
+            let reported_growth = reported.saturating_sub(last_reported);
+            if estimated_growth >= MIN_CALIBRATION_SAMPLE_TOKENS && reported_growth > 0 {
+                let ratio = reported_growth as f32 / estimated_growth as f32;
+                let old = self.token_counter.calibration();
+                let new = (old * (1.0 + CALIBRATION_RATE * (ratio - 1.0)))
+                    .clamp(CALIBRATION_RANGE.0, CALIBRATION_RANGE.1);
+                self.token_counter.set_calibration(new);
+                self.used_tokens = (self.used_tokens as f32 * new / old).round() as u32;
+                debug!(
+                    "{} tokenizer calibration {:.3} -> {:.3} (reported growth {}, estimated {})",
+                    self.token_counter.tokenizer().name(),
+                    old,
+                    new,
+                    reported_growth,
+                    estimated_growth
+                );
+            }
+        }
+
+        self.last_usage_sample = Some((reported, self.used_tokens));
+    }
+
+    /// Estimate tokens when no context window (and so no provider tokenizer) is at hand
     pub fn estimate_tokens(text: &str) -> u32 {
-        // Better heuristic:
-        // - Average English text: ~4 characters per token
-        // - Code/JSON: ~3 characters per token (more symbols)
-        // - Add 10% buffer for safety
-        let base_estimate = if text.contains("{") || text.contains("```") || text.contains("fn ") {
-            (text.len() as f32 / 3.0).ceil() as u32 // Code/JSON
-        } else {
-            (text.len() as f32 / 4.0).ceil() as u32 // Regular text
-        };
-        (base_estimate as f32 * 1.1).ceil() as u32 // Add 10% buffer
+        tokenizer::default_tokenizer().count_tokens(text)
     }
 
     pub fn update_usage(&mut self, usage: &Usage) {
@@ -171,11 +240,7 @@ impl ContextWindow {
             .collect();
 
         self.conversation_history = system_messages;
-        self.used_tokens = self
-            .conversation_history
-            .iter()
-            .map(|m| Self::estimate_tokens(&m.content))
-            .sum();
+        self.recalculate_tokens();
         self.last_thinning_percentage = 0;
     }
 
@@ -240,6 +305,7 @@ Format this as a detailed but concise summary that can be used to resume the con
         // Clear the conversation history
         self.conversation_history.clear();
         self.used_tokens = 0;
+        self.last_usage_sample = None;
 
         // Re-add the original system prompt first (critical invariant)
         if let Some(system_prompt) = original_system_prompt {
@@ -303,6 +369,7 @@ Format this as a detailed but concise summary that can be used to resume the con
         // Clear the conversation history
         self.conversation_history.clear();
         self.used_tokens = 0;
+        self.last_usage_sample = None;
 
         // Re-add the original system prompt first (critical invariant)
         if let Some(system_prompt) = original_system_prompt {
@@ -749,9 +816,11 @@ Format this as a detailed but concise summary that can be used to resume the con
     pub fn recalculate_tokens(&mut self) {
         let mut total = 0;
         for message in &self.conversation_history {
-            total += Self::estimate_tokens(&message.content);
+            total += self.count_tokens(&message.content);
         }
         self.used_tokens = total;
+        // The history was rewritten, so the next usage report can't be compared with the last
+        self.last_usage_sample = None;
 
         debug!("Recalculated tokens after thinning: {} tokens", total);
     }
@@ -813,6 +882,36 @@ mod tests {
         assert_eq!(cw.remaining_tokens(), 70);
     }
 
+    #[test]
+    fn test_usage_reports_calibrate_token_counter() {
+        let usage = |prompt_tokens| Usage {
+            prompt_tokens,
+            completion_tokens: 0,
+            total_tokens: prompt_tokens,
+            ..Default::default()
+        };
+
+        let mut cw = ContextWindow::new(100_000);
+        cw.add_message(Message::new(MessageRole::User, "hello ".repeat(100)));
+        cw.update_usage_from_response(&usage(5_000));
+        assert_eq!(cw.token_counter().calibration(), 1.0);
+
+        // The provider sees twice the growth we estimated
+        let before = cw.used_tokens;
+        cw.add_message(Message::new(MessageRole::User, "hello ".repeat(1_000)));
+        let growth = cw.used_tokens - before;
+        cw.update_usage_from_response(&usage(5_000 + 2 * growth));
+
+        let calibration = cw.token_counter().calibration();
+        assert!((1.29..1.31).contains(&calibration), "got {}", calibration);
+        assert!(cw.used_tokens > before + growth);
+        assert!(cw.count_tokens("hello world") >= 3);
+
+        // Constant reports (no growth) leave the calibration alone
+        cw.update_usage_from_response(&usage(5_000 + 2 * growth));
+        assert_eq!(cw.token_counter().calibration(), calibration);
+    }
+
     #[test]
     fn test_should_compact_at_80_percent() {
         let mut cw = ContextWindow::new(100);
@@ -0,0 +1,339 @@
+//! Session cost ledger.
+//!
+//! Accumulates the `Usage` reported by providers into per (provider, model, role)
+//! totals and prices them with the pricing table from `g3-config`. The ledger is
+//! persisted as `ledger.json` next to `session.json` so a run's spend can be audited.
+
+use g3_config::ModelPricing;
+use g3_providers::Usage;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Which part of a run made a request
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentRole {
+    #[default]
+    Player,
+    Coach,
+    Planner,
+    Scout,
+}
+
+impl std::fmt::Display for AgentRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            AgentRole::Player => "player",
+            AgentRole::Coach => "coach",
+            AgentRole::Planner => "planner",
+            AgentRole::Scout => "scout",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Accumulated usage for one (provider, model, role) combination
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LedgerEntry {
+    pub provider: String,
+    pub model: String,
+    pub role: AgentRole,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_write_tokens: u64,
+    /// Spend in USD, or None when the model has no known price
+    pub cost_usd: Option<f64>,
+}
+
+impl LedgerEntry {
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens + self.cache_read_tokens + self.cache_write_tokens
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CostLedger {
+    pub entries: Vec<LedgerEntry>,
+}
+
+impl CostLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one response's usage to the ledger
+    pub fn record(
+        &mut self,
+        provider: &str,
+        model: &str,
+        role: AgentRole,
+        usage: &Usage,
+        pricing: Option<ModelPricing>,
+    ) {
+        let index = match self
+            .entries
+            .iter()
+            .position(|e| e.provider == provider && e.model == model && e.role == role)
+        {
+            Some(index) => index,
+            None => {
+                self.entries.push(LedgerEntry {
+                    provider: provider.to_string(),
+                    model: model.to_string(),
+                    role,
+                    requests: 0,
+                    input_tokens: 0,
+                    output_tokens: 0,
+                    cache_read_tokens: 0,
+                    cache_write_tokens: 0,
+                    cost_usd: pricing.map(|_| 0.0),
+                });
+                self.entries.len() - 1
+            }
+        };
+
+        let entry = &mut self.entries[index];
+        entry.requests += 1;
+        entry.input_tokens += usage.prompt_tokens as u64;
+        entry.output_tokens += usage.completion_tokens as u64;
+        entry.cache_read_tokens += usage.cache_read_tokens as u64;
+        entry.cache_write_tokens += usage.cache_creation_tokens as u64;
+        if let Some(pricing) = pricing {
+            let cost = pricing.cost(
+                usage.prompt_tokens as u64,
+                usage.completion_tokens as u64,
+                usage.cache_read_tokens as u64,
+                usage.cache_creation_tokens as u64,
+            );
+            entry.cost_usd = Some(entry.cost_usd.unwrap_or(0.0) + cost);
+        }
+    }
+
+    /// Fold another ledger's entries into this one
+    pub fn merge(&mut self, other: &CostLedger) {
+        for other_entry in &other.entries {
+            match self.entries.iter_mut().find(|e| {
+                e.provider == other_entry.provider
+                    && e.model == other_entry.model
+                    && e.role == other_entry.role
+            }) {
+                Some(entry) => {
+                    entry.requests += other_entry.requests;
+                    entry.input_tokens += other_entry.input_tokens;
+                    entry.output_tokens += other_entry.output_tokens;
+                    entry.cache_read_tokens += other_entry.cache_read_tokens;
+                    entry.cache_write_tokens += other_entry.cache_write_tokens;
+                    entry.cost_usd = match (entry.cost_usd, other_entry.cost_usd) {
+                        (None, None) => None,
+                        (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+                    };
+                }
+                None => self.entries.push(other_entry.clone()),
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Total spend in USD across all priced entries
+    pub fn total_cost(&self) -> f64 {
+        self.entries.iter().filter_map(|e| e.cost_usd).sum()
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.entries.iter().map(LedgerEntry::total_tokens).sum()
+    }
+
+    /// Whether some usage could not be priced, making `total_cost` a lower bound
+    pub fn has_unpriced_usage(&self) -> bool {
+        self.entries.iter().any(|e| e.cost_usd.is_none())
+    }
+
+    /// Human-readable spend breakdown, one line per entry, each prefixed by `indent`
+    pub fn format_breakdown(&self, indent: &str) -> String {
+        let mut out = String::new();
+        if self.entries.is_empty() {
+            out.push_str(&format!("{}• No provider usage recorded\n", indent));
+            return out;
+        }
+
+        out.push_str(&format!(
+            "{}• Total Spend:       {:>10}{}\n",
+            indent,
+            format!("${:.4}", self.total_cost()),
+            if self.has_unpriced_usage() {
+                " (excludes unpriced models)"
+            } else {
+                ""
+            }
+        ));
+        out.push_str(&format!(
+            "{}• Total Tokens:      {:>10}\n",
+            indent,
+            self.total_tokens()
+        ));
+        for entry in &self.entries {
+            let cost = entry
+                .cost_usd
+                .map(|c| format!("${:.4}", c))
+                .unwrap_or_else(|| "unpriced".to_string());
+            out.push_str(&format!(
+                "{}• {} {} ({}): {} | {} reqs, {} in / {} out / {} cache read / {} cache write\n",
+                indent,
+                entry.provider,
+                entry.model,
+                entry.role,
+                cost,
+                entry.requests,
+                entry.input_tokens,
+                entry.output_tokens,
+                entry.cache_read_tokens,
+                entry.cache_write_tokens
+            ));
+        }
+        out
+    }
+
+    /// Write the ledger, with its totals, as pretty-printed JSON
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let data = serde_json::json!({
+            "total_cost_usd": self.total_cost(),
+            "total_tokens": self.total_tokens(),
+            "has_unpriced_usage": self.has_unpriced_usage(),
+            "entries": self.entries,
+        });
+        std::fs::write(path, serde_json::to_string_pretty(&data)?)?;
+        Ok(())
+    }
+
+    /// Load a ledger written by `save`
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(input: u32, output: u32, cache_read: u32, cache_write: u32) -> Usage {
+        Usage {
+            prompt_tokens: input,
+            completion_tokens: output,
+            total_tokens: input + output,
+            cache_creation_tokens: cache_write,
+            cache_read_tokens: cache_read,
+        }
+    }
+
+    const SONNET: ModelPricing = ModelPricing {
+        input: 3.0,
+        output: 15.0,
+        cache_read: 0.3,
+        cache_write: 3.75,
+    };
+
+    #[test]
+    fn test_record_accumulates_per_provider_model_role() {
+        let mut ledger = CostLedger::new();
+        let sonnet = Some(SONNET);
+        ledger.record(
+            "anthropic.default",
+            "claude-sonnet-4-5",
+            AgentRole::Player,
+            &usage(1_000_000, 0, 0, 0),
+            sonnet,
+        );
+        ledger.record(
+            "anthropic.default",
+            "claude-sonnet-4-5",
+            AgentRole::Player,
+            &usage(0, 100_000, 1_000_000, 0),
+            sonnet,
+        );
+        ledger.record(
+            "anthropic.default",
+            "claude-sonnet-4-5",
+            AgentRole::Coach,
+            &usage(0, 0, 0, 1_000_000),
+            sonnet,
+        );
+        ledger.record(
+            "ollama.default",
+            "qwen2.5-coder:7b",
+            AgentRole::Scout,
+            &usage(500, 50, 0, 0),
+            None,
+        );
+
+        assert_eq!(ledger.entries.len(), 3);
+        let player = &ledger.entries[0];
+        assert_eq!(player.requests, 2);
+        assert_eq!(player.input_tokens, 1_000_000);
+        assert_eq!(player.output_tokens, 100_000);
+        assert_eq!(player.cache_read_tokens, 1_000_000);
+        // 3.00 input + 1.50 output + 0.30 cache read
+        assert!((player.cost_usd.unwrap() - 4.8).abs() < 1e-9);
+        assert!((ledger.entries[1].cost_usd.unwrap() - 3.75).abs() < 1e-9);
+        assert_eq!(ledger.entries[2].cost_usd, None);
+
+        assert!((ledger.total_cost() - 8.55).abs() < 1e-9);
+        assert_eq!(ledger.total_tokens(), 3_100_550);
+        assert!(ledger.has_unpriced_usage());
+
+        let breakdown = ledger.format_breakdown("   ");
+        assert!(breakdown.contains("Total Spend:"));
+        assert!(breakdown.contains("$8.5500 (excludes unpriced models)"));
+        assert!(breakdown.contains("anthropic.default claude-sonnet-4-5 (coach): $3.7500"));
+        assert!(breakdown.contains("ollama.default qwen2.5-coder:7b (scout): unpriced"));
+    }
+
+    #[test]
+    fn test_merge_and_persist() {
+        let mut player = CostLedger::new();
+        player.record(
+            "anthropic.default",
+            "claude-sonnet-4-5",
+            AgentRole::Player,
+            &usage(1_000_000, 0, 0, 0),
+            Some(SONNET),
+        );
+        let mut coach = CostLedger::new();
+        coach.record(
+            "anthropic.default",
+            "claude-sonnet-4-5",
+            AgentRole::Coach,
+            &usage(1_000_000, 0, 0, 0),
+            Some(SONNET),
+        );
+        coach.record(
+            "anthropic.default",
+            "claude-sonnet-4-5",
+            AgentRole::Player,
+            &usage(1_000_000, 0, 0, 0),
+            Some(SONNET),
+        );
+
+        player.merge(&coach);
+        assert_eq!(player.entries.len(), 2);
+        assert_eq!(player.entries[0].requests, 2);
+        assert!((player.total_cost() - 9.0).abs() < 1e-9);
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("ledger.json");
+        player.save(&path).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!((json["total_cost_usd"].as_f64().unwrap() - 9.0).abs() < 1e-9);
+        assert_eq!(json["entries"][1]["role"], "coach");
+
+        let loaded = CostLedger::load(&path).unwrap();
+        assert_eq!(loaded.entries, player.entries);
+    }
+}
@@ -0,0 +1,436 @@
+//! Locating a diff hunk's old text in a file.
+//!
+//! Models often get whitespace or a character or two wrong in diff context, so
+//! matching is tried in tiers and the first tier that finds the text wins:
+//! exact, ignoring trailing whitespace, ignoring indentation, then fuzzy (lines
+//! at least [`FUZZY_THRESHOLD`] similar on average, none below
+//! [`FUZZY_LINE_MINIMUM`]). A tier that finds the text in more than one place
+//! refuses rather than guessing.
+
+use std::ops::Range;
+
+/// Average per-line similarity a fuzzy match needs
+pub const FUZZY_THRESHOLD: f64 = 0.9;
+
+/// Similarity every line of a fuzzy match needs, so one unrelated line can't
+/// hide behind a long window of matching ones
+pub const FUZZY_LINE_MINIMUM: f64 = 0.7;
+
+/// How a hunk's old text was matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchTier {
+    Exact,
+    TrailingWhitespace,
+    Indentation,
+    Fuzzy,
+}
+
+impl MatchTier {
+    /// How the text matched, for tool results
+    pub fn describe(self) -> &'static str {
+        match self {
+            MatchTier::Exact => "exactly",
+            MatchTier::TrailingWhitespace => "ignoring trailing whitespace",
+            MatchTier::Indentation => "ignoring indentation",
+            MatchTier::Fuzzy => "approximately",
+        }
+    }
+}
+
+/// Where a hunk's old text was found, and what replaces it
+#[derive(Debug, Clone, PartialEq)]
+pub struct HunkMatch {
+    pub tier: MatchTier,
+    /// Byte range of the matched text
+    pub range: Range<usize>,
+    /// Text for `range`. Outside exact matches, the file's own lines are kept for
+    /// the hunk's context, and new lines are re-indented.
+    pub replacement: String,
+    /// First matched line (1-indexed)
+    pub line: usize,
+    /// Average line similarity (1.0 unless fuzzy)
+    pub similarity: f64,
+}
+
+/// The lines most like a hunk's old text, when it could not be matched
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosestMatch {
+    /// First line (1-indexed)
+    pub line: usize,
+    pub similarity: f64,
+    pub lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchError {
+    /// The first tier that found the text found it at each of these lines
+    Ambiguous { tier: MatchTier, lines: Vec<usize> },
+    NotFound { closest: Option<ClosestMatch> },
+}
+
+/// A line-by-line tier and how it compares a hunk line with a file line
+type LineTier = (MatchTier, fn(&str, &str) -> bool);
+
+/// Find `old_block` in `text`, trying each [`MatchTier`] in turn
+pub fn locate_hunk(text: &str, old_block: &str, new_block: &str) -> Result<HunkMatch, MatchError> {
+    // Pure insertions without context go at the start
+    if old_block.is_empty() {
+        return Ok(HunkMatch {
+            tier: MatchTier::Exact,
+            range: 0..0,
+            replacement: new_block.to_string(),
+            line: 1,
+            similarity: 1.0,
+        });
+    }
+
+    let exact: Vec<usize> = text.match_indices(old_block).map(|(pos, _)| pos).collect();
+    // Prefer occurrences that are whole lines, so `-x` doesn't match inside `max`
+    let whole_lines: Vec<usize> = exact
+        .iter()
+        .copied()
+        .filter(|&pos| {
+            let end = pos + old_block.len();
+            (pos == 0 || text[..pos].ends_with('\n')) && (end == text.len() || text[end..].starts_with('\n'))
+        })
+        .collect();
+    let exact = if whole_lines.is_empty() { exact } else { whole_lines };
+    match exact.as_slice() {
+        [pos] => {
+            return Ok(HunkMatch {
+                tier: MatchTier::Exact,
+                range: *pos..*pos + old_block.len(),
+                replacement: new_block.to_string(),
+                line: line_of(text, *pos),
+                similarity: 1.0,
+            })
+        }
+        [] => {}
+        positions => {
+            return Err(MatchError::Ambiguous {
+                tier: MatchTier::Exact,
+                lines: positions.iter().map(|&pos| line_of(text, pos)).collect(),
+            })
+        }
+    }
+
+    let lines = split_lines(text);
+    let old_lines: Vec<&str> = old_block.split('\n').collect();
+    if old_lines.len() > lines.len() || old_lines.iter().all(|line| line.trim().is_empty()) {
+        return Err(MatchError::NotFound { closest: None });
+    }
+    let windows = 0..=lines.len() - old_lines.len();
+
+    let line_tiers: [LineTier; 2] = [
+        (MatchTier::TrailingWhitespace, |a, b| a.trim_end() == b.trim_end()),
+        (MatchTier::Indentation, |a, b| a.trim() == b.trim()),
+    ];
+    for (tier, same) in line_tiers {
+        let found: Vec<usize> = windows
+            .clone()
+            .filter(|&start| {
+                old_lines
+                    .iter()
+                    .zip(&lines[start..])
+                    .all(|(old, (_, line))| same(old, line))
+            })
+            .collect();
+        match found.as_slice() {
+            [start] => return Ok(window_match(tier, &lines, *start, &old_lines, new_block, 1.0)),
+            [] => {}
+            starts => {
+                return Err(MatchError::Ambiguous {
+                    tier,
+                    lines: starts.iter().map(|start| start + 1).collect(),
+                })
+            }
+        }
+    }
+
+    // Fuzzy: score every window, skipping ones that can't reach the threshold or
+    // beat the best so far
+    let mut best: Option<(usize, f64)> = None;
+    let mut fuzzy: Vec<(usize, f64)> = Vec::new();
+    for start in windows {
+        let floor = best.map_or(0.0, |(_, score)| score).min(FUZZY_THRESHOLD);
+        let Some((score, lowest)) = window_similarity(&old_lines, &lines[start..], floor) else {
+            continue;
+        };
+        match best {
+            Some((_, best_score)) if best_score >= score => {}
+            _ => best = Some((start, score)),
+        }
+        if score >= FUZZY_THRESHOLD && lowest >= FUZZY_LINE_MINIMUM {
+            fuzzy.push((start, score));
+        }
+    }
+    match fuzzy.as_slice() {
+        [(start, score)] => Ok(window_match(MatchTier::Fuzzy, &lines, *start, &old_lines, new_block, *score)),
+        [] => Err(MatchError::NotFound {
+            closest: best.filter(|(_, score)| *score > 0.0).map(|(start, score)| ClosestMatch {
+                line: start + 1,
+                similarity: score,
+                lines: lines[start..start + old_lines.len()]
+                    .iter()
+                    .map(|(_, line)| line.to_string())
+                    .collect(),
+            }),
+        }),
+        matches => Err(MatchError::Ambiguous {
+            tier: MatchTier::Fuzzy,
+            lines: matches.iter().map(|(start, _)| start + 1).collect(),
+        }),
+    }
+}
+
+/// 1-indexed line of a byte position
+fn line_of(text: &str, pos: usize) -> usize {
+    text[..pos].matches('\n').count() + 1
+}
+
+/// Lines with their byte offsets; a trailing newline leaves an empty last line
+fn split_lines(text: &str) -> Vec<(usize, &str)> {
+    let mut offset = 0;
+    text.split('\n')
+        .map(|line| {
+            let start = offset;
+            offset += line.len() + 1;
+            (start, line)
+        })
+        .collect()
+}
+
+/// Build the match for `old_lines` found at line index `start`
+fn window_match(
+    tier: MatchTier,
+    lines: &[(usize, &str)],
+    start: usize,
+    old_lines: &[&str],
+    new_block: &str,
+    similarity: f64,
+) -> HunkMatch {
+    let window = &lines[start..start + old_lines.len()];
+    let (first_offset, _) = window[0];
+    let (last_offset, last_line) = window[window.len() - 1];
+    let file_lines: Vec<&str> = window.iter().map(|(_, line)| *line).collect();
+    HunkMatch {
+        tier,
+        range: first_offset..last_offset + last_line.len(),
+        replacement: rebuild_window(&file_lines, old_lines, new_block),
+        line: start + 1,
+        similarity,
+    }
+}
+
+/// The new text for a window matched loosely. Context the hunk doesn't change
+/// keeps the file's version, wherever it is in the window; added lines are moved
+/// from the hunk's indentation to the file's.
+fn rebuild_window(file_lines: &[&str], old_lines: &[&str], new_block: &str) -> String {
+    let new_lines: Vec<&str> = new_block.split('\n').collect();
+
+    let (old_indent, file_indent) = old_lines
+        .iter()
+        .zip(file_lines)
+        .find(|(old, _)| !old.trim().is_empty())
+        .map(|(old, file)| (indentation(old), indentation(file)))
+        .unwrap_or(("", ""));
+    let reindent = |line: &str| match line.strip_prefix(old_indent) {
+        Some(rest) if !line.trim().is_empty() => format!("{}{}", file_indent, rest),
+        _ => line.to_string(),
+    };
+
+    // Old lines line up one-to-one with the window, so each unchanged line is
+    // taken from the file and everything between them from the hunk
+    let mut rebuilt: Vec<String> = Vec::new();
+    let mut next_new = 0;
+    for (old, new) in common_lines(old_lines, &new_lines) {
+        rebuilt.extend(new_lines[next_new..new].iter().map(|line| reindent(line)));
+        rebuilt.push(file_lines[old].to_string());
+        next_new = new + 1;
+    }
+    rebuilt.extend(new_lines[next_new..].iter().map(|line| reindent(line)));
+    rebuilt.join("\n")
+}
+
+/// Index pairs of the lines `old` and `new` have in common (longest common
+/// subsequence), in order
+fn common_lines(old: &[&str], new: &[&str]) -> Vec<(usize, usize)> {
+    // lengths[i][j]: longest common subsequence of old[i..] and new[j..]
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Average and lowest similarity of `old_lines` to the lines at the start of
+/// `lines`, or `None` as soon as the average can no longer reach `floor`
+fn window_similarity(old_lines: &[&str], lines: &[(usize, &str)], floor: f64) -> Option<(f64, f64)> {
+    let count = old_lines.len() as f64;
+    let mut total = 0.0;
+    let mut lowest: f64 = 1.0;
+    for (i, (old, (_, line))) in old_lines.iter().zip(lines).enumerate() {
+        let similarity = line_similarity(old.trim(), line.trim());
+        total += similarity;
+        lowest = lowest.min(similarity);
+        let remaining = (old_lines.len() - i - 1) as f64;
+        if (total + remaining) / count < floor {
+            return None;
+        }
+    }
+    Some((total / count, lowest))
+}
+
+/// 1 minus the edit distance over the longer length, by characters
+fn line_similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    // The distance is at least the length difference
+    if (a.len().min(b.len()) as f64) / (longest as f64) < 0.5 {
+        return 0.0;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(text: &str, found: &HunkMatch) -> String {
+        let mut text = text.to_string();
+        text.replace_range(found.range.clone(), &found.replacement);
+        text
+    }
+
+    #[test]
+    fn exact_match_prefers_whole_lines() {
+        let text = "let max = 1;\nx\n";
+        let found = locate_hunk(text, "x", "y").unwrap();
+        assert_eq!(found.tier, MatchTier::Exact);
+        assert_eq!(found.line, 2);
+        assert_eq!(apply(text, &found), "let max = 1;\ny\n");
+    }
+
+    #[test]
+    fn exact_duplicates_are_ambiguous() {
+        let text = "a\nold\nb\nold\n";
+        let error = locate_hunk(text, "old", "new").unwrap_err();
+        assert_eq!(error, MatchError::Ambiguous { tier: MatchTier::Exact, lines: vec![2, 4] });
+    }
+
+    #[test]
+    fn trailing_whitespace_is_ignored() {
+        let text = "fn a() {  \n    1\n}\n";
+        let found = locate_hunk(text, "fn a() {\n    1\n}", "fn a() {\n    2\n}").unwrap();
+        assert_eq!(found.tier, MatchTier::TrailingWhitespace);
+        // Unchanged context keeps the file's own text
+        assert_eq!(apply(text, &found), "fn a() {  \n    2\n}\n");
+    }
+
+    #[test]
+    fn indentation_is_normalised_and_new_lines_reindented() {
+        let text = "mod m {\n        fn a() {\n            1\n        }\n}\n";
+        let found = locate_hunk(text, "fn a() {\n    1\n}", "fn a() {\n    2\n    3\n}").unwrap();
+        assert_eq!(found.tier, MatchTier::Indentation);
+        assert_eq!(found.line, 2);
+        assert_eq!(
+            apply(text, &found),
+            "mod m {\n        fn a() {\n            2\n            3\n        }\n}\n"
+        );
+    }
+
+    #[test]
+    fn fuzzy_match_tolerates_small_differences() {
+        let text = "fn total(items: &[Item]) -> u32 {\n    items.iter().map(|i| i.price).sum()\n}\n";
+        let old = "fn total(items: &[Item]) -> u32 {\n    items.iter().map(|it| it.price).sum()\n}";
+        let new = "fn total(items: &[Item]) -> u32 {\n    items.iter().map(|i| i.price * i.qty).sum()\n}";
+        let found = locate_hunk(text, old, new).unwrap();
+        assert_eq!(found.tier, MatchTier::Fuzzy);
+        assert!(found.similarity >= FUZZY_THRESHOLD);
+        assert_eq!(apply(text, &found), format!("{}\n", new));
+    }
+
+    #[test]
+    fn fuzzy_match_keeps_differing_context_inside_the_window() {
+        let text = "fn run() {\n    let limit = compute(10);\n    old_call(limit);\n    finish();\n}\n";
+        let old = "fn run() {\n    let limit = compute(1O);\n    old_call(limit);\n    finish();\n}";
+        let new = "fn run() {\n    let limit = compute(1O);\n    new_call(limit);\n    finish();\n}";
+        let found = locate_hunk(text, old, new).unwrap();
+        assert_eq!(found.tier, MatchTier::Fuzzy);
+        // The file's context line stays, only the changed line is replaced
+        assert_eq!(
+            apply(text, &found),
+            "fn run() {\n    let limit = compute(10);\n    new_call(limit);\n    finish();\n}\n"
+        );
+    }
+
+    #[test]
+    fn fuzzy_match_needs_every_line_similar() {
+        let text: String = (0..10).map(|i| format!("let value_{} = {};\n", i, i)).collect::<String>()
+            + "cleanup();\n";
+        let mut old: Vec<String> = (0..10).map(|i| format!("let value_{} = {};", i, i)).collect();
+        old[5] = "something else entirely".to_string();
+        let old = old.join("\n");
+        // The average clears the threshold, but one line is nothing like the file
+        match locate_hunk(&text, &old, "x").unwrap_err() {
+            MatchError::NotFound { closest: Some(closest) } => {
+                assert_eq!(closest.line, 1);
+                assert!(closest.similarity >= FUZZY_THRESHOLD);
+            }
+            other => panic!("expected no match, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn not_found_reports_closest_lines() {
+        let text = "alpha\nfn compute(a: i32) {\n    a * 2\n}\nomega\n";
+        let error = locate_hunk(text, "fn compute(b: u64) {\n    b * 3 + 1\n}", "x").unwrap_err();
+        match error {
+            MatchError::NotFound { closest: Some(closest) } => {
+                assert_eq!(closest.line, 2);
+                assert_eq!(closest.lines[0], "fn compute(a: i32) {");
+                assert!(closest.similarity < FUZZY_THRESHOLD);
+            }
+            other => panic!("expected a closest match, got {:?}", other),
+        }
+    }
+}
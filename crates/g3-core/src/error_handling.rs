@@ -188,6 +188,11 @@ pub enum RecoverableError {
 
 /// Classify an error as recoverable or non-recoverable
 pub fn classify_error(error: &anyhow::Error) -> ErrorType {
+    // A budget stop must never be retried
+    if crate::budget::is_budget_exceeded(error) {
+        return ErrorType::NonRecoverable;
+    }
+
     let error_str = error.to_string().to_lowercase();
 
     // Check for recoverable error patterns
@@ -0,0 +1,125 @@
+//! Fingerprints of files as the agent last saw them.
+//!
+//! `read_file` records each file's length, modification time and content hash, and the
+//! agent's own `write_file`/`str_replace` edits refresh them. Before either tool changes
+//! a file, its fingerprint is compared with the file on disk, which catches edits made
+//! since the agent last looked (by the user, an editor or a background process).
+//!
+//! Fingerprints belong to the agent's session and are saved in its continuation, so a
+//! resumed session still knows what the agent has seen.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// A file's state when the agent last read or wrote it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileFingerprint {
+    pub len: u64,
+    /// Modification time in milliseconds since the Unix epoch, if the platform reports it
+    pub modified_ms: Option<u64>,
+    /// Hex SHA-256 of the file's content, which stays the same across builds so a
+    /// resumed session can compare against it
+    pub hash: String,
+}
+
+impl FileFingerprint {
+    fn new(content: &[u8], metadata: Option<&std::fs::Metadata>) -> Self {
+        Self {
+            len: content.len() as u64,
+            modified_ms: metadata.and_then(modified_ms),
+            hash: content_hash(content),
+        }
+    }
+}
+
+/// How a file on disk compares with the agent's fingerprint of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    /// The agent has not read or written the file in this session
+    Untracked,
+    /// Unchanged since the agent last saw it
+    Fresh,
+    /// Modified since the agent last saw it
+    Changed,
+    /// Deleted (or unreadable) since the agent last saw it
+    Missing,
+}
+
+/// Fingerprints by absolute path
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FileFingerprints {
+    files: HashMap<String, FileFingerprint>,
+}
+
+impl FileFingerprints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember `content` as what the agent has seen of `path`
+    pub fn record(&mut self, path: &Path, content: &[u8]) {
+        let metadata = std::fs::metadata(path).ok();
+        self.files
+            .insert(key(path), FileFingerprint::new(content, metadata.as_ref()));
+    }
+
+    /// Compare `path` on disk with its fingerprint
+    pub fn check(&self, path: &Path) -> Freshness {
+        let Some(seen) = self.files.get(&key(path)) else {
+            return Freshness::Untracked;
+        };
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return Freshness::Missing;
+        };
+        // Same size and timestamp: trust it without reading the file again
+        if seen.modified_ms.is_some()
+            && metadata.len() == seen.len
+            && modified_ms(&metadata) == seen.modified_ms
+        {
+            return Freshness::Fresh;
+        }
+        match std::fs::read(path) {
+            Ok(content) if content_hash(&content) == seen.hash => Freshness::Fresh,
+            Ok(_) => Freshness::Changed,
+            Err(_) => Freshness::Missing,
+        }
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&FileFingerprint> {
+        self.files.get(&key(path))
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
+}
+
+/// Canonical path when the file exists, so `./a.rs` and `src/../a.rs` share a fingerprint
+fn key(path: &Path) -> String {
+    let resolved = std::fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf());
+    resolved.to_string_lossy().into_owned()
+}
+
+fn content_hash(content: &[u8]) -> String {
+    let digest = Sha256::digest(content);
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn modified_ms(metadata: &std::fs::Metadata) -> Option<u64> {
+    let modified = metadata.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
@@ -0,0 +1,260 @@
+//! Lifecycle hooks (`[[hooks.<event>]]`).
+//!
+//! Hooks are shell commands run on agent events: before and after each tool call,
+//! after each turn, after compaction and when the session ends. Each gets a JSON
+//! payload describing the event on stdin, plus `G3_HOOK_EVENT`, `G3_SESSION_ID` and
+//! (for tool events) `G3_TOOL_NAME` in its environment. A `pre_tool` hook that exits
+//! non-zero blocks the call, and its output is returned to the model instead.
+
+use g3_config::{HookConfig, HooksConfig};
+use serde_json::{json, Value};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, warn};
+
+use crate::ToolCall;
+
+/// Events that hooks can be registered for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    PreTool,
+    PostTool,
+    PostTurn,
+    OnCompaction,
+    OnSessionEnd,
+}
+
+impl HookEvent {
+    /// Name of the event in config and payloads
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::PreTool => "pre_tool",
+            HookEvent::PostTool => "post_tool",
+            HookEvent::PostTurn => "post_turn",
+            HookEvent::OnCompaction => "on_compaction",
+            HookEvent::OnSessionEnd => "on_session_end",
+        }
+    }
+
+    fn hooks(self, config: &HooksConfig) -> &[HookConfig] {
+        match self {
+            HookEvent::PreTool => &config.pre_tool,
+            HookEvent::PostTool => &config.post_tool,
+            HookEvent::PostTurn => &config.post_turn,
+            HookEvent::OnCompaction => &config.on_compaction,
+            HookEvent::OnSessionEnd => &config.on_session_end,
+        }
+    }
+}
+
+/// How a hook run ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookOutcome {
+    pub command: String,
+    pub success: bool,
+    /// stderr, or stdout when stderr is empty
+    pub message: String,
+}
+
+/// Run the hooks registered for `event`, in config order. For tool events, only hooks
+/// whose `tools` list is empty or names `tool` run. `payload` gets the event name and
+/// session id added. With `stop_on_failure`, no hooks run after the first that fails.
+pub async fn run_hooks(
+    config: &HooksConfig,
+    event: HookEvent,
+    tool: Option<&str>,
+    mut payload: Value,
+    session_id: Option<&str>,
+    working_dir: Option<&str>,
+    stop_on_failure: bool,
+) -> Vec<HookOutcome> {
+    let hooks: Vec<&HookConfig> = event
+        .hooks(config)
+        .iter()
+        .filter(|hook| match tool {
+            Some(tool) => hook.tools.is_empty() || hook.tools.iter().any(|t| t == tool),
+            None => true,
+        })
+        .collect();
+    if hooks.is_empty() {
+        return Vec::new();
+    }
+
+    if let Value::Object(fields) = &mut payload {
+        fields.insert("event".to_string(), json!(event.name()));
+        fields.insert("session_id".to_string(), json!(session_id));
+    }
+    let input = payload.to_string();
+
+    let mut outcomes = Vec::new();
+    for hook in hooks {
+        let outcome = run_hook(hook, event, tool, &input, session_id, working_dir).await;
+        if !outcome.success {
+            warn!(
+                "{} hook `{}` failed: {}",
+                event.name(),
+                outcome.command,
+                outcome.message
+            );
+        }
+        let failed = !outcome.success;
+        outcomes.push(outcome);
+        if failed && stop_on_failure {
+            break;
+        }
+    }
+    outcomes
+}
+
+/// Run the `pre_tool` hooks for a call. Returns the message for the model if a hook
+/// blocked it.
+pub async fn pre_tool(
+    config: &HooksConfig,
+    tool_call: &ToolCall,
+    session_id: Option<&str>,
+    working_dir: Option<&str>,
+) -> Option<String> {
+    let payload = json!({ "tool": tool_call.tool, "args": tool_call.args });
+    let outcomes = run_hooks(
+        config,
+        HookEvent::PreTool,
+        Some(&tool_call.tool),
+        payload,
+        session_id,
+        working_dir,
+        true,
+    )
+    .await;
+    let blocked = outcomes.into_iter().find(|outcome| !outcome.success)?;
+    let reason = if blocked.message.is_empty() {
+        "no reason given".to_string()
+    } else {
+        blocked.message
+    };
+    Some(format!(
+        "🚫 {} was blocked by a pre_tool hook (`{}`): {}",
+        tool_call.tool, blocked.command, reason
+    ))
+}
+
+/// Run the `post_tool` hooks for a call. Failures are appended to the result so the
+/// model sees them (e.g. a formatter or linter rejecting an edit). Also returns
+/// whether every hook succeeded.
+pub async fn post_tool(
+    config: &HooksConfig,
+    tool_call: &ToolCall,
+    result: String,
+    session_id: Option<&str>,
+    working_dir: Option<&str>,
+) -> (String, bool) {
+    let payload = json!({ "tool": tool_call.tool, "args": tool_call.args, "result": result });
+    let outcomes = run_hooks(
+        config,
+        HookEvent::PostTool,
+        Some(&tool_call.tool),
+        payload,
+        session_id,
+        working_dir,
+        false,
+    )
+    .await;
+    let succeeded = outcomes.iter().all(|outcome| outcome.success);
+    let result = outcomes
+        .into_iter()
+        .filter(|outcome| !outcome.success)
+        .fold(result, |mut result, outcome| {
+            result.push_str(&format!(
+                "\n⚠️ post_tool hook `{}` failed: {}",
+                outcome.command, outcome.message
+            ));
+            result
+        });
+    (result, succeeded)
+}
+
+async fn run_hook(
+    hook: &HookConfig,
+    event: HookEvent,
+    tool: Option<&str>,
+    input: &str,
+    session_id: Option<&str>,
+    working_dir: Option<&str>,
+) -> HookOutcome {
+    let failure = |message: String| HookOutcome {
+        command: hook.command.clone(),
+        success: false,
+        message,
+    };
+    debug!("Running {} hook: {}", event.name(), hook.command);
+
+    let mut command = tokio::process::Command::new("sh");
+    command
+        .arg("-c")
+        .arg(&hook.command)
+        .env("G3_HOOK_EVENT", event.name())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(dir) = working_dir {
+        command.current_dir(dir);
+    }
+    if let Some(session_id) = session_id {
+        command.env("G3_SESSION_ID", session_id);
+    }
+    if let Some(tool) = tool {
+        command.env("G3_TOOL_NAME", tool);
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return failure(format!("could not be started: {}", e)),
+    };
+
+    // Write the payload while collecting output, both under the timeout: a hook that
+    // doesn't read its stdin can't stall the write past the deadline
+    let stdin = child.stdin.take();
+    let write_payload = async {
+        if let Some(mut stdin) = stdin {
+            // Hooks that don't read the payload may exit before we finish writing
+            if let Err(e) = stdin.write_all(input.as_bytes()).await {
+                debug!("{} hook did not read its payload: {}", event.name(), e);
+            }
+        }
+    };
+    // Dropping the child on timeout kills it
+    let output = async { tokio::join!(write_payload, child.wait_with_output()).1 };
+    let output = if hook.timeout_seconds > 0 {
+        match tokio::time::timeout(Duration::from_secs(hook.timeout_seconds), output).await {
+            Ok(output) => output,
+            Err(_) => {
+                return failure(format!("timed out after {}s", hook.timeout_seconds));
+            }
+        }
+    } else {
+        output.await
+    };
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => return failure(e.to_string()),
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let message = if stderr.trim().is_empty() {
+        stdout.trim().to_string()
+    } else {
+        stderr.trim().to_string()
+    };
+    let message = match (output.status.success(), output.status.code()) {
+        (true, _) => message,
+        (false, Some(code)) if message.is_empty() => format!("exit code {}", code),
+        (false, None) if message.is_empty() => "killed by a signal".to_string(),
+        (false, _) => message,
+    };
+    HookOutcome {
+        command: hook.command.clone(),
+        success: output.status.success(),
+        message,
+    }
+}
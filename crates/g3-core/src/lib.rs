@@ -1,21 +1,34 @@
 pub mod acd;
 pub mod background_process;
+pub mod budget;
+pub mod builder;
+pub mod checkpoints;
 pub mod code_search;
 pub mod compaction;
 pub mod context_window;
+pub mod cost_ledger;
+pub mod diff_match;
 pub mod error_handling;
 pub mod feedback_extraction;
+pub mod file_fingerprints;
+pub mod hooks;
+pub mod mcp;
+pub mod mcp_server;
+pub mod patch;
 pub mod paths;
+pub mod permissions;
 pub mod project;
 pub mod provider_config;
 pub mod provider_registration;
 pub mod retry;
 pub mod session;
 pub mod session_continuation;
+pub mod session_diff;
 pub mod stats;
 pub mod streaming;
 pub mod streaming_parser;
 pub mod task_result;
+pub mod tokenizer;
 pub mod tool_definitions;
 pub mod tool_dispatch;
 pub mod tools;
@@ -34,16 +47,28 @@ pub use session_continuation::{
 };
 pub use task_result::TaskResult;
 
+// Re-export what embedders need to build and drive an agent
+pub use builder::AgentBuilder;
+pub use g3_providers::ProviderRegistry;
+pub use ui_writer::{NullUiWriter, UiWriter};
+
 // Re-export context window types
 pub use context_window::{ContextWindow, ThinScope};
 
+// Re-export cost ledger and budget types
+pub use budget::{is_budget_exceeded, BudgetExceeded, BudgetKind};
+pub use cost_ledger::{AgentRole, CostLedger, LedgerEntry};
+pub use file_fingerprints::{FileFingerprint, FileFingerprints, Freshness};
+pub use tokenizer::Tokenizer;
+
+// Re-export the command sandbox
+pub use g3_execution::Sandbox;
+
 // Export agent prompt generation for CLI use
 pub use prompts::get_agent_system_prompt;
 
 #[cfg(test)]
 mod task_result_comprehensive_tests;
-use crate::ui_writer::UiWriter;
-
 #[cfg(test)]
 mod tilde_expansion_tests;
 
@@ -53,8 +78,7 @@ mod prompts;
 
 use anyhow::Result;
 use g3_config::Config;
-use g3_providers::{CacheControl, CompletionRequest, Message, MessageRole, ProviderRegistry};
-use prompts::{get_system_prompt_for_native, SYSTEM_PROMPT_FOR_NON_NATIVE_TOOL_USE};
+use g3_providers::{CacheControl, CompletionRequest, Message, MessageRole};
 use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
 use tokio_util::sync::CancellationToken;
@@ -64,10 +88,14 @@ use tracing::{debug, error, warn};
 use paths::get_todo_path;
 pub use paths::{
     ensure_session_dir, get_background_processes_dir, get_context_summary_file, get_discovery_dir,
-    get_errors_dir, get_g3_dir, get_session_file, get_session_logs_dir, get_session_todo_path,
-    get_thinned_dir, G3_WORKSPACE_PATH_ENV,
+    get_errors_dir, get_g3_dir, get_ledger_file, get_session_file, get_session_logs_dir,
+    get_session_todo_path, get_thinned_dir, G3_WORKSPACE_PATH_ENV,
 };
 
+/// Text the system prompt must contain: the start of g3's tool-use instructions
+const SYSTEM_PROMPT_MARKER: &str = "IMPORTANT: You must call tools to achieve goals";
+
+/// A tool call requested by the model, or made directly via [`Agent::execute_tool`]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCall {
     pub tool: String,
@@ -84,6 +112,7 @@ pub struct DiscoveryOptions<'a> {
     pub fast_start_path: Option<&'a str>,
 }
 
+/// Where the streaming loop is within a completion
 #[derive(Debug, Clone)]
 pub enum StreamState {
     Generating,
@@ -121,7 +150,17 @@ pub struct Agent<W: UiWriter> {
     requirements_sha: Option<String>,
     /// Working directory for tool execution (set by --codebase-fast-start)
     working_dir: Option<String>,
+    /// Tools offered to the model when restricted by `AgentBuilder::tools`
+    allowed_tools: Option<std::collections::HashSet<String>>,
     background_process_manager: std::sync::Arc<background_process::BackgroundProcessManager>,
+    /// Long-lived shell for persistent `shell` calls; replaced when the session changes
+    shell_session: std::sync::Arc<tokio::sync::Mutex<Option<g3_execution::ShellSession>>>,
+    /// External MCP tool servers from `[mcp_servers]`
+    mcp_servers: std::sync::Arc<mcp::McpServers>,
+    /// Current user turn, for grouping file checkpoints
+    turn: checkpoints::Turn,
+    /// Tells the model about undone edits at the start of the next task
+    pending_undo_note: Option<String>,
     /// Pending images to attach to the next user message
     pending_images: Vec<g3_providers::ImageContent>,
     /// Whether this agent is running in agent mode (--agent flag)
@@ -132,9 +171,24 @@ pub struct Agent<W: UiWriter> {
     auto_memory: bool,
     /// Whether aggressive context dehydration is enabled (--acd flag)
     acd_enabled: bool,
+    /// Provider spend for the run; shared with coach agents spawned from this one
+    cost_ledger: std::sync::Arc<std::sync::Mutex<CostLedger>>,
+    /// Role this agent's requests are booked under in the cost ledger
+    agent_role: AgentRole,
+    /// Cost, token and wall-time limits for the run
+    budget: budget::BudgetTracker,
+    /// What the agent has seen of each file it read or wrote this session
+    file_fingerprints: std::sync::Arc<std::sync::Mutex<FileFingerprints>>,
+    /// Permission decisions made this session (see `permissions::authorize`)
+    permission_log: std::sync::Arc<std::sync::Mutex<Vec<permissions::PermissionLogEntry>>>,
 }
 
 impl<W: UiWriter> Agent<W> {
+    /// Start configuring an agent; see [`AgentBuilder`]
+    pub fn builder(config: Config, ui_writer: W) -> AgentBuilder<W> {
+        AgentBuilder::new(config, ui_writer)
+    }
+
     pub async fn new(config: Config, ui_writer: W) -> Result<Self> {
         Self::new_with_mode(config, ui_writer, false, false).await
     }
@@ -197,103 +251,16 @@ impl<W: UiWriter> Agent<W> {
         quiet: bool,
         custom_system_prompt: Option<String>,
     ) -> Result<Self> {
-        // Register providers using the extracted module
-        let providers_to_register =
-            provider_registration::determine_providers_to_register(&config, is_autonomous);
-        let providers =
-            provider_registration::register_providers(&config, &providers_to_register).await?;
-
-        // Determine context window size based on active provider
-        let mut context_warnings = Vec::new();
-        let context_length =
-            Self::get_configured_context_length(&config, &providers, &mut context_warnings)?;
-        let mut context_window = ContextWindow::new(context_length);
-
-        // Surface any context warnings to the user via UI
-        for warning in context_warnings {
-            ui_writer.print_context_status(&format!("⚠️ {}", warning));
-        }
-
-        // Add system prompt as the FIRST message (before README)
-        // This ensures the agent always has proper tool usage instructions
-        let provider = providers.get(None)?;
-        let provider_has_native_tool_calling = provider.has_native_tool_calling();
-        let _ = provider; // Drop provider reference to avoid borrowing issues
-
-        let system_prompt = if let Some(custom_prompt) = custom_system_prompt {
-            // Use custom system prompt (for agent mode)
-            custom_prompt
-        } else {
-            // Use default system prompt based on provider capabilities
-            if provider_has_native_tool_calling {
-                // For native tool calling providers, use a more explicit system prompt
-                get_system_prompt_for_native()
-            } else {
-                // For non-native providers (embedded models), use JSON format instructions
-                SYSTEM_PROMPT_FOR_NON_NATIVE_TOOL_USE.to_string()
-            }
-        };
-
-        let system_message = Message::new(MessageRole::System, system_prompt);
-        context_window.add_message(system_message);
-
-        // If README content is provided, add it as a second system message (after the main system prompt)
+        let mut builder = AgentBuilder::new(config, ui_writer)
+            .autonomous(is_autonomous)
+            .quiet(quiet);
         if let Some(readme) = readme_content {
-            let readme_message = Message::new(MessageRole::System, readme);
-            context_window.add_message(readme_message);
+            builder = builder.project_context(readme);
         }
-
-        // NOTE: TODO lists are now session-scoped and stored in .g3/sessions/<session_id>/todo.g3.md
-        // We don't load any TODO at initialization since we don't have a session_id yet.
-        // The agent will use todo_read to load the TODO once a session is established.
-
-        // Initialize computer controller if enabled
-        let computer_controller = if config.computer_control.enabled {
-            match g3_computer_control::create_controller() {
-                Ok(controller) => Some(controller),
-                Err(e) => {
-                    warn!("Failed to initialize computer control: {}", e);
-                    None
-                }
-            }
-        } else {
-            None
-        };
-
-        Ok(Self {
-            providers,
-            context_window,
-            auto_compact: config.agent.auto_compact,
-            pending_90_compaction: false,
-            thinning_events: Vec::new(),
-            compaction_events: Vec::new(),
-            first_token_times: Vec::new(),
-            config,
-            session_id: None,
-            tool_call_metrics: Vec::new(),
-            ui_writer,
-            // TODO content starts empty - session-scoped TODOs are loaded via todo_read
-            todo_content: std::sync::Arc::new(tokio::sync::RwLock::new(String::new())),
-            is_autonomous,
-            quiet,
-            computer_controller,
-            webdriver_session: std::sync::Arc::new(tokio::sync::RwLock::new(None)),
-            webdriver_process: std::sync::Arc::new(tokio::sync::RwLock::new(None)),
-            tool_call_count: 0,
-            tool_calls_this_turn: Vec::new(),
-            requirements_sha: None,
-            working_dir: None,
-            background_process_manager: std::sync::Arc::new(
-                background_process::BackgroundProcessManager::new(
-                    paths::get_background_processes_dir(),
-                ),
-            ),
-            pending_images: Vec::new(),
-            is_agent_mode: false,
-            agent_name: None,
-            auto_memory: false,
-            acd_enabled: false,
-        })
+        if let Some(prompt) = custom_system_prompt {
+            builder = builder.system_prompt(prompt);
+        }
+        builder.build().await
     }
 
     /// Validate that the system prompt is the first message in the conversation history.
@@ -324,12 +291,34 @@ impl<W: UiWriter> Agent<W> {
         // Agent mode replaces the identity line but keeps all other instructions
         let has_tool_instructions = first_message
             .content
-            .contains("IMPORTANT: You must call tools to achieve goals");
+            .contains(SYSTEM_PROMPT_MARKER);
         if !has_tool_instructions {
             panic!("FATAL: First system message does not contain the system prompt. This likely means the README was added before the system prompt.");
         }
     }
 
+    /// Tools offered to native tool-calling providers: built-in, plugin and MCP tools,
+    /// narrowed to the builder's tool set if one was given
+    pub fn tool_definitions(&self) -> Vec<g3_providers::Tool> {
+        let mut tool_config = tool_definitions::ToolConfig::new(
+            self.config.webdriver.enabled,
+            self.config.computer_control.enabled,
+        );
+        // Exclude research tool for scout agent to prevent recursion
+        if self.agent_name.as_deref() == Some("scout") {
+            tool_config = tool_config.with_research_excluded();
+        }
+        let mut tools = tool_definitions::create_tool_definitions(tool_config);
+        tools.extend(tool_definitions::create_plugin_tool_definitions(
+            &self.config.plugin_tools,
+        ));
+        tools.extend(self.mcp_servers.tool_definitions());
+        if let Some(allowed) = &self.allowed_tools {
+            tools.retain(|tool| allowed.contains(&tool.name));
+        }
+        tools
+    }
+
     /// Convert cache config string to CacheControl enum
     fn parse_cache_control(cache_config: &str) -> Option<CacheControl> {
         match cache_config {
@@ -606,6 +595,18 @@ impl<W: UiWriter> Agent<W> {
                     16384 // Conservative default for other Databricks models
                 }
             }
+            // Gemini 1.5 and later accept 1M input tokens; max_tokens only caps output
+            "gemini" => 1_048_576,
+            // Ollama truncates to num_ctx, which g3 always sends explicitly
+            "ollama" => provider
+                .context_length()
+                .unwrap_or(g3_providers::ollama::DEFAULT_NUM_CTX),
+            "replay" => config
+                .providers
+                .replay
+                .get(config_name)
+                .and_then(|c| c.context_length)
+                .unwrap_or(config.agent.fallback_default_max_tokens as u32),
             _ => config.agent.fallback_default_max_tokens as u32,
         };
 
@@ -733,10 +734,27 @@ impl<W: UiWriter> Agent<W> {
             self.session_id = Some(self.generate_session_id(description));
         }
 
+        // Refuse new work once the run's budget is used up
+        self.check_budget()?;
+
+        // Number the turn after any checkpoints left by earlier runs of this session
+        let latest_checkpoint = self
+            .session_id
+            .as_deref()
+            .map(checkpoints::latest_turn)
+            .unwrap_or(0);
+        self.turn = checkpoints::Turn {
+            number: self.turn.number.max(latest_checkpoint) + 1,
+            prompt: description.to_string(),
+        };
+
         // Add user message to context window
         let mut user_message = {
             let provider = self.providers.get(None)?;
-            let content = format!("Task: {}", description);
+            let content = match self.pending_undo_note.take() {
+                Some(note) => format!("{}\n\nTask: {}", note, description),
+                None => format!("Task: {}", description),
+            };
 
             // Apply cache control if provider supports it
             if let Some(cache_config) = self.get_provider_cache_control() {
@@ -805,17 +823,8 @@ impl<W: UiWriter> Agent<W> {
         let provider_name = provider.name().to_string();
         let _has_native_tool_calling = provider.has_native_tool_calling();
         let _supports_cache_control = provider.supports_cache_control();
-        // Check if we should exclude the research tool (scout agent to prevent recursion)
-        let exclude_research = self.agent_name.as_deref() == Some("scout");
         let tools = if provider.has_native_tool_calling() {
-            let mut tool_config = tool_definitions::ToolConfig::new(
-                self.config.webdriver.enabled,
-                self.config.computer_control.enabled,
-            );
-            if exclude_research {
-                tool_config = tool_config.with_research_excluded();
-            }
-            Some(tool_definitions::create_tool_definitions(tool_config))
+            Some(self.tool_definitions())
         } else {
             None
         };
@@ -867,6 +876,7 @@ impl<W: UiWriter> Agent<W> {
             prompt_tokens: 100,                                   // Estimate
             completion_tokens: response_content.len() as u32 / 4, // Rough estimate
             total_tokens: 100 + (response_content.len() as u32 / 4),
+            ..Default::default()
         };
 
         // Update context window with estimated token usage
@@ -881,7 +891,8 @@ impl<W: UiWriter> Agent<W> {
             response_content.clone()
         };
         if !content_for_context.trim().is_empty() {
-            let assistant_message = Message::new(MessageRole::Assistant, content_for_context);
+            let assistant_message =
+                Message::new(MessageRole::Assistant, content_for_context.clone());
             self.context_window.add_message(assistant_message);
         } else {
             debug!("Assistant response was empty (likely only tool execution), skipping message addition");
@@ -890,6 +901,16 @@ impl<W: UiWriter> Agent<W> {
         // Save context window at the end of successful interaction
         self.save_context_window("completed");
 
+        self.run_lifecycle_hooks(
+            hooks::HookEvent::PostTurn,
+            serde_json::json!({
+                "turn": self.turn.number,
+                "prompt": self.turn.prompt,
+                "response": content_for_context,
+            }),
+        )
+        .await;
+
         // Check if we need to do 90% auto-compaction
         if self.pending_90_compaction {
             self.ui_writer
@@ -916,7 +937,117 @@ impl<W: UiWriter> Agent<W> {
         if self.quiet {
             return;
         }
-        session::save_context_window(self.session_id.as_deref(), &self.context_window, status);
+        session::save_context_window(
+            self.session_id.as_deref(),
+            &self.context_window,
+            &self.get_permission_log(),
+            status,
+        );
+        if let Some(ref session_id) = self.session_id {
+            session::save_cost_ledger(session_id, &self.get_cost_ledger());
+        }
+    }
+
+    /// Book a provider response's usage in the cost ledger under the active provider
+    fn record_usage(&self, usage: &g3_providers::Usage) {
+        let Ok(provider) = self.providers.get(None) else {
+            return;
+        };
+        let pricing = self.config.pricing_for(provider.model());
+        if let Ok(mut ledger) = self.cost_ledger.lock() {
+            ledger.record(provider.name(), provider.model(), self.agent_role, usage, pricing);
+        }
+    }
+
+    /// Snapshot of the provider spend recorded so far
+    pub fn get_cost_ledger(&self) -> CostLedger {
+        self.cost_ledger
+            .lock()
+            .map(|ledger| ledger.clone())
+            .unwrap_or_default()
+    }
+
+    /// Permission decisions made so far this session
+    pub fn get_permission_log(&self) -> Vec<permissions::PermissionLogEntry> {
+        self.permission_log
+            .lock()
+            .map(|log| log.clone())
+            .unwrap_or_default()
+    }
+
+    /// What the agent has seen of the files it read or wrote this session
+    pub fn get_file_fingerprints(&self) -> FileFingerprints {
+        self.file_fingerprints
+            .lock()
+            .map(|fingerprints| fingerprints.clone())
+            .unwrap_or_default()
+    }
+
+    /// Shared handle to the cost ledger, for agents that should book into the same run
+    pub fn cost_ledger_handle(&self) -> std::sync::Arc<std::sync::Mutex<CostLedger>> {
+        self.cost_ledger.clone()
+    }
+
+    /// Book this agent's usage into another agent's ledger (e.g. a coach into its player's)
+    pub fn set_cost_ledger(&mut self, ledger: std::sync::Arc<std::sync::Mutex<CostLedger>>) {
+        self.cost_ledger = ledger;
+    }
+
+    /// Set the role this agent's usage is booked under
+    pub fn set_agent_role(&mut self, role: AgentRole) {
+        self.agent_role = role;
+    }
+
+    /// Check the run against its budget limits (`max_cost`, `max_tokens_total`,
+    /// `max_wall_time_seconds`). Warns once per limit at 80%. Once a limit is reached
+    /// the session and its continuation are saved and a `BudgetExceeded` error is returned.
+    pub fn check_budget(&mut self) -> Result<()> {
+        if self.budget.is_unlimited() {
+            return Ok(());
+        }
+
+        match self.budget.check(&self.get_cost_ledger()) {
+            budget::BudgetStatus::WithinBudget => Ok(()),
+            budget::BudgetStatus::Warning(message) => {
+                warn!("Budget warning: {}", message);
+                self.ui_writer
+                    .print_context_status(&format!("⚠️ Budget warning: {}\n", message));
+                Ok(())
+            }
+            budget::BudgetStatus::Exceeded(exceeded) => Err(self.stop_for_budget(exceeded)),
+        }
+    }
+
+    /// Save the session after a budget ran out and return the error that stops the run
+    fn stop_for_budget(&mut self, exceeded: BudgetExceeded) -> anyhow::Error {
+        warn!("{}", exceeded);
+        self.ui_writer
+            .print_context_status(&format!("🛑 {}. Stopping and saving session.\n", exceeded));
+        self.save_context_window("budget_exceeded");
+        self.save_session_continuation(Some(exceeded.to_string()));
+        exceeded.into()
+    }
+
+    /// Warn when the cost budget can't see the active model's spend
+    pub(crate) fn warn_if_cost_budget_unpriced(&self) {
+        let Ok(provider) = self.providers.get(None) else {
+            return;
+        };
+        if let Some(warning) = budget::unpriced_cost_budget_warning(&self.config, provider.model()) {
+            warn!("{}", warning);
+            self.ui_writer
+                .print_context_status(&format!("⚠️ {}\n", warning));
+        }
+    }
+
+    /// When the wall-time budget started counting
+    pub fn budget_started(&self) -> Instant {
+        self.budget.started()
+    }
+
+    /// Count the wall-time budget from an earlier start (e.g. a coach joining its player's run)
+    pub fn set_budget_started(&mut self, started: Instant) {
+        self.budget.set_started(started);
     }
 
     /// Write context window summary to file
@@ -934,6 +1065,12 @@ impl<W: UiWriter> Agent<W> {
         &self.context_window
     }
 
+    /// Count context tokens with a custom tokenizer instead of the one picked for the
+    /// provider. Switching provider on failover picks a built-in tokenizer again.
+    pub fn set_tokenizer(&mut self, tokenizer: std::sync::Arc<dyn Tokenizer>) {
+        self.context_window.set_tokenizer(tokenizer);
+    }
+
     /// Add a message directly to the context window.
     /// Used for injecting discovery messages before the first LLM turn.
     pub fn add_message_to_context(&mut self, message: Message) {
@@ -978,8 +1115,6 @@ impl<W: UiWriter> Agent<W> {
     /// Manually trigger context compaction regardless of context window size
     /// Returns Ok(true) if compaction was successful, Ok(false) if it failed
     pub async fn force_compact(&mut self) -> Result<bool> {
-        use crate::compaction::{perform_compaction, CompactionConfig};
-
         debug!("Manual compaction triggered");
 
         self.ui_writer.print_context_status(&format!(
@@ -987,10 +1122,6 @@ impl<W: UiWriter> Agent<W> {
             self.context_window.percentage_used() as u32
         ));
 
-        let provider = self.providers.get(None)?;
-        let provider_name = provider.name().to_string();
-        let _ = provider; // Release borrow early
-
         // Get the latest user message to preserve it
         let latest_user_msg = self
             .context_window
@@ -1000,25 +1131,22 @@ impl<W: UiWriter> Agent<W> {
             .find(|m| matches!(m.role, MessageRole::User))
             .map(|m| m.content.clone());
 
-        let compaction_config = CompactionConfig {
-            provider_name: &provider_name,
-            latest_user_msg,
-        };
-
-        let result = perform_compaction(
-            &self.providers,
-            &mut self.context_window,
-            &self.config,
-            compaction_config,
-            &self.ui_writer,
-            &mut self.thinning_events,
-        )
-        .await?;
+        let result = self.compact_with_failover(latest_user_msg).await?;
 
+        if let Some(ref usage) = result.usage {
+            self.record_usage(usage);
+        }
         if result.success {
             self.ui_writer
                 .print_context_status("✅ Context compacted successfully.\n");
             self.compaction_events.push(result.chars_saved);
+            self.ui_writer
+                .notify_compaction(result.chars_saved, self.context_window.percentage_used());
+            self.run_lifecycle_hooks(
+                hooks::HookEvent::OnCompaction,
+                serde_json::json!({ "trigger": "manual", "chars_saved": result.chars_saved }),
+            )
+            .await;
             Ok(true)
         } else {
             self.ui_writer.print_context_status(
@@ -1088,14 +1216,11 @@ impl<W: UiWriter> Agent<W> {
             return Ok(false);
         }
 
-        use crate::compaction::{perform_compaction, CompactionConfig};
-
         self.ui_writer.print_context_status(&format!(
             "\n🗜️ Context window reaching capacity ({}%). Compacting...",
             self.context_window.percentage_used() as u32
         ));
 
-        let provider_name = self.providers.get(None)?.name().to_string();
         let latest_user_msg = request
             .messages
             .iter()
@@ -1103,26 +1228,24 @@ impl<W: UiWriter> Agent<W> {
             .find(|m| matches!(m.role, MessageRole::User))
             .map(|m| m.content.clone());
 
-        let compaction_config = CompactionConfig {
-            provider_name: &provider_name,
-            latest_user_msg,
-        };
-
-        let result = perform_compaction(
-            &self.providers,
-            &mut self.context_window,
-            &self.config,
-            compaction_config,
-            &self.ui_writer,
-            &mut self.thinning_events,
-        )
-        .await?;
+        let result = self.compact_with_failover(latest_user_msg).await?;
 
+        if let Some(ref usage) = result.usage {
+            self.record_usage(usage);
+        }
         if result.success {
             self.ui_writer
                 .print_context_status("✅ Context compacted successfully. Continuing...\n");
             self.compaction_events.push(result.chars_saved);
+            self.ui_writer
+                .notify_compaction(result.chars_saved, self.context_window.percentage_used());
+            self.run_lifecycle_hooks(
+                hooks::HookEvent::OnCompaction,
+                serde_json::json!({ "trigger": "auto", "chars_saved": result.chars_saved }),
+            )
+            .await;
             request.messages = self.context_window.conversation_history.clone();
+            self.prepare_request_for_provider(request);
             return Ok(true);
         }
 
@@ -1132,6 +1255,40 @@ impl<W: UiWriter> Agent<W> {
         ))
     }
 
+    /// Summarize the context with the active provider, falling over to the next provider
+    /// in the chain when the summary request fails
+    async fn compact_with_failover(
+        &mut self,
+        latest_user_msg: Option<String>,
+    ) -> Result<compaction::CompactionResult> {
+        use crate::compaction::{perform_compaction, CompactionConfig};
+
+        loop {
+            let provider_name = self.providers.get(None)?.name().to_string();
+            let compaction_config = CompactionConfig {
+                provider_name: &provider_name,
+                latest_user_msg: latest_user_msg.clone(),
+            };
+
+            let result = perform_compaction(
+                &self.providers,
+                &mut self.context_window,
+                &self.config,
+                compaction_config,
+                &self.ui_writer,
+                &mut self.thinning_events,
+            )
+            .await?;
+
+            if let Some(error) = result.error.as_deref().filter(|_| !result.success) {
+                if self.failover_to_next_provider(&anyhow::anyhow!(error.to_string())) {
+                    continue;
+                }
+            }
+            return Ok(result);
+        }
+    }
+
     /// Check if a tool call is a duplicate of the last tool call in the previous assistant message.
     /// Returns Some("DUP IN MSG") if it's a duplicate, None otherwise.
     fn check_duplicate_in_previous_message(&self, tool_call: &ToolCall) -> Option<String> {
@@ -1235,6 +1392,7 @@ impl<W: UiWriter> Agent<W> {
     pub fn get_stats(&self) -> String {
         use crate::stats::AgentStatsSnapshot;
 
+        let cost_ledger = self.get_cost_ledger();
         let snapshot = AgentStatsSnapshot {
             context_window: &self.context_window,
             thinning_events: &self.thinning_events,
@@ -1242,6 +1400,7 @@ impl<W: UiWriter> Agent<W> {
             first_token_times: &self.first_token_times,
             tool_call_metrics: &self.tool_call_metrics,
             provider_info: self.get_provider_info().ok(),
+            cost_ledger: &cost_ledger,
         };
 
         snapshot.format()
@@ -1272,6 +1431,9 @@ impl<W: UiWriter> Agent<W> {
             }
         };
 
+        // Coach agents may have booked into a shared ledger since the last save
+        session::save_cost_ledger(&session_id, &self.get_cost_ledger());
+
         // Get the session log path (now in .g3/sessions/<session_id>/session.json)
         let session_log_path = get_session_file(&session_id);
 
@@ -1301,7 +1463,7 @@ impl<W: UiWriter> Agent<W> {
                 truncate_to_word_boundary(content, 60)
             });
 
-        let continuation = SessionContinuation::new(
+        let mut continuation = SessionContinuation::new(
             self.is_agent_mode,
             self.agent_name.clone(),
             session_id,
@@ -1312,6 +1474,7 @@ impl<W: UiWriter> Agent<W> {
             todo_snapshot,
             working_directory,
         );
+        continuation.file_fingerprints = self.get_file_fingerprints();
 
         if let Err(e) = save_continuation(&continuation) {
             error!("Failed to save session continuation: {}", e);
@@ -1325,6 +1488,9 @@ impl<W: UiWriter> Agent<W> {
     pub fn set_agent_mode(&mut self, agent_name: &str) {
         self.is_agent_mode = true;
         self.agent_name = Some(agent_name.to_string());
+        if agent_name == "scout" {
+            self.agent_role = AgentRole::Scout;
+        }
         debug!("Agent mode enabled for agent: {}", agent_name);
     }
 
@@ -1365,9 +1531,16 @@ impl<W: UiWriter> Agent<W> {
         self.ui_writer.finish_streaming_markdown();
         self.save_context_window("completed");
 
+        let ttft = first_token_time.unwrap_or_else(|| stream_start.elapsed());
+        let turn_tokens = turn_accumulated_usage.as_ref().map(|u| u.total_tokens);
+        self.ui_writer.notify_turn_timing(
+            stream_start.elapsed(),
+            ttft,
+            turn_tokens,
+            self.context_window.percentage_used(),
+        );
+
         let final_response = if show_timing {
-            let ttft = first_token_time.unwrap_or_else(|| stream_start.elapsed());
-            let turn_tokens = turn_accumulated_usage.as_ref().map(|u| u.total_tokens);
             let timing_footer = streaming::format_timing_footer(
                 stream_start.elapsed(),
                 ttft,
@@ -1586,11 +1759,7 @@ Skip if nothing new. Be brief."#;
         let provider = self.providers.get(None)?;
         let provider_name = provider.name().to_string();
         let tools = if provider.has_native_tool_calling() {
-            let tool_config = tool_definitions::ToolConfig::new(
-                self.config.webdriver.enabled,
-                self.config.computer_control.enabled,
-            );
-            Some(tool_definitions::create_tool_definitions(tool_config))
+            Some(self.tool_definitions())
         } else {
             None
         };
@@ -1608,7 +1777,7 @@ Skip if nothing new. Be brief."#;
         };
 
         // Execute the reminder turn (show_timing = false to keep it quiet)
-        self.stream_completion_with_tools(request, false).await?;
+        self.stream_completion(request, false).await?;
 
         Ok(true)
     }
@@ -1622,6 +1791,103 @@ Skip if nothing new. Be brief."#;
         }
     }
 
+    /// Run the `on_session_end` hooks. Call once, when g3 is about to exit.
+    pub async fn end_session(&self) {
+        self.run_lifecycle_hooks(
+            hooks::HookEvent::OnSessionEnd,
+            serde_json::json!({ "turns": self.turn.number }),
+        )
+        .await;
+    }
+
+    /// Run the hooks for a non-tool event, reporting failures to the user
+    async fn run_lifecycle_hooks(&self, event: hooks::HookEvent, payload: serde_json::Value) {
+        let outcomes = hooks::run_hooks(
+            &self.config.hooks,
+            event,
+            None,
+            payload,
+            self.session_id.as_deref(),
+            self.working_dir.as_deref(),
+            false,
+        )
+        .await;
+        for outcome in outcomes.iter().filter(|outcome| !outcome.success) {
+            self.ui_writer.print_context_status(&format!(
+                "⚠️ {} hook `{}` failed: {}",
+                event.name(),
+                outcome.command,
+                outcome.message
+            ));
+        }
+    }
+
+    /// Throw away the persistent shell (killing anything it left running); the next
+    /// persistent `shell` call starts a fresh one
+    pub fn reset_shell_session(&mut self) {
+        self.shell_session = std::sync::Arc::new(tokio::sync::Mutex::new(None));
+    }
+
+    /// Every file the session changed, diffed against its state when the session first touched it
+    pub fn session_diff(&self) -> Result<Vec<session_diff::FileDiff>> {
+        match &self.session_id {
+            Some(session_id) => session_diff::session_diff(session_id),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Checkpoints of the files edited in each turn of this session, oldest first
+    pub fn checkpoints(&self) -> Result<Vec<checkpoints::Checkpoint>> {
+        match &self.session_id {
+            Some(session_id) => checkpoints::list_checkpoints(session_id),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Undo the file edits of the latest turn that made any.
+    /// Returns the restored checkpoint, or `None` if there was nothing to undo.
+    pub fn undo_last_turn(&mut self) -> Result<Option<checkpoints::Checkpoint>> {
+        match self.checkpoints()?.last() {
+            Some(checkpoint) => Ok(self.undo_to_turn(checkpoint.turn)?.into_iter().next()),
+            None => Ok(None),
+        }
+    }
+
+    /// Undo the file edits of `turn` and every later turn, newest first, leaving files as
+    /// they were before `turn`. Returns the restored checkpoints.
+    pub fn undo_to_turn(&mut self, turn: u32) -> Result<Vec<checkpoints::Checkpoint>> {
+        let Some(session_id) = self.session_id.clone() else {
+            return Ok(Vec::new());
+        };
+        let mut restored = Vec::new();
+        for checkpoint in self.checkpoints()?.iter().rev() {
+            if checkpoint.turn < turn {
+                break;
+            }
+            if let Some(checkpoint) = checkpoints::restore_checkpoint(&session_id, checkpoint.turn)? {
+                restored.push(checkpoint);
+            }
+        }
+
+        if !restored.is_empty() {
+            let paths: Vec<String> = restored
+                .iter()
+                .flat_map(|checkpoint| &checkpoint.files)
+                .map(|file| file.path.display().to_string())
+                .collect();
+            let note = format!(
+                "Note: the user undid your file edits from turn(s) {} of this session. These files were restored to their earlier state: {}. Re-read them before editing.",
+                restored.iter().map(|c| c.turn.to_string()).collect::<Vec<_>>().join(", "),
+                paths.join(", ")
+            );
+            self.pending_undo_note = Some(match self.pending_undo_note.take() {
+                Some(earlier) => format!("{}\n{}", earlier, note),
+                None => note,
+            });
+        }
+        Ok(restored)
+    }
+
     /// Clear session state and continuation artifacts (for /clear command)
     pub fn clear_session(&mut self) {
         use crate::session_continuation::clear_continuation;
@@ -1634,6 +1900,11 @@ Skip if nothing new. Be brief."#;
             error!("Failed to clear continuation artifacts: {}", e);
         }
 
+        self.reset_shell_session();
+        if let Ok(mut fingerprints) = self.file_fingerprints.lock() {
+            fingerprints.clear();
+        }
+
         debug!("Session cleared");
     }
 
@@ -1645,6 +1916,11 @@ Skip if nothing new. Be brief."#;
     ) -> Result<bool> {
         use std::path::PathBuf;
 
+        // Files the agent saw before the resume still count as read
+        if let Ok(mut fingerprints) = self.file_fingerprints.lock() {
+            *fingerprints = continuation.file_fingerprints.clone();
+        }
+
         let session_log_path = PathBuf::from(&continuation.session_log_path);
 
         // If context < 80%, try to restore full context
@@ -1732,6 +2008,9 @@ Skip if nothing new. Be brief."#;
 
         // Update session ID to the new session
         self.session_id = Some(continuation.session_id.clone());
+        self.reset_shell_session();
+        self.turn = checkpoints::Turn::default();
+        self.pending_undo_note = None;
 
         // Update agent mode info from continuation
         self.is_agent_mode = continuation.is_agent_mode;
@@ -1754,16 +2033,30 @@ Skip if nothing new. Be brief."#;
         request: CompletionRequest,
         show_timing: bool,
     ) -> Result<TaskResult> {
-        self.stream_completion_with_tools(request, show_timing)
+        // The wall-time budget is a hard deadline, even mid-stream or mid-tool
+        let Some(remaining) = self.budget.remaining_wall_time() else {
+            return self.stream_completion_with_tools(request, show_timing).await;
+        };
+        match tokio::time::timeout(remaining, self.stream_completion_with_tools(request, show_timing))
             .await
+        {
+            Ok(result) => result,
+            Err(_) => {
+                let exceeded = self
+                    .budget
+                    .wall_time_exceeded()
+                    .expect("a deadline implies a wall-time budget");
+                Err(self.stop_for_budget(exceeded))
+            }
+        }
     }
 
     /// Create tool definitions for native tool calling providers
 
     /// Helper method to stream with retry logic
     async fn stream_with_retry(
-        &self,
-        request: &CompletionRequest,
+        &mut self,
+        request: &mut CompletionRequest,
         error_context: &error_handling::ErrorContext,
     ) -> Result<g3_providers::CompletionStream> {
         use crate::error_handling::{calculate_retry_delay, classify_error, ErrorType};
@@ -1793,27 +2086,123 @@ Skip if nothing new. Be brief."#;
                     );
                     return Ok(stream);
                 }
-                Err(e) if attempt < max_attempts => {
-                    if matches!(classify_error(&e), ErrorType::Recoverable(_)) {
+                Err(e) => {
+                    let recoverable = matches!(classify_error(&e), ErrorType::Recoverable(_));
+                    if recoverable && attempt < max_attempts {
                         let delay = calculate_retry_delay(attempt, self.is_autonomous);
                         warn!(
                             "Recoverable error on attempt {}/{}: {}. Retrying in {:?}...",
                             attempt, max_attempts, e, delay
                         );
                         tokio::time::sleep(delay).await;
-                    } else {
-                        error_context.clone().log_error(&e);
-                        return Err(e);
+                        continue;
                     }
-                }
-                Err(e) => {
+
                     error_context.clone().log_error(&e);
+
+                    // Non-recoverable or retries exhausted: try the next provider in the chain
+                    if self.failover_to_next_provider(&e) {
+                        self.prepare_request_for_provider(request);
+                        attempt = 0;
+                        continue;
+                    }
                     return Err(e);
                 }
             }
         }
     }
 
+    /// Switch the active provider to the next one in the configured failover chain.
+    ///
+    /// The context window is left untouched, so the conversation continues on the new
+    /// provider. The switch is sticky for the rest of the session. Returns false when
+    /// there is no provider left to fall over to.
+    fn failover_to_next_provider(&mut self, error: &anyhow::Error) -> bool {
+        let Ok(current) = self.providers.get(None).map(|p| p.name().to_string()) else {
+            return false;
+        };
+        let Some(next) = provider_config::next_failover_provider(&self.config, &current) else {
+            return false;
+        };
+        let next = next.to_string();
+
+        if let Err(e) = self
+            .providers
+            .set_default(&provider_config::registered_name(&self.config, &next))
+        {
+            warn!("Failover provider {} is not available: {}", next, e);
+            return false;
+        }
+        self.warn_if_cost_budget_unpriced();
+
+        // The new provider's model may count tokens differently, and may call tools
+        // differently too
+        if let Ok(provider) = self.providers.get(None) {
+            let tokenizer = tokenizer::for_provider(provider.name(), provider.model());
+            let native_tool_calling = provider.has_native_tool_calling();
+            self.context_window.set_tokenizer(tokenizer);
+            self.switch_default_system_prompt(native_tool_calling);
+        }
+
+        let reason = error.to_string();
+        let reason = reason.lines().next().unwrap_or_default();
+        warn!("Provider {} failed ({}), failing over to {}", current, reason, next);
+        self.ui_writer.print_context_status(&format!(
+            "⚠️ Provider {} failed: {}\n   Failing over to {}\n",
+            current,
+            truncate_to_word_boundary(reason, 200),
+            next
+        ));
+        true
+    }
+
+    /// Swap the default system prompt for the one matching the provider's tool-calling
+    /// style. Custom (agent mode) prompts are left alone.
+    fn switch_default_system_prompt(&mut self, native_tool_calling: bool) {
+        use crate::prompts::{get_system_prompt_for_native, SYSTEM_PROMPT_FOR_NON_NATIVE_TOOL_USE};
+
+        let native = get_system_prompt_for_native();
+        let (from, to) = if native_tool_calling {
+            (SYSTEM_PROMPT_FOR_NON_NATIVE_TOOL_USE, native.as_str())
+        } else {
+            (native.as_str(), SYSTEM_PROMPT_FOR_NON_NATIVE_TOOL_USE)
+        };
+        if let Some(system) = self.context_window.conversation_history.first_mut() {
+            if matches!(system.role, MessageRole::System) && system.content == from {
+                system.content = to.to_string();
+            }
+        }
+    }
+
+    /// Rebuild the provider-specific parts of a request after a failover: the system
+    /// prompt, native tool definitions, max_tokens and temperature.
+    fn prepare_request_for_provider(&self, request: &mut CompletionRequest) {
+        let Ok(provider) = self.providers.get(None) else {
+            return;
+        };
+        let provider_name = provider.name().to_string();
+        request.tools = if provider.has_native_tool_calling() {
+            Some(self.tool_definitions())
+        } else {
+            None
+        };
+
+        if let (Some(first), Some(system)) = (
+            request.messages.first_mut(),
+            self.context_window.conversation_history.first(),
+        ) {
+            if matches!(first.role, MessageRole::System) && matches!(system.role, MessageRole::System) {
+                first.content = system.content.clone();
+            }
+        }
+
+        if request.max_tokens.is_some() {
+            let max_tokens = self.resolve_max_tokens(&provider_name);
+            request.max_tokens = Some(self.preflight_validate_max_tokens(&provider_name, max_tokens).0);
+        }
+        request.temperature = Some(self.resolve_temperature(&provider_name));
+    }
+
     async fn stream_completion_with_tools(
         &mut self,
         mut request: CompletionRequest,
@@ -1860,6 +2249,9 @@ Skip if nothing new. Be brief."#;
                 break;
             }
 
+            // Stop before the next request if the run's budget is used up
+            self.check_budget()?;
+
             // Add a small delay between iterations to prevent "model busy" errors
             if iteration_count > 1 {
                 tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
@@ -1905,7 +2297,7 @@ Skip if nothing new. Be brief."#;
             );
 
             // Try to get stream with retry logic
-            let mut stream = match self.stream_with_retry(&request, &error_context).await {
+            let mut stream = match self.stream_with_retry(&mut request, &error_context).await {
                 Ok(s) => s,
                 Err(e) => {
                     error!("Failed to start stream: {}", e);
@@ -1917,7 +2309,7 @@ Skip if nothing new. Be brief."#;
                         );
                         tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
 
-                        match self.stream_with_retry(&request, &error_context).await {
+                        match self.stream_with_retry(&mut request, &error_context).await {
                             Ok(s) => s,
                             Err(e2) => {
                                 error!("Failed to start stream after retry: {}", e2);
@@ -1941,7 +2333,9 @@ Skip if nothing new. Be brief."#;
             let mut raw_chunks: Vec<String> = Vec::new(); // Store raw chunks for debugging
 
             let mut accumulated_usage: Option<g3_providers::Usage> = None;
+            let mut usage_recorded = false; // Whether this stream's usage is in the cost ledger
             let mut stream_stop_reason: Option<String> = None; // Track why the stream stopped
+            let mut failed_over = false; // Stream failed mid-way and the provider was switched
 
             while let Some(chunk_result) = stream.next().await {
                 match chunk_result {
@@ -2129,6 +2523,11 @@ Skip if nothing new. Be brief."#;
                                 }
                             };
                             let exec_duration = exec_start.elapsed();
+                            self.ui_writer.notify_tool_result(
+                                &tool_call.tool,
+                                &tool_result,
+                                exec_duration,
+                            );
 
                             // Track tool call metrics
                             let tool_success = !tool_result.contains("❌");
@@ -2304,16 +2703,7 @@ Skip if nothing new. Be brief."#;
                             // Ensure tools are included for native providers in subsequent iterations
                             let provider_for_tools = self.providers.get(None)?;
                             if provider_for_tools.has_native_tool_calling() {
-                                let mut tool_config = tool_definitions::ToolConfig::new(
-                                    self.config.webdriver.enabled,
-                                    self.config.computer_control.enabled,
-                                );
-                                // Exclude research tool for scout agent to prevent recursion
-                                if self.agent_name.as_deref() == Some("scout") {
-                                    tool_config = tool_config.with_research_excluded();
-                                }
-                                request.tools =
-                                    Some(tool_definitions::create_tool_definitions(tool_config));
+                                request.tools = Some(self.tool_definitions());
                             }
 
                             // DO NOT add final_display_content to full_response here!
@@ -2389,6 +2779,11 @@ Skip if nothing new. Be brief."#;
                             debug!("Stream finished: tool_executed={}, current_response_len={}, full_response_len={}, chunks_received={}",
                                 tool_executed, current_response.len(), full_response.len(), chunks_received);
 
+                            if let Some(ref usage) = accumulated_usage {
+                                self.record_usage(usage);
+                                usage_recorded = true;
+                            }
+
                             // Capture the stop reason from the final chunk
                             if let Some(ref reason) = chunk.stop_reason {
                                 debug!("Stream stop_reason: {}", reason);
@@ -2523,6 +2918,14 @@ Skip if nothing new. Be brief."#;
                             error!("{}", error_details);
                             warn!("Stream error after tool execution, attempting to continue");
                             break; // Break to outer loop to start new stream
+                        } else if self.failover_to_next_provider(&e) {
+                            // Nothing from this stream was kept, so ask the next provider
+                            // the same question
+                            error!("{}", error_details);
+                            self.prepare_request_for_provider(&mut request);
+                            self.ui_writer.reset_json_filter();
+                            failed_over = true;
+                            break;
                         } else {
                             // Log raw chunks before failing
                             error!("Fatal streaming error. Raw chunks received before error:");
@@ -2535,14 +2938,24 @@ Skip if nothing new. Be brief."#;
                 }
             }
 
+            if failed_over {
+                if let Some(usage) = accumulated_usage.filter(|_| !usage_recorded) {
+                    self.record_usage(&usage);
+                }
+                continue;
+            }
+
             // Update context window with actual usage if available
             if let Some(usage) = accumulated_usage {
+                if !usage_recorded {
+                    self.record_usage(&usage);
+                }
                 debug!("Updating context window with actual usage from stream");
                 self.context_window.update_usage_from_response(&usage);
             } else {
                 // Fall back to estimation if no usage data was provided
                 debug!("No usage data from stream, using estimation");
-                let estimated_tokens = ContextWindow::estimate_tokens(&current_response);
+                let estimated_tokens = self.context_window.count_tokens(&current_response);
                 self.context_window.add_streaming_tokens(estimated_tokens);
             }
 
@@ -2735,7 +3148,8 @@ Skip if nothing new. Be brief."#;
 
     pub async fn execute_tool(&mut self, tool_call: &ToolCall) -> Result<String> {
         // Tool tracking is handled by execute_tool_in_dir
-        self.execute_tool_in_dir(tool_call, None).await
+        let working_dir = self.working_dir.clone();
+        self.execute_tool_in_dir(tool_call, working_dir.as_deref()).await
     }
 
     /// Execute a tool with an optional working directory (for discovery commands)
@@ -2780,6 +3194,20 @@ Skip if nothing new. Be brief."#;
         );
         debug!("======================");
 
+        // Tools outside the builder's tool set don't exist as far as the model knows
+        if let Some(allowed) = &self.allowed_tools {
+            if !allowed.contains(&tool_call.tool) {
+                warn!("Tool not in the agent's tool set: {}", tool_call.tool);
+                return Ok(format!("❓ Unknown tool: {}", tool_call.tool));
+            }
+        }
+
+        let supports_vision = self
+            .providers
+            .get(None)
+            .map(|provider| provider.supports_vision())
+            .unwrap_or(false);
+
         // Create tool context for dispatch
         let mut ctx = tools::executor::ToolContext {
             config: &self.config,
@@ -2790,12 +3218,19 @@ Skip if nothing new. Be brief."#;
             webdriver_session: &self.webdriver_session,
             webdriver_process: &self.webdriver_process,
             background_process_manager: &self.background_process_manager,
+            shell_session: &self.shell_session,
+            mcp_servers: &self.mcp_servers,
+            turn: &self.turn,
             todo_content: &self.todo_content,
+            file_fingerprints: &self.file_fingerprints,
+            permission_log: &self.permission_log,
             pending_images: &mut self.pending_images,
+            supports_vision,
             is_autonomous: self.is_autonomous,
             requirements_sha: self.requirements_sha.as_deref(),
             context_total_tokens: self.context_window.total_tokens,
             context_used_tokens: self.context_window.used_tokens,
+            token_counter: self.context_window.token_counter().clone(),
         };
 
         // Dispatch to the appropriate tool handler
@@ -2806,7 +3241,7 @@ Skip if nothing new. Be brief."#;
 }
 
 // Re-export utility functions
-pub use utils::apply_unified_diff_to_string;
+pub use utils::{apply_unified_diff_to_string, apply_unified_diff_with_report, DiffApplication};
 use utils::truncate_to_word_boundary;
 
 /// Parse insertions and deletions from a str_replace result.
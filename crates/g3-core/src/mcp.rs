@@ -0,0 +1,413 @@
+//! Model Context Protocol (MCP) client for external tool servers.
+//!
+//! Each server declared under `[mcp_servers.<name>]` is launched as a child process
+//! speaking newline-delimited JSON-RPC 2.0 over stdio. At startup g3 performs the
+//! `initialize` handshake and `tools/list`; the listed tools are offered to the model
+//! next to the built-in ones, and calls to them are forwarded with `tools/call`.
+//!
+//! A server that fails to start is skipped with a warning rather than stopping g3.
+
+use anyhow::{anyhow, bail, Context, Result};
+use g3_config::McpServerConfig;
+use g3_providers::Tool;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::ToolCall;
+
+/// MCP protocol revision g3 speaks, as a client and as a server
+pub(crate) const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Tool names providers accept: `^[a-zA-Z0-9_-]{1,64}$`
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// The running MCP servers and the tools they provide
+#[derive(Default)]
+pub struct McpServers {
+    servers: Vec<McpServer>,
+    tools: Vec<McpTool>,
+}
+
+/// A server tool as offered to the model
+struct McpTool {
+    /// Index of the owning server in `McpServers::servers`
+    server: usize,
+    /// The tool's name on the server
+    remote_name: String,
+    /// Definition sent to the model; its name is unique across built-in and server tools
+    definition: Tool,
+}
+
+impl McpServers {
+    /// Start every enabled server in `configs`, in name order. Tools whose names clash
+    /// with `reserved` (the built-in tools) or an earlier server's tools are offered as
+    /// `<server>__<tool>`. Returns the servers and a warning for each one that failed.
+    pub async fn start(
+        configs: &HashMap<String, McpServerConfig>,
+        reserved: &[String],
+    ) -> (Self, Vec<String>) {
+        let mut names: Vec<&String> = configs
+            .iter()
+            .filter(|(_, config)| config.enabled)
+            .map(|(name, _)| name)
+            .collect();
+        names.sort();
+
+        let mut taken: HashSet<String> = reserved.iter().cloned().collect();
+        let mut servers = Self::default();
+        let mut warnings = Vec::new();
+        for name in names {
+            let (server, tools) = match McpServer::start(name, &configs[name]).await {
+                Ok(started) => started,
+                Err(e) => {
+                    warn!("Failed to start MCP server '{}': {:#}", name, e);
+                    warnings.push(format!("MCP server '{}' unavailable: {:#}", name, e));
+                    continue;
+                }
+            };
+            debug!("MCP server '{}' provides {} tool(s)", name, tools.len());
+
+            let index = servers.servers.len();
+            for mut definition in tools {
+                let remote_name = definition.name.clone();
+                let mut exposed = sanitize_tool_name(&remote_name);
+                if taken.contains(&exposed) {
+                    exposed = sanitize_tool_name(&format!("{}__{}", name, remote_name));
+                }
+                if !taken.insert(exposed.clone()) {
+                    warn!("Skipping MCP tool '{}' from '{}': name already in use", remote_name, name);
+                    continue;
+                }
+                definition.name = exposed;
+                servers.tools.push(McpTool {
+                    server: index,
+                    remote_name,
+                    definition,
+                });
+            }
+            servers.servers.push(server);
+        }
+        (servers, warnings)
+    }
+
+    /// Definitions of all server tools, to append to the built-in ones
+    pub fn tool_definitions(&self) -> Vec<Tool> {
+        self.tools.iter().map(|tool| tool.definition.clone()).collect()
+    }
+
+    /// Names of the running servers
+    pub fn server_names(&self) -> Vec<&str> {
+        self.servers.iter().map(|server| server.name.as_str()).collect()
+    }
+
+    /// Run `tool_call` on the server that owns it.
+    /// Returns `None` if no server provides a tool with that name.
+    pub async fn call(&self, tool_call: &ToolCall) -> Option<Result<String>> {
+        let tool = self
+            .tools
+            .iter()
+            .find(|tool| tool.definition.name == tool_call.tool)?;
+        let server = &self.servers[tool.server];
+        let arguments = match &tool_call.args {
+            Value::Null => json!({}),
+            args => args.clone(),
+        };
+        debug!("Calling MCP tool '{}' on '{}'", tool.remote_name, server.name);
+        Some(Ok(match server.call_tool(&tool.remote_name, arguments).await {
+            Ok(output) => output,
+            Err(e) => format!("❌ MCP server '{}' failed to run {}: {:#}", server.name, tool.remote_name, e),
+        }))
+    }
+}
+
+/// A running MCP server process
+struct McpServer {
+    name: String,
+    timeout: Duration,
+    connection: Mutex<Connection>,
+}
+
+/// The server's stdio, and the child process (killed when this is dropped)
+struct Connection {
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    next_id: u64,
+}
+
+impl McpServer {
+    /// Launch the server, perform the handshake and list its tools
+    async fn start(name: &str, config: &McpServerConfig) -> Result<(Self, Vec<Tool>)> {
+        let program = shellexpand::tilde(&config.command).to_string();
+        let mut command = tokio::process::Command::new(&program);
+        command
+            .args(&config.args)
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(cwd) = &config.cwd {
+            command.current_dir(shellexpand::tilde(cwd).as_ref());
+        }
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("failed to run '{}'", program))?;
+
+        let stdin = child.stdin.take().context("server stdin unavailable")?;
+        let stdout = child.stdout.take().context("server stdout unavailable")?;
+        if let Some(stderr) = child.stderr.take() {
+            let name = name.to_string();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    debug!("[mcp:{}] {}", name, line);
+                }
+            });
+        }
+
+        let server = Self {
+            name: name.to_string(),
+            timeout: Duration::from_secs(config.timeout_seconds),
+            connection: Mutex::new(Connection {
+                _child: child,
+                stdin,
+                stdout: BufReader::new(stdout).lines(),
+                next_id: 1,
+            }),
+        };
+
+        let initialized = server
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "g3", "version": env!("CARGO_PKG_VERSION") },
+                }),
+            )
+            .await?;
+        let server_info = initialized.get("serverInfo").cloned().unwrap_or_default();
+        debug!("MCP server '{}' initialized: {}", name, server_info);
+        server.notify("notifications/initialized").await?;
+
+        let tools = server.list_tools().await?;
+        Ok((server, tools))
+    }
+
+    /// All pages of `tools/list`, as tool definitions
+    async fn list_tools(&self) -> Result<Vec<Tool>> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self.request("tools/list", params).await?;
+            for tool in result.get("tools").and_then(Value::as_array).into_iter().flatten() {
+                let Some(name) = tool.get("name").and_then(Value::as_str) else {
+                    continue;
+                };
+                let description = tool
+                    .get("description")
+                    .and_then(Value::as_str)
+                    .filter(|description| !description.trim().is_empty())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("Tool '{}' from the {} MCP server", name, self.name));
+                let input_schema = tool
+                    .get("inputSchema")
+                    .filter(|schema| schema.is_object())
+                    .cloned()
+                    .unwrap_or_else(|| json!({ "type": "object", "properties": {} }));
+                tools.push(Tool {
+                    name: name.to_string(),
+                    description,
+                    input_schema,
+                });
+            }
+            cursor = result
+                .get("nextCursor")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    /// Run a tool and render its result content as text
+    async fn call_tool(&self, name: &str, arguments: Value) -> Result<String> {
+        let result = self
+            .request("tools/call", json!({ "name": name, "arguments": arguments }))
+            .await?;
+
+        let mut parts = Vec::new();
+        for item in result.get("content").and_then(Value::as_array).into_iter().flatten() {
+            let part = match item.get("type").and_then(Value::as_str) {
+                Some("text") => item.get("text").and_then(Value::as_str).unwrap_or_default().to_string(),
+                Some("image") => format!(
+                    "[image: {}]",
+                    item.get("mimeType").and_then(Value::as_str).unwrap_or("unknown type")
+                ),
+                Some("resource") => {
+                    let resource = item.get("resource").unwrap_or(&Value::Null);
+                    match resource.get("text").and_then(Value::as_str) {
+                        Some(text) => text.to_string(),
+                        None => format!(
+                            "[resource: {}]",
+                            resource.get("uri").and_then(Value::as_str).unwrap_or("unknown")
+                        ),
+                    }
+                }
+                _ => item.to_string(),
+            };
+            parts.push(part);
+        }
+        if parts.is_empty() {
+            if let Some(structured) = result.get("structuredContent") {
+                parts.push(structured.to_string());
+            }
+        }
+
+        let output = parts.join("\n");
+        if result.get("isError").and_then(Value::as_bool).unwrap_or(false) {
+            Ok(format!("❌ {}", output))
+        } else if output.is_empty() {
+            Ok("✅ Done (no output)".to_string())
+        } else {
+            Ok(output)
+        }
+    }
+
+    /// Send a request and wait for its response, up to the server's timeout
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let mut connection = self.connection.lock().await;
+        let id = connection.next_id;
+        connection.next_id += 1;
+        // A server that stops reading its stdin blocks the write, so it is timed too
+        let exchange = async {
+            connection
+                .send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+                .await?;
+            connection.receive(id).await
+        };
+        tokio::time::timeout(self.timeout, exchange)
+            .await
+            .map_err(|_| {
+                anyhow!(
+                    "no answer to {} within {}s",
+                    method,
+                    self.timeout.as_secs()
+                )
+            })?
+    }
+
+    async fn notify(&self, method: &str) -> Result<()> {
+        let mut connection = self.connection.lock().await;
+        connection
+            .send(&json!({ "jsonrpc": "2.0", "method": method }))
+            .await
+    }
+}
+
+impl Connection {
+    async fn send(&mut self, message: &Value) -> Result<()> {
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        self.stdin
+            .write_all(line.as_bytes())
+            .await
+            .context("server closed its input")?;
+        self.stdin.flush().await?;
+        Ok(())
+    }
+
+    /// Read messages until the response to request `id` arrives. Notifications and
+    /// late responses to earlier (timed out) requests are skipped; requests from the
+    /// server are refused, since g3 offers no client capabilities.
+    async fn receive(&mut self, id: u64) -> Result<Value> {
+        loop {
+            let Some(line) = self.stdout.next_line().await? else {
+                bail!("server exited");
+            };
+            let message: Value = match serde_json::from_str(&line) {
+                Ok(message) => message,
+                Err(_) => {
+                    debug!("Ignoring non-JSON line from MCP server: {}", line);
+                    continue;
+                }
+            };
+
+            if message.get("method").is_some() {
+                if let Some(request_id) = message.get("id") {
+                    self.send(&json!({
+                        "jsonrpc": "2.0",
+                        "id": request_id,
+                        "error": { "code": -32601, "message": "Method not supported by g3" },
+                    }))
+                    .await?;
+                }
+                continue;
+            }
+            if message.get("id").and_then(Value::as_u64) != Some(id) {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                bail!(
+                    "{} (code {})",
+                    error.get("message").and_then(Value::as_str).unwrap_or("unknown error"),
+                    error.get("code").unwrap_or(&Value::Null)
+                );
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+}
+
+/// Make a tool name acceptable to providers
+fn sanitize_tool_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .take(MAX_TOOL_NAME_LEN)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_tool_name() {
+        assert_eq!(sanitize_tool_name("create_ticket"), "create_ticket");
+        assert_eq!(sanitize_tool_name("db.query rows"), "db_query_rows");
+        assert_eq!(sanitize_tool_name(&"x".repeat(80)).len(), 64);
+    }
+
+    #[tokio::test]
+    async fn test_missing_server_is_skipped_with_warning() {
+        let mut configs = HashMap::new();
+        configs.insert(
+            "broken".to_string(),
+            McpServerConfig {
+                command: "/nonexistent/g3-mcp-server".to_string(),
+                args: Vec::new(),
+                env: HashMap::new(),
+                cwd: None,
+                enabled: true,
+                timeout_seconds: 5,
+            },
+        );
+        let (servers, warnings) = McpServers::start(&configs, &[]).await;
+        assert!(servers.server_names().is_empty());
+        assert!(servers.tool_definitions().is_empty());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("broken"), "{}", warnings[0]);
+    }
+}
@@ -0,0 +1,222 @@
+//! Serve g3's built-in tools over MCP (`g3 mcp-serve`).
+//!
+//! Speaks newline-delimited JSON-RPC 2.0 on stdin/stdout. Tool calls go through the
+//! same `dispatch_tool` path the agent uses, so permissions, tool hooks, checkpoints
+//! and the session diff all apply. No LLM provider is needed: there is no agent loop.
+//!
+//! Tools that only make sense inside the agent (`research`, `rehydrate`) or that draw
+//! to the terminal (`read_image`, `screenshot`) are not offered. Calls that the
+//! `[permissions]` policy would ask about are refused, since there is nobody to ask.
+
+use anyhow::Result;
+use g3_config::Config;
+use g3_providers::Tool;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::{Mutex, RwLock};
+use tracing::debug;
+
+use crate::background_process::BackgroundProcessManager;
+use crate::checkpoints::Turn;
+use crate::file_fingerprints::FileFingerprints;
+use crate::mcp::{McpServers, PROTOCOL_VERSION};
+use crate::permissions::PermissionLogEntry;
+use crate::tool_definitions::{create_tool_definitions, ToolConfig};
+use crate::tools::executor::ToolContext;
+use crate::ui_writer::NullUiWriter;
+use crate::webdriver_session::WebDriverSession;
+use crate::{hooks, paths, session, tool_dispatch, ToolCall};
+
+/// Built-in tools not offered over MCP
+const EXCLUDED_TOOLS: &[&str] = &["research", "rehydrate", "read_image", "screenshot"];
+
+/// Context size reported to tools that size their output by it (e.g. `read_file`)
+const DEFAULT_CONTEXT_TOKENS: u32 = 200_000;
+
+/// State shared by the tool calls of one `mcp-serve` process
+pub struct McpToolServer {
+    config: Config,
+    session_id: String,
+    tools: Vec<Tool>,
+    ui_writer: NullUiWriter,
+    webdriver_session: Arc<RwLock<Option<Arc<Mutex<WebDriverSession>>>>>,
+    webdriver_process: Arc<RwLock<Option<tokio::process::Child>>>,
+    background_process_manager: Arc<BackgroundProcessManager>,
+    shell_session: Arc<Mutex<Option<g3_execution::ShellSession>>>,
+    mcp_servers: Arc<McpServers>,
+    turn: Turn,
+    todo_content: Arc<RwLock<String>>,
+    file_fingerprints: Arc<std::sync::Mutex<FileFingerprints>>,
+    permission_log: Arc<std::sync::Mutex<Vec<PermissionLogEntry>>>,
+}
+
+impl McpToolServer {
+    pub fn new(config: Config) -> Self {
+        let tools = create_tool_definitions(
+            ToolConfig::new(config.webdriver.enabled, false).with_research_excluded(),
+        )
+        .into_iter()
+        .filter(|tool| !EXCLUDED_TOOLS.contains(&tool.name.as_str()))
+        .collect();
+
+        Self {
+            config,
+            session_id: session::generate_session_id("mcp serve", None),
+            tools,
+            ui_writer: NullUiWriter,
+            webdriver_session: Arc::new(RwLock::new(None)),
+            webdriver_process: Arc::new(RwLock::new(None)),
+            background_process_manager: Arc::new(BackgroundProcessManager::new(
+                paths::get_background_processes_dir(),
+            )),
+            shell_session: Arc::new(Mutex::new(None)),
+            mcp_servers: Arc::new(McpServers::default()),
+            turn: Turn::default(),
+            todo_content: Arc::new(RwLock::new(String::new())),
+            file_fingerprints: Arc::new(std::sync::Mutex::new(FileFingerprints::new())),
+            permission_log: Arc::new(std::sync::Mutex::new(Vec::new())),
+        }
+    }
+
+    /// The tools offered to clients
+    pub fn tool_definitions(&self) -> &[Tool] {
+        &self.tools
+    }
+
+    /// Session that todos, checkpoints and logs of this server are stored under
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Answer MCP requests on stdin until it closes
+    pub async fn serve_stdio(mut self) -> Result<()> {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        let mut stdout = tokio::io::stdout();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => self.handle_message(&message).await,
+                Err(e) => Some(error_response(Value::Null, -32700, &format!("Parse error: {}", e))),
+            };
+            if let Some(response) = response {
+                let mut line = serde_json::to_string(&response)?;
+                line.push('\n');
+                stdout.write_all(line.as_bytes()).await?;
+                stdout.flush().await?;
+            }
+        }
+        debug!("MCP client closed stdin, shutting down");
+        hooks::run_hooks(
+            &self.config.hooks,
+            hooks::HookEvent::OnSessionEnd,
+            None,
+            json!({}),
+            Some(&self.session_id),
+            None,
+            false,
+        )
+        .await;
+        Ok(())
+    }
+
+    /// Handle one JSON-RPC message. Returns the response, or `None` for notifications.
+    pub async fn handle_message(&mut self, message: &Value) -> Option<Value> {
+        let id = message.get("id").cloned()?;
+        let method = message.get("method").and_then(Value::as_str).unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        debug!("MCP request {}: {}", id, method);
+
+        let result = match method {
+            "initialize" => json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "g3", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "ping" => json!({}),
+            "tools/list" => {
+                let tools: Vec<Value> = self
+                    .tools
+                    .iter()
+                    .map(|tool| {
+                        json!({
+                            "name": tool.name,
+                            "description": tool.description,
+                            "inputSchema": tool.input_schema,
+                        })
+                    })
+                    .collect();
+                json!({ "tools": tools })
+            }
+            "tools/call" => {
+                let Some(name) = params.get("name").and_then(Value::as_str) else {
+                    return Some(error_response(id, -32602, "Missing tool name"));
+                };
+                if !self.tools.iter().any(|tool| tool.name == name) {
+                    return Some(error_response(id, -32602, &format!("Unknown tool: {}", name)));
+                }
+                let tool_call = ToolCall {
+                    tool: name.to_string(),
+                    args: params.get("arguments").cloned().unwrap_or_else(|| json!({})),
+                };
+                self.call_tool(&tool_call).await
+            }
+            _ => return Some(error_response(id, -32601, &format!("Method not found: {}", method))),
+        };
+        Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+    }
+
+    /// Run a tool through `dispatch_tool` and wrap its output as an MCP tool result
+    async fn call_tool(&mut self, tool_call: &ToolCall) -> Value {
+        let mut pending_images = Vec::new();
+        let mut ctx = ToolContext {
+            config: &self.config,
+            ui_writer: &self.ui_writer,
+            session_id: Some(&self.session_id),
+            working_dir: None,
+            computer_controller: None,
+            webdriver_session: &self.webdriver_session,
+            webdriver_process: &self.webdriver_process,
+            background_process_manager: &self.background_process_manager,
+            shell_session: &self.shell_session,
+            mcp_servers: &self.mcp_servers,
+            turn: &self.turn,
+            todo_content: &self.todo_content,
+            file_fingerprints: &self.file_fingerprints,
+            permission_log: &self.permission_log,
+            pending_images: &mut pending_images,
+            supports_vision: false,
+            is_autonomous: true,
+            requirements_sha: None,
+            context_total_tokens: self
+                .config
+                .agent
+                .max_context_length
+                .unwrap_or(DEFAULT_CONTEXT_TOKENS),
+            context_used_tokens: 0,
+            token_counter: Default::default(),
+        };
+
+        let (text, is_error) = match tool_dispatch::dispatch_tool(tool_call, &mut ctx).await {
+            Ok(output) => {
+                let is_error = output.starts_with('❌');
+                (output, is_error)
+            }
+            Err(e) => (format!("❌ {:#}", e), true),
+        };
+        json!({
+            "content": [{ "type": "text", "text": text }],
+            "isError": is_error,
+        })
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
@@ -0,0 +1,634 @@
+//! Multi-file patches for the `apply_patch` tool.
+//!
+//! A patch is a unified diff over any number of files, as written by `git diff` or
+//! `diff -ruN`. Each file section creates, modifies, deletes or renames one file.
+//! The whole patch is worked out in memory first, with the same hunk matching as
+//! `str_replace`; nothing is written unless every hunk applies. If a write then
+//! fails, the files already changed are put back.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::diff_match::HunkMatch;
+use crate::utils::{apply_unified_diff_with_report, parse_unified_diff_hunks};
+
+const DEV_NULL: &str = "/dev/null";
+
+/// What a file section does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchKind {
+    Create,
+    Modify,
+    Delete,
+    Rename,
+}
+
+impl PatchKind {
+    /// One-letter status, as in `git status --short`
+    pub fn letter(&self) -> char {
+        match self {
+            PatchKind::Create => 'A',
+            PatchKind::Modify => 'M',
+            PatchKind::Delete => 'D',
+            PatchKind::Rename => 'R',
+        }
+    }
+}
+
+/// One file's section of a patch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePatch {
+    /// Path before the change; `None` when the file is created
+    pub old_path: Option<String>,
+    /// Path after the change; `None` when the file is deleted
+    pub new_path: Option<String>,
+    /// The section's hunks (`@@` headers and `+`/`-`/` ` lines)
+    pub diff: String,
+}
+
+impl FilePatch {
+    pub fn kind(&self) -> PatchKind {
+        match (&self.old_path, &self.new_path) {
+            (None, _) => PatchKind::Create,
+            (_, None) => PatchKind::Delete,
+            (Some(old), Some(new)) if old != new => PatchKind::Rename,
+            _ => PatchKind::Modify,
+        }
+    }
+
+    /// Every path the section writes or removes
+    pub fn paths(&self) -> Vec<&str> {
+        let mut paths: Vec<&str> = self.old_path.iter().map(String::as_str).collect();
+        if let Some(new) = &self.new_path {
+            if !paths.contains(&new.as_str()) {
+                paths.push(new);
+            }
+        }
+        paths
+    }
+
+    /// Path shown to the user: `old -> new` for renames
+    pub fn display_path(&self) -> String {
+        match (&self.old_path, &self.new_path) {
+            (Some(old), Some(new)) if old != new => format!("{} -> {}", old, new),
+            (Some(path), _) | (None, Some(path)) => path.clone(),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// Added and removed lines in the section
+    pub fn line_counts(&self) -> (usize, usize) {
+        let insertions = self.diff.lines().filter(|line| line.starts_with('+')).count();
+        let deletions = self.diff.lines().filter(|line| line.starts_with('-')).count();
+        (insertions, deletions)
+    }
+}
+
+/// Section being collected by `parse_patch`
+#[derive(Default)]
+struct Section {
+    /// Paths from a `diff --git a/... b/...` line
+    git_paths: Option<(String, String)>,
+    old_header: Option<String>,
+    new_header: Option<String>,
+    rename_from: Option<String>,
+    rename_to: Option<String>,
+    created: bool,
+    deleted: bool,
+    diff: Vec<String>,
+}
+
+impl Section {
+    fn has_headers(&self) -> bool {
+        self.old_header.is_some() || self.new_header.is_some()
+    }
+
+    /// Whether the header paths carry git's `a/` and `b/` prefixes
+    fn has_git_prefixes(&self) -> bool {
+        let (git_old, git_new) = match &self.git_paths {
+            Some((old, new)) => (Some(old), Some(new)),
+            None => (None, None),
+        };
+        let prefixed = |paths: [Option<&String>; 2], prefix: &str| {
+            paths
+                .into_iter()
+                .flatten()
+                .all(|path| path == DEV_NULL || path.starts_with(prefix))
+        };
+        prefixed([self.old_header.as_ref(), git_old], "a/")
+            && prefixed([self.new_header.as_ref(), git_new], "b/")
+    }
+
+    fn finish(self, number: usize, strip_prefixes: bool) -> Result<Option<FilePatch>> {
+        let strip = |path: String, prefix: &str| match path.strip_prefix(prefix) {
+            Some(stripped) if strip_prefixes => stripped.to_string(),
+            _ => path,
+        };
+        let (git_old, git_new) = match self.git_paths {
+            Some((old, new)) => (Some(strip(old, "a/")), Some(strip(new, "b/"))),
+            None => (None, None),
+        };
+        // `rename from`/`rename to` paths never have prefixes
+        let side = |header: Option<String>, rename: Option<String>, git: Option<String>, prefix| {
+            match header {
+                Some(path) if path == DEV_NULL => None,
+                Some(path) => Some(strip(path, prefix)),
+                None => rename.or(git),
+            }
+        };
+        let mut old_path = side(self.old_header, self.rename_from, git_old, "a/");
+        let mut new_path = side(self.new_header, self.rename_to, git_new, "b/");
+        if self.created {
+            old_path = None;
+        }
+        if self.deleted {
+            new_path = None;
+        }
+
+        let diff = self.diff.join("\n");
+        if old_path.is_none() && new_path.is_none() {
+            if diff.trim().is_empty() {
+                return Ok(None);
+            }
+            anyhow::bail!("File section {} has no `---`/`+++` header naming the file", number);
+        }
+        // Mode-only changes have nothing to apply
+        if old_path == new_path && !self.created && !self.deleted && diff.trim().is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(FilePatch {
+            old_path,
+            new_path,
+            diff,
+        }))
+    }
+}
+
+/// Split a multi-file unified diff into file sections.
+///
+/// Understands `diff --git` headers (with `new file`, `deleted file` and `rename`
+/// lines) and plain `---`/`+++` headers; `a/` and `b/` prefixes are removed.
+pub fn parse_patch(patch: &str) -> Result<Vec<FilePatch>> {
+    let lines: Vec<&str> = patch.lines().collect();
+    let mut sections: Vec<Section> = Vec::new();
+    let mut current: Option<Section> = None;
+
+    let mut idx = 0;
+    while idx < lines.len() {
+        let line = lines[idx];
+        idx += 1;
+
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            sections.extend(current.take());
+            current = Some(Section {
+                git_paths: split_git_paths(rest),
+                ..Section::default()
+            });
+            continue;
+        }
+        if line.starts_with("diff ") {
+            sections.extend(current.take());
+            current = Some(Section::default());
+            continue;
+        }
+        // A file header is a `---` line directly followed by a `+++` line
+        if let Some(old) = line.strip_prefix("--- ") {
+            if let Some(new) = lines.get(idx).and_then(|next| next.strip_prefix("+++ ")) {
+                idx += 1;
+                let starts_new_file = match &current {
+                    Some(section) => section.has_headers() || !section.diff.is_empty(),
+                    None => true,
+                };
+                if starts_new_file {
+                    sections.extend(current.take());
+                }
+                let section = current.get_or_insert_with(Section::default);
+                section.old_header = Some(header_path(old));
+                section.new_header = Some(header_path(new));
+                continue;
+            }
+        }
+        if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            anyhow::bail!("Binary patches are not supported: {}", line);
+        }
+
+        let Some(section) = current.as_mut() else {
+            // Text before the first file header (e.g. a commit message)
+            continue;
+        };
+        if section.diff.is_empty() {
+            if let Some(path) = line.strip_prefix("rename from ") {
+                section.rename_from = Some(path.to_string());
+                continue;
+            }
+            if let Some(path) = line.strip_prefix("rename to ") {
+                section.rename_to = Some(path.to_string());
+                continue;
+            }
+            if line.starts_with("new file mode") {
+                section.created = true;
+                continue;
+            }
+            if line.starts_with("deleted file mode") {
+                section.deleted = true;
+                continue;
+            }
+            if line.starts_with("index ")
+                || line.starts_with("similarity index")
+                || line.starts_with("dissimilarity index")
+                || line.starts_with("old mode")
+                || line.starts_with("new mode")
+                || line.starts_with("copy from")
+                || line.starts_with("copy to")
+            {
+                continue;
+            }
+        }
+        section.diff.push(line.to_string());
+    }
+    sections.extend(current);
+
+    // Only strip `a/` and `b/` when every header has them; otherwise they are directories
+    let strip_prefixes = sections.iter().all(Section::has_git_prefixes);
+    let mut files = Vec::new();
+    for (idx, section) in sections.into_iter().enumerate() {
+        files.extend(section.finish(idx + 1, strip_prefixes)?);
+    }
+    if files.is_empty() {
+        anyhow::bail!("No file changes found. Expected a unified diff with `---`/`+++` file headers");
+    }
+    Ok(files)
+}
+
+/// `a/src/x.rs b/src/x.rs` -> (`a/src/x.rs`, `b/src/x.rs`)
+fn split_git_paths(rest: &str) -> Option<(String, String)> {
+    let split = rest.rfind(" b/")?;
+    Some((rest[..split].to_string(), rest[split + 1..].to_string()))
+}
+
+/// Path from a `---`/`+++` line, without the timestamp `diff -u` adds after a tab
+fn header_path(header: &str) -> String {
+    header.split('\t').next().unwrap_or(header).trim().to_string()
+}
+
+/// A file section worked out against the current files, ready to write
+#[derive(Debug, Clone)]
+pub struct PlannedChange {
+    pub patch: FilePatch,
+    /// Resolved path before the change
+    pub old_path: Option<PathBuf>,
+    /// Resolved path after the change
+    pub new_path: Option<PathBuf>,
+    /// New content; `None` when the file is deleted
+    pub content: Option<String>,
+    /// How each hunk matched (empty for created files)
+    pub hunks: Vec<HunkMatch>,
+}
+
+impl PlannedChange {
+    pub fn kind(&self) -> PatchKind {
+        self.patch.kind()
+    }
+}
+
+/// Apply every file section in memory. Relative paths are resolved against `base`.
+/// Nothing is written; the first section that doesn't apply is reported with its path.
+pub fn plan_patch(files: &[FilePatch], base: Option<&Path>) -> Result<Vec<PlannedChange>> {
+    let resolve = |path: &str| -> PathBuf {
+        let path = PathBuf::from(shellexpand::tilde(path).into_owned());
+        match base {
+            Some(base) if path.is_relative() => base.join(path),
+            _ => path,
+        }
+    };
+
+    let mut seen: Vec<PathBuf> = Vec::new();
+    let mut changes = Vec::with_capacity(files.len());
+    for file in files {
+        let old_path = file.old_path.as_deref().map(resolve);
+        let new_path = file.new_path.as_deref().map(resolve);
+        let mut paths: Vec<&PathBuf> = old_path.iter().chain(new_path.iter()).collect();
+        paths.dedup();
+        for path in paths {
+            let absolute = std::path::absolute(path).unwrap_or_else(|_| path.clone());
+            if seen.contains(&absolute) {
+                anyhow::bail!("{} appears more than once in the patch", path.display());
+            }
+            seen.push(absolute);
+        }
+
+        let (content, hunks) = plan_file(file, old_path.as_deref(), new_path.as_deref())
+            .with_context(|| file.display_path())?;
+        changes.push(PlannedChange {
+            patch: file.clone(),
+            old_path,
+            new_path,
+            content,
+            hunks,
+        });
+    }
+    Ok(changes)
+}
+
+fn plan_file(
+    file: &FilePatch,
+    old_path: Option<&Path>,
+    new_path: Option<&Path>,
+) -> Result<(Option<String>, Vec<HunkMatch>)> {
+    match (old_path, new_path) {
+        (None, Some(new_path)) => {
+            if new_path.exists() {
+                anyhow::bail!("cannot create the file, it already exists");
+            }
+            Ok((Some(created_content(&file.diff)?), Vec::new()))
+        }
+        (Some(old_path), new_path) => {
+            let current = std::fs::read_to_string(old_path)
+                .with_context(|| format!("failed to read {}", old_path.display()))?;
+            if let Some(new_path) = new_path {
+                if new_path != old_path && new_path.exists() {
+                    anyhow::bail!("cannot rename onto {}, it already exists", new_path.display());
+                }
+            }
+            if file.diff.trim().is_empty() {
+                // A deletion without hunks doesn't say what it removes, so only an
+                // empty file can go that way
+                if new_path.is_none() && !current.is_empty() {
+                    anyhow::bail!("the file isn't empty, and the deletion has no hunks removing its content");
+                }
+                return Ok((new_path.map(|_| current), Vec::new()));
+            }
+            let applied = apply_unified_diff_with_report(&current, &file.diff, None, None)?;
+            if new_path.is_none() {
+                // Deletions must remove everything the file has now
+                if !applied.content.trim().is_empty() {
+                    anyhow::bail!("the file has content the deletion doesn't remove");
+                }
+                return Ok((None, applied.hunks));
+            }
+            Ok((Some(applied.content), applied.hunks))
+        }
+        (None, None) => anyhow::bail!("no file path"),
+    }
+}
+
+/// Content of a created file: the added lines of its hunks
+fn created_content(diff: &str) -> Result<String> {
+    let hunks = parse_unified_diff_hunks(diff);
+    if hunks.iter().any(|(old_block, _)| !old_block.is_empty()) {
+        anyhow::bail!("a new file's hunks can only add lines");
+    }
+    let mut content = hunks
+        .into_iter()
+        .map(|(_, new_block)| new_block)
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !content.is_empty() && !diff.contains("\\ No newline at end of file") {
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+/// What a path held before the patch was written
+struct Original {
+    path: PathBuf,
+    /// Content and permissions; `None` when nothing was there
+    file: Option<(Vec<u8>, std::fs::Permissions)>,
+}
+
+/// Write planned changes. If any write fails, every file touched so far is restored,
+/// directories created for new files are removed, and the error says what failed.
+pub fn write_changes(changes: &[PlannedChange]) -> Result<()> {
+    // Keep what every touched path holds now, so a failure can be undone
+    let mut originals: Vec<Original> = Vec::new();
+    for change in changes {
+        for path in change.old_path.iter().chain(change.new_path.iter()) {
+            if !originals.iter().any(|saved| &saved.path == path) {
+                let permissions = std::fs::metadata(path).map(|metadata| metadata.permissions());
+                originals.push(Original {
+                    path: path.clone(),
+                    file: std::fs::read(path).ok().zip(permissions.ok()),
+                });
+            }
+        }
+    }
+
+    let mut created_dirs: Vec<PathBuf> = Vec::new();
+    for change in changes {
+        if let Err(e) = write_change(change, &mut created_dirs) {
+            let failed = change.patch.display_path();
+            let restore_errors = restore(&originals, &created_dirs);
+            if restore_errors.is_empty() {
+                anyhow::bail!("{}: {} (all files were restored)", failed, e);
+            }
+            anyhow::bail!(
+                "{}: {}. Restoring the other files also failed: {}",
+                failed,
+                e,
+                restore_errors.join("; ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Write one change, adding any directories it creates to `created_dirs`
+fn write_change(change: &PlannedChange, created_dirs: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut create_parent = |path: &Path| match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => {
+            // Outermost first; noted before creating them, in case that fails halfway
+            let missing: Vec<PathBuf> = parent
+                .ancestors()
+                .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+                .map(Path::to_path_buf)
+                .collect();
+            created_dirs.extend(missing.into_iter().rev());
+            std::fs::create_dir_all(parent)
+        }
+        _ => Ok(()),
+    };
+    match (&change.old_path, &change.new_path, &change.content) {
+        (Some(old_path), Some(new_path), Some(content)) if old_path != new_path => {
+            // Rename first so the file keeps its permissions
+            create_parent(new_path)?;
+            std::fs::rename(old_path, new_path)?;
+            std::fs::write(new_path, content)
+        }
+        (_, Some(new_path), Some(content)) => {
+            create_parent(new_path)?;
+            std::fs::write(new_path, content)
+        }
+        (Some(old_path), None, _) => std::fs::remove_file(old_path),
+        _ => Ok(()),
+    }
+}
+
+/// Put back the saved files, with their permissions, and remove the created
+/// directories; returns what couldn't be restored
+fn restore(originals: &[Original], created_dirs: &[PathBuf]) -> Vec<String> {
+    let mut errors = Vec::new();
+    for Original { path, file } in originals {
+        let result = match file {
+            // Renamed and deleted files are written afresh, so their mode is set again
+            Some((bytes, permissions)) => path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::write(path, bytes))
+                .and_then(|_| std::fs::set_permissions(path, permissions.clone())),
+            None if path.exists() => std::fs::remove_file(path),
+            None => Ok(()),
+        };
+        match result {
+            Ok(()) => debug!("Restored {}", path.display()),
+            Err(e) => {
+                warn!("Failed to restore {}: {}", path.display(), e);
+                errors.push(format!("{}: {}", path.display(), e));
+            }
+        }
+    }
+    // Innermost first, now that the files in them are gone
+    for dir in created_dirs.iter().rev() {
+        match std::fs::remove_dir(dir) {
+            Ok(()) => debug!("Removed {}", dir.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                warn!("Failed to remove {}: {}", dir.display(), e);
+                errors.push(format!("{}: {}", dir.display(), e));
+            }
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_git_patch() {
+        let patch = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,2 +1,2 @@
+ mod a;
+-mod b;
++mod c;
+diff --git a/new.txt b/new.txt
+new file mode 100644
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1 @@
++hello
+diff --git a/old.txt b/old.txt
+deleted file mode 100644
+--- a/old.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+diff --git a/from.rs b/to.rs
+similarity index 100%
+rename from from.rs
+rename to to.rs
+";
+        let files = parse_patch(patch).unwrap();
+        let summary: Vec<(PatchKind, String)> = files
+            .iter()
+            .map(|file| (file.kind(), file.display_path()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (PatchKind::Modify, "src/lib.rs".to_string()),
+                (PatchKind::Create, "new.txt".to_string()),
+                (PatchKind::Delete, "old.txt".to_string()),
+                (PatchKind::Rename, "from.rs -> to.rs".to_string()),
+            ]
+        );
+        assert_eq!(files[0].diff, "@@ -1,2 +1,2 @@\n mod a;\n-mod b;\n+mod c;");
+        assert_eq!(files[0].line_counts(), (1, 1));
+        assert_eq!(created_content(&files[1].diff).unwrap(), "hello\n");
+    }
+
+    #[test]
+    fn test_parse_plain_patch() {
+        let patch = "\
+--- docs/a.md\t2024-01-01 00:00:00
++++ docs/a.md\t2024-01-02 00:00:00
+@@ -1 +1 @@
+-old
++new
+--- b/x.txt
++++ b/x.txt
+@@ -1 +1 @@
+-1
++2
+";
+        let files = parse_patch(patch).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].old_path.as_deref(), Some("docs/a.md"));
+        // Only some paths are prefixed, so `b/` is a real directory
+        assert_eq!(files[1].new_path.as_deref(), Some("b/x.txt"));
+    }
+
+    #[test]
+    fn test_parse_rejects_headerless_and_binary_patches() {
+        assert!(parse_patch("@@ -1 +1 @@\n-a\n+b\n").is_err());
+        assert!(parse_patch("").is_err());
+        let binary = "diff --git a/img.png b/img.png\nBinary files a/img.png and b/img.png differ\n";
+        assert!(parse_patch(binary).is_err());
+    }
+
+    #[test]
+    fn test_deletion_without_hunks_needs_an_empty_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), "keep me\n").unwrap();
+        std::fs::write(temp_dir.path().join("empty.txt"), "").unwrap();
+        let delete = |path: &str| {
+            let patch = format!("diff --git a/{0} b/{0}\ndeleted file mode 100644\n", path);
+            plan_patch(&parse_patch(&patch).unwrap(), Some(temp_dir.path()))
+        };
+
+        let error = delete("notes.txt").unwrap_err();
+        assert!(format!("{:#}", error).contains("the file isn't empty"), "{:#}", error);
+        let changes = delete("empty.txt").unwrap();
+        assert_eq!(changes[0].kind(), PatchKind::Delete);
+        assert_eq!(changes[0].content, None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failed_write_restores_renamed_file_and_removes_new_directories() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let script = temp_dir.path().join("run.sh");
+        std::fs::write(&script, "echo hi\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        // A file where the second section needs a directory makes its write fail
+        std::fs::write(temp_dir.path().join("blocker"), "").unwrap();
+
+        let patch = "\
+diff --git a/run.sh b/scripts/bin/run.sh
+similarity index 100%
+rename from run.sh
+rename to scripts/bin/run.sh
+diff --git a/blocker/new.txt b/blocker/new.txt
+new file mode 100644
+--- /dev/null
++++ b/blocker/new.txt
+@@ -0,0 +1 @@
++hello
+";
+        let changes = plan_patch(&parse_patch(patch).unwrap(), Some(temp_dir.path())).unwrap();
+        let error = write_changes(&changes).unwrap_err();
+        assert!(error.to_string().contains("all files were restored"), "{}", error);
+
+        assert_eq!(std::fs::read_to_string(&script).unwrap(), "echo hi\n");
+        let mode = std::fs::metadata(&script).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert!(!temp_dir.path().join("scripts").exists());
+    }
+}
@@ -58,16 +58,21 @@ pub fn get_discovery_dir() -> PathBuf {
     }
 }
 
-/// Get the base .g3 directory path.
-/// This is the root for all g3 session data in the current workspace.
-pub fn get_g3_dir() -> PathBuf {
+/// Get the workspace root: G3_WORKSPACE_PATH if set, otherwise the current directory.
+pub fn get_workspace_dir() -> PathBuf {
     if let Ok(workspace_path) = std::env::var(G3_WORKSPACE_PATH_ENV) {
-        PathBuf::from(workspace_path).join(".g3")
+        PathBuf::from(workspace_path)
     } else {
-        std::env::current_dir().unwrap_or_default().join(".g3")
+        std::env::current_dir().unwrap_or_default()
     }
 }
 
+/// Get the base .g3 directory path.
+/// This is the root for all g3 session data in the current workspace.
+pub fn get_g3_dir() -> PathBuf {
+    get_workspace_dir().join(".g3")
+}
+
 /// Get the session directory for a specific session ID.
 /// Returns .g3/sessions/<session_id>/
 pub fn get_session_logs_dir(session_id: &str) -> PathBuf {
@@ -92,6 +97,24 @@ pub fn get_thinned_dir(session_id: &str) -> PathBuf {
     get_session_logs_dir(session_id).join("thinned")
 }
 
+/// Get the directory for full `shell` output that was too long to return.
+/// Returns .g3/sessions/<session_id>/shell_output/
+pub fn get_shell_output_dir(session_id: &str) -> PathBuf {
+    get_session_logs_dir(session_id).join("shell_output")
+}
+
+/// Get the directory for file snapshots taken before edits, one subdirectory per turn.
+/// Returns .g3/sessions/<session_id>/checkpoints/
+pub fn get_checkpoints_dir(session_id: &str) -> PathBuf {
+    get_session_logs_dir(session_id).join("checkpoints")
+}
+
+/// Get the directory holding each touched file's content at session start.
+/// Returns .g3/sessions/<session_id>/baseline/
+pub fn get_baseline_dir(session_id: &str) -> PathBuf {
+    get_session_logs_dir(session_id).join("baseline")
+}
+
 /// Get the fragments directory for a session (for ACD dehydrated context).
 /// Returns .g3/sessions/<session_id>/fragments/
 pub fn get_fragments_dir(session_id: &str) -> PathBuf {
@@ -104,6 +127,12 @@ pub fn get_session_file(session_id: &str) -> PathBuf {
     get_session_logs_dir(session_id).join("session.json")
 }
 
+/// Get the path to the cost ledger file for a session.
+/// Returns .g3/sessions/<session_id>/ledger.json
+pub fn get_ledger_file(session_id: &str) -> PathBuf {
+    get_session_logs_dir(session_id).join("ledger.json")
+}
+
 /// Get the path to the context summary file for a session.
 /// Returns .g3/sessions/<session_id>/context_summary.txt
 pub fn get_context_summary_file(session_id: &str) -> PathBuf {
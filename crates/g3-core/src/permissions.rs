@@ -0,0 +1,645 @@
+//! Tool permission policy.
+//!
+//! Every tool call is checked against the `[permissions]` config before it is
+//! dispatched. Tools can be allowed, denied or set to ask; shell commands are matched
+//! against glob rules; and writes outside the workspace can be restricted. "Ask"
+//! decisions prompt the user, and are refused in autonomous mode where nobody is
+//! there to answer. Every decision is logged and recorded in the session log.
+
+use g3_config::{PermissionMode, PermissionsConfig};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::patch::parse_patch;
+use crate::paths::get_workspace_dir;
+use crate::tools::executor::ToolContext;
+use crate::tools::file_ops::PATH_CONTENT_KEYS;
+use crate::ui_writer::UiWriter;
+use crate::ToolCall;
+
+/// Tools whose `command` argument is matched against the shell rules
+const SHELL_TOOLS: &[&str] = &["shell", "background_process"];
+
+/// Tools whose target path is checked against the workspace
+const WRITE_TOOLS: &[&str] = &["write_file", "str_replace"];
+
+/// Tool whose `patch` argument names the paths it writes
+const PATCH_TOOL: &str = "apply_patch";
+
+/// What the policy says about a tool call, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionDecision {
+    pub mode: PermissionMode,
+    pub reason: String,
+}
+
+impl PermissionDecision {
+    fn new(mode: PermissionMode, reason: impl Into<String>) -> Self {
+        Self {
+            mode,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// A permission decision as recorded in the session log. Only what the call touches
+/// is kept, not its full arguments (file contents, diffs).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermissionLogEntry {
+    pub timestamp: u64,
+    pub tool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// allowed, denied, denied_unattended, approved or rejected
+    pub outcome: String,
+    pub reason: String,
+}
+
+impl PermissionLogEntry {
+    fn new(tool_call: &ToolCall, outcome: &str, reason: &str) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Self {
+            timestamp,
+            tool: tool_call.tool.clone(),
+            command: tool_call
+                .args
+                .get("command")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+            paths: touched_paths(tool_call),
+            outcome: outcome.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Evaluate a tool call against the policy. Paths are resolved against `workspace`.
+pub fn evaluate(config: &PermissionsConfig, tool_call: &ToolCall, workspace: &Path) -> PermissionDecision {
+    let tool = tool_call.tool.as_str();
+    let tool_mode = config.tools.get(tool).copied();
+    let base = match tool_mode {
+        Some(mode) => PermissionDecision::new(mode, format!("tool `{}` is set to {}", tool, mode_name(mode))),
+        None => PermissionDecision::new(PermissionMode::Allow, "allowed by default"),
+    };
+    if base.mode == PermissionMode::Deny {
+        return base;
+    }
+
+    if SHELL_TOOLS.contains(&tool) {
+        if let Some(command) = tool_call.args.get("command").and_then(|v| v.as_str()) {
+            return evaluate_command(config, command, base);
+        }
+    }
+
+    if WRITE_TOOLS.contains(&tool) {
+        if let Some(path) = target_path(&tool_call.args) {
+            return evaluate_write_path(config, path, workspace, base);
+        }
+    }
+
+    if tool == PATCH_TOOL {
+        if let Some(patch) = tool_call.args.get("patch").and_then(|v| v.as_str()) {
+            return evaluate_patch(config, patch, workspace, base);
+        }
+    }
+
+    base
+}
+
+/// A patch is judged by the strictest decision for any path it touches. Patches that
+/// don't parse are left to the tool, which refuses them.
+fn evaluate_patch(
+    config: &PermissionsConfig,
+    patch: &str,
+    workspace: &Path,
+    base: PermissionDecision,
+) -> PermissionDecision {
+    let Ok(files) = parse_patch(patch) else {
+        return base;
+    };
+    let mut decision = base.clone();
+    for path in files.iter().flat_map(|file| file.paths()) {
+        let path_decision = evaluate_write_path(config, path, workspace, base.clone());
+        if strictness(path_decision.mode) > strictness(decision.mode) {
+            decision = path_decision;
+        }
+    }
+    decision
+}
+
+fn evaluate_command(config: &PermissionsConfig, command: &str, base: PermissionDecision) -> PermissionDecision {
+    let rules = &config.shell;
+    let parts = command_parts(command);
+
+    for part in &parts {
+        if let Some(rule) = first_match(&rules.deny, part) {
+            return PermissionDecision::new(
+                PermissionMode::Deny,
+                format!("`{}` matches deny rule `{}`", part, rule),
+            );
+        }
+    }
+    for part in &parts {
+        if let Some(rule) = first_match(&rules.ask, part) {
+            return PermissionDecision::new(
+                PermissionMode::Ask,
+                format!("`{}` matches ask rule `{}`", part, rule),
+            );
+        }
+    }
+    // Substitutions, subshells and nested shells run commands the parts don't show in
+    // full, so allow rules can't vouch for them
+    if !parts.is_empty()
+        && !hides_commands(command, &parts)
+        && parts.iter().all(|part| first_match(&rules.allow, part).is_some())
+    {
+        return PermissionDecision::new(PermissionMode::Allow, "command matches allow rules");
+    }
+    base
+}
+
+fn evaluate_write_path(
+    config: &PermissionsConfig,
+    path: &str,
+    workspace: &Path,
+    base: PermissionDecision,
+) -> PermissionDecision {
+    let resolved = resolve_path(path, workspace);
+    let workspace = resolve_symlinks(&normalize(workspace));
+    if resolved.starts_with(&workspace) {
+        return base;
+    }
+
+    let resolved_str = resolved.to_string_lossy();
+    if first_match(&config.paths.allow, &resolved_str).is_some() {
+        return base;
+    }
+
+    let mode = config.paths.outside_workspace;
+    if strictness(mode) > strictness(base.mode) {
+        PermissionDecision::new(mode, format!("`{}` is outside the workspace", resolved_str))
+    } else {
+        base
+    }
+}
+
+/// Check a tool call against the policy, asking the user if needed.
+///
+/// Returns `Some(message)` with the refusal to hand back to the model when the call
+/// must not run. Every decision is logged and added to the agent's permission log,
+/// which is saved with the session.
+pub fn authorize<W: UiWriter>(tool_call: &ToolCall, ctx: &ToolContext<'_, W>) -> Option<String> {
+    let workspace = ctx
+        .working_dir
+        .map(PathBuf::from)
+        .unwrap_or_else(get_workspace_dir);
+    let decision = evaluate(&ctx.config.permissions, tool_call, &workspace);
+
+    let (allowed, outcome) = match decision.mode {
+        PermissionMode::Allow => (true, "allowed"),
+        PermissionMode::Deny => (false, "denied"),
+        PermissionMode::Ask if ctx.is_autonomous => (false, "denied_unattended"),
+        PermissionMode::Ask => {
+            let question = format!(
+                "🔐 Allow {} {}? ({})",
+                tool_call.tool,
+                describe(tool_call),
+                decision.reason
+            );
+            if ctx.ui_writer.prompt_user_yes_no(&question) {
+                (true, "approved")
+            } else {
+                (false, "rejected")
+            }
+        }
+    };
+
+    let entry = PermissionLogEntry::new(tool_call, outcome, &decision.reason);
+    info!(
+        "Permission for {}{}{}: {} ({})",
+        entry.tool,
+        entry.command.as_deref().map(|c| format!(" `{}`", c)).unwrap_or_default(),
+        if entry.paths.is_empty() { String::new() } else { format!(" on {}", entry.paths.join(", ")) },
+        entry.outcome,
+        entry.reason
+    );
+    if let Ok(mut log) = ctx.permission_log.lock() {
+        log.push(entry);
+    }
+
+    if allowed {
+        return None;
+    }
+    warn!("Blocked {} call: {}", tool_call.tool, decision.reason);
+    Some(match outcome {
+        "denied_unattended" => format!(
+            "🚫 Permission denied for {}: {} and needs approval, which is not available in autonomous mode. Find another way to proceed.",
+            tool_call.tool, decision.reason
+        ),
+        "rejected" => format!(
+            "🚫 Permission denied for {}: the user declined. Do not retry this call; ask the user how to proceed if it is required.",
+            tool_call.tool
+        ),
+        _ => format!(
+            "🚫 Permission denied for {}: {}. Do not retry this call.",
+            tool_call.tool, decision.reason
+        ),
+    })
+}
+
+/// Short description of what a tool call will touch, for the approval prompt
+fn describe(tool_call: &ToolCall) -> String {
+    if let Some(command) = tool_call.args.get("command").and_then(|v| v.as_str()) {
+        return format!("`{}`", command);
+    }
+    if let Some(path) = target_path(&tool_call.args) {
+        return format!("on `{}`", path);
+    }
+    if let Some(Ok(files)) = tool_call.args.get("patch").and_then(|v| v.as_str()).map(parse_patch) {
+        let paths: Vec<String> = files.iter().map(|file| format!("`{}`", file.display_path())).collect();
+        return format!("on {}", paths.join(", "));
+    }
+    let args = tool_call.args.to_string();
+    if args.chars().count() > 120 {
+        format!("{}...", args.chars().take(120).collect::<String>())
+    } else {
+        args
+    }
+}
+
+/// Paths a call writes: the target of a write tool, or every path in a patch
+fn touched_paths(tool_call: &ToolCall) -> Vec<String> {
+    if let Some(path) = target_path(&tool_call.args) {
+        return vec![path.to_string()];
+    }
+    match tool_call.args.get("patch").and_then(|v| v.as_str()).map(parse_patch) {
+        Some(Ok(files)) => files
+            .iter()
+            .flat_map(|file| file.paths())
+            .map(|path| path.to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn target_path(args: &serde_json::Value) -> Option<&str> {
+    PATH_CONTENT_KEYS
+        .iter()
+        .find_map(|(key, _)| args.get(*key).and_then(|v| v.as_str()))
+}
+
+fn mode_name(mode: PermissionMode) -> &'static str {
+    match mode {
+        PermissionMode::Allow => "allow",
+        PermissionMode::Ask => "ask",
+        PermissionMode::Deny => "deny",
+    }
+}
+
+fn strictness(mode: PermissionMode) -> u8 {
+    match mode {
+        PermissionMode::Allow => 0,
+        PermissionMode::Ask => 1,
+        PermissionMode::Deny => 2,
+    }
+}
+
+/// Split a compound command (`a && b | c; d & e`) into its whitespace-normalized parts.
+/// Substitutions (`$(...)`, backticks) and subshells are split out too, and the script
+/// of a nested `sh -c '...'` is added with its own parts.
+fn command_parts(command: &str) -> Vec<String> {
+    let chars: Vec<char> = command.chars().collect();
+    let mut pieces = vec![String::new()];
+    let mut i = 0;
+    while i < chars.len() {
+        let (c, next) = (chars[i], chars.get(i + 1).copied());
+        // `2>&1`, `>&2` and `&>` are redirections, not background jobs
+        let redirection = c == '&' && (next == Some('>') || (i > 0 && matches!(chars[i - 1], '>' | '<')));
+        match c {
+            '&' if !redirection => {
+                if next == Some('&') {
+                    i += 1;
+                }
+                pieces.push(String::new());
+            }
+            '|' | ';' | '\n' | '(' | ')' | '`' => pieces.push(String::new()),
+            '$' if next == Some('(') => {
+                i += 1;
+                pieces.push(String::new());
+            }
+            _ => pieces.last_mut().unwrap().push(c),
+        }
+        i += 1;
+    }
+
+    let mut parts = Vec::new();
+    for piece in pieces {
+        let part = piece.split_whitespace().collect::<Vec<_>>().join(" ");
+        if part.is_empty() {
+            continue;
+        }
+        let script = nested_script(&part);
+        parts.push(part);
+        if let Some(script) = script {
+            parts.extend(command_parts(&script));
+        }
+    }
+    parts
+}
+
+/// The script of a nested shell (`bash -c 'rm -rf /'` gives `rm -rf /`)
+fn nested_script(part: &str) -> Option<String> {
+    let words: Vec<&str> = part.split(' ').collect();
+    let shell = words[0].rsplit('/').next().unwrap_or(words[0]);
+    if !matches!(shell, "sh" | "bash" | "zsh" | "dash" | "ksh" | "fish") {
+        return None;
+    }
+    // `-c`, or combined flags such as `-lc`
+    let flag = words[1..]
+        .iter()
+        .take_while(|word| word.starts_with('-') && !word.starts_with("--"))
+        .position(|word| word.contains('c'))?;
+    // Quotes are trimmed at each end on their own, since a separator inside the
+    // script (`sh -c 'a; b'`) has already cut it in two
+    let script = words[flag + 2..].join(" ");
+    Some(script.trim_matches(['\'', '"']).to_string())
+}
+
+/// Whether `command` runs anything its parts don't show as a plain command
+fn hides_commands(command: &str, parts: &[String]) -> bool {
+    command.contains(['`', '(', ')'])
+        || parts
+            .iter()
+            .any(|part| part == "eval" || part.starts_with("eval ") || nested_script(part).is_some())
+}
+
+fn first_match<'a>(rules: &'a [String], text: &str) -> Option<&'a str> {
+    rules
+        .iter()
+        .map(|rule| rule.as_str())
+        .find(|rule| glob_match(&rule.split_whitespace().collect::<Vec<_>>().join(" "), text))
+}
+
+/// Glob match where `*` matches any run of characters and `?` a single character
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn resolve_path(path: &str, workspace: &Path) -> PathBuf {
+    let expanded = PathBuf::from(shellexpand::tilde(path).as_ref());
+    let absolute = if expanded.is_absolute() {
+        expanded
+    } else {
+        workspace.join(expanded)
+    };
+    resolve_symlinks(&normalize(&absolute))
+}
+
+/// Canonicalize the longest existing ancestor of `path` and re-append the rest, so a
+/// symlink inside the workspace can't point a write outside it. Paths that don't
+/// exist yet are judged by where their nearest existing parent really is.
+fn resolve_symlinks(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return rest.iter().rev().fold(canonical, |dir, name| dir.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+/// Resolve `.` and `..` without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(tool: &str, args: serde_json::Value) -> ToolCall {
+        ToolCall {
+            tool: tool.to_string(),
+            args,
+        }
+    }
+
+    fn shell(command: &str) -> ToolCall {
+        call("shell", json!({ "command": command }))
+    }
+
+    fn policy() -> PermissionsConfig {
+        let mut config = PermissionsConfig::default();
+        config.tools.insert("webdriver_start".into(), PermissionMode::Deny);
+        config.shell.deny = vec!["rm -rf *".into()];
+        config.shell.ask = vec!["git push*".into()];
+        config.shell.allow = vec!["cargo *".into(), "ls".into()];
+        config.paths.outside_workspace = PermissionMode::Ask;
+        config.paths.allow = vec!["/tmp/*".into()];
+        config
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("rm -rf *", "rm -rf /"));
+        assert!(glob_match("git push*", "git push --force origin main"));
+        assert!(glob_match("ca?go test", "cargo test"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("rm -rf *", "rm -r build"));
+        assert!(!glob_match("ls", "lsof"));
+    }
+
+    #[test]
+    fn test_everything_allowed_by_default() {
+        let config = PermissionsConfig::default();
+        let workspace = Path::new("/work");
+        assert_eq!(evaluate(&config, &shell("rm -rf /"), workspace).mode, PermissionMode::Allow);
+        let write = call("write_file", json!({ "file_path": "/etc/hosts", "content": "" }));
+        assert_eq!(evaluate(&config, &write, workspace).mode, PermissionMode::Allow);
+    }
+
+    #[test]
+    fn test_shell_rules() {
+        let config = policy();
+        let workspace = Path::new("/work");
+        let mode = |command: &str| evaluate(&config, &shell(command), workspace).mode;
+
+        assert_eq!(mode("rm -rf target"), PermissionMode::Deny);
+        assert_eq!(mode("cargo build && rm  -rf   /"), PermissionMode::Deny);
+        assert_eq!(mode("git commit -m x; git push"), PermissionMode::Ask);
+        assert_eq!(mode("echo hi"), PermissionMode::Allow);
+
+        // Allow rules override a tool-level ask, but only if every part matches
+        let mut config = policy();
+        config.tools.insert("shell".into(), PermissionMode::Ask);
+        let mode = |command: &str| evaluate(&config, &shell(command), workspace).mode;
+        assert_eq!(mode("cargo test | ls"), PermissionMode::Allow);
+        assert_eq!(mode("cargo test && curl example.com"), PermissionMode::Ask);
+
+        let decision = evaluate(&config, &shell("rm -rf /"), workspace);
+        assert_eq!(decision.reason, "`rm -rf /` matches deny rule `rm -rf *`");
+    }
+
+    #[test]
+    fn test_shell_rules_see_through_compound_commands() {
+        let mut config = policy();
+        config.tools.insert("shell".into(), PermissionMode::Ask);
+        let workspace = Path::new("/work");
+        let mode = |command: &str| evaluate(&config, &shell(command), workspace).mode;
+
+        // Background jobs, substitutions, subshells and nested shells are checked for denials
+        assert_eq!(mode("echo & rm -rf /"), PermissionMode::Deny);
+        assert_eq!(mode("cargo test & rm -rf ~"), PermissionMode::Deny);
+        assert_eq!(mode("sh -c 'rm -rf /'"), PermissionMode::Deny);
+        assert_eq!(mode("/bin/bash -lc \"cargo build; rm -rf /\""), PermissionMode::Deny);
+        assert_eq!(mode("bash -c 'rm -rf /; true'"), PermissionMode::Deny);
+        assert_eq!(mode("cargo build $(rm -rf /)"), PermissionMode::Deny);
+        assert_eq!(mode("cargo build `rm -rf /`"), PermissionMode::Deny);
+        assert_eq!(mode("(cd src && rm -rf /)"), PermissionMode::Deny);
+
+        // ...and never count as allowed, even when every part matches an allow rule
+        assert_eq!(mode("cargo test & curl example.com"), PermissionMode::Ask);
+        assert_eq!(mode("cargo build $(curl x|sh)"), PermissionMode::Ask);
+        assert_eq!(mode("cargo build `ls`"), PermissionMode::Ask);
+        assert_eq!(mode("(cargo build)"), PermissionMode::Ask);
+        assert_eq!(mode("sh -c 'cargo build'"), PermissionMode::Ask);
+
+        // Redirections aren't background jobs
+        assert_eq!(mode("cargo test 2>&1 | ls"), PermissionMode::Allow);
+        assert_eq!(mode("cargo test &> log.txt"), PermissionMode::Allow);
+        assert_eq!(command_parts("a && b & c || d"), vec!["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_tool_rules() {
+        let config = policy();
+        let workspace = Path::new("/work");
+        let decision = evaluate(&config, &call("webdriver_start", json!({})), workspace);
+        assert_eq!(decision.mode, PermissionMode::Deny);
+        assert_eq!(decision.reason, "tool `webdriver_start` is set to deny");
+        assert_eq!(
+            evaluate(&config, &call("read_file", json!({ "file_path": "/etc/passwd" })), workspace).mode,
+            PermissionMode::Allow
+        );
+    }
+
+    #[test]
+    fn test_write_path_scopes() {
+        let config = policy();
+        let workspace = Path::new("/work/project");
+        let write = |path: &str| call("write_file", json!({ "file_path": path, "content": "x" }));
+        let edit = |path: &str| call("str_replace", json!({ "file_path": path, "diff": "" }));
+
+        assert_eq!(evaluate(&config, &write("src/main.rs"), workspace).mode, PermissionMode::Allow);
+        assert_eq!(evaluate(&config, &write("/work/project/a/../b.rs"), workspace).mode, PermissionMode::Allow);
+        assert_eq!(evaluate(&config, &write("/tmp/scratch.txt"), workspace).mode, PermissionMode::Allow);
+
+        let decision = evaluate(&config, &edit("../other/lib.rs"), workspace);
+        assert_eq!(decision.mode, PermissionMode::Ask);
+        assert_eq!(decision.reason, "`/work/other/lib.rs` is outside the workspace");
+
+        // A stricter tool-level rule still applies inside the workspace
+        let mut config = policy();
+        config.tools.insert("write_file".into(), PermissionMode::Ask);
+        assert_eq!(evaluate(&config, &write("src/main.rs"), workspace).mode, PermissionMode::Ask);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_out_of_workspace_is_outside() {
+        // The scratch directories live under /tmp, which the policy allows
+        let mut config = policy();
+        config.paths.allow.clear();
+        let root = tempfile::TempDir::new().unwrap();
+        let workspace = root.path().join("project");
+        let outside = root.path().join("elsewhere");
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, workspace.join("link")).unwrap();
+        let write = |path: &str| call("write_file", json!({ "file_path": path, "content": "x" }));
+
+        assert_eq!(evaluate(&config, &write("src/new/main.rs"), &workspace).mode, PermissionMode::Allow);
+        let decision = evaluate(&config, &write("link/new/file.txt"), &workspace);
+        assert_eq!(decision.mode, PermissionMode::Ask);
+        assert!(decision.reason.contains("elsewhere"), "{}", decision.reason);
+    }
+
+    #[test]
+    fn test_log_entry_keeps_only_what_the_call_touches() {
+        let entry = PermissionLogEntry::new(
+            &call("write_file", json!({ "file_path": "src/main.rs", "content": "secret" })),
+            "allowed",
+            "allowed by default",
+        );
+        assert_eq!(entry.paths, vec!["src/main.rs"]);
+        assert_eq!(entry.command, None);
+        assert!(!serde_json::to_string(&entry).unwrap().contains("secret"));
+
+        let entry = PermissionLogEntry::new(&shell("ls -la"), "denied", "tool `shell` is set to deny");
+        assert_eq!(entry.command.as_deref(), Some("ls -la"));
+        assert!(entry.paths.is_empty());
+    }
+
+    #[test]
+    fn test_patch_paths_checked() {
+        let config = policy();
+        let workspace = Path::new("/work/project");
+        let patch = |paths: &[&str]| {
+            let patch: String = paths
+                .iter()
+                .map(|path| format!("--- {0}\n+++ {0}\n@@ -1 +1 @@\n-a\n+b\n", path))
+                .collect();
+            call("apply_patch", json!({ "patch": patch }))
+        };
+
+        assert_eq!(evaluate(&config, &patch(&["src/a.rs", "src/b.rs"]), workspace).mode, PermissionMode::Allow);
+        let decision = evaluate(&config, &patch(&["src/a.rs", "../other/lib.rs"]), workspace);
+        assert_eq!(decision.mode, PermissionMode::Ask);
+        assert_eq!(decision.reason, "`/work/other/lib.rs` is outside the workspace");
+    }
+}
@@ -188,9 +188,10 @@ Short description for providers without native calling specs:
   - Returns PID and log file path. Use shell tool to read logs (`tail -100 <logfile>`), check status (`ps -p <pid>`), or stop (`kill <pid>`)
   - Note: Process runs independently; logs are captured to a file for later inspection
 
-- **read_file**: Read the contents of a file (supports partial reads via start/end)
-  - Format: {\"tool\": \"read_file\", \"args\": {\"file_path\": \"path/to/file\", \"start\": 0, \"end\": 100}
+- **read_file**: Read the contents of a file (supports partial reads via start_line/end_line, symbol, or character start/end)
+  - Format: {\"tool\": \"read_file\", \"args\": {\"file_path\": \"path/to/file\", \"start_line\": 1, \"end_line\": 50}
   - Example: {\"tool\": \"read_file\", \"args\": {\"file_path\": \"src/main.rs\"}
+  - Example (one function): {\"tool\": \"read_file\", \"args\": {\"file_path\": \"src/config.rs\", \"symbol\": \"Config::load\"}
   - Example (partial): {\"tool\": \"read_file\", \"args\": {\"file_path\": \"large.log\", \"start\": 0, \"end\": 1000}
 
 - **read_image**: Read an image file for visual analysis (PNG, JPEG, GIF, WebP)
@@ -205,6 +206,9 @@ Short description for providers without native calling specs:
   - Format: {\"tool\": \"str_replace\", \"args\": {\"file_path\": \"path/to/file\", \"diff\": \"--- old\\n-old text\\n+++ new\\n+new text\"}
   - Example: {\"tool\": \"str_replace\", \"args\": {\"file_path\": \"src/main.rs\", \"diff\": \"--- old\\n-old_code();\\n+++ new\\n+new_code();\"}
 
+- **apply_patch**: Apply a unified diff that spans several files, all or nothing (modify, create, delete, rename)
+  - Format: {\"tool\": \"apply_patch\", \"args\": {\"patch\": \"--- a/path/one\\n+++ b/path/one\\n@@ -1 +1 @@\\n-old\\n+new\\n--- /dev/null\\n+++ b/path/two\\n@@ -0,0 +1 @@\\n+created\"}}
+
 - **todo_read**: Read the current session's TODO list from todo.g3.md (session-scoped)
   - Format: {\"tool\": \"todo_read\", \"args\": {}}
   - Example: {\"tool\": \"todo_read\", \"args\": {}}
@@ -22,6 +22,30 @@ pub fn parse_provider_ref(provider_name: &str) -> (&str, &str) {
     }
 }
 
+/// The name the provider for a reference is registered under. OpenAI-compatible
+/// providers register under their bare name (`openrouter` for `openrouter.default`),
+/// all others under the full reference.
+pub fn registered_name(config: &Config, provider_ref: &str) -> String {
+    let (provider_type, _) = parse_provider_ref(provider_ref);
+    if config.providers.openai_compatible.contains_key(provider_type) {
+        provider_type.to_string()
+    } else {
+        provider_ref.to_string()
+    }
+}
+
+/// Find the provider to fall over to after `current_provider` fails.
+///
+/// Walks the default provider chain (which is the coach/player/planner chain for configs
+/// produced by `Config::for_coach` and friends) and returns the entry after the current one.
+/// `current_provider` may be a chain reference or a registered provider name.
+pub fn next_failover_provider<'a>(config: &'a Config, current_provider: &str) -> Option<&'a str> {
+    let current = registered_name(config, current_provider);
+    let chain = config.providers.default_provider.providers();
+    let position = chain.iter().position(|p| registered_name(config, p) == current)?;
+    chain.get(position + 1).map(String::as_str)
+}
+
 /// Get the configured max_tokens for a provider from config.
 pub fn get_max_tokens(config: &Config, provider_name: &str) -> Option<u32> {
     let (provider_type, config_name) = parse_provider_ref(provider_name);
@@ -31,6 +55,8 @@ pub fn get_max_tokens(config: &Config, provider_name: &str) -> Option<u32> {
         "openai" => config.providers.openai.get(config_name)?.max_tokens,
         "databricks" => config.providers.databricks.get(config_name)?.max_tokens,
         "embedded" => config.providers.embedded.get(config_name)?.max_tokens,
+        "gemini" => config.providers.gemini.get(config_name)?.max_tokens,
+        "ollama" => config.providers.ollama.get(config_name)?.max_tokens,
         _ => None,
     }
 }
@@ -44,6 +70,8 @@ pub fn get_temperature(config: &Config, provider_name: &str) -> Option<f32> {
         "openai" => config.providers.openai.get(config_name)?.temperature,
         "databricks" => config.providers.databricks.get(config_name)?.temperature,
         "embedded" => config.providers.embedded.get(config_name)?.temperature,
+        "gemini" => config.providers.gemini.get(config_name)?.temperature,
+        "ollama" => config.providers.ollama.get(config_name)?.temperature,
         _ => None,
     }
 }
@@ -216,6 +244,60 @@ mod tests {
         assert_eq!(name, "default");
     }
 
+    #[test]
+    fn test_next_failover_provider() {
+        let mut config = Config::default();
+        config.providers.default_provider = vec![
+            "anthropic.default".to_string(),
+            "databricks.default".to_string(),
+            "openai.default".to_string(),
+        ]
+        .try_into()
+        .unwrap();
+
+        assert_eq!(
+            next_failover_provider(&config, "anthropic.default"),
+            Some("databricks.default")
+        );
+        assert_eq!(
+            next_failover_provider(&config, "databricks.default"),
+            Some("openai.default")
+        );
+        assert_eq!(next_failover_provider(&config, "openai.default"), None);
+        assert_eq!(next_failover_provider(&config, "embedded.local"), None);
+    }
+
+    #[test]
+    fn test_next_failover_provider_from_openai_compatible() {
+        let mut config = Config::default();
+        config.providers.openai_compatible.insert(
+            "openrouter".to_string(),
+            g3_config::OpenAIConfig {
+                api_key: "key".to_string(),
+                model: "some-model".to_string(),
+                base_url: None,
+                max_tokens: None,
+                temperature: None,
+                vision: None,
+            },
+        );
+        config.providers.default_provider = vec![
+            "openrouter.default".to_string(),
+            "anthropic.default".to_string(),
+        ]
+        .try_into()
+        .unwrap();
+
+        // The provider registers as "openrouter"; both spellings find it in the chain
+        assert_eq!(registered_name(&config, "openrouter.default"), "openrouter");
+        assert_eq!(registered_name(&config, "anthropic.default"), "anthropic.default");
+        assert_eq!(next_failover_provider(&config, "openrouter"), Some("anthropic.default"));
+        assert_eq!(
+            next_failover_provider(&config, "openrouter.default"),
+            Some("anthropic.default")
+        );
+    }
+
     #[test]
     fn test_parse_provider_ref_with_custom_name() {
         let (ptype, name) = parse_provider_ref("openai.gpt4");
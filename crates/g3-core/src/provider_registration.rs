@@ -1,35 +1,35 @@
 //! Provider registration logic for the Agent.
 //!
-//! This module handles the registration of LLM providers (Anthropic, OpenAI, Databricks, Embedded)
-//! based on configuration. It consolidates the duplicated registration patterns into a single
+//! This module handles the registration of LLM providers (Anthropic, OpenAI, Databricks, Embedded,
+//! Gemini, Ollama, Replay) based on configuration. It consolidates the duplicated registration patterns into a single
 //! cohesive module.
 
 use anyhow::Result;
-use g3_config::Config;
+use g3_config::{Config, ReplayMode};
 use g3_providers::ProviderRegistry;
-use tracing::debug;
+use tracing::{debug, warn};
+
+use crate::provider_config;
 
 /// Determines which providers should be registered based on mode and configuration.
 ///
 /// In autonomous mode, registers coach and player providers in addition to the default.
-/// In normal mode, only registers the default provider.
+/// In normal mode, only registers the default provider. Failover providers listed after
+/// the primary in each chain are always included.
 pub fn determine_providers_to_register(config: &Config, is_autonomous: bool) -> Vec<String> {
+    let mut chains = vec![&config.providers.default_provider];
     if is_autonomous {
-        let mut providers = vec![config.providers.default_provider.clone()];
-        if let Some(coach) = &config.providers.coach {
-            if !providers.contains(coach) {
-                providers.push(coach.clone());
-            }
-        }
-        if let Some(player) = &config.providers.player {
-            if !providers.contains(player) {
-                providers.push(player.clone());
-            }
+        chains.extend(config.providers.coach.as_ref());
+        chains.extend(config.providers.player.as_ref());
+    }
+
+    let mut providers: Vec<String> = Vec::new();
+    for provider in chains.into_iter().flat_map(|chain| chain.providers()) {
+        if !providers.contains(provider) {
+            providers.push(provider.clone());
         }
-        providers
-    } else {
-        vec![config.providers.default_provider.clone()]
     }
+    providers
 }
 
 /// Checks if a provider reference should be registered.
@@ -58,13 +58,19 @@ pub async fn register_providers(
     register_openai_compatible_providers(config, providers_to_register, &mut registry)?;
     register_anthropic_providers(config, providers_to_register, &mut registry)?;
     register_databricks_providers(config, providers_to_register, &mut registry).await?;
+    register_gemini_providers(config, providers_to_register, &mut registry)?;
+    register_ollama_providers(config, providers_to_register, &mut registry).await?;
+    register_replay_providers(config, providers_to_register, &mut registry).await?;
 
     // Set default provider
     debug!(
         "Setting default provider to: {}",
         config.providers.default_provider
     );
-    registry.set_default(&config.providers.default_provider)?;
+    registry.set_default(&provider_config::registered_name(
+        config,
+        config.providers.default_provider.primary(),
+    ))?;
     debug!("Default provider set successfully");
 
     Ok(registry)
@@ -108,7 +114,8 @@ fn register_openai_providers(
                 openai_config.base_url.clone(),
                 openai_config.max_tokens,
                 openai_config.temperature,
-            )?;
+            )?
+            .with_vision(openai_config.vision);
             registry.register(openai_provider);
         }
     }
@@ -130,7 +137,8 @@ fn register_openai_compatible_providers(
                 openai_config.base_url.clone(),
                 openai_config.max_tokens,
                 openai_config.temperature,
-            )?;
+            )?
+            .with_vision(openai_config.vision);
             registry.register(openai_provider);
         }
     }
@@ -199,6 +207,142 @@ async fn register_databricks_providers(
     Ok(())
 }
 
+/// Register Google Gemini providers from configuration.
+fn register_gemini_providers(
+    config: &Config,
+    providers_to_register: &[String],
+    registry: &mut ProviderRegistry,
+) -> Result<()> {
+    for (name, gemini_config) in &config.providers.gemini {
+        if should_register(providers_to_register, "gemini", name) {
+            let gemini_provider = g3_providers::GeminiProvider::new_with_name(
+                format!("gemini.{}", name),
+                gemini_config.api_key.clone(),
+                Some(gemini_config.model.clone()),
+                gemini_config.base_url.clone(),
+                gemini_config.max_tokens,
+                gemini_config.temperature,
+            )?;
+            registry.register(gemini_provider);
+        }
+    }
+    Ok(())
+}
+
+/// Register Ollama providers from configuration.
+///
+/// This is async because a config without a `model` is resolved against the server's
+/// installed models.
+async fn register_ollama_providers(
+    config: &Config,
+    providers_to_register: &[String],
+    registry: &mut ProviderRegistry,
+) -> Result<()> {
+    for (name, ollama_config) in &config.providers.ollama {
+        if should_register(providers_to_register, "ollama", name) {
+            let model = match &ollama_config.model {
+                Some(model) => model.clone(),
+                None => {
+                    let models =
+                        g3_providers::OllamaProvider::list_models(ollama_config.base_url.as_deref())
+                            .await?;
+                    let model = models.first().map(|m| m.name.clone()).ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Provider 'ollama.{}' has no model configured and the Ollama server has no models installed",
+                            name
+                        )
+                    })?;
+                    debug!("Discovered Ollama model for ollama.{}: {}", name, model);
+                    model
+                }
+            };
+
+            // Without a configured num_ctx, use the model's own context length
+            let num_ctx = match ollama_config.num_ctx {
+                Some(num_ctx) => Some(num_ctx),
+                None => g3_providers::OllamaProvider::model_context_length(
+                    ollama_config.base_url.as_deref(),
+                    &model,
+                )
+                .await
+                .unwrap_or_else(|e| {
+                    warn!("Could not read the context length of {} from Ollama: {}", model, e);
+                    None
+                }),
+            };
+            debug!("Ollama num_ctx for ollama.{}: {:?}", name, num_ctx);
+
+            let ollama_provider = g3_providers::OllamaProvider::new_with_name(
+                format!("ollama.{}", name),
+                model,
+                ollama_config.base_url.clone(),
+                num_ctx,
+                ollama_config.max_tokens,
+                ollama_config.temperature,
+            )?;
+            registry.register(ollama_provider);
+        }
+    }
+    Ok(())
+}
+
+/// Register record/replay (cassette) providers from configuration.
+///
+/// In record mode the source provider named by `record_from` is constructed through a
+/// nested registration and wrapped, so every interaction is written to the cassette.
+///
+/// Only the exact references in `providers_to_register` are built: record and replay
+/// entries usually share a cassette, and a record entry needs its source's credentials.
+async fn register_replay_providers(
+    config: &Config,
+    providers_to_register: &[String],
+    registry: &mut ProviderRegistry,
+) -> Result<()> {
+    for (name, replay_config) in &config.providers.replay {
+        let provider_name = format!("replay.{}", name);
+        if providers_to_register.contains(&provider_name) {
+            let cassette_path = shellexpand::tilde(&replay_config.cassette).to_string();
+
+            let replay_provider = match replay_config.mode {
+                ReplayMode::Replay => g3_providers::ReplayProvider::from_cassette(
+                    provider_name,
+                    cassette_path,
+                    replay_config.strict,
+                )?,
+                ReplayMode::Record => {
+                    let source = replay_config.record_from.as_deref().ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Provider '{}' is in record mode but has no record_from provider",
+                            provider_name
+                        )
+                    })?;
+                    if source.starts_with("replay.") {
+                        anyhow::bail!(
+                            "Provider '{}' cannot record from another replay provider ('{}')",
+                            provider_name,
+                            source
+                        );
+                    }
+
+                    let source_config = config.with_provider_override(source)?;
+                    let mut source_registry =
+                        Box::pin(register_providers(&source_config, &[source.to_string()]))
+                            .await?;
+                    let inner = source_registry.take(source).ok_or_else(|| {
+                        anyhow::anyhow!("Failed to construct source provider '{}'", source)
+                    })?;
+
+                    debug!("Recording {} into cassette {}", source, cassette_path);
+                    g3_providers::ReplayProvider::recording(provider_name, cassette_path, inner)?
+                }
+            };
+
+            registry.register(replay_provider);
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -222,12 +366,99 @@ mod tests {
         assert!(!should_register(&providers, "anthropic", "default"));
     }
 
+    #[tokio::test]
+    async fn test_replay_registers_only_the_referenced_entry() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cassette_path = dir.path().join("session.json");
+        let cassette = g3_providers::replay::Cassette {
+            version: 1,
+            provider: "anthropic.default".to_string(),
+            model: "test-model".to_string(),
+            native_tool_calling: true,
+            supports_cache_control: false,
+            supports_vision: false,
+            max_tokens: 1024,
+            temperature: 0.0,
+            interactions: Vec::new(),
+        };
+        cassette.save(&cassette_path).unwrap();
+        let saved = std::fs::read_to_string(&cassette_path).unwrap();
+
+        // The documented setup: a record entry and a replay entry sharing one cassette
+        let mut config = Config::default();
+        let cassette = cassette_path.to_string_lossy().into_owned();
+        for (name, mode, record_from) in [
+            ("record", ReplayMode::Record, Some("anthropic.default".to_string())),
+            ("ci", ReplayMode::Replay, None),
+        ] {
+            config.providers.replay.insert(
+                name.to_string(),
+                g3_config::ReplayConfig {
+                    cassette: cassette.clone(),
+                    mode,
+                    record_from,
+                    strict: true,
+                    context_length: None,
+                },
+            );
+        }
+        config.providers.default_provider = "replay.ci".into();
+
+        // Building replay.record would need Anthropic credentials and truncate the cassette
+        let registry = register_providers(&config, &["replay.ci".to_string()]).await.unwrap();
+        assert_eq!(registry.list_providers(), vec!["replay.ci"]);
+        assert_eq!(std::fs::read_to_string(&cassette_path).unwrap(), saved);
+    }
+
+    #[tokio::test]
+    async fn test_openai_compatible_provider_can_be_the_default() {
+        let mut config = Config::default();
+        config.providers.openai_compatible.insert(
+            "openrouter".to_string(),
+            g3_config::OpenAIConfig {
+                api_key: "key".to_string(),
+                model: "some-model".to_string(),
+                base_url: Some("http://127.0.0.1:9".to_string()),
+                max_tokens: None,
+                temperature: None,
+                vision: None,
+            },
+        );
+        config.providers.default_provider = "openrouter.default".into();
+
+        let registry = register_providers(&config, &["openrouter.default".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(registry.list_providers(), vec!["openrouter"]);
+    }
+
     #[test]
     fn test_determine_providers_normal_mode() {
         // Create a minimal config for testing
         let config = Config::default();
         let providers = determine_providers_to_register(&config, false);
         assert_eq!(providers.len(), 1);
-        assert_eq!(providers[0], config.providers.default_provider);
+        assert_eq!(providers[0], config.providers.default_provider.primary());
+    }
+
+    #[test]
+    fn test_determine_providers_includes_failover_chains() {
+        let mut config = Config::default();
+        config.providers.default_provider = "anthropic.default".into();
+        config.providers.coach = Some(
+            vec!["anthropic.default".to_string(), "databricks.default".to_string()]
+                .try_into()
+                .unwrap(),
+        );
+        config.providers.player = Some("openai.default".into());
+
+        let providers = determine_providers_to_register(&config, true);
+        assert_eq!(
+            providers,
+            vec!["anthropic.default", "databricks.default", "openai.default"]
+        );
+
+        let providers = determine_providers_to_register(&config, false);
+        assert_eq!(providers, vec!["anthropic.default"]);
     }
 }
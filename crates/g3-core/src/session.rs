@@ -5,7 +5,11 @@
 //! operations from the Agent, keeping the Agent as a thin orchestrator.
 
 use crate::context_window::ContextWindow;
-use crate::paths::{ensure_session_dir, get_context_summary_file, get_g3_dir, get_session_file};
+use crate::cost_ledger::CostLedger;
+use crate::paths::{
+    ensure_session_dir, get_context_summary_file, get_g3_dir, get_ledger_file, get_session_file,
+};
+use crate::permissions::PermissionLogEntry;
 use g3_providers::MessageRole;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
@@ -91,9 +95,11 @@ pub fn generate_session_id(description: &str, agent_name: Option<&str>) -> Strin
 ///
 /// If session_id is provided, saves to `.g3/sessions/<session_id>/session.json`.
 /// Otherwise, saves to `.g3/sessions/anonymous_<timestamp>/session.json`.
+/// The session's permission decisions are saved alongside the conversation.
 pub fn save_context_window(
     session_id: Option<&str>,
     context_window: &ContextWindow,
+    permission_decisions: &[PermissionLogEntry],
     status: &str,
 ) {
     let timestamp = SystemTime::now()
@@ -128,7 +134,8 @@ pub fn save_context_window(
             "total_tokens": context_window.total_tokens,
             "percentage_used": context_window.percentage_used(),
             "conversation_history": context_window.conversation_history
-        }
+        },
+        "permission_decisions": permission_decisions
     });
 
     match serde_json::to_string_pretty(&context_data) {
@@ -143,6 +150,23 @@ pub fn save_context_window(
     }
 }
 
+/// Save the cost ledger to `.g3/sessions/<session_id>/ledger.json`, next to session.json.
+///
+/// Nothing is written until the ledger has recorded some usage.
+pub fn save_cost_ledger(session_id: &str, ledger: &CostLedger) {
+    if ledger.is_empty() {
+        return;
+    }
+    if let Err(e) = ensure_session_dir(session_id) {
+        error!("Failed to create session directory: {}", e);
+        return;
+    }
+    let path = get_ledger_file(session_id);
+    if let Err(e) = ledger.save(&path) {
+        error!("Failed to save cost ledger to {:?}: {}", &path, e);
+    }
+}
+
 /// Write a human-readable context window summary to file.
 ///
 /// Format: message_id, role, token_count, indicator, first_120_chars
@@ -161,7 +185,7 @@ pub fn write_context_window_summary(session_id: &str, context_window: &ContextWi
 
     for message in &context_window.conversation_history {
         // Estimate tokens for this message
-        let message_tokens = ContextWindow::estimate_tokens(&message.content);
+        let message_tokens = context_window.count_tokens(&message.content);
 
         // Format token count and get indicator
         let token_str = format_token_count(message_tokens);
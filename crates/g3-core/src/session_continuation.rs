@@ -13,6 +13,8 @@ use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use tracing::{debug, error, warn};
 
+use crate::file_fingerprints::FileFingerprints;
+
 /// Version of the session continuation format
 const CONTINUATION_VERSION: &str = "1.0";
 
@@ -45,6 +47,9 @@ pub struct SessionContinuation {
     pub todo_snapshot: Option<String>,
     /// Working directory where the session was running
     pub working_directory: String,
+    /// What the agent had seen of the files it read or wrote
+    #[serde(default)]
+    pub file_fingerprints: FileFingerprints,
 }
 
 impl SessionContinuation {
@@ -72,6 +77,7 @@ impl SessionContinuation {
             context_percentage,
             todo_snapshot,
             working_directory,
+            file_fingerprints: FileFingerprints::default(),
         }
     }
 
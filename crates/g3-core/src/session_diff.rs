@@ -0,0 +1,445 @@
+//! Session diff: what the agent changed since the session started.
+//!
+//! The first time `write_file`, `str_replace`, `apply_patch` or `shell` touches a path
+//! in a session, its content is copied into `.g3/sessions/<session_id>/baseline/`. The
+//! session diff compares each recorded path against that copy, so it works in
+//! directories that are not git repositories and ignores changes made before the session.
+//!
+//! `shell` is tracked on a best-effort basis: only paths that a command visibly writes
+//! (redirections, `tee`, `touch`, `rm`, `mv`, `cp`, `sed -i`, ...) are recorded.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::paths::get_baseline_dir;
+
+const MANIFEST_FILE: &str = "baseline.json";
+
+/// Lines of unchanged context around each hunk
+const CONTEXT_LINES: usize = 3;
+
+/// Above this many line pairs, a changed region is shown as removed-then-added
+/// instead of being aligned line by line
+const MAX_ALIGN_CELLS: usize = 4_000_000;
+
+/// A file's state when the session first touched it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaselineFile {
+    /// Absolute path of the touched file
+    pub path: PathBuf,
+    /// Name of the saved copy in the baseline directory; `None` if the file didn't exist
+    pub saved_as: Option<String>,
+}
+
+/// How a file differs from its session-start state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// One changed file in the session diff
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+    /// Unified diff of the change (headers included)
+    pub diff: String,
+}
+
+/// Save `path` as it is now, unless the session already recorded it
+pub fn record_original(session_id: &str, path: &Path) -> Result<()> {
+    let path = std::path::absolute(path)?;
+    let dir = get_baseline_dir(session_id);
+    let mut files = load_manifest(&dir)?;
+    if files.iter().any(|file| file.path == path) {
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(&dir)?;
+    let saved_as = if path.is_file() {
+        let name = format!("file_{}", files.len());
+        std::fs::copy(&path, dir.join(&name))
+            .with_context(|| format!("Failed to save the original of {}", path.display()))?;
+        Some(name)
+    } else if path.exists() {
+        // Directories and other non-files are not diffed
+        return Ok(());
+    } else {
+        None
+    };
+    debug!("Recorded session baseline for {}", path.display());
+    files.push(BaselineFile { path, saved_as });
+    std::fs::write(dir.join(MANIFEST_FILE), serde_json::to_string_pretty(&files)?)?;
+    Ok(())
+}
+
+/// The paths the session has touched, in the order they were first touched
+pub fn touched_files(session_id: &str) -> Result<Vec<BaselineFile>> {
+    load_manifest(&get_baseline_dir(session_id))
+}
+
+/// Diff every touched file against its session-start state, skipping unchanged ones
+pub fn session_diff(session_id: &str) -> Result<Vec<FileDiff>> {
+    let dir = get_baseline_dir(session_id);
+    let mut diffs = Vec::new();
+    for file in load_manifest(&dir)? {
+        let before = match &file.saved_as {
+            Some(name) => Some(std::fs::read(dir.join(name))?),
+            None => None,
+        };
+        let after = if file.path.is_file() {
+            Some(std::fs::read(&file.path)?)
+        } else {
+            None
+        };
+        let kind = match (&before, &after) {
+            (None, None) => continue,
+            (Some(before), Some(after)) if before == after => continue,
+            (None, Some(_)) => ChangeKind::Added,
+            (Some(_), None) => ChangeKind::Deleted,
+            (Some(_), Some(_)) => ChangeKind::Modified,
+        };
+
+        let label = display_path(&file.path);
+        let old_label = match kind {
+            ChangeKind::Added => "/dev/null".to_string(),
+            _ => format!("a/{}", label),
+        };
+        let new_label = match kind {
+            ChangeKind::Deleted => "/dev/null".to_string(),
+            _ => format!("b/{}", label),
+        };
+        let before = before.unwrap_or_default();
+        let after = after.unwrap_or_default();
+        let diff = match (std::str::from_utf8(&before), std::str::from_utf8(&after)) {
+            (Ok(old), Ok(new)) => unified_diff(old, new, &old_label, &new_label),
+            _ => format!("Binary files {} and {} differ\n", old_label, new_label),
+        };
+        diffs.push(FileDiff {
+            path: file.path,
+            kind,
+            diff,
+        });
+    }
+    Ok(diffs)
+}
+
+/// Render the session diff as one patch, with a summary line per file at the top
+pub fn format_session_diff(diffs: &[FileDiff]) -> String {
+    let mut output = String::new();
+    for file in diffs {
+        let marker = match file.kind {
+            ChangeKind::Added => "A",
+            ChangeKind::Modified => "M",
+            ChangeKind::Deleted => "D",
+        };
+        output.push_str(&format!("{} {}\n", marker, display_path(&file.path)));
+    }
+    for file in diffs {
+        output.push('\n');
+        output.push_str(&file.diff);
+    }
+    output
+}
+
+/// Unified diff between two texts, with `CONTEXT_LINES` lines of context.
+/// Returns an empty string if they are equal. Lines are compared with their line
+/// endings, so adding or removing the final newline shows up as a change, marked
+/// `\ No newline at end of file` as in git.
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+    if ops.iter().all(|op| matches!(op, DiffOp::Equal(..))) {
+        return String::new();
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_label, new_label);
+    let mut i = 0;
+    while i < ops.len() {
+        // Find the next change and the run of changes (with short gaps) that follows it
+        let Some(first_change) = ops[i..].iter().position(|op| !matches!(op, DiffOp::Equal(..))) else {
+            break;
+        };
+        let start = (i + first_change).saturating_sub(CONTEXT_LINES).max(i);
+        let mut end = i + first_change;
+        let mut equal_run = 0;
+        let mut j = end;
+        while j < ops.len() {
+            if matches!(ops[j], DiffOp::Equal(..)) {
+                equal_run += 1;
+                if equal_run > CONTEXT_LINES * 2 {
+                    break;
+                }
+            } else {
+                equal_run = 0;
+                end = j;
+            }
+            j += 1;
+        }
+        let end = (end + 1 + CONTEXT_LINES).min(ops.len());
+
+        let hunk = &ops[start..end];
+        let (old_start, new_start) = match hunk[0] {
+            DiffOp::Equal(o, n) | DiffOp::Delete(o, n) | DiffOp::Insert(o, n) => (o, n),
+        };
+        let old_count = hunk.iter().filter(|op| !matches!(op, DiffOp::Insert(..))).count();
+        let new_count = hunk.iter().filter(|op| !matches!(op, DiffOp::Delete(..))).count();
+        output.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+        for op in hunk {
+            let (prefix, line) = match *op {
+                DiffOp::Equal(o, _) => (' ', old_lines[o]),
+                DiffOp::Delete(o, _) => ('-', old_lines[o]),
+                DiffOp::Insert(_, n) => ('+', new_lines[n]),
+            };
+            output.push(prefix);
+            output.push_str(line);
+            if !line.ends_with('\n') {
+                output.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        i = end;
+    }
+    output
+}
+
+/// Paths a shell command visibly writes to, resolved against `cwd`.
+///
+/// This is a heuristic: it looks at output redirections and the arguments of common
+/// file-modifying commands, and misses anything else (build tools, scripts, ...).
+pub fn shell_write_targets(command: &str, cwd: &Path) -> Vec<PathBuf> {
+    let mut targets = Vec::new();
+    for segment in command.split(['\n', ';', '|', '&']) {
+        let words = split_words(segment);
+        let mut args = Vec::new();
+        let mut iter = words.iter().peekable();
+        while let Some(word) = iter.next() {
+            // Redirections: `> file`, `>> file`, `>file`, `2> file`
+            let redirect = word.trim_start_matches(|c: char| c.is_ascii_digit());
+            if let Some(rest) = redirect.strip_prefix('>') {
+                let rest = rest.trim_start_matches('>');
+                if rest.is_empty() {
+                    if let Some(target) = iter.next() {
+                        targets.push(target.clone());
+                    }
+                } else {
+                    targets.push(rest.to_string());
+                }
+                continue;
+            }
+            args.push(word.as_str());
+        }
+
+        // Skip leading `VAR=value` assignments and `sudo`
+        let mut args = args.into_iter().skip_while(|arg| arg.contains('=') || *arg == "sudo");
+        let Some(program) = args.next() else {
+            continue;
+        };
+        let operands: Vec<&str> = args.filter(|arg| !arg.starts_with('-')).collect();
+        match program.rsplit('/').next().unwrap_or(program) {
+            "tee" | "touch" | "rm" | "mv" | "truncate" => {
+                targets.extend(operands.iter().map(|arg| arg.to_string()));
+            }
+            "cp" | "ln" | "install" => {
+                targets.extend(operands.last().map(|arg| arg.to_string()));
+            }
+            "sed" | "perl" if segment.contains(" -i") || segment.contains(" -pi") => {
+                // The first operand is the script
+                targets.extend(operands.iter().skip(1).map(|arg| arg.to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    targets
+        .into_iter()
+        .filter(|target| !target.is_empty() && !target.starts_with("/dev/") && !target.starts_with('&'))
+        .map(|target| {
+            let path = PathBuf::from(shellexpand::tilde(&target).as_ref());
+            if path.is_absolute() {
+                path
+            } else {
+                cwd.join(path)
+            }
+        })
+        .collect()
+}
+
+/// Split a command segment into words, honouring simple single and double quotes
+fn split_words(segment: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    for c in segment.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => quote = Some(c),
+            (None, c) if c.is_whitespace() => {
+                if !current.is_empty() {
+                    words.push(std::mem::take(&mut current));
+                }
+            }
+            (None, c) => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// A line-level edit; each carries the old and new line index it applies at
+#[derive(Debug, Clone, Copy)]
+enum DiffOp {
+    Equal(usize, usize),
+    Delete(usize, usize),
+    Insert(usize, usize),
+}
+
+/// Align two sets of lines: common prefix and suffix first, then a longest common
+/// subsequence over the changed middle (if it is small enough)
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut ops: Vec<DiffOp> = (0..prefix).map(|i| DiffOp::Equal(i, i)).collect();
+    if old_mid.len().saturating_mul(new_mid.len()) > MAX_ALIGN_CELLS {
+        ops.extend((0..old_mid.len()).map(|o| DiffOp::Delete(prefix + o, prefix)));
+        ops.extend((0..new_mid.len()).map(|n| DiffOp::Insert(prefix + old_mid.len(), prefix + n)));
+    } else {
+        // lcs[o][n] = length of the LCS of old_mid[o..] and new_mid[n..]
+        let width = new_mid.len() + 1;
+        let mut lcs = vec![0u32; (old_mid.len() + 1) * width];
+        for o in (0..old_mid.len()).rev() {
+            for n in (0..new_mid.len()).rev() {
+                lcs[o * width + n] = if old_mid[o] == new_mid[n] {
+                    lcs[(o + 1) * width + n + 1] + 1
+                } else {
+                    lcs[(o + 1) * width + n].max(lcs[o * width + n + 1])
+                };
+            }
+        }
+        let (mut o, mut n) = (0, 0);
+        while o < old_mid.len() || n < new_mid.len() {
+            if o < old_mid.len() && n < new_mid.len() && old_mid[o] == new_mid[n] {
+                ops.push(DiffOp::Equal(prefix + o, prefix + n));
+                o += 1;
+                n += 1;
+            } else if o < old_mid.len()
+                && (n == new_mid.len() || lcs[(o + 1) * width + n] >= lcs[o * width + n + 1])
+            {
+                ops.push(DiffOp::Delete(prefix + o, prefix + n));
+                o += 1;
+            } else {
+                ops.push(DiffOp::Insert(prefix + o, prefix + n));
+                n += 1;
+            }
+        }
+    }
+    let old_end = old.len() - suffix;
+    let new_end = new.len() - suffix;
+    ops.extend((0..suffix).map(|i| DiffOp::Equal(old_end + i, new_end + i)));
+    ops
+}
+
+/// Format a hunk range (`start,count`, 1-based; an empty range names the line before it)
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// Show paths under the current directory relative to it
+fn display_path(path: &Path) -> String {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+fn load_manifest(dir: &Path) -> Result<Vec<BaselineFile>> {
+    let manifest = dir.join(MANIFEST_FILE);
+    if !manifest.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&manifest)?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Invalid session baseline manifest {}", manifest.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff_hunks() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+        let diff = unified_diff(old, new, "a/x", "b/x");
+        assert_eq!(
+            diff,
+            "--- a/x\n+++ b/x\n\
+             @@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n\
+             @@ -10,3 +10,4 @@\n j\n k\n l\n+m\n"
+        );
+        assert_eq!(unified_diff(old, old, "a/x", "b/x"), "");
+    }
+
+    #[test]
+    fn test_unified_diff_new_file() {
+        let diff = unified_diff("", "one\ntwo\n", "/dev/null", "b/x");
+        assert_eq!(diff, "--- /dev/null\n+++ b/x\n@@ -0,0 +1,2 @@\n+one\n+two\n");
+    }
+
+    #[test]
+    fn test_unified_diff_final_newline() {
+        let diff = unified_diff("a\nb\n", "a\nb", "a/x", "b/x");
+        assert_eq!(
+            diff,
+            "--- a/x\n+++ b/x\n@@ -1,2 +1,2 @@\n a\n-b\n+b\n\\ No newline at end of file\n"
+        );
+
+        let diff = unified_diff("a\nb", "a\nb\nc\n", "a/x", "b/x");
+        assert_eq!(
+            diff,
+            "--- a/x\n+++ b/x\n@@ -1,2 +1,3 @@\n a\n-b\n\\ No newline at end of file\n+b\n+c\n"
+        );
+    }
+
+    #[test]
+    fn test_shell_write_targets() {
+        let cwd = Path::new("/work");
+        let targets = |command| shell_write_targets(command, cwd);
+        assert_eq!(
+            targets("echo hi > out.txt && cat a.txt | tee -a log.txt 2>/dev/null"),
+            vec![PathBuf::from("/work/out.txt"), PathBuf::from("/work/log.txt")]
+        );
+        assert_eq!(
+            targets("sed -i 's/a/b/' src/lib.rs; cp -r a.txt /tmp/b.txt"),
+            vec![PathBuf::from("/work/src/lib.rs"), PathBuf::from("/tmp/b.txt")]
+        );
+        assert_eq!(targets("rm -f \"my file.txt\""), vec![PathBuf::from("/work/my file.txt")]);
+        assert!(targets("cargo build 2>&1").is_empty());
+        assert!(targets("ls -la").is_empty());
+    }
+}
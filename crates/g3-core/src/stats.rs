@@ -7,6 +7,7 @@ use g3_providers::MessageRole;
 use std::time::Duration;
 
 use crate::context_window::ContextWindow;
+use crate::cost_ledger::CostLedger;
 
 /// Data required to format agent statistics.
 /// This struct captures a snapshot of agent state for formatting.
@@ -17,6 +18,7 @@ pub struct AgentStatsSnapshot<'a> {
     pub first_token_times: &'a [Duration],
     pub tool_call_metrics: &'a [(String, Duration, bool)],
     pub provider_info: Option<(String, String)>,
+    pub cost_ledger: &'a CostLedger,
 }
 
 impl<'a> AgentStatsSnapshot<'a> {
@@ -33,6 +35,7 @@ impl<'a> AgentStatsSnapshot<'a> {
         self.format_performance_metrics(&mut stats);
         self.format_conversation_history(&mut stats);
         self.format_tool_call_metrics(&mut stats);
+        self.format_cost(&mut stats);
         self.format_provider_info(&mut stats);
 
         stats.push_str(&"=".repeat(60));
@@ -184,6 +187,12 @@ impl<'a> AgentStatsSnapshot<'a> {
         stats.push('\n');
     }
 
+    fn format_cost(&self, stats: &mut String) {
+        stats.push_str("💰 Spend:\n");
+        stats.push_str(&self.cost_ledger.format_breakdown("   "));
+        stats.push('\n');
+    }
+
     fn format_provider_info(&self, stats: &mut String) {
         stats.push_str("🔌 Provider:\n");
         if let Some((provider, model)) = &self.provider_info {
@@ -197,6 +206,8 @@ impl<'a> AgentStatsSnapshot<'a> {
 mod tests {
     use super::*;
     use crate::context_window::ContextWindow;
+    use crate::cost_ledger::AgentRole;
+    use g3_providers::Usage;
 
     #[test]
     fn test_format_stats_empty() {
@@ -208,6 +219,7 @@ mod tests {
             first_token_times: &[],
             tool_call_metrics: &[],
             provider_info: None,
+            cost_ledger: &CostLedger::new(),
         };
 
         let stats = snapshot.format();
@@ -215,6 +227,7 @@ mod tests {
         assert!(stats.contains("Used Tokens"));
         assert!(stats.contains("Thinning Events"));
         assert!(stats.contains("Tool Call Metrics"));
+        assert!(stats.contains("No provider usage recorded"));
     }
 
     #[test]
@@ -233,6 +246,24 @@ mod tests {
             ("write_file".to_string(), Duration::from_millis(200), false),
         ];
 
+        let mut cost_ledger = CostLedger::new();
+        cost_ledger.record(
+            "anthropic.default",
+            "claude-3",
+            AgentRole::Player,
+            &Usage {
+                prompt_tokens: 1_000_000,
+                completion_tokens: 0,
+                total_tokens: 1_000_000,
+                ..Default::default()
+            },
+            Some(g3_config::ModelPricing {
+                input: 3.0,
+                output: 15.0,
+                ..Default::default()
+            }),
+        );
+
         let snapshot = AgentStatsSnapshot {
             context_window: &context_window,
             thinning_events: &thinning_events,
@@ -240,6 +271,7 @@ mod tests {
             first_token_times: &first_token_times,
             tool_call_metrics: &tool_call_metrics,
             provider_info: Some(("anthropic".to_string(), "claude-3".to_string())),
+            cost_ledger: &cost_ledger,
         };
 
         let stats = snapshot.format();
@@ -256,6 +288,10 @@ mod tests {
         assert!(stats.contains("Successful:                 2"));
         assert!(stats.contains("Failed:                     1"));
         
+        // Check spend
+        assert!(stats.contains("Total Spend:          $3.0000"));
+        assert!(stats.contains("anthropic.default claude-3 (player): $3.0000"));
+
         // Check provider info
         assert!(stats.contains("Provider:          anthropic"));
         assert!(stats.contains("Model:             claude-3"));
@@ -0,0 +1,261 @@
+//! Token counting.
+//!
+//! g3 needs token counts before a provider reports any usage: to decide when to thin
+//! or compact the context, and how much of a file fits in a `read_file` result. The
+//! `Tokenizer` trait lets each provider family count in its own way; `for_provider`
+//! picks the closest built-in tokenizer for a provider/model pair.
+//!
+//! `BpeTokenizer` encodes with the real cl100k/o200k BPE rank tables (embedded by
+//! `tiktoken-rs`), so OpenAI counts are exact. Other families are counted with the
+//! closest encoding, and the `ContextWindow` calibrates counts against the usage
+//! reported by the provider.
+
+use std::sync::Arc;
+
+use tiktoken_rs::CoreBPE;
+
+/// Counts tokens for a model family
+pub trait Tokenizer: Send + Sync + std::fmt::Debug {
+    /// Short name shown in logs (e.g. "cl100k")
+    fn name(&self) -> &str;
+
+    /// Number of tokens `text` encodes to
+    fn count_tokens(&self, text: &str) -> u32;
+}
+
+/// Embedded BPE encodings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BpeEncoding {
+    /// GPT-4 / GPT-3.5 (about 100k tokens)
+    Cl100k,
+    /// GPT-4o and later OpenAI models (about 200k tokens, better multilingual coverage)
+    O200k,
+}
+
+impl BpeEncoding {
+    /// The rank table, loaded on first use and shared by every tokenizer
+    fn bpe(self) -> &'static CoreBPE {
+        match self {
+            BpeEncoding::Cl100k => tiktoken_rs::cl100k_base_singleton(),
+            BpeEncoding::O200k => tiktoken_rs::o200k_base_singleton(),
+        }
+    }
+}
+
+/// Tokenizer for the cl100k/o200k BPE encodings
+#[derive(Debug, Clone, Copy)]
+pub struct BpeTokenizer {
+    encoding: BpeEncoding,
+}
+
+impl BpeTokenizer {
+    pub fn new(encoding: BpeEncoding) -> Self {
+        Self { encoding }
+    }
+
+    pub fn encoding(&self) -> BpeEncoding {
+        self.encoding
+    }
+}
+
+impl Tokenizer for BpeTokenizer {
+    fn name(&self) -> &str {
+        match self.encoding {
+            BpeEncoding::Cl100k => "cl100k",
+            BpeEncoding::O200k => "o200k",
+        }
+    }
+
+    fn count_tokens(&self, text: &str) -> u32 {
+        // Special-token text (e.g. "<|endoftext|>") in files is counted as plain text
+        let bpe = self.encoding.bpe();
+        split_long_runs(text)
+            .map(|segment| bpe.encode_ordinary(segment).len() as u32)
+            .sum()
+    }
+}
+
+/// Longest run of whitespace, or of anything else, encoded in one piece
+const MAX_RUN_BYTES: usize = 256;
+
+/// Split `text` inside runs of more than `MAX_RUN_BYTES` of whitespace or of
+/// non-whitespace. The pre-tokenizer regex and BPE merging are quadratic in a run's
+/// length, and the regex overflows its stack on very long runs, so a minified or
+/// padded file would take minutes to count or panic.
+fn split_long_runs(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let mut run_start = 0;
+        let mut run_is_space = None;
+        let mut cut = rest.len();
+        for (i, c) in rest.char_indices() {
+            if run_is_space != Some(c.is_whitespace()) {
+                run_start = i;
+                run_is_space = Some(c.is_whitespace());
+            } else if i + c.len_utf8() - run_start > MAX_RUN_BYTES {
+                cut = i;
+                break;
+            }
+        }
+        let (segment, tail) = rest.split_at(cut);
+        rest = tail;
+        Some(segment)
+    })
+}
+
+/// Approximation of the Claude tokenizer, which produces noticeably more tokens than
+/// cl100k for the same text (especially code).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AnthropicTokenizer;
+
+/// Claude tokens per cl100k token
+const ANTHROPIC_TOKEN_RATIO: f32 = 1.15;
+
+impl Tokenizer for AnthropicTokenizer {
+    fn name(&self) -> &str {
+        "anthropic"
+    }
+
+    fn count_tokens(&self, text: &str) -> u32 {
+        (BpeTokenizer::new(BpeEncoding::Cl100k).count_tokens(text) as f32 * ANTHROPIC_TOKEN_RATIO).ceil() as u32
+    }
+}
+
+/// A tokenizer plus a correction factor learned from provider-reported usage.
+/// Cheap to clone; this is what the context window and tools count tokens with.
+#[derive(Debug, Clone)]
+pub struct TokenCounter {
+    tokenizer: Arc<dyn Tokenizer>,
+    calibration: f32,
+}
+
+impl TokenCounter {
+    pub fn new(tokenizer: Arc<dyn Tokenizer>) -> Self {
+        Self {
+            tokenizer,
+            calibration: 1.0,
+        }
+    }
+
+    pub fn tokenizer(&self) -> &Arc<dyn Tokenizer> {
+        &self.tokenizer
+    }
+
+    /// Factor applied to the tokenizer's counts
+    pub fn calibration(&self) -> f32 {
+        self.calibration
+    }
+
+    pub fn set_calibration(&mut self, calibration: f32) {
+        self.calibration = calibration;
+    }
+
+    pub fn count(&self, text: &str) -> u32 {
+        (self.tokenizer.count_tokens(text) as f32 * self.calibration).ceil() as u32
+    }
+}
+
+impl Default for TokenCounter {
+    fn default() -> Self {
+        Self::new(default_tokenizer())
+    }
+}
+
+/// Tokenizer used when the provider is unknown
+pub fn default_tokenizer() -> Arc<dyn Tokenizer> {
+    Arc::new(BpeTokenizer::new(BpeEncoding::Cl100k))
+}
+
+/// Pick the tokenizer closest to what a provider's model uses
+pub fn for_provider(provider_name: &str, model: &str) -> Arc<dyn Tokenizer> {
+    let provider_type = provider_name.split('.').next().unwrap_or(provider_name);
+    let model = model.to_lowercase();
+
+    if provider_type == "anthropic" || model.contains("claude") {
+        return Arc::new(AnthropicTokenizer);
+    }
+
+    // Large-vocabulary models: recent OpenAI models and Gemini/Gemma (SentencePiece, 256k)
+    const LARGE_VOCAB: &[&str] = &["gpt-4o", "gpt-4.1", "gpt-4.5", "gpt-5", "chatgpt", "gemini", "gemma"];
+    let is_o_series = ["o1", "o3", "o4"]
+        .iter()
+        .any(|m| model == *m || model.starts_with(&format!("{}-", m)));
+    if provider_type == "gemini" || is_o_series || LARGE_VOCAB.iter().any(|m| model.contains(m)) {
+        return Arc::new(BpeTokenizer::new(BpeEncoding::O200k));
+    }
+
+    default_tokenizer()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cl100k(text: &str) -> u32 {
+        BpeTokenizer::new(BpeEncoding::Cl100k).count_tokens(text)
+    }
+
+    fn o200k(text: &str) -> u32 {
+        BpeTokenizer::new(BpeEncoding::O200k).count_tokens(text)
+    }
+
+    #[test]
+    fn test_cl100k_counts() {
+        // Reference counts from tiktoken's cl100k_base
+        assert_eq!(cl100k(""), 0);
+        assert_eq!(cl100k("Hello world"), 2);
+        assert_eq!(cl100k("tiktoken is great!"), 6);
+        assert_eq!(cl100k("The quick brown fox jumps over the lazy dog."), 10);
+        assert_eq!(cl100k("I'm sure it's fine"), 6);
+        assert_eq!(cl100k("1234567"), 3);
+    }
+
+    #[test]
+    fn test_o200k_counts() {
+        // Reference counts from tiktoken's o200k_base
+        assert_eq!(o200k(""), 0);
+        assert_eq!(o200k("Hello world"), 2);
+        assert_eq!(o200k("The quick brown fox jumps over the lazy dog."), 10);
+
+        // The larger vocabulary covers non-English text better
+        let russian = "Привет, мир! Как дела?";
+        assert!(o200k(russian) < cl100k(russian));
+    }
+
+    #[test]
+    fn test_long_runs_are_counted_in_pieces() {
+        let start = std::time::Instant::now();
+        let tokens = cl100k(&"x".repeat(300_000));
+        assert!((30_000..=75_000).contains(&tokens), "got {}", tokens);
+        assert!(cl100k(&" ".repeat(1_000_000)) > 0);
+        assert!(start.elapsed() < std::time::Duration::from_secs(20));
+
+        // Runs are only split past MAX_RUN_BYTES, and every byte lands in a segment
+        let text = format!("short words {} and ü{}\n{}", "y".repeat(300), "é".repeat(200), "\n".repeat(300));
+        let segments: Vec<&str> = split_long_runs(&text).collect();
+        assert_eq!(segments.concat(), text);
+        assert_eq!(segments.len(), 4);
+        assert!(segments.iter().all(|s| s.len() <= 3 * MAX_RUN_BYTES));
+        assert_eq!(split_long_runs("Hello world").count(), 1);
+    }
+
+    #[test]
+    fn test_anthropic_counts_more_than_cl100k() {
+        let text = "pub fn estimate(text: &str) -> u32 { text.len() as u32 / 4 }";
+        assert!(AnthropicTokenizer.count_tokens(text) > cl100k(text));
+    }
+
+    #[test]
+    fn test_for_provider() {
+        assert_eq!(for_provider("anthropic.default", "claude-sonnet-4-5").name(), "anthropic");
+        assert_eq!(for_provider("databricks.default", "databricks-claude-sonnet-4").name(), "anthropic");
+        assert_eq!(for_provider("openai.default", "gpt-4o").name(), "o200k");
+        assert_eq!(for_provider("openai.default", "o3-mini").name(), "o200k");
+        assert_eq!(for_provider("gemini.default", "gemini-2.5-pro").name(), "o200k");
+        assert_eq!(for_provider("openai.default", "gpt-4-turbo").name(), "cl100k");
+        assert_eq!(for_provider("ollama.default", "qwen2.5-coder:7b").name(), "cl100k");
+    }
+}
@@ -4,8 +4,11 @@
 //! used by the agent when interacting with LLM providers that support native
 //! tool calling.
 
+use g3_config::PluginToolConfig;
 use g3_providers::Tool;
 use serde_json::json;
+use std::collections::HashMap;
+use tracing::warn;
 
 /// Configuration for which optional tool sets to enable
 #[derive(Debug, Clone, Copy, Default)]
@@ -46,6 +49,36 @@ pub fn create_tool_definitions(config: ToolConfig) -> Vec<Tool> {
     tools
 }
 
+/// Create definitions for the enabled `[plugin_tools]`, sorted by name.
+///
+/// Plugins named like a built-in tool are skipped, since the built-in one would
+/// always be dispatched instead.
+pub fn create_plugin_tool_definitions(plugins: &HashMap<String, PluginToolConfig>) -> Vec<Tool> {
+    let builtin = create_tool_definitions(ToolConfig::new(true, true));
+    let mut names: Vec<&String> = plugins
+        .iter()
+        .filter(|(_, plugin)| plugin.enabled)
+        .map(|(name, _)| name)
+        .collect();
+    names.sort();
+
+    names
+        .into_iter()
+        .filter(|name| {
+            let clashes = builtin.iter().any(|tool| &tool.name == *name);
+            if clashes {
+                warn!("Ignoring plugin tool '{}': a built-in tool has that name", name);
+            }
+            !clashes
+        })
+        .map(|name| Tool {
+            name: name.clone(),
+            description: plugins[name].description.clone(),
+            input_schema: plugins[name].input_schema.clone(),
+        })
+        .collect()
+}
+
 /// Create the core tools that are always available
 fn create_core_tools(exclude_research: bool) -> Vec<Tool> {
     let mut tools = vec![
@@ -58,6 +91,18 @@ fn create_core_tools(exclude_research: bool) -> Vec<Tool> {
                     "command": {
                         "type": "string",
                         "description": "The shell command to execute"
+                    },
+                    "timeout_secs": {
+                        "type": "integer",
+                        "description": "Kill the command (and everything it started) after this many seconds. Defaults to the configured shell timeout; raise it for long builds or test runs."
+                    },
+                    "persistent": {
+                        "type": "boolean",
+                        "description": "Run in this session's long-lived shell, where `cd`, `export` and `source venv/bin/activate` carry over to later persistent calls. The result ends with the shell's cwd and environment changes."
+                    },
+                    "reset_session": {
+                        "type": "boolean",
+                        "description": "Discard the long-lived shell (and anything it left running) before running the command, starting again from the workspace with a clean environment."
                     }
                 },
                 "required": ["command"]
@@ -87,7 +132,7 @@ fn create_core_tools(exclude_research: bool) -> Vec<Tool> {
         },
         Tool {
             name: "read_file".to_string(),
-            description: "Read the contents of a file. Optionally read a specific character range.".to_string(),
+            description: "Read the contents of a file. Optionally read a range of lines, a single definition by symbol name (returned with line numbers), or a character range.".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -102,6 +147,18 @@ fn create_core_tools(exclude_research: bool) -> Vec<Tool> {
                     "end": {
                         "type": "integer",
                         "description": "Ending character position (0-indexed, EXCLUSIVE). If omitted, reads to end of file."
+                    },
+                    "start_line": {
+                        "type": "integer",
+                        "description": "First line to read (1-indexed, inclusive). Lines are returned numbered. Cannot be combined with start/end."
+                    },
+                    "end_line": {
+                        "type": "integer",
+                        "description": "Last line to read (1-indexed, INCLUSIVE). If omitted, reads to end of file."
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "Name of a function, method, struct, class, trait, enum or type to read, e.g. `parse_config` or `Config::load`. Returns just its definition, with line numbers."
                     }
                 },
                 "required": ["file_path"]
@@ -124,7 +181,7 @@ fn create_core_tools(exclude_research: bool) -> Vec<Tool> {
         },
         Tool {
             name: "write_file".to_string(),
-            description: "Write content to a file (creates or overwrites). You MUST provide all arguments".to_string(),
+            description: "Write content to a file (creates or overwrites). A file that changed on disk since you last read it is refused; read it again first. You MUST provide all arguments".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -142,7 +199,7 @@ fn create_core_tools(exclude_research: bool) -> Vec<Tool> {
         },
         Tool {
             name: "str_replace".to_string(),
-            description: "Apply a unified diff to a file. Supports multiple hunks and context lines. Optionally constrain the search to a [start, end) character range (0-indexed; end is EXCLUSIVE). Useful to disambiguate matches or limit scope in large files.".to_string(),
+            description: "Apply a unified diff to a file. Supports multiple hunks and context lines. Context that differs from the file only in whitespace or slightly is still matched (the result says how); a hunk that matches in several places is refused with their line numbers, and one that can't be found returns the closest lines. Optionally constrain the search to a [start, end) character range (0-indexed; end is EXCLUSIVE). Useful to disambiguate matches or limit scope in large files.".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -166,6 +223,20 @@ fn create_core_tools(exclude_research: bool) -> Vec<Tool> {
                 "required": ["file_path", "diff"]
             }),
         },
+        Tool {
+            name: "apply_patch".to_string(),
+            description: "Apply a multi-file unified diff (as written by `git diff`) atomically: files can be modified, created (--- /dev/null), deleted (+++ /dev/null) or renamed (rename from/rename to). Every hunk is matched like str_replace before anything is written; if any hunk fails, no file is changed. Use it for changes that span several files.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "patch": {
+                        "type": "string",
+                        "description": "Unified diff with a ---/+++ header (or diff --git header) for each file, followed by its @@ hunks. Paths are relative to the working directory; a/ and b/ prefixes are removed."
+                    }
+                },
+                "required": ["patch"]
+            }),
+        },
         Tool {
             name: "screenshot".to_string(),
             description: "Capture a screenshot of a specific application window. You MUST specify the window_id parameter with the application name (e.g., 'Safari', 'Terminal', 'Google Chrome'). The tool will automatically use the native screencapture command with the application's window ID for a clean capture. Use list_windows first to identify available windows.".to_string(),
@@ -508,10 +579,10 @@ mod tests {
     fn test_core_tools_count() {
         let tools = create_core_tools(false);
         // Should have the core tools: shell, background_process, read_file, read_image,
-        // write_file, str_replace, screenshot,
+        // write_file, str_replace, apply_patch, screenshot,
         // todo_read, todo_write, coverage, code_search, research, remember
         // (13 total - memory is auto-loaded, only remember tool needed)
-        assert_eq!(tools.len(), 14);
+        assert_eq!(tools.len(), 15);
     }
 
     #[test]
@@ -525,7 +596,7 @@ mod tests {
     fn test_create_tool_definitions_core_only() {
         let config = ToolConfig::default();
         let tools = create_tool_definitions(config);
-        assert_eq!(tools.len(), 14);
+        assert_eq!(tools.len(), 15);
     }
 
     #[test]
@@ -533,7 +604,7 @@ mod tests {
         let config = ToolConfig::new(true, true);
         let tools = create_tool_definitions(config);
         // 13 core + 15 webdriver = 28
-        assert_eq!(tools.len(), 29);
+        assert_eq!(tools.len(), 30);
     }
 
     #[test]
@@ -546,13 +617,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_plugin_tool_definitions() {
+        let plugin = |description: &str, enabled: bool| PluginToolConfig {
+            description: description.to_string(),
+            command: "true".to_string(),
+            args: Vec::new(),
+            input_schema: json!({ "type": "object", "properties": { "key": { "type": "string" } } }),
+            timeout_seconds: 10,
+            enabled,
+        };
+        let mut plugins = HashMap::new();
+        plugins.insert("ticket_lookup".to_string(), plugin("Look up a ticket", true));
+        plugins.insert("deploy_status".to_string(), plugin("Deploy status", true));
+        plugins.insert("disabled_tool".to_string(), plugin("Off", false));
+        plugins.insert("shell".to_string(), plugin("Shadows the built-in", true));
+
+        let tools = create_plugin_tool_definitions(&plugins);
+        let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["deploy_status", "ticket_lookup"]);
+        assert_eq!(tools[1].description, "Look up a ticket");
+        assert_eq!(tools[1].input_schema["properties"]["key"]["type"], "string");
+    }
+
     #[test]
     fn test_research_tool_excluded() {
         let tools_with_research = create_core_tools(false);
         let tools_without_research = create_core_tools(true);
         
-        assert_eq!(tools_with_research.len(), 14);
-        assert_eq!(tools_without_research.len(), 13);
+        assert_eq!(tools_with_research.len(), 15);
+        assert_eq!(tools_without_research.len(), 14);
         
         assert!(tools_with_research.iter().any(|t| t.name == "research"));
         assert!(!tools_without_research.iter().any(|t| t.name == "research"));
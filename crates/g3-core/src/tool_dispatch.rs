@@ -1,26 +1,63 @@
 //! Tool dispatch module - routes tool calls to their implementations.
 //!
 //! This module provides a clean dispatch mechanism that routes tool calls
-//! to the appropriate handler in the `tools/` module.
+//! to the appropriate handler in the `tools/` module (or a plugin tool or MCP
+//! server), after checking them against the permission policy, and runs the
+//! `pre_tool` / `post_tool` hooks around them.
 
 use anyhow::Result;
 use tracing::{debug, warn};
 
+use crate::{hooks, permissions};
 use crate::tools::executor::ToolContext;
-use crate::tools::{acd, file_ops, memory, misc, research, shell, todo, webdriver};
+use crate::tools::{acd, file_ops, memory, misc, plugin, research, shell, todo, webdriver};
 use crate::ui_writer::UiWriter;
 use crate::ToolCall;
 
 /// Dispatch a tool call to the appropriate handler.
 ///
 /// This function routes tool calls to their implementations in the `tools/` module,
-/// providing a single point of dispatch for all tool execution.
+/// providing a single point of dispatch for all tool execution. Calls refused by the
+/// `[permissions]` policy or blocked by a `pre_tool` hook return the refusal as the
+/// tool result.
 pub async fn dispatch_tool<W: UiWriter>(
     tool_call: &ToolCall,
     ctx: &mut ToolContext<'_, W>,
 ) -> Result<String> {
     debug!("Dispatching tool: {}", tool_call.tool);
 
+    if let Some(refusal) = permissions::authorize(tool_call, ctx) {
+        return Ok(refusal);
+    }
+
+    let config = ctx.config;
+    if let Some(refusal) =
+        hooks::pre_tool(&config.hooks, tool_call, ctx.session_id, ctx.working_dir).await
+    {
+        return Ok(refusal);
+    }
+
+    let result = route_tool(tool_call, ctx).await?;
+
+    if config.hooks.post_tool.is_empty() {
+        return Ok(result);
+    }
+    let written = file_ops::fresh_written_paths(tool_call, ctx);
+    let (result, succeeded) =
+        hooks::post_tool(&config.hooks, tool_call, result, ctx.session_id, ctx.working_dir).await;
+    // A formatter rewriting what the tool just wrote isn't an outside edit, so the
+    // agent's next write to the file shouldn't be refused as stale
+    if succeeded {
+        file_ops::refresh_fingerprints(ctx, &written);
+    }
+    Ok(result)
+}
+
+/// Run a tool call with the handler for its name.
+async fn route_tool<W: UiWriter>(
+    tool_call: &ToolCall,
+    ctx: &mut ToolContext<'_, W>,
+) -> Result<String> {
     match tool_call.tool.as_str() {
         // Shell tools
         "shell" => shell::execute_shell(tool_call, ctx).await,
@@ -31,6 +68,7 @@ pub async fn dispatch_tool<W: UiWriter>(
         "read_image" => file_ops::execute_read_image(tool_call, ctx).await,
         "write_file" => file_ops::execute_write_file(tool_call, ctx).await,
         "str_replace" => file_ops::execute_str_replace(tool_call, ctx).await,
+        "apply_patch" => file_ops::execute_apply_patch(tool_call, ctx).await,
 
         // TODO management
         "todo_read" => todo::execute_todo_read(tool_call, ctx).await,
@@ -67,10 +105,18 @@ pub async fn dispatch_tool<W: UiWriter>(
         "webdriver_refresh" => webdriver::execute_webdriver_refresh(tool_call, ctx).await,
         "webdriver_quit" => webdriver::execute_webdriver_quit(tool_call, ctx).await,
 
-        // Unknown tool
-        _ => {
-            warn!("Unknown tool: {}", tool_call.tool);
-            Ok(format!("❓ Unknown tool: {}", tool_call.tool))
+        // Plugin tools from config, then tools from external MCP servers, otherwise unknown
+        name => {
+            if let Some(plugin) = ctx.config.plugin_tools.get(name).filter(|p| p.enabled) {
+                return plugin::execute_plugin_tool(tool_call, plugin, ctx).await;
+            }
+            match ctx.mcp_servers.call(tool_call).await {
+                Some(result) => result,
+                None => {
+                    warn!("Unknown tool: {}", tool_call.tool);
+                    Ok(format!("❓ Unknown tool: {}", tool_call.tool))
+                }
+            }
         }
     }
 }
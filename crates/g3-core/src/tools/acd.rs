@@ -131,7 +131,12 @@ mod tests {
         webdriver_session: Arc<RwLock<Option<Arc<tokio::sync::Mutex<WebDriverSession>>>>>,
         webdriver_process: Arc<RwLock<Option<tokio::process::Child>>>,
         background_process_manager: Arc<BackgroundProcessManager>,
+        shell_session: Arc<tokio::sync::Mutex<Option<g3_execution::ShellSession>>>,
+        mcp_servers: Arc<crate::mcp::McpServers>,
+        turn: crate::checkpoints::Turn,
         todo_content: Arc<RwLock<String>>,
+        file_fingerprints: Arc<std::sync::Mutex<crate::file_fingerprints::FileFingerprints>>,
+        permission_log: Arc<std::sync::Mutex<Vec<crate::permissions::PermissionLogEntry>>>,
         pending_images: Vec<g3_providers::ImageContent>,
         config: g3_config::Config,
     }
@@ -143,7 +148,12 @@ mod tests {
                 webdriver_session: Arc::new(RwLock::new(None)),
                 webdriver_process: Arc::new(RwLock::new(None)),
                 background_process_manager: Arc::new(BackgroundProcessManager::new(std::path::PathBuf::from("/tmp"))),
+                shell_session: Arc::new(tokio::sync::Mutex::new(None)),
+                mcp_servers: Arc::new(Default::default()),
+                turn: Default::default(),
                 todo_content: Arc::new(RwLock::new(String::new())),
+                file_fingerprints: Default::default(),
+                permission_log: Default::default(),
                 pending_images: Vec::new(),
                 config: g3_config::Config::default(),
             }
@@ -162,12 +172,19 @@ mod tests {
             webdriver_session: &test_ctx.webdriver_session,
             webdriver_process: &test_ctx.webdriver_process,
             background_process_manager: &test_ctx.background_process_manager,
+            shell_session: &test_ctx.shell_session,
+            mcp_servers: &test_ctx.mcp_servers,
+            turn: &test_ctx.turn,
             todo_content: &test_ctx.todo_content,
+            file_fingerprints: &test_ctx.file_fingerprints,
+            permission_log: &test_ctx.permission_log,
             pending_images: &mut test_ctx.pending_images,
+            supports_vision: true,
             is_autonomous: false,
             requirements_sha: None,
             context_total_tokens: 100000,
             context_used_tokens: 10000,
+            token_counter: Default::default(),
         };
 
         let tool_call = ToolCall {
@@ -192,12 +209,19 @@ mod tests {
             webdriver_session: &test_ctx.webdriver_session,
             webdriver_process: &test_ctx.webdriver_process,
             background_process_manager: &test_ctx.background_process_manager,
+            shell_session: &test_ctx.shell_session,
+            mcp_servers: &test_ctx.mcp_servers,
+            turn: &test_ctx.turn,
             todo_content: &test_ctx.todo_content,
+            file_fingerprints: &test_ctx.file_fingerprints,
+            permission_log: &test_ctx.permission_log,
             pending_images: &mut test_ctx.pending_images,
+            supports_vision: true,
             is_autonomous: false,
             requirements_sha: None,
             context_total_tokens: 100000,
             context_used_tokens: 10000,
+            token_counter: Default::default(),
         };
 
         let tool_call = ToolCall {
@@ -222,12 +246,19 @@ mod tests {
             webdriver_session: &test_ctx.webdriver_session,
             webdriver_process: &test_ctx.webdriver_process,
             background_process_manager: &test_ctx.background_process_manager,
+            shell_session: &test_ctx.shell_session,
+            mcp_servers: &test_ctx.mcp_servers,
+            turn: &test_ctx.turn,
             todo_content: &test_ctx.todo_content,
+            file_fingerprints: &test_ctx.file_fingerprints,
+            permission_log: &test_ctx.permission_log,
             pending_images: &mut test_ctx.pending_images,
+            supports_vision: true,
             is_autonomous: false,
             requirements_sha: None,
             context_total_tokens: 100000,
             context_used_tokens: 10000,
+            token_counter: Default::default(),
         };
 
         let tool_call = ToolCall {
@@ -5,11 +5,17 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::background_process::BackgroundProcessManager;
+use crate::checkpoints::Turn;
+use crate::file_fingerprints::FileFingerprints;
+use crate::permissions::PermissionLogEntry;
+use crate::mcp::McpServers;
 use crate::paths::{ensure_session_dir, get_session_todo_path, get_todo_path};
+use crate::tokenizer::TokenCounter;
 use crate::ui_writer::UiWriter;
 use crate::webdriver_session::WebDriverSession;
 use crate::ToolCall;
 use g3_config::Config;
+use g3_execution::ShellSession;
 
 /// Context passed to tool executors containing shared state.
 pub struct ToolContext<'a, W: UiWriter> {
@@ -21,12 +27,26 @@ pub struct ToolContext<'a, W: UiWriter> {
     pub webdriver_session: &'a Arc<RwLock<Option<Arc<tokio::sync::Mutex<WebDriverSession>>>>>,
     pub webdriver_process: &'a Arc<RwLock<Option<tokio::process::Child>>>,
     pub background_process_manager: &'a Arc<BackgroundProcessManager>,
+    /// The agent session's long-lived shell, started on the first persistent `shell` call
+    pub shell_session: &'a Arc<tokio::sync::Mutex<Option<ShellSession>>>,
+    /// External MCP servers; tool names the built-in tools don't handle are routed here
+    pub mcp_servers: &'a Arc<McpServers>,
+    /// The user turn this call belongs to; file edits are checkpointed under it
+    pub turn: &'a Turn,
     pub todo_content: &'a Arc<RwLock<String>>,
+    /// What the agent has seen of each file, for catching edits made behind its back
+    pub file_fingerprints: &'a Arc<std::sync::Mutex<FileFingerprints>>,
+    /// Permission decisions made this session, saved in the session log
+    pub permission_log: &'a Arc<std::sync::Mutex<Vec<PermissionLogEntry>>>,
     pub pending_images: &'a mut Vec<g3_providers::ImageContent>,
+    /// Whether the current model accepts images (see `LLMProvider::supports_vision`)
+    pub supports_vision: bool,
     pub is_autonomous: bool,
     pub requirements_sha: Option<&'a str>,
     pub context_total_tokens: u32,
     pub context_used_tokens: u32,
+    /// Counts tokens the way the context window does
+    pub token_counter: TokenCounter,
 }
 
 impl<'a, W: UiWriter> ToolContext<'a, W> {
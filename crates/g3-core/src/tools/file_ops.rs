@@ -1,30 +1,34 @@
-//! File operation tools: read_file, write_file, str_replace, read_image.
+//! File operation tools: read_file, write_file, str_replace, apply_patch, read_image.
 
 use anyhow::Result;
-use tracing::debug;
+use tracing::{debug, warn};
 
+use crate::checkpoints;
+use crate::diff_match::{HunkMatch, MatchTier};
+use crate::file_fingerprints::Freshness;
+use crate::code_search::TreeSitterSearcher;
+use crate::patch::{self, PatchKind};
+use crate::session_diff;
+use crate::tokenizer::TokenCounter;
 use crate::ui_writer::UiWriter;
 use crate::utils::resolve_path_with_unicode_fallback;
-use crate::utils::apply_unified_diff_to_string;
+use crate::utils::apply_unified_diff_with_report;
 use crate::ToolCall;
 
 use super::executor::ToolContext;
 
-/// Bytes per token heuristic (conservative estimate for code/text mix)
-const BYTES_PER_TOKEN: f32 = 3.5;
-
 /// Maximum percentage of context window a single file read can consume
 const MAX_FILE_READ_PERCENT: f32 = 0.20; // 20%
 
-/// Estimate token count from byte size
-fn estimate_tokens_from_bytes(bytes: usize) -> u32 {
-    ((bytes as f32 / BYTES_PER_TOKEN) * 1.1).ceil() as u32 // 10% safety buffer
-}
-
 /// Calculate the maximum bytes we should read based on context window state.
 /// Returns None if no limit needed, Some(max_bytes) if limiting required.
-fn calculate_read_limit(file_bytes: usize, total_tokens: u32, used_tokens: u32) -> Option<usize> {
-    let file_tokens = estimate_tokens_from_bytes(file_bytes);
+fn calculate_read_limit(
+    content: &str,
+    token_counter: &TokenCounter,
+    total_tokens: u32,
+    used_tokens: u32,
+) -> Option<usize> {
+    let file_tokens = token_counter.count(content);
     let max_tokens_for_file = (total_tokens as f32 * MAX_FILE_READ_PERCENT) as u32;
     
     // Tier 1: File is small enough (< 20% of context) - no limit
@@ -44,12 +48,27 @@ fn calculate_read_limit(file_bytes: usize, total_tokens: u32, used_tokens: u32)
         max_tokens_for_file
     };
     
-    // Convert tokens back to bytes
-    let max_bytes = (effective_max_tokens as f32 * BYTES_PER_TOKEN / 1.1) as usize;
+    // Convert tokens back to bytes at this file's own bytes-per-token ratio
+    let bytes_per_token = content.len() as f32 / file_tokens.max(1) as f32;
+    let max_bytes = (effective_max_tokens as f32 * bytes_per_token) as usize;
     
     Some(max_bytes)
 }
 
+/// Expand `~` and resolve a relative path against the agent's working directory, the
+/// same base the permission checks use. Without a working directory, paths are left
+/// relative to the process's current directory.
+fn resolve_tool_path<W: UiWriter>(ctx: &ToolContext<'_, W>, path: &str) -> String {
+    let expanded = shellexpand::tilde(path);
+    match ctx.working_dir {
+        Some(dir) if std::path::Path::new(expanded.as_ref()).is_relative() => std::path::Path::new(dir)
+            .join(expanded.as_ref())
+            .to_string_lossy()
+            .into_owned(),
+        _ => expanded.into_owned(),
+    }
+}
+
 /// Execute the `read_file` tool.
 pub async fn execute_read_file<W: UiWriter>(
     tool_call: &ToolCall,
@@ -62,10 +81,9 @@ pub async fn execute_read_file<W: UiWriter>(
         None => return Ok("❌ Missing file_path argument".to_string()),
     };
 
-    // Expand tilde (~) to home directory
-    let expanded_path = shellexpand::tilde(file_path);
+    let tool_path = resolve_tool_path(ctx, file_path);
     // Try to resolve with Unicode space fallback (macOS uses U+202F in screenshot names)
-    let resolved_path = resolve_path_with_unicode_fallback(expanded_path.as_ref());
+    let resolved_path = resolve_path_with_unicode_fallback(&tool_path);
     let path_str = resolved_path.as_ref();
 
     // Extract optional start and end positions
@@ -80,21 +98,55 @@ pub async fn execute_read_file<W: UiWriter>(
         .and_then(|v| v.as_u64())
         .map(|n| n as usize);
 
+    // Line-based reads: a line range, or the definition of a symbol
+    let start_line = tool_call
+        .args
+        .get("start_line")
+        .and_then(|v| v.as_u64())
+        .map(|n| n as usize);
+    let end_line = tool_call
+        .args
+        .get("end_line")
+        .and_then(|v| v.as_u64())
+        .map(|n| n as usize);
+    let symbol = tool_call.args.get("symbol").and_then(|v| v.as_str());
+    let by_lines = start_line.is_some() || end_line.is_some();
+    if (by_lines || symbol.is_some()) && (start_char.is_some() || end_char.is_some()) {
+        return Ok(
+            "❌ Use either start/end (characters) or start_line/end_line/symbol (lines), not both"
+                .to_string(),
+        );
+    }
+    if by_lines && symbol.is_some() {
+        return Ok("❌ Use either symbol or start_line/end_line, not both".to_string());
+    }
+
     debug!(
-        "Reading file: {}, start={:?}, end={:?}",
-        path_str, start_char, end_char
+        "Reading file: {}, start={:?}, end={:?}, start_line={:?}, end_line={:?}, symbol={:?}",
+        path_str, start_char, end_char, start_line, end_line, symbol
     );
 
     match std::fs::read_to_string(path_str) {
         Ok(content) => {
+            remember_content(ctx, path_str, &content);
             let total_file_len = content.len();
             
             // Calculate token-aware limit for the content we're about to read
             let read_limit = calculate_read_limit(
-                total_file_len,
+                &content,
+                &ctx.token_counter,
                 ctx.context_total_tokens,
                 ctx.context_used_tokens,
             );
+            let context_pct = (ctx.context_used_tokens as f32 / ctx.context_total_tokens as f32 * 100.0) as u32;
+
+            if let Some(symbol) = symbol {
+                let path = std::path::Path::new(path_str);
+                return Ok(read_symbol(path, &content, symbol, read_limit, context_pct));
+            }
+            if by_lines {
+                return Ok(read_line_range(&content, start_line, end_line, read_limit, context_pct));
+            }
 
             // Validate user-specified range
             let user_start = start_char.unwrap_or(0);
@@ -158,7 +210,6 @@ pub async fn execute_read_file<W: UiWriter>(
             // Format output based on whether truncation occurred
             if was_truncated {
                 // Token-aware truncation header
-                let context_pct = (ctx.context_used_tokens as f32 / ctx.context_total_tokens as f32 * 100.0) as u32;
                 Ok(format!(
                     "{}\n🔍 {} lines read (truncated, chars {}-{} of {}, context {}%)",
                     partial_content, line_count, start_boundary, end_boundary, total_file_len, context_pct
@@ -182,6 +233,150 @@ pub async fn execute_read_file<W: UiWriter>(
     }
 }
 
+/// Number lines like `cat -n`, starting at `first_line`. Stops before the text
+/// exceeds `max_bytes`, but always shows at least one line. Returns the text and
+/// how many lines it shows.
+fn number_lines(lines: &[&str], first_line: usize, max_bytes: Option<usize>) -> (String, usize) {
+    let width = (first_line + lines.len().saturating_sub(1)).to_string().len();
+    let mut numbered = String::new();
+    let mut shown = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let entry = format!("{:>width$}\t{}", first_line + i, line, width = width);
+        if shown > 0 && max_bytes.is_some_and(|max| numbered.len() + entry.len() + 1 > max) {
+            break;
+        }
+        if shown > 0 {
+            numbered.push('\n');
+        }
+        numbered.push_str(&entry);
+        shown += 1;
+    }
+    (numbered, shown)
+}
+
+/// `read_file` with `start_line`/`end_line` (1-indexed, inclusive). Ranges too big
+/// for the context budget are paged: the footer says where to continue.
+fn read_line_range(
+    content: &str,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+    read_limit: Option<usize>,
+    context_pct: u32,
+) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let total_lines = lines.len();
+    let start = start_line.unwrap_or(1);
+    if start == 0 {
+        return "❌ start_line is 1-indexed; use start_line 1 for the first line".to_string();
+    }
+    if total_lines == 0 {
+        return "🔍 0 lines read (file is empty)".to_string();
+    }
+    if start > total_lines {
+        return format!(
+            "❌ start_line {} is past the end of the file ({} lines)",
+            start, total_lines
+        );
+    }
+    let requested_end = end_line.unwrap_or(total_lines);
+    if requested_end < start {
+        return format!(
+            "❌ start_line {} is greater than end_line {}",
+            start, requested_end
+        );
+    }
+    let end = requested_end.min(total_lines);
+
+    let (numbered, shown) = number_lines(&lines[start - 1..end], start, read_limit);
+    let last = start + shown - 1;
+    if last < end {
+        format!(
+            "{}\n🔍 {} lines read (truncated, lines {}-{} of {}, context {}%; continue with start_line {})",
+            numbered, shown, start, last, total_lines, context_pct, last + 1
+        )
+    } else if requested_end > total_lines {
+        format!(
+            "{}\n🔍 {} lines read (lines {}-{}, end_line clamped from {} to file length {})",
+            numbered, shown, start, end, requested_end, total_lines
+        )
+    } else {
+        format!(
+            "{}\n🔍 {} lines read (lines {}-{} of {})",
+            numbered, shown, start, end, total_lines
+        )
+    }
+}
+
+/// `read_file` with `symbol`: every definition of the symbol found by tree-sitter,
+/// with line numbers. Definitions past the context budget are listed by line range.
+fn read_symbol(
+    path: &std::path::Path,
+    content: &str,
+    symbol: &str,
+    read_limit: Option<usize>,
+    context_pct: u32,
+) -> String {
+    let ranges = match TreeSitterSearcher::new()
+        .and_then(|mut searcher| searcher.find_symbol(path, content, symbol))
+    {
+        Ok(ranges) => ranges,
+        Err(e) => {
+            return format!(
+                "❌ Cannot look up '{}' in {}: {}",
+                symbol,
+                path.display(),
+                e
+            )
+        }
+    };
+    if ranges.is_empty() {
+        return format!(
+            "❌ No definition of '{}' found in {}",
+            symbol,
+            path.display()
+        );
+    }
+
+    let lines: Vec<&str> = content.lines().collect();
+    let mut sections = Vec::new();
+    let mut remaining = read_limit;
+    let mut lines_read = 0;
+    for range in &ranges {
+        let end = range.end_line.min(lines.len());
+        let header = format!("📍 {} `{}` (lines {}-{})", range.kind, symbol, range.start_line, end);
+        if remaining == Some(0) {
+            sections.push(format!("{} not shown (context {}%)", header, context_pct));
+            continue;
+        }
+        let (numbered, shown) = number_lines(&lines[range.start_line - 1..end], range.start_line, remaining);
+        remaining = remaining.map(|max| max.saturating_sub(numbered.len()));
+        lines_read += shown;
+        let last = range.start_line + shown - 1;
+        if last < end {
+            remaining = Some(0);
+            sections.push(format!(
+                "{}\n{}\n… truncated (context {}%; continue with start_line {})",
+                header, numbered, context_pct, last + 1
+            ));
+        } else {
+            sections.push(format!("{}\n{}", header, numbered));
+        }
+    }
+
+    let definitions = if ranges.len() == 1 {
+        "1 definition".to_string()
+    } else {
+        format!("{} definitions", ranges.len())
+    };
+    format!(
+        "{}\n🔍 {} lines read ({} of `{}`)",
+        sections.join("\n\n"),
+        lines_read,
+        definitions,
+        symbol
+    )
+}
+
 /// Execute the `read_image` tool.
 pub async fn execute_read_image<W: UiWriter>(
     tool_call: &ToolCall,
@@ -206,17 +401,30 @@ pub async fn execute_read_image<W: UiWriter>(
         return Ok("❌ Missing or empty file_paths argument".to_string());
     }
 
+    // Images would be dropped (or rejected) by a text-only model, so don't pretend to read them
+    if !ctx.supports_vision {
+        return Ok(
+            "❌ The current model cannot see images, so read_image is unavailable. \
+             Switch to a vision-capable model to inspect images."
+                .to_string(),
+        );
+    }
+
     let mut results: Vec<String> = Vec::new();
     let mut success_count = 0;
 
+    // Inline images are for terminals; writers that want full output are read by programs
+    let show_inline = !ctx.ui_writer.wants_full_output();
+
     // Print └─ and newline before images to break out of tool output box
-    println!("└─\n");
+    if show_inline {
+        println!("└─\n");
+    }
 
     for path_str in &paths {
-        // Expand tilde (~) to home directory
-        let expanded_path = shellexpand::tilde(path_str);
+        let tool_path = resolve_tool_path(ctx, path_str);
         // Try to resolve with Unicode space fallback (macOS uses U+202F in screenshot names)
-        let resolved_path = resolve_path_with_unicode_fallback(expanded_path.as_ref());
+        let resolved_path = resolve_path_with_unicode_fallback(&tool_path);
         let path = std::path::Path::new(resolved_path.as_ref());
 
         // Check file exists
@@ -266,7 +474,9 @@ pub async fn execute_read_image<W: UiWriter>(
                 };
 
                 // Output imgcat inline image to terminal (height constrained)
-                print_imgcat(&bytes, path_str, &dim_str, media_type, &size_str, 5);
+                if show_inline {
+                    print_imgcat(&bytes, path_str, &dim_str, media_type, &size_str, 5);
+                }
 
                 // Store the image to be attached to the next user message
                 use base64::Engine;
@@ -283,7 +493,9 @@ pub async fn execute_read_image<W: UiWriter>(
     }
 
     // Print ┌─ to resume tool output box
-    print!("┌─\n");
+    if show_inline {
+        print!("┌─\n");
+    }
 
     let summary = if success_count == paths.len() {
         format!("{} image(s) read.", success_count)
@@ -302,7 +514,7 @@ pub async fn execute_read_image<W: UiWriter>(
 /// Execute the `write_file` tool.
 pub async fn execute_write_file<W: UiWriter>(
     tool_call: &ToolCall,
-    _ctx: &ToolContext<'_, W>,
+    ctx: &ToolContext<'_, W>,
 ) -> Result<String> {
     debug!("Processing write_file tool call");
     debug!("Raw tool_call.args: {:?}", tool_call.args);
@@ -317,9 +529,8 @@ pub async fn execute_write_file<W: UiWriter>(
     );
 
     if let (Some(path), Some(content)) = (path_str, content_str) {
-        // Expand tilde (~) to home directory
-        let expanded_path = shellexpand::tilde(path);
-        let path = expanded_path.as_ref();
+        let tool_path = resolve_tool_path(ctx, path);
+        let path = tool_path.as_str();
 
         debug!("Writing to file: {}", path);
 
@@ -333,8 +544,18 @@ pub async fn execute_write_file<W: UiWriter>(
             }
         }
 
+        // Overwriting would silently discard edits the agent has never seen
+        if freshness(ctx, path) == Freshness::Changed {
+            return Ok(format!(
+                "❌ '{}' changed on disk since you last read it. Read it again before overwriting it.",
+                path
+            ));
+        }
+
+        checkpoint_before_edit(ctx, path);
         match std::fs::write(path, content) {
             Ok(()) => {
+                remember_content(ctx, path, content);
                 let line_count = content.lines().count();
                 let char_count = content.len();
                 let char_display = if char_count >= 1000 {
@@ -367,7 +588,7 @@ pub async fn execute_write_file<W: UiWriter>(
 /// Execute the `str_replace` tool.
 pub async fn execute_str_replace<W: UiWriter>(
     tool_call: &ToolCall,
-    _ctx: &ToolContext<'_, W>,
+    ctx: &ToolContext<'_, W>,
 ) -> Result<String> {
     debug!("Processing str_replace tool call");
 
@@ -377,10 +598,7 @@ pub async fn execute_str_replace<W: UiWriter>(
     };
 
     let file_path = match args_obj.get("file_path").and_then(|v| v.as_str()) {
-        Some(path) => {
-            let expanded_path = shellexpand::tilde(path);
-            expanded_path.into_owned()
-        }
+        Some(path) => resolve_tool_path(ctx, path),
         None => return Ok("❌ Missing or invalid file_path argument".to_string()),
     };
 
@@ -410,11 +628,35 @@ pub async fn execute_str_replace<W: UiWriter>(
         Err(e) => return Ok(format!("❌ Failed to read file '{}': {}", file_path, e)),
     };
 
+    let changed_on_disk = freshness(ctx, &file_path) == Freshness::Changed;
+
     // Apply unified diff to content
-    let result = match apply_unified_diff_to_string(&file_content, diff, start_char, end_char) {
-        Ok(r) => r,
+    let applied = match apply_unified_diff_with_report(&file_content, diff, start_char, end_char) {
+        Ok(applied) => applied,
         Err(e) => return Ok(format!("❌ {}", e)),
     };
+    let result = applied.content;
+
+    // A loose match in a file edited behind the agent's back may land on those edits
+    if changed_on_disk && applied.hunks.iter().any(|hunk| hunk.tier != MatchTier::Exact) {
+        return Ok(format!(
+            "❌ '{}' changed on disk since you last read it, and the diff only matched loosely. \
+             Read it again and make the diff match the current content.",
+            file_path
+        ));
+    }
+
+    // Tell the model about hunks that only matched loosely, so it can check them
+    let mut notes: Vec<String> = applied
+        .hunks
+        .iter()
+        .enumerate()
+        .filter(|(_, hunk)| hunk.tier != MatchTier::Exact)
+        .map(|(idx, hunk)| describe_loose_match(idx, hunk))
+        .collect();
+    if changed_on_disk {
+        notes.push("the file changed on disk since you last read it; read it again to review".to_string());
+    }
 
     // Count insertions and deletions from the diff
     let mut insertions = 0;
@@ -428,16 +670,205 @@ pub async fn execute_str_replace<W: UiWriter>(
     }
 
     // Write the result back to the file
+    checkpoint_before_edit(ctx, &file_path);
     match std::fs::write(&file_path, &result) {
-        Ok(()) => Ok(format!("✅ \x1b[32m+{} insertions\x1b[0m | \x1b[31m-{} deletions\x1b[0m", insertions, deletions)),
+        Ok(()) => {
+            remember_content(ctx, &file_path, &result);
+            let summary = format!(
+                "✅ \x1b[32m+{} insertions\x1b[0m | \x1b[31m-{} deletions\x1b[0m",
+                insertions, deletions
+            );
+            if notes.is_empty() {
+                Ok(summary)
+            } else {
+                Ok(format!("{}\n⚠️ {}", summary, notes.join("; ")))
+            }
+        }
         Err(e) => Ok(format!("❌ Failed to write to file '{}': {}", file_path, e)),
     }
 }
 
+/// Execute the `apply_patch` tool.
+pub async fn execute_apply_patch<W: UiWriter>(
+    tool_call: &ToolCall,
+    ctx: &ToolContext<'_, W>,
+) -> Result<String> {
+    debug!("Processing apply_patch tool call");
+
+    let patch_text = match tool_call.args.get("patch").and_then(|v| v.as_str()) {
+        Some(patch_text) => patch_text,
+        None => return Ok("❌ Missing patch argument".to_string()),
+    };
+
+    let files = match patch::parse_patch(patch_text) {
+        Ok(files) => files,
+        Err(e) => return Ok(format!("❌ {}", e)),
+    };
+    // Every hunk is applied in memory before anything is written
+    let base = ctx.working_dir.map(std::path::Path::new);
+    let changes = match patch::plan_patch(&files, base) {
+        Ok(changes) => changes,
+        Err(e) => return Ok(format!("❌ Patch not applied, no files were changed. {:#}", e)),
+    };
+
+    // Same stale-edit rules as str_replace; deleting a changed file would lose its edits
+    let mut notes = Vec::new();
+    for change in &changes {
+        let Some(old_path) = &change.old_path else {
+            continue;
+        };
+        if freshness(ctx, &old_path.to_string_lossy()) != Freshness::Changed {
+            continue;
+        }
+        let loose = change.hunks.iter().any(|hunk| hunk.tier != MatchTier::Exact);
+        if loose || change.kind() == PatchKind::Delete {
+            return Ok(format!(
+                "❌ Patch not applied, no files were changed. '{}' changed on disk since you last read it. \
+                 Read it again and make the patch match the current content.",
+                change.patch.display_path()
+            ));
+        }
+        notes.push(format!(
+            "{} changed on disk since you last read it; read it again to review",
+            change.patch.display_path()
+        ));
+    }
+    for change in &changes {
+        for (idx, hunk) in change.hunks.iter().enumerate() {
+            if hunk.tier != MatchTier::Exact {
+                notes.push(format!("{}: {}", change.patch.display_path(), describe_loose_match(idx, hunk)));
+            }
+        }
+    }
+
+    for change in &changes {
+        for path in change.old_path.iter().chain(change.new_path.iter()) {
+            checkpoint_before_edit(ctx, &path.to_string_lossy());
+        }
+    }
+    if let Err(e) = patch::write_changes(&changes) {
+        return Ok(format!("❌ Patch failed while writing {:#}", e));
+    }
+
+    let mut insertions = 0;
+    let mut deletions = 0;
+    let mut summary = Vec::with_capacity(changes.len());
+    for change in &changes {
+        if let (Some(path), Some(content)) = (&change.new_path, &change.content) {
+            remember_content(ctx, &path.to_string_lossy(), content);
+        }
+        let (added, removed) = change.patch.line_counts();
+        insertions += added;
+        deletions += removed;
+        summary.push(format!(
+            "{} {} (+{} -{})",
+            change.kind().letter(),
+            change.patch.display_path(),
+            added,
+            removed
+        ));
+    }
+
+    let mut output = format!(
+        "✅ patched {} file{}: \x1b[32m+{} insertions\x1b[0m | \x1b[31m-{} deletions\x1b[0m\n{}",
+        changes.len(),
+        if changes.len() == 1 { "" } else { "s" },
+        insertions,
+        deletions,
+        summary.join("\n")
+    );
+    if !notes.is_empty() {
+        output.push_str(&format!("\n⚠️ {}", notes.join("; ")));
+    }
+    Ok(output)
+}
+
 // Helper functions
 
+/// Note for a hunk that didn't match exactly, so the model can check it
+fn describe_loose_match(idx: usize, hunk: &HunkMatch) -> String {
+    match hunk.tier {
+        MatchTier::Fuzzy => format!(
+            "hunk {} matched {} at line {} ({:.0}% similar)",
+            idx + 1,
+            hunk.tier.describe(),
+            hunk.line,
+            hunk.similarity * 100.0
+        ),
+        _ => format!("hunk {} matched {} at line {}", idx + 1, hunk.tier.describe(), hunk.line),
+    }
+}
+
+/// Save a file's current content so the turn's edits can be undone and the session
+/// diff has a baseline (needs a session)
+fn checkpoint_before_edit<W: UiWriter>(ctx: &ToolContext<'_, W>, path: &str) {
+    if let Some(session_id) = ctx.session_id {
+        let path = std::path::Path::new(path);
+        if let Err(e) = checkpoints::snapshot_file(session_id, ctx.turn, path) {
+            warn!("Failed to checkpoint {}: {}", path.display(), e);
+        }
+        if let Err(e) = session_diff::record_original(session_id, path) {
+            warn!("Failed to record session baseline for {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Remember `content` as the agent's view of `path`
+fn remember_content<W: UiWriter>(ctx: &ToolContext<'_, W>, path: &str, content: &str) {
+    if let Ok(mut fingerprints) = ctx.file_fingerprints.lock() {
+        fingerprints.record(std::path::Path::new(path), content.as_bytes());
+    }
+}
+
+/// Paths a write tool call writes whose fingerprints still match the files on disk,
+/// resolved the way the tools resolve them
+pub(crate) fn fresh_written_paths<W: UiWriter>(tool_call: &ToolCall, ctx: &ToolContext<'_, W>) -> Vec<String> {
+    let args = &tool_call.args;
+    let paths: Vec<String> = match tool_call.tool.as_str() {
+        "write_file" => extract_path_and_content(args).0.map(str::to_string).into_iter().collect(),
+        "str_replace" => args.get("file_path").and_then(|v| v.as_str()).map(str::to_string).into_iter().collect(),
+        "apply_patch" => args
+            .get("patch")
+            .and_then(|v| v.as_str())
+            .and_then(|text| patch::parse_patch(text).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|file| file.new_path)
+            .collect(),
+        _ => Vec::new(),
+    };
+    paths
+        .iter()
+        .map(|path| resolve_tool_path(ctx, path))
+        .filter(|path| freshness(ctx, path) == Freshness::Fresh)
+        .collect()
+}
+
+/// Take what is on disk now as the agent's view of `paths`, after something acting for
+/// the agent (a `post_tool` formatter) rewrote them
+pub(crate) fn refresh_fingerprints<W: UiWriter>(ctx: &ToolContext<'_, W>, paths: &[String]) {
+    for path in paths {
+        match std::fs::read(path) {
+            Ok(content) => {
+                if let Ok(mut fingerprints) = ctx.file_fingerprints.lock() {
+                    fingerprints.record(std::path::Path::new(path), &content);
+                }
+            }
+            Err(e) => debug!("Not refreshing the fingerprint of {}: {}", path, e),
+        }
+    }
+}
+
+/// Whether `path` changed since the agent last read or wrote it
+fn freshness<W: UiWriter>(ctx: &ToolContext<'_, W>, path: &str) -> Freshness {
+    match ctx.file_fingerprints.lock() {
+        Ok(fingerprints) => fingerprints.check(std::path::Path::new(path)),
+        Err(_) => Freshness::Untracked,
+    }
+}
+
 /// Known argument key pairs for path and content.
-const PATH_CONTENT_KEYS: &[(&str, &str)] = &[
+pub(crate) const PATH_CONTENT_KEYS: &[(&str, &str)] = &[
     ("file_path", "content"),  // Standard format
     ("path", "content"),       // Anthropic-style
     ("filename", "text"),      // Alternative naming
@@ -562,3 +993,88 @@ pub fn print_imgcat(
     // Blank line before next image (no │ prefix)
     println!();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines of prose, `lines` long
+    fn prose_file(lines: usize) -> String {
+        "The quick brown fox jumps over the lazy dog, then naps in the sun.\n".repeat(lines)
+    }
+
+    /// Bytes of `content` that hold `tokens` tokens at its own density
+    fn bytes_for_tokens(content: &str, counter: &TokenCounter, tokens: u32) -> usize {
+        (tokens as f32 * content.len() as f32 / counter.count(content) as f32) as usize
+    }
+
+    fn assert_about(actual: usize, expected: usize) {
+        let diff = actual.abs_diff(expected);
+        assert!(diff <= expected / 100, "expected ~{} bytes, got {}", expected, actual);
+    }
+
+    #[test]
+    fn test_tier1_small_file_no_limit() {
+        // ~1k tokens, well under 20% of a 100k context
+        let limit = calculate_read_limit(&prose_file(70), &TokenCounter::default(), 100_000, 0);
+        assert!(limit.is_none(), "Small file should have no limit");
+    }
+
+    #[test]
+    fn test_tier2_large_file_capped_at_20_percent() {
+        // ~100k tokens, capped at 20% of the context = 20k tokens
+        let counter = TokenCounter::default();
+        let content = prose_file(7_000);
+        let max_bytes = calculate_read_limit(&content, &counter, 100_000, 0).expect("Large file should be limited");
+        assert_about(max_bytes, bytes_for_tokens(&content, &counter, 20_000));
+    }
+
+    #[test]
+    fn test_tier3_context_nearly_full() {
+        // Context 70% full: 30k tokens available, so capped at half of that = 15k tokens
+        let counter = TokenCounter::default();
+        let content = prose_file(7_000);
+        let max_bytes = calculate_read_limit(&content, &counter, 100_000, 70_000).unwrap();
+        assert_about(max_bytes, bytes_for_tokens(&content, &counter, 15_000));
+    }
+
+    #[test]
+    fn test_tier3_context_very_full() {
+        // Context 90% full: 10k tokens available, so capped at 5k tokens
+        let counter = TokenCounter::default();
+        let content = prose_file(7_000);
+        let max_bytes = calculate_read_limit(&content, &counter, 100_000, 90_000).unwrap();
+        assert_about(max_bytes, bytes_for_tokens(&content, &counter, 5_000));
+    }
+
+    #[test]
+    fn test_boundary_exactly_20_percent() {
+        let counter = TokenCounter::default();
+        let content = prose_file(1_000);
+        let file_tokens = counter.count(&content);
+
+        // Just under 20% of the context: not limited
+        assert!(calculate_read_limit(&content, &counter, file_tokens * 5 + 5, 0).is_none());
+
+        // Exactly 20%: limited to the whole file
+        let max_bytes = calculate_read_limit(&content, &counter, file_tokens * 5, 0).unwrap();
+        assert_about(max_bytes, content.len());
+    }
+
+    #[test]
+    fn test_limit_follows_token_density() {
+        // Symbol-dense code packs more tokens into each byte than prose
+        let prose = "the quick brown fox jumps over the lazy dog ".repeat(20_000);
+        let code = "a[i]+=b[j]*(c-1);\n".repeat(50_000);
+        let counter = TokenCounter::default();
+        let prose_limit = calculate_read_limit(&prose, &counter, 100_000, 0).unwrap();
+        let code_limit = calculate_read_limit(&code, &counter, 100_000, 0).unwrap();
+        assert!(code_limit < prose_limit, "code {} vs prose {}", code_limit, prose_limit);
+
+        // A calibrated counter that has learned the model counts twice as many tokens
+        let mut calibrated = TokenCounter::default();
+        calibrated.set_calibration(2.0);
+        let calibrated_limit = calculate_read_limit(&prose, &calibrated, 100_000, 0).unwrap();
+        assert!(calibrated_limit < prose_limit * 6 / 10);
+    }
+}
@@ -10,12 +10,14 @@
 //! - `research` - Web research via scout agent
 //! - `memory` - Project memory (read_memory, remember)
 //! - `acd` - Aggressive Context Dehydration (rehydrate)
+//! - `plugin` - Custom tools backed by executables from `[plugin_tools]`
 
 pub mod executor;
 pub mod acd;
 pub mod file_ops;
 pub mod memory;
 pub mod misc;
+pub mod plugin;
 pub mod research;
 pub mod shell;
 pub mod todo;
@@ -0,0 +1,102 @@
+//! Custom tools backed by executables declared in `[plugin_tools]`.
+//!
+//! The tool call's arguments are written to the program's stdin as a JSON object and
+//! stdin is closed; whatever it prints to stdout is the tool result. A non-zero exit
+//! status makes the call fail, with stderr as the explanation.
+
+use anyhow::Result;
+use g3_config::PluginToolConfig;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::debug;
+
+use crate::ui_writer::UiWriter;
+use crate::ToolCall;
+
+use super::executor::ToolContext;
+use super::shell::cap_output;
+
+/// Execute a plugin tool.
+pub async fn execute_plugin_tool<W: UiWriter>(
+    tool_call: &ToolCall,
+    plugin: &PluginToolConfig,
+    ctx: &ToolContext<'_, W>,
+) -> Result<String> {
+    let name = &tool_call.tool;
+    let program = shellexpand::tilde(&plugin.command).to_string();
+    debug!("Running plugin tool '{}': {} {:?}", name, program, plugin.args);
+
+    let mut command = tokio::process::Command::new(&program);
+    command
+        .args(&plugin.args)
+        .env("G3_TOOL_NAME", name)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(dir) = ctx.working_dir {
+        command.current_dir(dir);
+    }
+    if let Some(session_id) = ctx.session_id {
+        command.env("G3_SESSION_ID", session_id);
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return Ok(format!("❌ Failed to run plugin tool '{}' ({}): {}", name, program, e)),
+    };
+
+    let input = match &tool_call.args {
+        serde_json::Value::Null => "{}".to_string(),
+        args => serde_json::to_string(args)?,
+    };
+    // Write the arguments while collecting output, both under the timeout: a plugin
+    // that doesn't read its stdin can't stall the write past the deadline
+    let stdin = child.stdin.take();
+    let write_input = async {
+        if let Some(mut stdin) = stdin {
+            // A plugin that doesn't read its arguments may exit before we finish writing
+            if let Err(e) = stdin.write_all(input.as_bytes()).await {
+                debug!("Plugin tool '{}' did not read its input: {}", name, e);
+            }
+        }
+    };
+    // Dropping the child on timeout kills it
+    let output = async { tokio::join!(write_input, child.wait_with_output()).1 };
+    let output = if plugin.timeout_seconds > 0 {
+        match tokio::time::timeout(Duration::from_secs(plugin.timeout_seconds), output).await {
+            Ok(output) => output?,
+            Err(_) => {
+                return Ok(format!(
+                    "⏱️ Plugin tool '{}' timed out after {}s and was killed.",
+                    name, plugin.timeout_seconds
+                ))
+            }
+        }
+    } else {
+        output.await?
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let max_lines = ctx.config.agent.shell_output_max_lines;
+    if output.status.success() {
+        let stdout = stdout.trim();
+        if stdout.is_empty() {
+            Ok(format!("✅ {} completed (no output)", name))
+        } else {
+            Ok(cap_output(stdout, max_lines, ctx.session_id))
+        }
+    } else {
+        let status = match output.status.code() {
+            Some(code) => format!("exit code {}", code),
+            None => "a signal".to_string(),
+        };
+        let details = [stderr.trim(), stdout.trim()]
+            .into_iter()
+            .find(|text| !text.is_empty())
+            .map(|text| format!("\n{}", cap_output(text, max_lines, ctx.session_id)))
+            .unwrap_or_default();
+        Ok(format!("❌ Plugin tool '{}' failed with {}{}", name, status, details))
+    }
+}
@@ -1,8 +1,13 @@
 //! Shell command execution tools.
 
 use anyhow::Result;
-use tracing::debug;
+use g3_execution::{ExecutionResult, Sandbox, ShellSession};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
 
+use crate::paths::{get_shell_output_dir, get_workspace_dir};
+use crate::session_diff;
 use crate::ui_writer::UiWriter;
 use crate::utils::resolve_paths_in_shell_command;
 use crate::utils::shell_escape_command;
@@ -10,6 +15,9 @@ use crate::ToolCall;
 
 use super::executor::ToolContext;
 
+/// Characters of `shell` output returned to the model, split between the start and the end
+const MAX_OUTPUT_CHARS: usize = 40_000;
+
 /// Execute the `shell` tool.
 pub async fn execute_shell<W: UiWriter>(tool_call: &ToolCall, ctx: &ToolContext<'_, W>) -> Result<String> {
     debug!("Processing shell tool call");
@@ -28,7 +36,12 @@ pub async fn execute_shell<W: UiWriter>(tool_call: &ToolCall, ctx: &ToolContext<
     debug!("Resolved command: {}", resolved_command);
     let escaped_command = shell_escape_command(&resolved_command);
 
-    let executor = g3_execution::CodeExecutor::new();
+    let timeout_secs = tool_call
+        .args
+        .get("timeout_secs")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(ctx.config.agent.shell_timeout_seconds);
+    let timeout = (timeout_secs > 0).then(|| Duration::from_secs(timeout_secs));
 
     struct ToolOutputReceiver<'a, W: UiWriter> {
         ui_writer: &'a W,
@@ -44,6 +57,34 @@ pub async fn execute_shell<W: UiWriter>(tool_call: &ToolCall, ctx: &ToolContext<
         ui_writer: ctx.ui_writer,
     };
 
+    let reset = tool_call
+        .args
+        .get("reset_session")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if reset {
+        debug!("Resetting shell session");
+        ctx.shell_session.lock().await.take();
+    }
+    let persistent = tool_call
+        .args
+        .get("persistent")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(ctx.config.agent.persistent_shell);
+    if persistent {
+        return execute_in_session(&resolved_command, &escaped_command, &receiver, timeout, timeout_secs, reset, ctx).await;
+    }
+
+    let cwd = ctx
+        .working_dir
+        .map(PathBuf::from)
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    record_shell_targets(&resolved_command, &cwd, ctx);
+
+    let executor = g3_execution::CodeExecutor::new()
+        .with_sandbox(command_sandbox(ctx))
+        .with_timeout(timeout);
+
     debug!(
         "ABOUT TO CALL execute_bash_streaming_in_dir: escaped_command='{}', working_dir={:?}",
         escaped_command, ctx.working_dir
@@ -53,29 +94,220 @@ pub async fn execute_shell<W: UiWriter>(tool_call: &ToolCall, ctx: &ToolContext<
         .execute_bash_streaming_in_dir(&escaped_command, &receiver, ctx.working_dir)
         .await
     {
-        Ok(result) => {
-            if result.success {
-                Ok(if result.stdout.is_empty() {
-                    "⚡️ ran successfully".to_string()
-                } else {
-                    result.stdout.trim().to_string()
+        Ok(result) => Ok(format_result(&result, timeout_secs, ctx)),
+        Err(e) => Ok(format!("❌ Execution error: {}", e)),
+    }
+}
+
+/// Run a `shell` command in the agent session's long-lived bash, starting (or
+/// restarting) it as needed, and report the shell's directory and environment changes.
+async fn execute_in_session<W: UiWriter, R: g3_execution::OutputReceiver>(
+    raw_command: &str,
+    command: &str,
+    receiver: &R,
+    timeout: Option<Duration>,
+    timeout_secs: u64,
+    reset: bool,
+    ctx: &ToolContext<'_, W>,
+) -> Result<String> {
+    let mut session = ctx.shell_session.lock().await;
+
+    // A session that timed out, was interrupted or exited is replaced, keeping its directory
+    let mut notice = None;
+    let ready = session.as_mut().map(|shell| shell.is_ready());
+    let restart_dir = match (session.as_ref(), ready) {
+        (Some(shell), Some(false)) => {
+            notice = Some(
+                "⚠️ The previous shell session ended, so this ran in a fresh one: earlier `export`s and activated environments are gone.",
+            );
+            Some(shell.cwd().to_path_buf())
+        }
+        (Some(_), _) => None,
+        (None, _) => Some(
+            ctx.working_dir
+                .map(PathBuf::from)
+                .unwrap_or_else(|| std::env::current_dir().unwrap_or_default()),
+        ),
+    };
+    if let Some(dir) = restart_dir {
+        *session = None;
+        match ShellSession::start(Some(&dir), command_sandbox(ctx).as_ref()).await {
+            Ok(shell) => *session = Some(shell),
+            Err(e) => return Ok(format!("❌ Failed to start shell session: {}", e)),
+        }
+    }
+    let shell = session.as_mut().expect("shell session was just started");
+    record_shell_targets(raw_command, shell.cwd(), ctx);
+
+    let mut output = match shell.run(command, receiver, timeout).await {
+        Ok(result) => format_result(&result, timeout_secs, ctx),
+        Err(e) => format!("❌ Execution error: {}", e),
+    };
+    if let Some(notice) = notice {
+        output = format!("{}\n\n{}", notice, output);
+    }
+    if reset {
+        output = format!("🔄 Shell session reset.\n\n{}", output);
+    }
+
+    if !shell.is_ready() {
+        output.push_str("\n\nThe shell session ended with this command; the next persistent command starts a fresh one.");
+    } else {
+        output.push_str(&format!("\n\n📂 cwd: {}", shell.cwd().display()));
+        let changes = shell.env_changes();
+        if !changes.is_empty() {
+            let changes: Vec<String> = changes
+                .into_iter()
+                .map(|(name, value)| match value {
+                    Some(value) => format!("{}={}", name, truncate_value(value)),
+                    None => format!("-{}", name),
                 })
-            } else {
-                // Build error message with available information
-                let stderr = result.stderr.trim();
-                let stdout = result.stdout.trim();
-                if !stderr.is_empty() {
-                    Ok(format!("❌ {}", stderr))
-                } else if !stdout.is_empty() {
-                    // Sometimes error info is in stdout
-                    Ok(format!("❌ Exit code {}: {}", result.exit_code, stdout))
-                } else {
-                    Ok(format!("❌ Command failed with exit code {}", result.exit_code))
-                }
+                .collect();
+            output.push_str(&format!("\n🌱 env changes: {}", changes.join(", ")));
+        }
+    }
+    Ok(output)
+}
+
+/// Record the session baseline of files the command visibly writes, for the session diff
+fn record_shell_targets<W: UiWriter>(command: &str, cwd: &Path, ctx: &ToolContext<'_, W>) {
+    let Some(session_id) = ctx.session_id else {
+        return;
+    };
+    for path in session_diff::shell_write_targets(command, cwd) {
+        if let Err(e) = session_diff::record_original(session_id, &path) {
+            debug!("Failed to record session baseline for {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Shorten long environment values (PATH and friends) for the session summary
+fn truncate_value(value: &str) -> String {
+    const MAX_CHARS: usize = 60;
+    if value.chars().count() <= MAX_CHARS {
+        value.to_string()
+    } else {
+        format!("{}...", value.chars().take(MAX_CHARS).collect::<String>())
+    }
+}
+
+/// Turn a command's result into the `shell` tool's output
+fn format_result<W: UiWriter>(result: &ExecutionResult, timeout_secs: u64, ctx: &ToolContext<'_, W>) -> String {
+    let max_lines = ctx.config.agent.shell_output_max_lines;
+    let cap = |output: &str| cap_output(output, max_lines, ctx.session_id);
+    if result.timed_out {
+        let output = [result.stdout.trim(), result.stderr.trim()]
+            .iter()
+            .filter(|part| !part.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut message = format!(
+            "⏱️ Command timed out after {}s and was killed. If it needs longer, re-run it with a larger timeout_secs; for servers or watchers use background_process.",
+            timeout_secs
+        );
+        if !output.is_empty() {
+            message.push_str("\n\nOutput before the timeout:\n");
+            message.push_str(&cap(&output));
+        }
+        message
+    } else if result.success {
+        if result.stdout.is_empty() {
+            "⚡️ ran successfully".to_string()
+        } else {
+            cap(result.stdout.trim())
+        }
+    } else {
+        // Build error message with available information
+        let stderr = result.stderr.trim();
+        let stdout = result.stdout.trim();
+        if !stderr.is_empty() {
+            format!("❌ {}", cap(stderr))
+        } else if !stdout.is_empty() {
+            // Sometimes error info is in stdout
+            format!("❌ Exit code {}: {}", result.exit_code, cap(stdout))
+        } else {
+            format!("❌ Command failed with exit code {}", result.exit_code)
+        }
+    }
+}
+
+/// Keep the start and end of long output (within `max_lines` lines and
+/// `MAX_OUTPUT_CHARS` characters) and save the full output to the session directory,
+/// returning a pointer to it in place of the middle.
+pub(super) fn cap_output(output: &str, max_lines: usize, session_id: Option<&str>) -> String {
+    let head = take_head(output, max_lines / 2, MAX_OUTPUT_CHARS / 2);
+    let tail = take_tail(output, max_lines - max_lines / 2, MAX_OUTPUT_CHARS / 2);
+    if head.len() + tail.len() >= output.len() {
+        return output.to_string();
+    }
+
+    let omitted = &output[head.len()..output.len() - tail.len()];
+    let saved = match save_full_output(output, session_id) {
+        Ok(path) => format!("full output saved to {}", path.display()),
+        Err(e) => {
+            warn!("Failed to save shell output: {}", e);
+            "full output could not be saved".to_string()
+        }
+    };
+    format!(
+        "{}\n... [{} lines omitted of {}; {}] ...\n{}",
+        head.trim_end_matches('\n'),
+        omitted.lines().count(),
+        output.lines().count(),
+        saved,
+        tail.trim_start_matches('\n')
+    )
+}
+
+/// The longest prefix of `text` within `max_lines` lines and `max_chars` bytes
+fn take_head(text: &str, max_lines: usize, max_chars: usize) -> &str {
+    let mut lines = 0;
+    for (i, c) in text.char_indices() {
+        if i + c.len_utf8() > max_chars {
+            return &text[..i];
+        }
+        if c == '\n' {
+            lines += 1;
+            if lines >= max_lines {
+                return &text[..i + 1];
+            }
+        }
+    }
+    text
+}
+
+/// The longest suffix of `text` within `max_lines` lines and `max_chars` bytes
+fn take_tail(text: &str, max_lines: usize, max_chars: usize) -> &str {
+    let mut lines = 0;
+    for (i, c) in text.char_indices().rev() {
+        if text.len() - i > max_chars {
+            return &text[i + c.len_utf8()..];
+        }
+        if c == '\n' {
+            lines += 1;
+            if lines >= max_lines {
+                return &text[i..];
             }
         }
-        Err(e) => Ok(format!("❌ Execution error: {}", e)),
     }
+    text
+}
+
+/// Save command output under .g3/sessions/<id>/shell_output/ (or ~/tmp without a session)
+fn save_full_output(output: &str, session_id: Option<&str>) -> std::io::Result<PathBuf> {
+    let dir = match session_id {
+        Some(id) => get_shell_output_dir(id),
+        None => PathBuf::from(shellexpand::tilde("~/tmp").as_ref()),
+    };
+    std::fs::create_dir_all(&dir)?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = dir.join(format!("shell_{}.txt", timestamp));
+    std::fs::write(&path, output)?;
+    Ok(path)
 }
 
 /// Execute the `background_process` tool.
@@ -104,7 +336,11 @@ pub async fn execute_background_process<W: UiWriter>(
         .or_else(|| ctx.working_dir.map(std::path::PathBuf::from))
         .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
 
-    match ctx.background_process_manager.start(name, command, &work_dir) {
+    let sandbox = command_sandbox(ctx);
+    match ctx
+        .background_process_manager
+        .start_with_sandbox(name, command, &work_dir, sandbox.as_ref())
+    {
         Ok(info) => Ok(format!(
             "✅ Background process '{}' started\n\n\
             **PID:** {}\n\
@@ -127,3 +363,23 @@ pub async fn execute_background_process<W: UiWriter>(
         Err(e) => Ok(format!("❌ Failed to start background process: {}", e)),
     }
 }
+
+/// The sandbox for shell commands, if `[sandbox]` is enabled.
+/// The workspace (or the agent's working directory) stays writable.
+fn command_sandbox<W: UiWriter>(ctx: &ToolContext<'_, W>) -> Option<Sandbox> {
+    let config = &ctx.config.sandbox;
+    if !config.enabled {
+        return None;
+    }
+    let workspace = ctx
+        .working_dir
+        .map(PathBuf::from)
+        .unwrap_or_else(get_workspace_dir);
+    let sandbox = config
+        .writable_paths
+        .iter()
+        .fold(Sandbox::new(workspace), |sandbox, path| {
+            sandbox.with_writable_path(shellexpand::tilde(path).as_ref())
+        });
+    Some(sandbox.with_network(config.network))
+}
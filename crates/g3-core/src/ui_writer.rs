@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 /// Interface for UI output operations
 /// This trait abstracts all UI operations to allow different implementations
 /// (console, TUI, web, etc.) without coupling the core logic to specific output methods.
@@ -67,6 +69,27 @@ pub trait UiWriter: Send + Sync {
     /// Notify that an SSE event was received (including pings)
     fn notify_sse_received(&self);
 
+    /// Notify that a tool call has finished, with its full result.
+    /// Called for every tool, including ones that print their own output.
+    /// Default implementation does nothing.
+    fn notify_tool_result(&self, _tool_name: &str, _result: &str, _duration: Duration) {}
+
+    /// Notify that a response is complete, with the data behind the timing footer.
+    /// Called whether or not the footer is shown.
+    /// Default implementation does nothing.
+    fn notify_turn_timing(
+        &self,
+        _elapsed: Duration,
+        _time_to_first_token: Duration,
+        _turn_tokens: Option<u32>,
+        _context_percentage: f32,
+    ) {
+    }
+
+    /// Notify that the context window was compacted.
+    /// Default implementation does nothing.
+    fn notify_compaction(&self, _chars_saved: usize, _context_percentage: f32) {}
+
     /// Print a hint that a tool call is being streamed (show indicator immediately)
     /// This is called when the provider starts receiving a tool call but args are still streaming
     fn print_tool_streaming_hint(&self, tool_name: &str);
@@ -9,6 +9,8 @@
 use anyhow::Result;
 use tracing::debug;
 
+use crate::diff_match::{locate_hunk, HunkMatch, MatchError};
+
 /// Truncate a string to approximately max_len characters, ending at a word boundary.
 ///
 /// This function attempts to break at a space character for cleaner display.
@@ -185,6 +187,27 @@ pub fn apply_unified_diff_to_string(
     start_char: Option<usize>,
     end_char: Option<usize>,
 ) -> Result<String> {
+    apply_unified_diff_with_report(file_content, diff, start_char, end_char)
+        .map(|applied| applied.content)
+}
+
+/// A diff applied by [`apply_unified_diff_with_report`]
+#[derive(Debug, Clone)]
+pub struct DiffApplication {
+    pub content: String,
+    /// How each hunk matched, in order; lines are in the whole file
+    pub hunks: Vec<HunkMatch>,
+}
+
+/// Apply a unified diff like [`apply_unified_diff_to_string`], reporting how each
+/// hunk was matched (see [`crate::diff_match`]). Fails if a hunk can't be found or
+/// matches in more than one place, with the candidate lines or the closest match.
+pub fn apply_unified_diff_with_report(
+    file_content: &str,
+    diff: &str,
+    start_char: Option<usize>,
+    end_char: Option<usize>,
+) -> Result<DiffApplication> {
     // Parse full unified diff into hunks and apply sequentially.
     let hunks = parse_unified_diff_hunks(diff);
     if hunks.is_empty() {
@@ -240,8 +263,19 @@ pub fn apply_unified_diff_to_string(
         .unwrap_or(content_norm.len());
 
     let mut region_content = content_norm[start_boundary..end_boundary].to_string();
+    // Lines before the region, to report file line numbers
+    let region_line_offset = content_norm[..start_boundary].matches('\n').count();
+    let range_note = if start_char.is_some() || end_char.is_some() {
+        format!(
+            " (within character range {}:{})",
+            start_boundary, end_boundary
+        )
+    } else {
+        String::new()
+    };
 
     // Apply hunks in order
+    let mut applied = Vec::with_capacity(hunks.len());
     for (idx, (old_block, new_block)) in hunks.iter().enumerate() {
         debug!(
             "Applying hunk {}: old_len={}, new_len={}",
@@ -250,35 +284,67 @@ pub fn apply_unified_diff_to_string(
             new_block.len()
         );
 
-        if let Some(pos) = region_content.find(old_block) {
-            let endpos = pos + old_block.len();
-            region_content.replace_range(pos..endpos, new_block);
-        } else {
-            // Not found; provide helpful diagnostics with a short preview
-            // Use character-based slicing to avoid splitting multi-byte UTF-8 characters
-            let max_chars = 200;
-            let preview_len = old_block.chars().count().min(max_chars);
-            let mut old_preview: String = old_block.chars().take(preview_len).collect();
-            let was_truncated = old_block.chars().count() > max_chars;
-            if was_truncated {
-                old_preview.push_str("...");
+        match locate_hunk(&region_content, old_block, new_block) {
+            Ok(mut found) => {
+                debug!("Hunk {} matched {} at line {}", idx + 1, found.tier.describe(), found.line);
+                region_content.replace_range(found.range.clone(), &found.replacement);
+                found.line += region_line_offset;
+                applied.push(found);
+            }
+            Err(MatchError::Ambiguous { tier, lines }) => {
+                let lines: Vec<String> = lines
+                    .iter()
+                    .map(|line| (line + region_line_offset).to_string())
+                    .collect();
+                anyhow::bail!(
+                    "Hunk {} matches {} places {}{} (lines {}). Add more context lines, or pass start/end to narrow the search.",
+                    idx + 1,
+                    lines.len(),
+                    tier.describe(),
+                    range_note,
+                    lines.join(", ")
+                );
             }
+            Err(MatchError::NotFound { closest }) => {
+                // Not found; provide helpful diagnostics with a short preview
+                // Use character-based slicing to avoid splitting multi-byte UTF-8 characters
+                let max_chars = 200;
+                let preview_len = old_block.chars().count().min(max_chars);
+                let mut old_preview: String = old_block.chars().take(preview_len).collect();
+                let was_truncated = old_block.chars().count() > max_chars;
+                if was_truncated {
+                    old_preview.push_str("...");
+                }
 
-            let range_note = if start_char.is_some() || end_char.is_some() {
-                format!(
-                    " (within character range {}:{})",
-                    start_boundary, end_boundary
-                )
-            } else {
-                String::new()
-            };
-
-            anyhow::bail!(
-                "Pattern not found in file{}\nHunk {} failed. Searched for:\n{}",
-                range_note,
-                idx + 1,
-                old_preview
-            );
+                // Show the nearest text so the next attempt can copy it exactly
+                let closest_note = match closest {
+                    Some(closest) => {
+                        let first = closest.line + region_line_offset;
+                        let excerpt: Vec<String> = closest
+                            .lines
+                            .iter()
+                            .enumerate()
+                            .map(|(i, line)| format!("{}\t{}", first + i, line))
+                            .collect();
+                        format!(
+                            "\nClosest match (lines {}-{}, {:.0}% similar):\n{}",
+                            first,
+                            first + closest.lines.len() - 1,
+                            closest.similarity * 100.0,
+                            excerpt.join("\n")
+                        )
+                    }
+                    None => String::new(),
+                };
+
+                anyhow::bail!(
+                    "Pattern not found in file{}\nHunk {} failed. Searched for:\n{}{}",
+                    range_note,
+                    idx + 1,
+                    old_preview,
+                    closest_note
+                );
+            }
         }
     }
 
@@ -287,7 +353,10 @@ pub fn apply_unified_diff_to_string(
     result.push_str(&content_norm[..start_boundary]);
     result.push_str(&region_content);
     result.push_str(&content_norm[end_boundary..]);
-    Ok(result)
+    Ok(DiffApplication {
+        content: result,
+        hunks: applied,
+    })
 }
 
 /// Parse a unified diff into a list of hunks as (old_block, new_block).
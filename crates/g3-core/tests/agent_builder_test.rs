@@ -0,0 +1,192 @@
+//! AgentBuilder tests
+//!
+//! Builds agents the way an embedding program would: a pre-built provider registry,
+//! an explicit tool set, a working directory and a custom system prompt.
+
+use anyhow::Result;
+use g3_config::Config;
+use g3_core::{get_agent_system_prompt, AgentBuilder, NullUiWriter, ProviderRegistry, ToolCall};
+use g3_providers::{CompletionRequest, CompletionResponse, CompletionStream, LLMProvider};
+use serde_json::json;
+use tempfile::TempDir;
+
+/// Provider that is never called; the tests only exercise construction and tools
+struct OfflineProvider;
+
+#[async_trait::async_trait]
+impl LLMProvider for OfflineProvider {
+    async fn complete(&self, _request: CompletionRequest) -> Result<CompletionResponse> {
+        anyhow::bail!("offline")
+    }
+
+    async fn stream(&self, _request: CompletionRequest) -> Result<CompletionStream> {
+        anyhow::bail!("offline")
+    }
+
+    fn name(&self) -> &str {
+        "offline.default"
+    }
+
+    fn model(&self) -> &str {
+        "offline-model"
+    }
+
+    fn has_native_tool_calling(&self) -> bool {
+        true
+    }
+
+    fn max_tokens(&self) -> u32 {
+        4096
+    }
+
+    fn temperature(&self) -> f32 {
+        0.0
+    }
+}
+
+fn offline_providers() -> ProviderRegistry {
+    let mut providers = ProviderRegistry::new();
+    providers.register(OfflineProvider);
+    providers
+}
+
+fn shell_call(command: &str) -> ToolCall {
+    ToolCall {
+        tool: "shell".to_string(),
+        args: json!({ "command": command }),
+    }
+}
+
+#[tokio::test]
+async fn test_builder_uses_given_providers_tools_and_working_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut agent = AgentBuilder::new(Config::default(), NullUiWriter)
+        .providers(offline_providers())
+        .tools(["shell", "read_file"])
+        .working_dir(temp_dir.path())
+        .project_context("Project notes")
+        .build()
+        .await
+        .unwrap();
+
+    let (provider, model) = agent.get_provider_info().unwrap();
+    assert_eq!(provider, "offline.default");
+    assert_eq!(model, "offline-model");
+
+    let mut offered: Vec<String> = agent.tool_definitions().into_iter().map(|t| t.name).collect();
+    offered.sort();
+    assert_eq!(offered, vec!["read_file", "shell"]);
+
+    // Tools run in the builder's working directory
+    let result = agent.execute_tool(&shell_call("pwd -P")).await.unwrap();
+    let expected = temp_dir.path().canonicalize().unwrap();
+    assert!(result.contains(expected.to_str().unwrap()), "{}", result);
+
+    // Tools outside the set are refused
+    let result = agent
+        .execute_tool(&ToolCall {
+            tool: "todo_read".to_string(),
+            args: json!({}),
+        })
+        .await
+        .unwrap();
+    assert_eq!(result, "❓ Unknown tool: todo_read");
+
+    let history = &agent.get_context_window().conversation_history;
+    assert_eq!(history[1].content, "Project notes");
+}
+
+#[tokio::test]
+async fn test_file_tools_resolve_relative_paths_in_working_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut agent = AgentBuilder::new(Config::default(), NullUiWriter)
+        .providers(offline_providers())
+        .tools(["read_file", "write_file", "str_replace"])
+        .working_dir(temp_dir.path())
+        .build()
+        .await
+        .unwrap();
+    let call = |tool: &str, args: serde_json::Value| ToolCall {
+        tool: tool.to_string(),
+        args,
+    };
+
+    let result = agent
+        .execute_tool(&call("write_file", json!({ "file_path": "notes/plan.txt", "content": "step one\n" })))
+        .await
+        .unwrap();
+    assert!(result.starts_with("wrote"), "{}", result);
+    let written = temp_dir.path().join("notes/plan.txt");
+    assert_eq!(std::fs::read_to_string(&written).unwrap(), "step one\n");
+    assert!(!std::path::Path::new("notes/plan.txt").exists());
+
+    let result = agent
+        .execute_tool(&call("read_file", json!({ "file_path": "notes/plan.txt" })))
+        .await
+        .unwrap();
+    assert!(result.contains("step one"), "{}", result);
+
+    let result = agent
+        .execute_tool(&call(
+            "str_replace",
+            json!({ "file_path": "notes/plan.txt", "diff": "@@ -1 +1 @@\n-step one\n+step two\n" }),
+        ))
+        .await
+        .unwrap();
+    assert!(result.starts_with("✅"), "{}", result);
+    assert_eq!(std::fs::read_to_string(&written).unwrap(), "step two\n");
+}
+
+#[tokio::test]
+async fn test_builder_offers_all_tools_by_default() {
+    let agent = AgentBuilder::new(Config::default(), NullUiWriter)
+        .providers(offline_providers())
+        .build()
+        .await
+        .unwrap();
+    let offered: Vec<String> = agent.tool_definitions().into_iter().map(|t| t.name).collect();
+    assert!(offered.contains(&"shell".to_string()));
+    assert!(offered.contains(&"todo_write".to_string()));
+}
+
+#[tokio::test]
+async fn test_builder_rejects_invalid_settings() {
+    let error = AgentBuilder::new(Config::default(), NullUiWriter)
+        .providers(offline_providers())
+        .tools(["shell", "no_such_tool"])
+        .build()
+        .await
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("no_such_tool"), "{}", error);
+
+    let error = AgentBuilder::new(Config::default(), NullUiWriter)
+        .providers(offline_providers())
+        .system_prompt("You are a helpful assistant.")
+        .build()
+        .await
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("get_agent_system_prompt"), "{}", error);
+
+    let error = AgentBuilder::new(Config::default(), NullUiWriter)
+        .providers(offline_providers())
+        .working_dir("/definitely/not/a/real/dir")
+        .build()
+        .await
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("does not exist"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_builder_accepts_agent_system_prompt() {
+    let prompt = get_agent_system_prompt("You are Reviewer, a code review agent.", true);
+    let agent = AgentBuilder::new(Config::default(), NullUiWriter)
+        .providers(offline_providers())
+        .system_prompt(prompt.clone())
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(agent.get_context_window().conversation_history[0].content, prompt);
+}
@@ -0,0 +1,145 @@
+//! apply_patch tests
+//!
+//! Applies multi-file patches through a real agent in a scratch directory and checks
+//! that they land completely or not at all.
+
+mod common;
+
+use common::{create_agent, tool_call};
+use g3_core::ToolCall;
+use serial_test::serial;
+use std::fs;
+use tempfile::TempDir;
+
+fn apply_patch(patch: &str) -> ToolCall {
+    tool_call("apply_patch", serde_json::json!({ "patch": patch }))
+}
+
+fn read(temp_dir: &TempDir, path: &str) -> String {
+    fs::read_to_string(temp_dir.path().join(path)).unwrap()
+}
+
+const REFACTOR: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,3 @@
+ pub mod config;
+-pub mod helpers;
++pub mod util;
+ pub mod server;
+diff --git a/src/helpers.rs b/src/util.rs
+similarity index 80%
+rename from src/helpers.rs
+rename to src/util.rs
+--- a/src/helpers.rs
++++ b/src/util.rs
+@@ -1,3 +1,3 @@
+-// Helpers
++// Utilities
+ pub fn double(x: i32) -> i32 {
+     x * 2
+diff --git a/src/legacy.rs b/src/legacy.rs
+deleted file mode 100644
+--- a/src/legacy.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-pub fn old() {}
+diff --git a/src/server/mod.rs b/src/server/mod.rs
+new file mode 100644
+--- /dev/null
++++ b/src/server/mod.rs
+@@ -0,0 +1,2 @@
++use crate::util::double;
++pub fn port() -> i32 { double(4040) }
+";
+
+fn write_project(temp_dir: &TempDir) {
+    fs::create_dir_all(temp_dir.path().join("src")).unwrap();
+    fs::write(temp_dir.path().join("src/lib.rs"), "pub mod config;\npub mod helpers;\npub mod server;\n").unwrap();
+    fs::write(
+        temp_dir.path().join("src/helpers.rs"),
+        "// Helpers\npub fn double(x: i32) -> i32 {\n    x * 2\n}\n",
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("src/legacy.rs"), "pub fn old() {}\n").unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_apply_patch_creates_modifies_deletes_and_renames() {
+    let temp_dir = TempDir::new().unwrap();
+    write_project(&temp_dir);
+    let mut agent = create_agent(&temp_dir, "apply patch test").await;
+
+    let result = agent.execute_tool(&apply_patch(REFACTOR)).await.unwrap();
+    assert!(result.starts_with("✅ patched 4 files"), "{}", result);
+    assert!(result.contains("M src/lib.rs (+1 -1)"), "{}", result);
+    assert!(result.contains("R src/helpers.rs -> src/util.rs (+1 -1)"), "{}", result);
+    assert!(result.contains("D src/legacy.rs (+0 -1)"), "{}", result);
+    assert!(result.contains("A src/server/mod.rs (+2 -0)"), "{}", result);
+
+    assert_eq!(read(&temp_dir, "src/lib.rs"), "pub mod config;\npub mod util;\npub mod server;\n");
+    assert_eq!(
+        read(&temp_dir, "src/util.rs"),
+        "// Utilities\npub fn double(x: i32) -> i32 {\n    x * 2\n}\n"
+    );
+    assert!(!temp_dir.path().join("src/helpers.rs").exists());
+    assert!(!temp_dir.path().join("src/legacy.rs").exists());
+    assert_eq!(
+        read(&temp_dir, "src/server/mod.rs"),
+        "use crate::util::double;\npub fn port() -> i32 { double(4040) }\n"
+    );
+
+    // The whole patch is one turn's edit, so undo puts everything back
+    agent.undo_last_turn().unwrap().unwrap();
+    assert_eq!(read(&temp_dir, "src/lib.rs"), "pub mod config;\npub mod helpers;\npub mod server;\n");
+    assert_eq!(read(&temp_dir, "src/legacy.rs"), "pub fn old() {}\n");
+    assert!(temp_dir.path().join("src/helpers.rs").exists());
+    assert!(!temp_dir.path().join("src/util.rs").exists());
+    assert!(!temp_dir.path().join("src/server/mod.rs").exists());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_apply_patch_changes_nothing_when_a_hunk_fails() {
+    let temp_dir = TempDir::new().unwrap();
+    write_project(&temp_dir);
+    let mut agent = create_agent(&temp_dir, "apply patch test").await;
+
+    // The last section doesn't match src/legacy.rs
+    let patch = REFACTOR.replace("-pub fn old() {}", "-pub fn older() -> u32 { 42 }");
+    let result = agent.execute_tool(&apply_patch(&patch)).await.unwrap();
+    assert!(result.starts_with("❌ Patch not applied, no files were changed."), "{}", result);
+    assert!(result.contains("src/legacy.rs"), "{}", result);
+
+    assert_eq!(read(&temp_dir, "src/lib.rs"), "pub mod config;\npub mod helpers;\npub mod server;\n");
+    assert!(temp_dir.path().join("src/helpers.rs").exists());
+    assert!(!temp_dir.path().join("src/util.rs").exists());
+    assert!(!temp_dir.path().join("src/server").exists());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_apply_patch_refuses_conflicting_sections() {
+    let temp_dir = TempDir::new().unwrap();
+    write_project(&temp_dir);
+    let mut agent = create_agent(&temp_dir, "apply patch test").await;
+
+    // Creating a file that exists
+    let patch = "--- /dev/null\n+++ b/src/lib.rs\n@@ -0,0 +1 @@\n+pub mod fresh;\n";
+    let result = agent.execute_tool(&apply_patch(patch)).await.unwrap();
+    assert!(result.contains("already exists"), "{}", result);
+
+    // Touching the same file twice
+    let section = "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-pub mod config;\n+pub mod settings;\n";
+    let result = agent
+        .execute_tool(&apply_patch(&format!("{}{}", section, section)))
+        .await
+        .unwrap();
+    assert!(result.contains("appears more than once"), "{}", result);
+    assert_eq!(read(&temp_dir, "src/lib.rs"), "pub mod config;\npub mod helpers;\npub mod server;\n");
+
+    let result = agent.execute_tool(&apply_patch("just some text")).await.unwrap();
+    assert!(result.starts_with("❌ No file changes found"), "{}", result);
+}
@@ -0,0 +1,111 @@
+//! Budget tests
+//!
+//! Drives an agent against a replay cassette whose responses carry usage, and checks
+//! that a run stops with `BudgetExceeded` once a limit is reached, leaving a session
+//! continuation behind so the work can be resumed.
+
+use g3_config::{Config, ReplayConfig, ReplayMode};
+use g3_core::ui_writer::NullUiWriter;
+use g3_core::{is_budget_exceeded, Agent, BudgetExceeded, BudgetKind};
+use g3_providers::replay::{Cassette, Interaction};
+use g3_providers::{make_final_chunk, make_text_chunk, Usage};
+use serial_test::serial;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+fn budget_config(temp_dir: &TempDir) -> Config {
+    let cassette_path = temp_dir.path().join("cassette.json");
+    let cassette = Cassette {
+        version: 1,
+        provider: "anthropic.default".to_string(),
+        model: "test-model".to_string(),
+        native_tool_calling: true,
+        supports_cache_control: false,
+        supports_vision: false,
+        max_tokens: 4096,
+        temperature: 0.1,
+        interactions: (0..3)
+            .map(|i| Interaction {
+                request_hash: "unmatched".to_string(),
+                chunks: Some(vec![
+                    make_text_chunk(format!("Reply {}", i)),
+                    make_final_chunk(
+                        vec![],
+                        Some(Usage {
+                            prompt_tokens: 1000,
+                            completion_tokens: 100,
+                            total_tokens: 1100,
+                            ..Default::default()
+                        }),
+                    ),
+                ]),
+                response: None,
+            })
+            .collect(),
+    };
+    cassette.save(&cassette_path).unwrap();
+
+    let mut config = Config::default();
+    config.providers.replay.insert(
+        "default".to_string(),
+        ReplayConfig {
+            cassette: cassette_path.to_string_lossy().to_string(),
+            mode: ReplayMode::Replay,
+            record_from: None,
+            strict: false,
+            context_length: Some(200_000),
+        },
+    );
+    config.providers.default_provider = "replay.default".into();
+    config
+}
+
+#[tokio::test]
+#[serial]
+async fn test_token_budget_stops_run_and_saves_continuation() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let mut config = budget_config(&temp_dir);
+    config.agent.max_tokens_total = Some(2000);
+
+    let mut agent = Agent::new_with_readme_and_quiet(config, NullUiWriter, None, false)
+        .await
+        .unwrap();
+
+    // 1100 tokens used, then 2200: the third request is never made
+    agent.execute_task("First", None, false).await.unwrap();
+    agent.execute_task("Second", None, false).await.unwrap();
+    let err = agent.execute_task("Third", None, false).await.unwrap_err();
+
+    assert!(is_budget_exceeded(&err), "unexpected error: {}", err);
+    let exceeded = err.downcast_ref::<BudgetExceeded>().unwrap();
+    assert_eq!(exceeded.kind, BudgetKind::Tokens);
+    assert_eq!(err.to_string(), "Budget exhausted: 2200 of 2000 token budget");
+    assert_eq!(agent.get_cost_ledger().entries[0].requests, 2);
+
+    let continuation = g3_core::load_continuation()
+        .unwrap()
+        .expect("continuation should be saved on a budget stop");
+    assert_eq!(continuation.session_id, agent.get_session_id().unwrap());
+    assert_eq!(continuation.summary.as_deref(), Some(err.to_string().as_str()));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_wall_time_budget_is_shared_from_run_start() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let mut config = budget_config(&temp_dir);
+    config.agent.max_wall_time_seconds = Some(60);
+
+    let mut agent = Agent::new_with_readme_and_quiet(config, NullUiWriter, None, true)
+        .await
+        .unwrap();
+    agent.execute_task("First", None, false).await.unwrap();
+
+    // A coach joining a run that started over a minute ago has no time left
+    agent.set_budget_started(Instant::now() - Duration::from_secs(61));
+    let err = agent.execute_task("Second", None, false).await.unwrap_err();
+    assert!(is_budget_exceeded(&err), "unexpected error: {}", err);
+    assert_eq!(agent.get_cost_ledger().entries[0].requests, 1);
+}
@@ -0,0 +1,80 @@
+//! File checkpoint tests
+//!
+//! Edits files through `write_file` and `str_replace` on a real agent, then undoes
+//! them through the agent's checkpoint API.
+
+mod common;
+
+use common::{create_agent, tool_call};
+use g3_core::checkpoints::{self, Turn};
+use g3_core::ToolCall;
+use serial_test::serial;
+use std::fs;
+use tempfile::TempDir;
+
+fn write_file(path: &str, content: &str) -> ToolCall {
+    tool_call("write_file", serde_json::json!({ "file_path": path, "content": content }))
+}
+
+#[tokio::test]
+#[serial]
+async fn test_undo_restores_edited_and_removes_created_files() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("existing.txt"), "line 1\nline 2\n").unwrap();
+    let mut agent = create_agent(&temp_dir, "checkpoint test").await;
+
+    let str_replace = tool_call(
+        "str_replace",
+        serde_json::json!({
+            "file_path": "existing.txt",
+            "diff": "@@ -1,2 +1,2 @@\n line 1\n-line 2\n+line two\n"
+        }),
+    );
+    let result = agent.execute_tool(&str_replace).await.unwrap();
+    assert!(result.contains("+1 insertions"), "{}", result);
+    // A second edit of the same file in the same turn keeps the first snapshot
+    agent.execute_tool(&write_file("existing.txt", "rewritten\n")).await.unwrap();
+    agent.execute_tool(&write_file("new/created.txt", "new\n")).await.unwrap();
+
+    let checkpoints = agent.checkpoints().unwrap();
+    assert_eq!(checkpoints.len(), 1);
+    let files: Vec<_> = checkpoints[0].files.iter().map(|f| f.saved_as.is_some()).collect();
+    assert_eq!(files, vec![true, false]);
+
+    let restored = agent.undo_last_turn().unwrap().unwrap();
+    assert_eq!(restored.files.len(), 2);
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("existing.txt")).unwrap(),
+        "line 1\nline 2\n"
+    );
+    assert!(!temp_dir.path().join("new/created.txt").exists());
+
+    assert!(agent.checkpoints().unwrap().is_empty());
+    assert!(agent.undo_last_turn().unwrap().is_none());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_undo_to_turn_rolls_back_later_turns_too() {
+    let temp_dir = TempDir::new().unwrap();
+    let file = temp_dir.path().join("notes.txt");
+    let mut agent = create_agent(&temp_dir, "checkpoint test").await;
+    let session_id = agent.get_session_id().unwrap().to_string();
+
+    // Three turns each rewriting the same file
+    for (number, content) in [(1, "one"), (2, "two"), (3, "three")] {
+        let turn = Turn {
+            number,
+            prompt: format!("turn {}", number),
+        };
+        checkpoints::snapshot_file(&session_id, &turn, &file).unwrap();
+        fs::write(&file, content).unwrap();
+    }
+    let turns: Vec<u32> = agent.checkpoints().unwrap().iter().map(|c| c.turn).collect();
+    assert_eq!(turns, vec![1, 2, 3]);
+
+    let restored = agent.undo_to_turn(2).unwrap();
+    assert_eq!(restored.iter().map(|c| c.turn).collect::<Vec<_>>(), vec![3, 2]);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "one");
+    assert_eq!(checkpoints::latest_turn(&session_id), 1);
+}
@@ -726,3 +726,50 @@ async fn test_racket_contracts() {
     assert!(names.contains(&"safe-divide"), "Should find 'safe-divide', found: {:?}", names);
     assert!(names.contains(&"non-negative-add"), "Should find 'non-negative-add', found: {:?}", names);
 }
+
+#[test]
+fn test_find_symbol_ranges() {
+    use g3_core::code_search::{SymbolRange, TreeSitterSearcher};
+    use std::path::Path;
+
+    let rust = r#"use std::fmt;
+
+/// A point
+#[derive(Debug)]
+pub struct Point {
+    x: i32,
+}
+
+impl Point {
+    pub fn new(x: i32) -> Self {
+        Self { x }
+    }
+}
+
+fn new() {}
+"#;
+    let mut searcher = TreeSitterSearcher::new().unwrap();
+    let path = Path::new("point.rs");
+
+    let found = searcher.find_symbol(path, rust, "Point").unwrap();
+    assert_eq!(
+        found,
+        vec![SymbolRange { kind: "struct_item".to_string(), start_line: 3, end_line: 7 }]
+    );
+
+    // Both `new`s, or just the method
+    let found = searcher.find_symbol(path, rust, "new").unwrap();
+    let lines: Vec<(usize, usize)> = found.iter().map(|r| (r.start_line, r.end_line)).collect();
+    assert_eq!(lines, vec![(10, 12), (15, 15)]);
+    let found = searcher.find_symbol(path, rust, "Point::new").unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!((found[0].start_line, found[0].end_line), (10, 12));
+
+    let python = "class Greeter:\n    @staticmethod\n    def hello():\n        return 'hi'\n";
+    let found = searcher.find_symbol(Path::new("greet.py"), python, "Greeter.hello").unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!((found[0].start_line, found[0].end_line), (2, 4));
+
+    assert!(searcher.find_symbol(path, rust, "missing").unwrap().is_empty());
+    assert!(searcher.find_symbol(Path::new("notes.txt"), "text", "x").is_err());
+}
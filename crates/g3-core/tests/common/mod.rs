@@ -0,0 +1,21 @@
+//! Fixtures shared by the tests that drive file-changing tools through a real agent
+
+use g3_config::Config;
+use g3_core::ui_writer::NullUiWriter;
+use g3_core::{Agent, ToolCall};
+use tempfile::TempDir;
+
+/// An agent working in `temp_dir`, with a session id so it records per-session state
+pub async fn create_agent(temp_dir: &TempDir, description: &str) -> Agent<NullUiWriter> {
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let mut agent = Agent::new(Config::default(), NullUiWriter).await.unwrap();
+    agent.init_session_id_for_test(description);
+    agent
+}
+
+pub fn tool_call(tool: &str, args: serde_json::Value) -> ToolCall {
+    ToolCall {
+        tool: tool.to_string(),
+        args,
+    }
+}
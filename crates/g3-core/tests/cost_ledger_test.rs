@@ -0,0 +1,140 @@
+//! Cost ledger tests
+//!
+//! Drives an agent against a replay cassette whose responses carry usage, and checks
+//! that the usage is booked in the session ledger, priced from `[pricing]`, shown in
+//! the stats output and persisted next to session.json.
+
+use g3_config::{Config, ModelPricing, ReplayConfig, ReplayMode};
+use g3_core::ui_writer::NullUiWriter;
+use g3_core::{Agent, AgentRole, CostLedger};
+use g3_providers::replay::{Cassette, Interaction};
+use g3_providers::{make_final_chunk, make_text_chunk, Usage};
+use serial_test::serial;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn write_cassette(path: &Path, model: &str, replies: usize) {
+    let cassette = Cassette {
+        version: 1,
+        provider: "anthropic.default".to_string(),
+        model: model.to_string(),
+        native_tool_calling: true,
+        supports_cache_control: true,
+        supports_vision: false,
+        max_tokens: 4096,
+        temperature: 0.1,
+        interactions: (0..replies)
+            .map(|i| Interaction {
+                request_hash: "unmatched".to_string(),
+                chunks: Some(vec![
+                    make_text_chunk(format!("Reply {}", i)),
+                    make_final_chunk(
+                        vec![],
+                        Some(Usage {
+                            prompt_tokens: 100_000,
+                            completion_tokens: 10_000,
+                            total_tokens: 110_000,
+                            cache_creation_tokens: 0,
+                            cache_read_tokens: 500_000,
+                        }),
+                    ),
+                ]),
+                response: None,
+            })
+            .collect(),
+    };
+    cassette.save(path).unwrap();
+}
+
+fn ledger_config(temp_dir: &TempDir, model: &str) -> Config {
+    let cassette = temp_dir.path().join("cassette.json");
+    write_cassette(&cassette, model, 2);
+
+    let mut config = Config::default();
+    config.providers.replay.insert(
+        "default".to_string(),
+        ReplayConfig {
+            cassette: cassette.to_string_lossy().to_string(),
+            mode: ReplayMode::Replay,
+            record_from: None,
+            strict: false,
+            context_length: Some(200_000),
+        },
+    );
+    config.providers.default_provider = "replay.default".into();
+    config
+}
+
+#[tokio::test]
+#[serial]
+async fn test_usage_is_booked_priced_and_persisted() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let mut config = ledger_config(&temp_dir, "test-model");
+    config.pricing.insert(
+        "test-model".to_string(),
+        ModelPricing {
+            input: 3.0,
+            output: 15.0,
+            cache_read: 0.3,
+            cache_write: 3.75,
+        },
+    );
+
+    let mut agent = Agent::new_with_readme_and_quiet(config, NullUiWriter, None, false)
+        .await
+        .unwrap();
+    agent.execute_task("First", None, false).await.unwrap();
+    agent.execute_task("Second", None, false).await.unwrap();
+
+    let ledger = agent.get_cost_ledger();
+    assert_eq!(ledger.entries.len(), 1);
+    let entry = &ledger.entries[0];
+    assert_eq!(entry.provider, "replay.default");
+    assert_eq!(entry.model, "test-model");
+    assert_eq!(entry.role, AgentRole::Player);
+    assert_eq!(entry.requests, 2);
+    assert_eq!(entry.input_tokens, 200_000);
+    assert_eq!(entry.output_tokens, 20_000);
+    assert_eq!(entry.cache_read_tokens, 1_000_000);
+    // Per request: 0.30 input + 0.15 output + 0.15 cache read
+    assert!((ledger.total_cost() - 1.2).abs() < 1e-9);
+
+    let stats = agent.get_stats();
+    assert!(stats.contains("Spend:"));
+    assert!(stats.contains("replay.default test-model (player): $1.2000"));
+
+    let session_id = agent.get_session_id().unwrap().to_string();
+    let persisted = CostLedger::load(&g3_core::get_ledger_file(&session_id)).unwrap();
+    assert_eq!(persisted.entries, ledger.entries);
+    assert!(g3_core::get_session_file(&session_id).exists());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_shared_ledger_books_roles_separately() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    // A model with no known price is recorded, but not priced
+    let player_config = ledger_config(&temp_dir, "local-model");
+    let mut player =
+        Agent::new_with_readme_and_quiet(player_config.clone(), NullUiWriter, None, true)
+            .await
+            .unwrap();
+    let mut coach = Agent::new_with_readme_and_quiet(player_config, NullUiWriter, None, true)
+        .await
+        .unwrap();
+    coach.set_agent_role(AgentRole::Coach);
+    coach.set_cost_ledger(player.cost_ledger_handle());
+
+    player.execute_task("Implement", None, false).await.unwrap();
+    coach.execute_task("Review", None, false).await.unwrap();
+
+    let ledger = player.get_cost_ledger();
+    let roles: Vec<_> = ledger.entries.iter().map(|e| e.role).collect();
+    assert_eq!(roles, vec![AgentRole::Player, AgentRole::Coach]);
+    assert!(ledger.entries.iter().all(|e| e.cost_usd.is_none()));
+    assert!(ledger.has_unpriced_usage());
+    assert_eq!(ledger.total_cost(), 0.0);
+}
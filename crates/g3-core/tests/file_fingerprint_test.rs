@@ -0,0 +1,133 @@
+//! Tests for stale-edit protection: write_file and str_replace on files that changed
+//! on disk since the agent last read them
+
+use g3_core::session_continuation::SessionContinuation;
+use g3_core::ui_writer::NullUiWriter;
+use g3_core::{Agent, ToolCall};
+use serde_json::json;
+use std::fs;
+use tempfile::TempDir;
+
+async fn call(agent: &mut Agent<NullUiWriter>, tool: &str, args: serde_json::Value) -> String {
+    let tool_call = ToolCall {
+        tool: tool.to_string(),
+        args,
+    };
+    agent.execute_tool(&tool_call).await.unwrap()
+}
+
+async fn new_agent() -> Agent<NullUiWriter> {
+    Agent::new(g3_config::Config::default(), NullUiWriter).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_write_file_refuses_file_changed_since_read() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("notes.txt");
+    fs::write(&path, "first draft\n").unwrap();
+    let path = path.to_string_lossy().into_owned();
+    let mut agent = new_agent().await;
+
+    call(&mut agent, "read_file", json!({ "file_path": path })).await;
+    fs::write(&path, "first draft\nplus the user's edit\n").unwrap();
+
+    let result = call(&mut agent, "write_file", json!({ "file_path": path, "content": "rewrite\n" })).await;
+    assert!(result.starts_with("❌"), "{}", result);
+    assert!(result.contains("changed on disk since you last read it"), "{}", result);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "first draft\nplus the user's edit\n");
+
+    // Reading it again makes the write safe
+    call(&mut agent, "read_file", json!({ "file_path": path })).await;
+    let result = call(&mut agent, "write_file", json!({ "file_path": path, "content": "rewrite\n" })).await;
+    assert!(result.starts_with("wrote"), "{}", result);
+
+    // The agent's own write counts as seen
+    let result = call(&mut agent, "write_file", json!({ "file_path": path, "content": "again\n" })).await;
+    assert!(result.starts_with("wrote"), "{}", result);
+}
+
+#[tokio::test]
+async fn test_write_file_allows_files_never_read() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("existing.txt");
+    fs::write(&path, "old\n").unwrap();
+    let mut agent = new_agent().await;
+
+    let result = call(
+        &mut agent,
+        "write_file",
+        json!({ "file_path": path.to_string_lossy(), "content": "new\n" }),
+    )
+    .await;
+    assert!(result.starts_with("wrote"), "{}", result);
+}
+
+#[tokio::test]
+async fn test_str_replace_on_file_changed_since_read() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("lib.rs");
+    fs::write(&path, "fn a() {\n    1\n}\n").unwrap();
+    let path = path.to_string_lossy().into_owned();
+    let mut agent = new_agent().await;
+
+    call(&mut agent, "read_file", json!({ "file_path": path })).await;
+    fs::write(&path, "fn a() {\n    1\n}\n\nfn b() {\n    2\n}\n").unwrap();
+
+    // An exact match is applied, with a warning
+    let diff = "@@ -1,3 +1,3 @@\n fn a() {\n-    1\n+    10\n }\n";
+    let result = call(&mut agent, "str_replace", json!({ "file_path": path, "diff": diff })).await;
+    assert!(result.starts_with("✅"), "{}", result);
+    assert!(result.contains("⚠️ the file changed on disk since you last read it"), "{}", result);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "fn a() {\n    10\n}\n\nfn b() {\n    2\n}\n");
+
+    // A loose match in a changed file is refused
+    fs::write(&path, "fn a() {\n    10\n}\n\nfn b() {\n  2\n}\n").unwrap();
+    let diff = "@@ -5,3 +5,3 @@\n fn b() {\n-    2\n+    20\n }\n";
+    let result = call(&mut agent, "str_replace", json!({ "file_path": path, "diff": diff })).await;
+    assert!(result.starts_with("❌"), "{}", result);
+    assert!(result.contains("only matched loosely"), "{}", result);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "fn a() {\n    10\n}\n\nfn b() {\n  2\n}\n");
+}
+
+#[tokio::test]
+async fn test_fingerprints_survive_restore_from_continuation() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("config.toml");
+    fs::write(&path, "key = 1\n").unwrap();
+    let path = path.to_string_lossy().into_owned();
+
+    let mut agent = new_agent().await;
+    call(&mut agent, "read_file", json!({ "file_path": path })).await;
+    let fingerprints = agent.get_file_fingerprints();
+    assert_eq!(fingerprints.len(), 1);
+    // A SHA-256 of the content, so a later build compares against the same hash
+    assert_eq!(
+        fingerprints.get(std::path::Path::new(&path)).unwrap().hash,
+        "52c159c121e79d90d6fb6488a4129b94a334dfcec99451f231751594db0c5a82"
+    );
+
+    let mut continuation = SessionContinuation::new(
+        false,
+        None,
+        "fingerprint_test".to_string(),
+        None,
+        Some("Edited config.toml".to_string()),
+        temp_dir.path().join("missing_session.json").to_string_lossy().into_owned(),
+        10.0,
+        None,
+        temp_dir.path().to_string_lossy().into_owned(),
+    );
+    continuation.file_fingerprints = fingerprints;
+
+    // Fingerprints round-trip through the saved continuation
+    let saved = serde_json::to_string(&continuation).unwrap();
+    let loaded: SessionContinuation = serde_json::from_str(&saved).unwrap();
+    assert_eq!(loaded.file_fingerprints, continuation.file_fingerprints);
+
+    let mut resumed = new_agent().await;
+    resumed.restore_from_continuation(&loaded).unwrap();
+    fs::write(&path, "key = 2 # edited while g3 was closed\n").unwrap();
+
+    let result = call(&mut resumed, "write_file", json!({ "file_path": path, "content": "key = 3\n" })).await;
+    assert!(result.contains("changed on disk since you last read it"), "{}", result);
+}
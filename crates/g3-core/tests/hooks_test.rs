@@ -0,0 +1,202 @@
+//! Lifecycle hook tests
+//!
+//! Registers `sh` hooks in an agent's config and checks that they see the event
+//! payload, can block tool calls, and report failures.
+
+use g3_config::{Config, HookConfig, HooksConfig};
+use g3_core::hooks::{self, HookEvent};
+use g3_core::ui_writer::NullUiWriter;
+use g3_core::{Agent, ToolCall};
+use serde_json::json;
+use std::fs;
+use tempfile::TempDir;
+
+fn hook(command: &str, tools: &[&str]) -> HookConfig {
+    HookConfig {
+        command: command.to_string(),
+        tools: tools.iter().map(|t| t.to_string()).collect(),
+        timeout_seconds: 10,
+    }
+}
+
+fn shell_call(command: &str) -> ToolCall {
+    ToolCall {
+        tool: "shell".to_string(),
+        args: json!({ "command": command }),
+    }
+}
+
+#[tokio::test]
+async fn test_pre_tool_hook_blocks_matching_calls() {
+    let mut config = Config::default();
+    config.hooks.pre_tool = vec![
+        hook("echo 'shell is disabled in this repo' >&2; exit 1", &["shell"]),
+        hook("exit 1", &["todo_write"]),
+    ];
+    let mut agent = Agent::new(config, NullUiWriter).await.unwrap();
+
+    let result = agent.execute_tool(&shell_call("echo hi")).await.unwrap();
+    assert!(result.starts_with("🚫 shell was blocked by a pre_tool hook"), "{}", result);
+    assert!(result.contains("shell is disabled in this repo"), "{}", result);
+
+    // Hooks for other tools don't apply
+    let result = agent
+        .execute_tool(&ToolCall {
+            tool: "todo_read".to_string(),
+            args: json!({}),
+        })
+        .await
+        .unwrap();
+    assert!(!result.contains("blocked"), "{}", result);
+}
+
+#[tokio::test]
+async fn test_post_tool_hook_gets_result_and_reports_failure() {
+    let temp_dir = TempDir::new().unwrap();
+    let payload_path = temp_dir.path().join("payload.json");
+    let mut config = Config::default();
+    config.hooks.post_tool = vec![
+        hook(&format!("cat > '{}'", payload_path.display()), &[]),
+        hook("echo 'formatting failed'; exit 2", &["shell"]),
+    ];
+    let mut agent = Agent::new(config, NullUiWriter).await.unwrap();
+
+    let result = agent.execute_tool(&shell_call("echo from-shell")).await.unwrap();
+    assert!(result.contains("from-shell"), "{}", result);
+    assert!(
+        result.ends_with("⚠️ post_tool hook `echo 'formatting failed'; exit 2` failed: formatting failed"),
+        "{}",
+        result
+    );
+
+    let payload: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&payload_path).unwrap()).unwrap();
+    assert_eq!(payload["event"], "post_tool");
+    assert_eq!(payload["tool"], "shell");
+    assert_eq!(payload["args"]["command"], "echo from-shell");
+    assert!(payload["result"].as_str().unwrap().contains("from-shell"));
+}
+
+#[tokio::test]
+async fn test_run_hooks_passes_payload_and_environment() {
+    let temp_dir = TempDir::new().unwrap();
+    let out = temp_dir.path().join("out");
+    let config = HooksConfig {
+        on_compaction: vec![
+            hook(&format!("echo \"$G3_HOOK_EVENT $G3_SESSION_ID\" > '{}'", out.display()), &[]),
+            hook("exit 4", &[]),
+            hook(&format!("cat >> '{}'", out.display()), &[]),
+        ],
+        ..Default::default()
+    };
+
+    let outcomes = hooks::run_hooks(
+        &config,
+        HookEvent::OnCompaction,
+        None,
+        json!({ "chars_saved": 42 }),
+        Some("session-1"),
+        None,
+        false,
+    )
+    .await;
+    let successes: Vec<bool> = outcomes.iter().map(|o| o.success).collect();
+    assert_eq!(successes, vec![true, false, true]);
+    assert_eq!(outcomes[1].message, "exit code 4");
+
+    let written = fs::read_to_string(&out).unwrap();
+    let (env_line, payload) = written.split_once('\n').unwrap();
+    assert_eq!(env_line, "on_compaction session-1");
+    let payload: serde_json::Value = serde_json::from_str(payload).unwrap();
+    assert_eq!(payload, json!({ "event": "on_compaction", "session_id": "session-1", "chars_saved": 42 }));
+
+    // pre_tool-style runs stop at the first failure
+    let outcomes = hooks::run_hooks(
+        &config,
+        HookEvent::OnCompaction,
+        None,
+        json!({}),
+        None,
+        None,
+        true,
+    )
+    .await;
+    assert_eq!(outcomes.len(), 2);
+
+    // Events without hooks run nothing
+    assert!(hooks::run_hooks(&config, HookEvent::PostTurn, None, json!({}), None, None, false)
+        .await
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_timeout_covers_writing_the_payload() {
+    // Never reads stdin, so a large payload fills the pipe
+    let config = HooksConfig {
+        on_compaction: vec![HookConfig {
+            timeout_seconds: 1,
+            ..hook("sleep 5", &[])
+        }],
+        ..Default::default()
+    };
+
+    let started = std::time::Instant::now();
+    let outcomes = hooks::run_hooks(
+        &config,
+        HookEvent::OnCompaction,
+        None,
+        json!({ "summary": "x".repeat(1 << 20) }),
+        None,
+        None,
+        false,
+    )
+    .await;
+    assert!(!outcomes[0].success);
+    assert_eq!(outcomes[0].message, "timed out after 1s");
+    assert!(started.elapsed() < std::time::Duration::from_secs(4));
+}
+
+#[tokio::test]
+async fn test_post_tool_formatter_keeps_the_file_fresh() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("lib.rs");
+    let path_str = path.to_string_lossy().into_owned();
+    // A "formatter" that rewrites whatever the agent just wrote
+    let formatter = format!("printf '// formatted\\n' >> '{}'", path.display());
+    let write = |content: &str| ToolCall {
+        tool: "write_file".to_string(),
+        args: json!({ "file_path": path_str, "content": content }),
+    };
+
+    let mut config = Config::default();
+    config.hooks.post_tool = vec![hook(&formatter, &["write_file", "str_replace"])];
+    let mut agent = Agent::new(config, NullUiWriter).await.unwrap();
+
+    let result = agent.execute_tool(&write("fn a() {}\n")).await.unwrap();
+    assert!(result.starts_with("wrote"), "{}", result);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "fn a() {}\n// formatted\n");
+
+    // The formatter's rewrite counts as seen, for writes and edits alike
+    let result = agent.execute_tool(&write("fn b() {}\n")).await.unwrap();
+    assert!(result.starts_with("wrote"), "{}", result);
+    let edit = ToolCall {
+        tool: "str_replace".to_string(),
+        args: json!({ "file_path": path_str, "diff": "@@ -1,2 +1,2 @@\n-fn b() {}\n+fn c() {}\n // formatted\n" }),
+    };
+    let result = agent.execute_tool(&edit).await.unwrap();
+    assert!(result.starts_with("✅"), "{}", result);
+    assert!(!result.contains("changed on disk"), "{}", result);
+
+    // Edits from anywhere else are still caught
+    fs::write(&path, "fn user_edit() {}\n").unwrap();
+    let result = agent.execute_tool(&write("fn d() {}\n")).await.unwrap();
+    assert!(result.contains("changed on disk since you last read it"), "{}", result);
+
+    // A failing hook's changes aren't taken as the agent's
+    let mut config = Config::default();
+    config.hooks.post_tool = vec![hook(&format!("{}; exit 1", formatter), &["write_file"])];
+    let mut agent = Agent::new(config, NullUiWriter).await.unwrap();
+    agent.execute_tool(&write("fn e() {}\n")).await.unwrap();
+    let result = agent.execute_tool(&write("fn f() {}\n")).await.unwrap();
+    assert!(result.contains("changed on disk since you last read it"), "{}", result);
+}
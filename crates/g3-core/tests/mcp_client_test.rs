@@ -0,0 +1,132 @@
+//! MCP client tests
+//!
+//! Runs the `mcp_test_server` example as an MCP server, both directly through
+//! `McpServers` and as a server declared in an agent's config.
+
+use g3_config::{Config, McpServerConfig};
+use g3_core::mcp::McpServers;
+use g3_core::ui_writer::NullUiWriter;
+use g3_core::{Agent, ToolCall};
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Build the test server example and return its path. It is an example rather than a
+/// binary so it isn't installed with g3, and Cargo only hands integration tests the
+/// paths of binaries, so the test builds it itself.
+fn test_server_path() -> &'static str {
+    static PATH: OnceLock<String> = OnceLock::new();
+    PATH.get_or_init(|| {
+        let status = Command::new(env!("CARGO"))
+            .args(["build", "--quiet", "--example", "mcp_test_server", "--manifest-path"])
+            .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+            .status()
+            .expect("failed to run cargo");
+        assert!(status.success(), "building the mcp_test_server example failed");
+
+        // Test binaries live in target/<profile>/deps, examples in target/<profile>/examples
+        let exe = std::env::current_exe().unwrap();
+        let profile_dir = exe.parent().and_then(|deps| deps.parent()).unwrap();
+        profile_dir
+            .join("examples")
+            .join(format!("mcp_test_server{}", std::env::consts::EXE_SUFFIX))
+            .to_string_lossy()
+            .into_owned()
+    })
+}
+
+fn test_server_configs() -> HashMap<String, McpServerConfig> {
+    test_server_configs_with_timeout(10)
+}
+
+fn test_server_configs_with_timeout(timeout_seconds: u64) -> HashMap<String, McpServerConfig> {
+    let mut configs = HashMap::new();
+    configs.insert(
+        "test".to_string(),
+        McpServerConfig {
+            command: test_server_path().to_string(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            cwd: None,
+            enabled: true,
+            timeout_seconds,
+        },
+    );
+    configs
+}
+
+fn tool_call(tool: &str, args: serde_json::Value) -> ToolCall {
+    ToolCall {
+        tool: tool.to_string(),
+        args,
+    }
+}
+
+#[tokio::test]
+async fn test_server_tools_are_listed_and_renamed_on_clash() {
+    let (servers, warnings) = McpServers::start(&test_server_configs(), &["shell".to_string()]).await;
+    assert!(warnings.is_empty(), "{:?}", warnings);
+    assert_eq!(servers.server_names(), vec!["test"]);
+
+    let tools = servers.tool_definitions();
+    let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, vec!["echo", "add", "fail", "test__shell", "stall"]);
+    assert_eq!(tools[0].description, "Echo the given text");
+    assert_eq!(tools[0].input_schema["required"][0], "text");
+    // Missing descriptions and schemas get defaults
+    assert!(tools[1].description.contains("test MCP server"));
+    assert_eq!(tools[2].input_schema["type"], "object");
+
+    let result = servers.call(&tool_call("add", serde_json::json!({ "a": 2, "b": 3 }))).await;
+    assert_eq!(result.unwrap().unwrap(), "5");
+    assert!(servers.call(&tool_call("shell", serde_json::json!({}))).await.is_none());
+}
+
+#[tokio::test]
+async fn test_agent_routes_unknown_tools_to_mcp_server() {
+    let config = Config {
+        mcp_servers: test_server_configs(),
+        ..Config::default()
+    };
+    let mut agent = Agent::new(config, NullUiWriter).await.unwrap();
+
+    let result = agent
+        .execute_tool(&tool_call("echo", serde_json::json!({ "text": "hello from g3" })))
+        .await
+        .unwrap();
+    assert_eq!(result, "hello from g3");
+
+    let result = agent.execute_tool(&tool_call("fail", serde_json::json!({}))).await.unwrap();
+    assert_eq!(result, "❌ tool failed");
+
+    // The clashing server tool is reachable under its prefixed name
+    let result = agent
+        .execute_tool(&tool_call("test__shell", serde_json::json!({})))
+        .await
+        .unwrap();
+    assert_eq!(result, "test server shell");
+
+    let result = agent
+        .execute_tool(&tool_call("no_such_tool", serde_json::json!({})))
+        .await
+        .unwrap();
+    assert!(result.contains("Unknown tool"), "{}", result);
+}
+
+#[tokio::test]
+async fn test_requests_to_a_stalled_server_time_out() {
+    let (servers, warnings) = McpServers::start(&test_server_configs_with_timeout(1), &[]).await;
+    assert!(warnings.is_empty(), "{:?}", warnings);
+
+    let result = servers.call(&tool_call("stall", serde_json::json!({}))).await.unwrap();
+    assert!(result.unwrap().contains("no answer to tools/call within 1s"));
+
+    // The server no longer reads its stdin, so writing a large request blocks
+    let text = "x".repeat(4 * 1024 * 1024);
+    let echo = tool_call("echo", serde_json::json!({ "text": text }));
+    let result = tokio::time::timeout(std::time::Duration::from_secs(10), servers.call(&echo))
+        .await
+        .expect("the request's write was not covered by the timeout")
+        .unwrap();
+    assert!(result.unwrap().contains("no answer to tools/call within 1s"));
+}
@@ -0,0 +1,92 @@
+//! MCP server tests
+//!
+//! Drives `McpToolServer` with JSON-RPC messages the way an MCP client would.
+
+use g3_config::Config;
+use g3_core::mcp_server::McpToolServer;
+use serde_json::{json, Value};
+use serial_test::serial;
+use std::fs;
+use tempfile::TempDir;
+
+async fn request(server: &mut McpToolServer, id: u64, method: &str, params: Value) -> Value {
+    server
+        .handle_message(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+        .await
+        .expect("requests get a response")
+}
+
+#[tokio::test]
+async fn test_handshake_and_tool_list() {
+    let mut server = McpToolServer::new(Config::default());
+
+    let response = request(&mut server, 1, "initialize", json!({ "protocolVersion": "2024-11-05" })).await;
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["result"]["serverInfo"]["name"], "g3");
+    assert!(response["result"]["capabilities"]["tools"].is_object());
+
+    // Notifications are not answered
+    let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+    assert!(server.handle_message(&notification).await.is_none());
+
+    let response = request(&mut server, 2, "tools/list", json!({})).await;
+    let tools = response["result"]["tools"].as_array().unwrap();
+    let names: Vec<&str> = tools.iter().map(|t| t["name"].as_str().unwrap()).collect();
+    for expected in ["read_file", "str_replace", "code_search", "todo_read", "todo_write", "background_process"] {
+        assert!(names.contains(&expected), "missing {} in {:?}", expected, names);
+    }
+    for excluded in ["research", "rehydrate", "read_image", "screenshot"] {
+        assert!(!names.contains(&excluded), "{} should not be served", excluded);
+    }
+    assert!(tools.iter().all(|t| t["inputSchema"]["type"] == "object"));
+
+    let response = request(&mut server, 3, "resources/list", json!({})).await;
+    assert_eq!(response["error"]["code"], -32601);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_tool_calls_edit_files() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    fs::write(temp_dir.path().join("lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+    let mut server = McpToolServer::new(Config::default());
+
+    let response = request(
+        &mut server,
+        1,
+        "tools/call",
+        json!({
+            "name": "str_replace",
+            "arguments": {
+                "file_path": "lib.rs",
+                "diff": "@@ -1,2 +1,2 @@\n fn a() {}\n-fn b() {}\n+fn c() {}\n"
+            }
+        }),
+    )
+    .await;
+    assert_eq!(response["result"]["isError"], false);
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("lib.rs")).unwrap(),
+        "fn a() {}\nfn c() {}\n"
+    );
+
+    let response = request(
+        &mut server,
+        2,
+        "tools/call",
+        json!({ "name": "read_file", "arguments": { "file_path": "missing.rs" } }),
+    )
+    .await;
+    assert_eq!(response["result"]["isError"], true);
+    assert_eq!(response["result"]["content"][0]["type"], "text");
+
+    let response = request(
+        &mut server,
+        3,
+        "tools/call",
+        json!({ "name": "research", "arguments": { "query": "anything" } }),
+    )
+    .await;
+    assert_eq!(response["error"]["code"], -32602);
+}
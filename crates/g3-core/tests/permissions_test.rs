@@ -0,0 +1,150 @@
+//! Permission policy tests
+//!
+//! Runs tool calls through a real agent configured with a `[permissions]` section and
+//! checks what was blocked, what was asked, and what was recorded in the permission
+//! log.
+
+use g3_config::{Config, PermissionMode};
+use g3_core::ui_writer::UiWriter;
+use g3_core::{Agent, ToolCall};
+use serial_test::serial;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+/// UI writer that records approval prompts and answers them from a queue
+#[derive(Clone, Default)]
+struct PromptingUiWriter {
+    prompts: Arc<Mutex<Vec<String>>>,
+    answers: Arc<Mutex<Vec<bool>>>,
+}
+
+impl UiWriter for PromptingUiWriter {
+    fn print(&self, _message: &str) {}
+    fn println(&self, _message: &str) {}
+    fn print_inline(&self, _message: &str) {}
+    fn print_system_prompt(&self, _prompt: &str) {}
+    fn print_context_status(&self, _message: &str) {}
+    fn print_context_thinning(&self, _message: &str) {}
+    fn print_tool_header(&self, _tool_name: &str, _tool_args: Option<&serde_json::Value>) {}
+    fn print_tool_arg(&self, _key: &str, _value: &str) {}
+    fn print_tool_output_header(&self) {}
+    fn update_tool_output_line(&self, _line: &str) {}
+    fn print_tool_output_line(&self, _line: &str) {}
+    fn print_tool_output_summary(&self, _hidden_count: usize) {}
+    fn print_tool_timing(&self, _duration_str: &str, _tokens_delta: u32, _context_percentage: f32) {}
+    fn print_agent_prompt(&self) {}
+    fn print_agent_response(&self, _content: &str) {}
+    fn notify_sse_received(&self) {}
+    fn print_tool_streaming_hint(&self, _tool_name: &str) {}
+    fn print_tool_streaming_active(&self) {}
+    fn flush(&self) {}
+    fn prompt_user_yes_no(&self, message: &str) -> bool {
+        self.prompts.lock().unwrap().push(message.to_string());
+        self.answers.lock().unwrap().pop().unwrap_or(false)
+    }
+    fn prompt_user_choice(&self, _message: &str, _options: &[&str]) -> usize {
+        0
+    }
+}
+
+fn permissions_config() -> Config {
+    let mut config = Config::default();
+    let permissions = &mut config.permissions;
+    permissions.tools.insert("todo_write".into(), PermissionMode::Deny);
+    permissions.shell.deny = vec!["rm -rf *".into()];
+    permissions.shell.ask = vec!["touch *".into()];
+    permissions.paths.outside_workspace = PermissionMode::Deny;
+    config
+}
+
+fn shell(command: &str) -> ToolCall {
+    ToolCall {
+        tool: "shell".to_string(),
+        args: serde_json::json!({ "command": command }),
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_denied_calls_do_not_run_and_are_logged() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    std::fs::create_dir(temp_dir.path().join("keep")).unwrap();
+
+    let ui_writer = PromptingUiWriter::default();
+    let mut agent = Agent::new(permissions_config(), ui_writer.clone()).await.unwrap();
+    agent.init_session_id_for_test("permissions test");
+
+    let result = agent.execute_tool(&shell("rm -rf keep")).await.unwrap();
+    assert!(result.starts_with("🚫 Permission denied for shell"), "{}", result);
+    assert!(temp_dir.path().join("keep").exists());
+
+    let outside = temp_dir.path().parent().unwrap().join("g3_permissions_test.txt");
+    let write = ToolCall {
+        tool: "write_file".to_string(),
+        args: serde_json::json!({ "file_path": outside.to_string_lossy(), "content": "x" }),
+    };
+    let result = agent.execute_tool(&write).await.unwrap();
+    assert!(result.contains("is outside the workspace"), "{}", result);
+    assert!(!outside.exists());
+
+    // Allowed calls still run
+    let result = agent.execute_tool(&shell("echo permitted")).await.unwrap();
+    assert!(result.contains("permitted"), "{}", result);
+    assert!(ui_writer.prompts.lock().unwrap().is_empty());
+
+    let entries = agent.get_permission_log();
+    let outcomes: Vec<&str> = entries.iter().map(|e| e.outcome.as_str()).collect();
+    assert_eq!(outcomes, vec!["denied", "denied", "allowed"]);
+    assert_eq!(entries[0].command.as_deref(), Some("rm -rf keep"));
+    assert_eq!(entries[0].reason, "`rm -rf keep` matches deny rule `rm -rf *`");
+    assert_eq!(entries[1].tool, "write_file");
+    assert_eq!(entries[1].paths, vec![outside.to_string_lossy().into_owned()]);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_ask_prompts_interactively() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    let ui_writer = PromptingUiWriter::default();
+    let mut agent = Agent::new(permissions_config(), ui_writer.clone()).await.unwrap();
+
+    // Answers are popped from the end: reject first, then approve
+    ui_writer.answers.lock().unwrap().extend([true, false]);
+    let result = agent.execute_tool(&shell("touch rejected.txt")).await.unwrap();
+    assert!(result.contains("the user declined"), "{}", result);
+    assert!(!temp_dir.path().join("rejected.txt").exists());
+
+    agent.execute_tool(&shell("touch approved.txt")).await.unwrap();
+    assert!(temp_dir.path().join("approved.txt").exists());
+
+    let prompts = ui_writer.prompts.lock().unwrap();
+    assert_eq!(prompts.len(), 2);
+    assert!(prompts[0].contains("`touch rejected.txt`"), "{}", prompts[0]);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_ask_fails_closed_in_autonomous_mode() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    let ui_writer = PromptingUiWriter::default();
+    ui_writer.answers.lock().unwrap().push(true);
+    let mut agent = Agent::new_autonomous(permissions_config(), ui_writer.clone())
+        .await
+        .unwrap();
+
+    let result = agent.execute_tool(&shell("touch unattended.txt")).await.unwrap();
+    assert!(result.contains("not available in autonomous mode"), "{}", result);
+    assert!(!temp_dir.path().join("unattended.txt").exists());
+    assert!(ui_writer.prompts.lock().unwrap().is_empty());
+
+    // Decisions are recorded even before the agent has a session ID
+    assert!(agent.get_session_id().is_none());
+    let entries = agent.get_permission_log();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].outcome, "denied_unattended");
+}
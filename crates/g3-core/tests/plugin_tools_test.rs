@@ -0,0 +1,81 @@
+//! Plugin tool tests
+//!
+//! Declares `sh` scripts as plugin tools in an agent's config and calls them.
+
+use g3_config::{Config, PluginToolConfig};
+use g3_core::ui_writer::NullUiWriter;
+use g3_core::{Agent, ToolCall};
+use serde_json::json;
+
+fn plugin(script: &str) -> PluginToolConfig {
+    PluginToolConfig {
+        description: "Test plugin".to_string(),
+        command: "sh".to_string(),
+        args: vec!["-c".to_string(), script.to_string()],
+        input_schema: json!({ "type": "object", "properties": {} }),
+        timeout_seconds: 10,
+        enabled: true,
+    }
+}
+
+fn tool_call(tool: &str, args: serde_json::Value) -> ToolCall {
+    ToolCall {
+        tool: tool.to_string(),
+        args,
+    }
+}
+
+#[tokio::test]
+async fn test_agent_runs_plugin_tools() {
+    let mut config = Config::default();
+    config.plugin_tools.insert("echo_args".to_string(), plugin("cat"));
+    config
+        .plugin_tools
+        .insert("whoami".to_string(), plugin("echo \"$G3_TOOL_NAME\""));
+    config
+        .plugin_tools
+        .insert("broken".to_string(), plugin("echo boom >&2; exit 3"));
+    config
+        .plugin_tools
+        .insert("slow".to_string(), PluginToolConfig { timeout_seconds: 1, ..plugin("sleep 5") });
+    config
+        .plugin_tools
+        .insert("disabled".to_string(), PluginToolConfig { enabled: false, ..plugin("cat") });
+    let mut agent = Agent::new(config, NullUiWriter).await.unwrap();
+
+    // Arguments arrive as JSON on stdin
+    let result = agent
+        .execute_tool(&tool_call("echo_args", json!({ "key": "ABC-123" })))
+        .await
+        .unwrap();
+    let echoed: serde_json::Value = serde_json::from_str(&result).unwrap();
+    assert_eq!(echoed, json!({ "key": "ABC-123" }));
+
+    let result = agent.execute_tool(&tool_call("whoami", json!({}))).await.unwrap();
+    assert_eq!(result, "whoami");
+
+    let result = agent.execute_tool(&tool_call("broken", json!({}))).await.unwrap();
+    assert_eq!(result, "❌ Plugin tool 'broken' failed with exit code 3\nboom");
+
+    let result = agent.execute_tool(&tool_call("slow", json!({}))).await.unwrap();
+    assert!(result.contains("timed out after 1s"), "{}", result);
+
+    let result = agent.execute_tool(&tool_call("disabled", json!({}))).await.unwrap();
+    assert!(result.contains("Unknown tool"), "{}", result);
+}
+
+#[tokio::test]
+async fn test_timeout_covers_writing_the_arguments() {
+    let mut config = Config::default();
+    // Never reads stdin, so a large argument fills the pipe
+    config
+        .plugin_tools
+        .insert("deaf".to_string(), PluginToolConfig { timeout_seconds: 1, ..plugin("sleep 5") });
+    let mut agent = Agent::new(config, NullUiWriter).await.unwrap();
+
+    let started = std::time::Instant::now();
+    let big = "x".repeat(1 << 20);
+    let result = agent.execute_tool(&tool_call("deaf", json!({ "text": big }))).await.unwrap();
+    assert!(result.contains("timed out after 1s"), "{}", result);
+    assert!(started.elapsed() < std::time::Duration::from_secs(4));
+}
@@ -0,0 +1,226 @@
+//! Provider failover tests
+//!
+//! Verifies that an agent configured with a provider chain falls over to the next
+//! provider when the current one fails with a non-recoverable error, and keeps the
+//! conversation context across the switch. Replay cassettes stand in for real providers.
+
+use g3_config::{Config, ReplayConfig, ReplayMode};
+use g3_core::ui_writer::NullUiWriter;
+use g3_core::Agent;
+use g3_providers::replay::{Cassette, Interaction};
+use g3_providers::{make_final_chunk, make_text_chunk, CompletionResponse, MessageRole, Usage};
+use serial_test::serial;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn stream_reply(reply: &str) -> Interaction {
+    Interaction {
+        request_hash: "unmatched".to_string(),
+        chunks: Some(vec![
+            make_text_chunk(reply.to_string()),
+            make_final_chunk(
+                vec![],
+                Some(Usage {
+                    prompt_tokens: 100,
+                    completion_tokens: 10,
+                    total_tokens: 110,
+                    ..Default::default()
+                }),
+            ),
+        ]),
+        response: None,
+    }
+}
+
+fn summary_reply(summary: &str) -> Interaction {
+    Interaction {
+        request_hash: "unmatched".to_string(),
+        chunks: None,
+        response: Some(CompletionResponse {
+            content: summary.to_string(),
+            usage: Usage::default(),
+            model: "replay-model".to_string(),
+        }),
+    }
+}
+
+fn write_cassette(path: &Path, provider: &str, native_tool_calling: bool, interactions: Vec<Interaction>) {
+    let cassette = Cassette {
+        version: 1,
+        provider: provider.to_string(),
+        model: "replay-model".to_string(),
+        native_tool_calling,
+        supports_cache_control: false,
+        supports_vision: false,
+        max_tokens: 4096,
+        temperature: 0.1,
+        interactions,
+    };
+    cassette.save(path).unwrap();
+}
+
+fn replay_config(cassette: &Path, strict: bool) -> ReplayConfig {
+    ReplayConfig {
+        cassette: cassette.to_string_lossy().to_string(),
+        mode: ReplayMode::Replay,
+        record_from: None,
+        strict,
+        context_length: Some(100_000),
+    }
+}
+
+/// A strict, empty cassette fails every request with a non-recoverable error; the
+/// lenient one answers anything.
+fn failover_config(temp_dir: &TempDir, chain: Vec<&str>) -> Config {
+    failover_config_with(
+        temp_dir,
+        chain,
+        true,
+        vec![
+            stream_reply("Hello from the fallback provider."),
+            stream_reply("Still on the fallback provider."),
+        ],
+    )
+}
+
+fn failover_config_with(
+    temp_dir: &TempDir,
+    chain: Vec<&str>,
+    fallback_native_tool_calling: bool,
+    fallback_interactions: Vec<Interaction>,
+) -> Config {
+    let broken = temp_dir.path().join("broken.json");
+    let good = temp_dir.path().join("good.json");
+    write_cassette(&broken, "anthropic.default", true, vec![]);
+    write_cassette(
+        &good,
+        "databricks.default",
+        fallback_native_tool_calling,
+        fallback_interactions,
+    );
+
+    let mut config = Config::default();
+    config
+        .providers
+        .replay
+        .insert("broken".to_string(), replay_config(&broken, true));
+    config
+        .providers
+        .replay
+        .insert("good".to_string(), replay_config(&good, false));
+    config.providers.default_provider = chain
+        .into_iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap();
+    config
+}
+
+#[tokio::test]
+#[serial]
+async fn test_failover_to_next_provider_keeps_context() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let config = failover_config(&temp_dir, vec!["replay.broken", "replay.good"]);
+
+    let mut agent = Agent::new_with_readme_and_quiet(config, NullUiWriter, None, true)
+        .await
+        .unwrap();
+    assert_eq!(agent.get_provider_info().unwrap().0, "replay.broken");
+
+    agent
+        .execute_task("Say hello", None, false)
+        .await
+        .expect("task should succeed on the fallback provider");
+    assert_eq!(agent.get_provider_info().unwrap().0, "replay.good");
+
+    // Later turns stay on the fallback and build on the same conversation
+    agent.execute_task("Again", None, false).await.unwrap();
+    assert_eq!(agent.get_provider_info().unwrap().0, "replay.good");
+    let user_messages: Vec<_> = agent
+        .get_context_window()
+        .conversation_history
+        .iter()
+        .filter(|m| matches!(m.role, MessageRole::User))
+        .map(|m| m.content.clone())
+        .collect();
+    assert_eq!(user_messages.len(), 2);
+    assert!(user_messages[0].contains("Say hello"));
+    assert!(user_messages[1].contains("Again"));
+
+    // Both recorded replies came from the fallback cassette, so it is now used up
+    let err = agent.execute_task("Once more", None, false).await.unwrap_err();
+    assert!(err.to_string().contains("good.json"), "unexpected error: {}", err);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_error_surfaces_when_chain_is_exhausted() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let config = failover_config(&temp_dir, vec!["replay.broken"]);
+
+    let mut agent = Agent::new_with_readme_and_quiet(config, NullUiWriter, None, true)
+        .await
+        .unwrap();
+
+    let err = agent.execute_task("Say hello", None, false).await.unwrap_err();
+    assert!(
+        err.to_string().contains("No recorded interaction"),
+        "unexpected error: {}",
+        err
+    );
+    assert_eq!(agent.get_provider_info().unwrap().0, "replay.broken");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_failover_switches_to_non_native_tool_prompt() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let config = failover_config_with(
+        &temp_dir,
+        vec!["replay.broken", "replay.good"],
+        false,
+        vec![stream_reply("Hello without native tools.")],
+    );
+
+    let mut agent = Agent::new_with_readme_and_quiet(config, NullUiWriter, None, true)
+        .await
+        .unwrap();
+    let system_prompt = |agent: &Agent<NullUiWriter>| {
+        agent.get_context_window().conversation_history[0].content.clone()
+    };
+    assert!(!system_prompt(&agent).contains("# Tool Call Format"));
+
+    agent.execute_task("Say hello", None, false).await.unwrap();
+    assert_eq!(agent.get_provider_info().unwrap().0, "replay.good");
+    // The fallback parses JSON tool calls from text, so it needs the JSON format prompt
+    assert!(system_prompt(&agent).contains("# Tool Call Format"));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_compaction_fails_over_to_next_provider() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let config = failover_config_with(
+        &temp_dir,
+        vec!["replay.broken", "replay.good"],
+        true,
+        vec![summary_reply("We said hello.")],
+    );
+
+    let mut agent = Agent::new_with_readme_and_quiet(config, NullUiWriter, None, true)
+        .await
+        .unwrap();
+
+    assert!(agent.force_compact().await.unwrap());
+    assert_eq!(agent.get_provider_info().unwrap().0, "replay.good");
+    assert!(agent
+        .get_context_window()
+        .conversation_history
+        .iter()
+        .any(|m| m.content.contains("We said hello.")));
+}
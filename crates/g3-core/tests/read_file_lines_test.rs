@@ -0,0 +1,88 @@
+//! Tests for line-range and symbol reads in read_file
+
+use g3_core::ui_writer::NullUiWriter;
+use g3_core::{Agent, ToolCall};
+use serde_json::json;
+use std::fs;
+use tempfile::TempDir;
+
+const SOURCE: &str = r#"//! Sample module
+
+/// Adds one
+fn add_one(x: i32) -> i32 {
+    x + 1
+}
+
+struct Counter {
+    count: u32,
+}
+
+impl Counter {
+    fn increment(&mut self) {
+        self.count += 1;
+    }
+}
+"#;
+
+async fn read(agent: &mut Agent<NullUiWriter>, args: serde_json::Value) -> String {
+    let tool_call = ToolCall {
+        tool: "read_file".to_string(),
+        args,
+    };
+    agent.execute_tool(&tool_call).await.unwrap()
+}
+
+fn sample_file(dir: &TempDir) -> String {
+    let path = dir.path().join("sample.rs");
+    fs::write(&path, SOURCE).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+#[tokio::test]
+async fn test_read_file_line_range() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = sample_file(&temp_dir);
+    let mut agent = Agent::new(g3_config::Config::default(), NullUiWriter).await.unwrap();
+
+    let result = read(&mut agent, json!({ "file_path": path, "start_line": 4, "end_line": 6 })).await;
+    assert_eq!(
+        result,
+        "4\tfn add_one(x: i32) -> i32 {\n5\t    x + 1\n6\t}\n🔍 3 lines read (lines 4-6 of 16)"
+    );
+
+    // Past the end is clamped; numbers are padded to the widest one shown
+    let result = read(&mut agent, json!({ "file_path": path, "start_line": 8, "end_line": 40 })).await;
+    assert!(result.starts_with(" 8\tstruct Counter {\n"), "{}", result);
+    assert!(result.ends_with("(lines 8-16, end_line clamped from 40 to file length 16)"), "{}", result);
+
+    let result = read(&mut agent, json!({ "file_path": path, "start_line": 30 })).await;
+    assert_eq!(result, "❌ start_line 30 is past the end of the file (16 lines)");
+
+    let result = read(&mut agent, json!({ "file_path": path, "start_line": 5, "end_line": 2 })).await;
+    assert_eq!(result, "❌ start_line 5 is greater than end_line 2");
+
+    let result = read(&mut agent, json!({ "file_path": path, "start_line": 1, "start": 0 })).await;
+    assert!(result.starts_with("❌ Use either start/end"), "{}", result);
+}
+
+#[tokio::test]
+async fn test_read_file_symbol() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = sample_file(&temp_dir);
+    let mut agent = Agent::new(g3_config::Config::default(), NullUiWriter).await.unwrap();
+
+    let result = read(&mut agent, json!({ "file_path": path, "symbol": "add_one" })).await;
+    assert_eq!(
+        result,
+        "📍 function_item `add_one` (lines 3-6)\n3\t/// Adds one\n4\tfn add_one(x: i32) -> i32 {\n5\t    x + 1\n6\t}\n🔍 4 lines read (1 definition of `add_one`)"
+    );
+
+    let result = read(&mut agent, json!({ "file_path": path, "symbol": "Counter::increment" })).await;
+    assert!(result.starts_with("📍 function_item `Counter::increment` (lines 13-15)\n13\t    fn increment"), "{}", result);
+
+    let result = read(&mut agent, json!({ "file_path": path, "symbol": "missing" })).await;
+    assert!(result.starts_with("❌ No definition of 'missing' found"), "{}", result);
+
+    let result = read(&mut agent, json!({ "file_path": path, "symbol": "add_one", "end_line": 3 })).await;
+    assert_eq!(result, "❌ Use either symbol or start_line/end_line, not both");
+}
@@ -0,0 +1,139 @@
+//! Command sandbox tests
+//!
+//! Runs `shell` and `background_process` through an agent with `[sandbox]` enabled.
+//! Skipped where the sandbox can't run (non-Linux, or user namespaces disabled).
+
+use g3_config::Config;
+use g3_core::ui_writer::NullUiWriter;
+use g3_core::{Agent, Sandbox, ToolCall};
+use serial_test::serial;
+use std::time::Duration;
+use tempfile::TempDir;
+
+fn sandbox_config(network: bool) -> Config {
+    let mut config = Config::default();
+    config.sandbox.enabled = true;
+    config.sandbox.network = network;
+    config
+}
+
+fn shell(command: &str) -> ToolCall {
+    ToolCall {
+        tool: "shell".to_string(),
+        args: serde_json::json!({ "command": command }),
+    }
+}
+
+/// A directory outside the workspace that the test user can normally write to
+fn outside_dir() -> TempDir {
+    tempfile::Builder::new()
+        .prefix("g3_sandbox_outside")
+        .tempdir_in(std::env::var("HOME").unwrap())
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn test_sandboxed_shell_can_only_write_the_workspace() {
+    if !Sandbox::is_supported() {
+        eprintln!("Skipping: sandbox not supported here");
+        return;
+    }
+    let workspace = TempDir::new().unwrap();
+    std::env::set_current_dir(workspace.path()).unwrap();
+    let outside = outside_dir();
+    let mut agent = Agent::new(sandbox_config(true), NullUiWriter).await.unwrap();
+
+    let result = agent.execute_tool(&shell("echo inside > inside.txt && cat inside.txt")).await.unwrap();
+    assert_eq!(result, "inside");
+
+    let outside_file = outside.path().join("escape.txt");
+    let result = agent
+        .execute_tool(&shell(&format!("echo out > {}", outside_file.display())))
+        .await
+        .unwrap();
+    assert!(result.contains("Read-only file system"), "{}", result);
+    assert!(!outside_file.exists());
+
+    // TMPDIR points somewhere writable, and the command is init of its own PID namespace
+    let result = agent.execute_tool(&shell("mktemp && echo $$")).await.unwrap();
+    assert!(result.ends_with("\n1"), "{}", result);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_sandbox_tmpdir_is_private() {
+    if !Sandbox::is_supported() {
+        eprintln!("Skipping: sandbox not supported here");
+        return;
+    }
+    let workspace = TempDir::new().unwrap();
+    std::env::set_current_dir(workspace.path()).unwrap();
+    let mut agent = Agent::new(sandbox_config(true), NullUiWriter).await.unwrap();
+
+    let result = agent
+        .execute_tool(&shell("echo $TMPDIR && stat -c '%a %u' $TMPDIR && id -u"))
+        .await
+        .unwrap();
+    let lines: Vec<&str> = result.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", result);
+    let tmpdir = std::path::Path::new(lines[0]);
+    assert!(tmpdir.file_name().unwrap().to_string_lossy().starts_with("g3-sandbox-"));
+    assert_eq!(lines[1], format!("700 {}", lines[2]));
+
+    // Later commands share it
+    let result = agent.execute_tool(&shell("echo $TMPDIR")).await.unwrap();
+    assert_eq!(result, lines[0]);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_sandbox_network_denial() {
+    if !Sandbox::is_supported() {
+        eprintln!("Skipping: sandbox not supported here");
+        return;
+    }
+    let workspace = TempDir::new().unwrap();
+    std::env::set_current_dir(workspace.path()).unwrap();
+    let mut agent = Agent::new(sandbox_config(false), NullUiWriter).await.unwrap();
+
+    // Only loopback exists in the sandbox's network namespace
+    let result = agent
+        .execute_tool(&shell("tail -n +3 /proc/net/dev | cut -d: -f1"))
+        .await
+        .unwrap();
+    assert_eq!(result.trim(), "lo");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_sandboxed_background_process() {
+    if !Sandbox::is_supported() {
+        eprintln!("Skipping: sandbox not supported here");
+        return;
+    }
+    let workspace = TempDir::new().unwrap();
+    std::env::set_current_dir(workspace.path()).unwrap();
+    let outside = outside_dir();
+    let outside_file = outside.path().join("escape.txt");
+    let mut agent = Agent::new(sandbox_config(true), NullUiWriter).await.unwrap();
+
+    let tool_call = ToolCall {
+        tool: "background_process".to_string(),
+        args: serde_json::json!({
+            "name": "writer",
+            "command": format!("echo out > {}; echo done > done.txt", outside_file.display()),
+        }),
+    };
+    let result = agent.execute_tool(&tool_call).await.unwrap();
+    assert!(result.contains("started"), "{}", result);
+
+    for _ in 0..50 {
+        if workspace.path().join("done.txt").exists() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(workspace.path().join("done.txt").exists());
+    assert!(!outside_file.exists());
+}
@@ -0,0 +1,88 @@
+//! Session diff tests
+//!
+//! Changes files through `write_file`, `str_replace` and `shell` on a real agent in a
+//! directory that is not a git repository, then checks the agent's session diff.
+
+mod common;
+
+use common::{create_agent, tool_call};
+use g3_core::session_diff::ChangeKind;
+use serial_test::serial;
+use std::fs;
+use tempfile::TempDir;
+
+#[tokio::test]
+#[serial]
+async fn test_session_diff_covers_tool_and_shell_edits() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("edited.txt"), "alpha\nbeta\ngamma\n").unwrap();
+    fs::write(temp_dir.path().join("doomed.txt"), "bye\n").unwrap();
+    fs::write(temp_dir.path().join("untouched.txt"), "same\n").unwrap();
+    let mut agent = create_agent(&temp_dir, "session diff test").await;
+
+    agent
+        .execute_tool(&tool_call(
+            "str_replace",
+            serde_json::json!({
+                "file_path": "edited.txt",
+                "diff": "@@ -1,3 +1,3 @@\n alpha\n-beta\n+BETA\n gamma\n"
+            }),
+        ))
+        .await
+        .unwrap();
+    // A later edit of the same file still diffs against the session-start content
+    agent
+        .execute_tool(&tool_call(
+            "write_file",
+            serde_json::json!({ "file_path": "edited.txt", "content": "alpha\nBETA\ngamma\ndelta\n" }),
+        ))
+        .await
+        .unwrap();
+    agent
+        .execute_tool(&tool_call(
+            "shell",
+            serde_json::json!({ "command": "echo hello > created.txt && rm doomed.txt", "persistent": false }),
+        ))
+        .await
+        .unwrap();
+
+    let diffs = agent.session_diff().unwrap();
+    let summary: Vec<(String, ChangeKind)> = diffs
+        .iter()
+        .map(|d| (d.path.file_name().unwrap().to_string_lossy().to_string(), d.kind))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("edited.txt".to_string(), ChangeKind::Modified),
+            ("created.txt".to_string(), ChangeKind::Added),
+            ("doomed.txt".to_string(), ChangeKind::Deleted),
+        ]
+    );
+    assert_eq!(
+        diffs[0].diff,
+        "--- a/edited.txt\n+++ b/edited.txt\n@@ -1,3 +1,4 @@\n alpha\n-beta\n+BETA\n gamma\n+delta\n"
+    );
+    assert!(diffs[1].diff.contains("+hello\n"), "{}", diffs[1].diff);
+    assert!(diffs[2].diff.contains("-bye\n"), "{}", diffs[2].diff);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_reverted_edits_drop_out_of_session_diff() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("notes.txt"), "original\n").unwrap();
+    let mut agent = create_agent(&temp_dir, "session diff test").await;
+
+    let write = |content: &str| {
+        tool_call(
+            "write_file",
+            serde_json::json!({ "file_path": "notes.txt", "content": content }),
+        )
+    };
+    agent.execute_tool(&write("changed\n")).await.unwrap();
+    assert_eq!(agent.session_diff().unwrap().len(), 1);
+
+    agent.execute_tool(&write("original\n")).await.unwrap();
+    assert!(agent.session_diff().unwrap().is_empty());
+}
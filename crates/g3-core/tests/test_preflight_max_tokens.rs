@@ -24,7 +24,7 @@ fn create_test_config_with_thinking(thinking_budget: Option<u32>) -> Config {
     });
     config.providers.anthropic = anthropic_configs;
     
-    config.providers.default_provider = "anthropic.default".to_string();
+    config.providers.default_provider = "anthropic.default".into();
     config
 }
 
@@ -38,6 +38,7 @@ fn test_update_usage_only_affects_cumulative() {
         prompt_tokens: 100,
         completion_tokens: 50,
         total_tokens: 150,
+        ..Default::default()
     };
     window.update_usage_from_response(&usage);
 
@@ -52,6 +53,7 @@ fn test_update_usage_only_affects_cumulative() {
         prompt_tokens: 200,
         completion_tokens: 75,
         total_tokens: 275,
+        ..Default::default()
     };
     window.update_usage_from_response(&usage2);
 
@@ -156,6 +158,7 @@ fn test_cumulative_vs_used_independence() {
         prompt_tokens: 500,
         completion_tokens: 200,
         total_tokens: 700,
+        ..Default::default()
     };
     window.update_usage_from_response(&usage);
 
@@ -291,6 +291,106 @@ mod shell_execution {
         assert!(result.contains(&*temp_path) || result.contains("private"), 
             "Should show current directory: {} (expected to contain {})", result, temp_path);
     }
+
+    /// Test that a command running past timeout_secs is killed with everything it started
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    #[serial]
+    async fn test_shell_timeout_kills_process_group() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut agent = create_test_agent(&temp_dir).await;
+
+        let tool_call = make_tool_call(
+            "shell",
+            serde_json::json!({
+                "command": "echo started; sleep 300 & echo $! > child.pid; wait",
+                "timeout_secs": 1
+            }),
+        );
+
+        let start = std::time::Instant::now();
+        let result = agent.execute_tool(&tool_call).await.unwrap();
+
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+        assert!(result.starts_with("⏱️ Command timed out after 1s"), "{}", result);
+        assert!(result.contains("started"), "Should keep output so far: {}", result);
+
+        // The backgrounded sleep is gone (or at most an unreaped zombie)
+        let pid = fs::read_to_string(temp_dir.path().join("child.pid")).unwrap();
+        let status = fs::read_to_string(format!("/proc/{}/status", pid.trim())).unwrap_or_default();
+        assert!(
+            status.is_empty() || status.contains("State:\tZ"),
+            "Child should have been killed: {}",
+            status
+        );
+    }
+
+    /// Test that persistent commands share one shell, and that it can be reset
+    #[tokio::test]
+    #[serial]
+    async fn test_shell_persistent_session() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        let mut agent = create_test_agent(&temp_dir).await;
+        let sub = temp_dir.path().canonicalize().unwrap().join("sub");
+
+        let persistent = |command: &str| {
+            make_tool_call("shell", serde_json::json!({ "command": command, "persistent": true }))
+        };
+
+        let result = agent.execute_tool(&persistent("cd sub && export G3_VENV=on")).await.unwrap();
+        assert!(result.contains(&format!("📂 cwd: {}", sub.display())), "{}", result);
+        assert!(result.contains("🌱 env changes: G3_VENV=on"), "{}", result);
+
+        let result = agent.execute_tool(&persistent("echo \"$G3_VENV\"; pwd")).await.unwrap();
+        assert!(result.starts_with(&format!("on\n{}", sub.display())), "{}", result);
+
+        // One-off commands still start from scratch
+        let one_off = make_tool_call("shell", serde_json::json!({ "command": "echo \"[$G3_VENV]\"" }));
+        assert_eq!(agent.execute_tool(&one_off).await.unwrap(), "[]");
+
+        let reset = make_tool_call(
+            "shell",
+            serde_json::json!({ "command": "echo \"[$G3_VENV]\"", "persistent": true, "reset_session": true }),
+        );
+        let result = agent.execute_tool(&reset).await.unwrap();
+        assert!(result.starts_with("🔄 Shell session reset.\n\n[]"), "{}", result);
+        assert!(!result.contains("env changes"), "{}", result);
+
+        // A session that exits is replaced on the next call, in the directory it was in
+        agent.execute_tool(&persistent("cd sub")).await.unwrap();
+        let result = agent.execute_tool(&persistent("exit 1")).await.unwrap();
+        assert!(result.contains("The shell session ended with this command"), "{}", result);
+        let result = agent.execute_tool(&persistent("pwd")).await.unwrap();
+        assert!(result.starts_with("⚠️ The previous shell session ended"), "{}", result);
+        assert!(result.contains(&format!("\n\n{}\n", sub.display())), "{}", result);
+    }
+
+    /// Test that long output is capped and saved in full to the session directory
+    #[tokio::test]
+    #[serial]
+    async fn test_shell_long_output_is_capped() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut agent = create_test_agent(&temp_dir).await;
+        agent.init_session_id_for_test("long output");
+
+        let tool_call = make_tool_call("shell", serde_json::json!({ "command": "seq 1 5000" }));
+        let result = agent.execute_tool(&tool_call).await.unwrap();
+
+        assert!(result.starts_with("1\n2\n"), "Should keep the start: {}", &result[..20]);
+        assert!(result.ends_with("4999\n5000"), "Should keep the end");
+        assert!(result.contains("[4600 lines omitted of 5000; full output saved to "), "{}", result);
+        assert!(result.lines().count() < 500);
+
+        let saved_dir = temp_dir
+            .path()
+            .join(".g3/sessions")
+            .join(agent.get_session_id().unwrap())
+            .join("shell_output");
+        let saved = fs::read_dir(saved_dir).unwrap().next().unwrap().unwrap().path();
+        assert!(result.contains(&*saved.to_string_lossy()));
+        assert_eq!(fs::read_to_string(saved).unwrap().lines().count(), 5000);
+    }
 }
 
 // =============================================================================
@@ -387,6 +487,55 @@ mod str_replace_execution {
             "Should indicate pattern not found: {:?}", result
         );
     }
+
+    /// Test that context with the wrong indentation still applies, and says so
+    #[tokio::test]
+    #[serial]
+    async fn test_str_replace_reports_loose_match() {
+        let temp_dir = TempDir::new().unwrap();
+        let test_file = temp_dir.path().join("test.rs");
+        fs::write(&test_file, "impl A {\n    fn a() {\n        1\n    }\n}\n").unwrap();
+
+        let mut agent = create_test_agent(&temp_dir).await;
+
+        let diff = "@@ -1,3 +1,3 @@\n fn a() {\n-    1\n+    2\n }\n";
+        let tool_call = make_tool_call(
+            "str_replace",
+            serde_json::json!({
+                "file_path": test_file.to_string_lossy(),
+                "diff": diff
+            }),
+        );
+
+        let result = agent.execute_tool(&tool_call).await.unwrap();
+        assert!(result.contains("hunk 1 matched ignoring indentation at line 2"), "{}", result);
+
+        let content = fs::read_to_string(&test_file).unwrap();
+        assert_eq!(content, "impl A {\n    fn a() {\n        2\n    }\n}\n");
+    }
+
+    /// Test that a hunk matching in several places is refused
+    #[tokio::test]
+    #[serial]
+    async fn test_str_replace_refuses_ambiguous_match() {
+        let temp_dir = TempDir::new().unwrap();
+        let test_file = temp_dir.path().join("test.txt");
+        fs::write(&test_file, "a\nold\nb\nold\n").unwrap();
+
+        let mut agent = create_test_agent(&temp_dir).await;
+
+        let tool_call = make_tool_call(
+            "str_replace",
+            serde_json::json!({
+                "file_path": test_file.to_string_lossy(),
+                "diff": "-old\n+new\n"
+            }),
+        );
+
+        let result = agent.execute_tool(&tool_call).await.unwrap();
+        assert!(result.starts_with("❌ Hunk 1 matches 2 places exactly (lines 2, 4)"), "{}", result);
+        assert_eq!(fs::read_to_string(&test_file).unwrap(), "a\nold\nb\nold\n");
+    }
 }
 
 // =============================================================================
@@ -464,3 +613,68 @@ mod todo_execution {
         assert!(content.contains("Persistent task"), "Content should persist: {}", content);
     }
 }
+
+// =============================================================================
+// Test: read_image tool execution
+// =============================================================================
+
+mod read_image_execution {
+    use super::*;
+
+    /// Minimal PNG header: enough for format detection
+    const PNG_BYTES: [u8; 16] = [
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44,
+        0x52,
+    ];
+
+    /// Test that a vision-capable model gets the image
+    #[tokio::test]
+    #[serial]
+    async fn test_read_image_on_vision_model() {
+        let temp_dir = TempDir::new().unwrap();
+        let image = temp_dir.path().join("shot.png");
+        fs::write(&image, PNG_BYTES).unwrap();
+
+        // The default config uses databricks-claude-sonnet-4
+        let mut agent = create_test_agent(&temp_dir).await;
+        let tool_call = make_tool_call(
+            "read_image",
+            serde_json::json!({ "file_paths": [image.to_string_lossy()] }),
+        );
+
+        let result = agent.execute_tool(&tool_call).await.unwrap();
+        assert!(result.contains("1 image(s) read"), "Should read the image: {}", result);
+    }
+
+    /// Test that a text-only model refuses instead of silently dropping the image
+    #[tokio::test]
+    #[serial]
+    async fn test_read_image_refused_on_text_only_model() {
+        let temp_dir = TempDir::new().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let image = temp_dir.path().join("shot.png");
+        fs::write(&image, PNG_BYTES).unwrap();
+
+        let mut config = g3_config::Config::default();
+        config.providers.openai.insert(
+            "default".to_string(),
+            g3_config::OpenAIConfig {
+                api_key: "test-key".to_string(),
+                model: "gpt-3.5-turbo".to_string(),
+                base_url: None,
+                max_tokens: None,
+                temperature: None,
+                vision: None,
+            },
+        );
+        config.providers.default_provider = "openai.default".into();
+        let mut agent = Agent::new(config, NullUiWriter).await.unwrap();
+
+        let tool_call = make_tool_call(
+            "read_image",
+            serde_json::json!({ "file_paths": [image.to_string_lossy()] }),
+        );
+        let result = agent.execute_tool(&tool_call).await.unwrap();
+        assert!(result.contains("cannot see images"), "Should refuse: {}", result);
+    }
+}
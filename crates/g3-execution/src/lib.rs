@@ -2,9 +2,16 @@ use anyhow::Result;
 use regex::Regex;
 use std::io::Write;
 use std::process::Command;
+use std::time::Duration;
 use tempfile::NamedTempFile;
 use tracing::{debug, error};
 
+pub mod sandbox;
+pub mod shell_session;
+
+pub use sandbox::Sandbox;
+pub use shell_session::ShellSession;
+
 /// Expand tilde (~) in a path to the user's home directory
 fn expand_tilde(path: &str) -> String {
     if path.starts_with("~") {
@@ -17,7 +24,10 @@ fn expand_tilde(path: &str) -> String {
 }
 
 pub struct CodeExecutor {
-    // Future: add configuration for execution limits, sandboxing, etc.
+    /// Run streamed bash commands inside this sandbox (Linux only)
+    sandbox: Option<Sandbox>,
+    /// Kill streamed bash commands (and everything they started) after this long
+    timeout: Option<Duration>,
 }
 
 #[derive(Debug, Clone)]
@@ -26,11 +36,28 @@ pub struct ExecutionResult {
     pub stderr: String,
     pub exit_code: i32,
     pub success: bool,
+    /// The command was killed because it ran past the executor's timeout
+    pub timed_out: bool,
 }
 
 impl CodeExecutor {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            sandbox: None,
+            timeout: None,
+        }
+    }
+
+    /// Run commands from `execute_bash_streaming_in_dir` in a sandbox
+    pub fn with_sandbox(mut self, sandbox: Option<Sandbox>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Kill commands from `execute_bash_streaming_in_dir` that run longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
     }
 
     /// Extract code blocks from LLM response and execute them
@@ -189,6 +216,7 @@ impl CodeExecutor {
             stderr: String::from_utf8_lossy(&output.stderr).to_string(),
             exit_code: output.status.code().unwrap_or(-1),
             success: output.status.success(),
+            timed_out: false,
         })
     }
 
@@ -216,6 +244,7 @@ impl CodeExecutor {
                 stderr: String::new(),
                 exit_code: 0,
                 success: true,
+                timed_out: false,
             });
         }
 
@@ -226,6 +255,7 @@ impl CodeExecutor {
             stderr: String::from_utf8_lossy(&output.stderr).to_string(),
             exit_code: output.status.code().unwrap_or(-1),
             success: output.status.success(),
+            timed_out: false,
         })
     }
 
@@ -242,6 +272,7 @@ impl CodeExecutor {
             stderr: String::from_utf8_lossy(&output.stderr).to_string(),
             exit_code: output.status.code().unwrap_or(-1),
             success: output.status.success(),
+            timed_out: false,
         })
     }
 }
@@ -321,6 +352,9 @@ impl CodeExecutor {
                 let expanded_dir = expand_tilde(dir);
                 cmd.current_dir(&expanded_dir);
             }
+            if let Some(sandbox) = &self.sandbox {
+                sandbox.apply(cmd.as_std_mut())?;
+            }
 
             cmd.spawn()?;
 
@@ -330,6 +364,7 @@ impl CodeExecutor {
                 stderr: String::new(),
                 exit_code: 0,
                 success: true,
+                timed_out: false,
             });
         }
 
@@ -339,6 +374,10 @@ impl CodeExecutor {
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
+        // Own process group, so a timeout or cancellation can kill everything the command started
+        #[cfg(unix)]
+        cmd.process_group(0);
+
         // Set working directory if provided
         if let Some(dir) = working_dir {
             debug!("Setting current_dir on command to: {}", dir);
@@ -354,6 +393,10 @@ impl CodeExecutor {
             );
             cmd.current_dir(&expanded_dir);
         }
+        if let Some(sandbox) = &self.sandbox {
+            debug!("Running in sandbox: {:?}", sandbox);
+            sandbox.apply(cmd.as_std_mut())?;
+        }
 
         debug!("About to spawn command...");
         let spawn_result = cmd.spawn();
@@ -367,6 +410,8 @@ impl CodeExecutor {
         };
         debug!("Command spawned successfully");
 
+        let mut process_group = ProcessGroupGuard(child.id());
+
         let stdout = child.stdout.take().unwrap();
         let stderr = child.stderr.take().unwrap();
 
@@ -378,6 +423,16 @@ impl CodeExecutor {
 
         let mut stdout_output = Vec::new();
         let mut stderr_output = Vec::new();
+        let mut stderr_open = true;
+
+        let deadline = async {
+            match self.timeout {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(deadline);
+        let mut timed_out = false;
 
         // Read output lines as they come
         loop {
@@ -395,29 +450,50 @@ impl CodeExecutor {
                         }
                     }
                 }
-                line = stderr_lines.next_line() => {
+                line = stderr_lines.next_line(), if stderr_open => {
                     match line {
                         Ok(Some(line)) => {
                             receiver.on_output_line(&line.to_string());
                             stderr_output.push(line);
                         }
-                        Ok(None) => {}, // stderr EOF, continue
+                        Ok(None) => stderr_open = false, // stderr EOF, continue
                         Err(e) => {
                             error!("Error reading stderr: {}", e);
                         }
                     }
                 }
-                else => break
+                _ = &mut deadline => {
+                    timed_out = true;
+                    break;
+                }
             }
         }
 
-        let status = child.wait().await?;
+        // stdout can close while the command keeps running, so the wait is bounded too
+        let status = if timed_out {
+            process_group.kill();
+            child.wait().await?
+        } else {
+            tokio::select! {
+                status = child.wait() => status?,
+                _ = &mut deadline => {
+                    timed_out = true;
+                    process_group.kill();
+                    child.wait().await?
+                }
+            }
+        };
+        process_group.disarm();
+        if timed_out {
+            debug!("Command timed out after {:?} and was killed", self.timeout);
+        }
 
         let result = ExecutionResult {
             stdout: stdout_output.join("\n"),
             stderr: stderr_output.join("\n"),
             exit_code: status.code().unwrap_or(-1),
-            success: status.success(),
+            success: status.success() && !timed_out,
+            timed_out,
         };
 
         debug!("========== execute_bash_streaming_in_dir END ==========");
@@ -433,6 +509,33 @@ impl CodeExecutor {
     }
 }
 
+/// Kills a command's process group when dropped, unless disarmed. Dropping happens when
+/// the command is cancelled mid-run (e.g. Ctrl+C); its own process group no longer
+/// receives the terminal's interrupt.
+pub(crate) struct ProcessGroupGuard(pub(crate) Option<u32>);
+
+impl ProcessGroupGuard {
+    pub(crate) fn kill(&mut self) {
+        #[cfg(unix)]
+        if let Some(pgid) = self.0 {
+            // SAFETY: kill has no memory safety requirements
+            unsafe {
+                libc::kill(-(pgid as libc::pid_t), libc::SIGKILL);
+            }
+        }
+    }
+
+    fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
 /// Check if rustup component llvm-tools-preview is installed
 pub fn is_llvm_tools_installed() -> Result<bool> {
     let output = Command::new("rustup")
@@ -0,0 +1,318 @@
+//! Linux namespace sandbox for shell commands.
+//!
+//! A sandboxed command runs in fresh user, mount and PID namespaces, and optionally a
+//! network namespace with nothing but loopback. The whole filesystem is remounted
+//! read-only except for the workspace (and any other writable paths), and `TMPDIR`
+//! points at a private scratch directory created for this g3 process. Only
+//! unprivileged user namespaces are needed, no setuid helper.
+
+use anyhow::Result;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Where a sandboxed command may write, and whether it may use the network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sandbox {
+    writable_paths: Vec<PathBuf>,
+    allow_network: bool,
+}
+
+impl Sandbox {
+    /// A sandbox in which only `workspace` is writable, with network access allowed
+    pub fn new(workspace: impl Into<PathBuf>) -> Self {
+        Self {
+            writable_paths: vec![workspace.into()],
+            allow_network: true,
+        }
+    }
+
+    /// Keep another directory writable
+    pub fn with_writable_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.writable_paths.push(path.into());
+        self
+    }
+
+    /// Allow or deny network access (loopback always works)
+    pub fn with_network(mut self, allow: bool) -> Self {
+        self.allow_network = allow;
+        self
+    }
+
+    pub fn writable_paths(&self) -> &[PathBuf] {
+        &self.writable_paths
+    }
+
+    pub fn allows_network(&self) -> bool {
+        self.allow_network
+    }
+
+    /// Set up `command` to run inside the sandbox when spawned.
+    ///
+    /// Fails if the platform has no namespace support; spawning fails if the namespaces
+    /// cannot be created (e.g. unprivileged user namespaces are disabled).
+    pub fn apply(&self, command: &mut Command) -> Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            linux::apply(self, command)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = command;
+            anyhow::bail!("Sandboxing needs Linux namespaces and is not available on this platform")
+        }
+    }
+
+    /// Whether sandboxed commands can run on this machine
+    pub fn is_supported() -> bool {
+        let mut command = Command::new("true");
+        // The probe shouldn't depend on the current directory still existing
+        command.current_dir("/");
+        Sandbox::new(std::env::temp_dir()).apply(&mut command).is_ok()
+            && command.status().map(|status| status.success()).unwrap_or(false)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::Sandbox;
+    use anyhow::{Context, Result};
+    use std::ffi::{CStr, CString};
+    use std::io;
+    use std::mem;
+    use std::os::raw::{c_int, c_uint};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use std::os::unix::process::CommandExt;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::ptr;
+    use std::sync::OnceLock;
+
+    /// Everything the child needs, prepared before spawning: between fork and exec
+    /// only async-signal-safe calls are allowed, so nothing may be allocated there.
+    struct Setup {
+        writable_paths: Vec<CString>,
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+        new_network: bool,
+    }
+
+    pub(super) fn apply(sandbox: &Sandbox, command: &mut Command) -> Result<()> {
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+
+        // Scratch directory, so tools that honour TMPDIR work with /tmp read-only
+        let scratch_dir = scratch_dir(uid)?;
+
+        let mut writable_paths = Vec::new();
+        let paths = sandbox.writable_paths.iter().map(PathBuf::as_path);
+        for path in paths.chain(std::iter::once(scratch_dir)) {
+            let path = path
+                .canonicalize()
+                .with_context(|| format!("Sandbox writable path {} is not accessible", path.display()))?;
+            writable_paths.push(CString::new(path.as_os_str().as_bytes())?);
+        }
+
+        let setup = Setup {
+            writable_paths,
+            uid_map: format!("{} {} 1\n", uid, uid).into_bytes(),
+            gid_map: format!("{} {} 1\n", gid, gid).into_bytes(),
+            new_network: !sandbox.allow_network,
+        };
+        command.env("TMPDIR", scratch_dir);
+        unsafe {
+            command.pre_exec(move || enter(&setup));
+        }
+        Ok(())
+    }
+
+    static SCRATCH_DIR: OnceLock<tempfile::TempDir> = OnceLock::new();
+
+    /// The scratch directory shared by this process's sandboxed commands. It gets a
+    /// random name and mode 0700 rather than a fixed path in the shared temp dir, which
+    /// another user could create first. Background commands outlive the tool call that
+    /// started them, so it is only removed when g3 exits.
+    fn scratch_dir(uid: libc::uid_t) -> Result<&'static Path> {
+        if let Some(dir) = SCRATCH_DIR.get() {
+            return Ok(dir.path());
+        }
+
+        let dir = tempfile::Builder::new()
+            .prefix("g3-sandbox-")
+            .permissions(std::fs::Permissions::from_mode(0o700))
+            .tempdir()
+            .context("Failed to create sandbox scratch dir")?;
+        let metadata = std::fs::symlink_metadata(dir.path())
+            .with_context(|| format!("Failed to inspect sandbox scratch dir {}", dir.path().display()))?;
+        if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o777 != 0o700 {
+            anyhow::bail!(
+                "Sandbox scratch dir {} is not a private directory owned by uid {}",
+                dir.path().display(),
+                uid
+            );
+        }
+        let mut created = false;
+        let dir = SCRATCH_DIR.get_or_init(|| {
+            created = true;
+            dir
+        });
+        if created {
+            unsafe { libc::atexit(remove_scratch_dir) };
+        }
+        Ok(dir.path())
+    }
+
+    /// Statics are never dropped, so the `TempDir` can't clean up after itself
+    extern "C" fn remove_scratch_dir() {
+        if let Some(dir) = SCRATCH_DIR.get() {
+            let _ = std::fs::remove_dir_all(dir.path());
+        }
+    }
+
+    /// Runs in the forked child before exec
+    fn enter(setup: &Setup) -> io::Result<()> {
+        let mut flags = libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWPID;
+        if setup.new_network {
+            flags |= libc::CLONE_NEWNET;
+        }
+        check(unsafe { libc::unshare(flags) })?;
+
+        // Keep our own uid/gid inside the namespace so file ownership looks normal
+        write_file(c"/proc/self/setgroups", b"deny")?;
+        write_file(c"/proc/self/uid_map", &setup.uid_map)?;
+        write_file(c"/proc/self/gid_map", &setup.gid_map)?;
+
+        if setup.new_network {
+            loopback_up()?;
+        }
+        remount_read_only(&setup.writable_paths)?;
+        reenter_working_dir()?;
+
+        // Only children join the new PID namespace: the command runs as its init in a
+        // forked child while this process waits and passes on the exit status.
+        match check(unsafe { libc::fork() })? {
+            0 => {
+                check(unsafe { libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) })?;
+                // A /proc for the new PID namespace, so ps and friends make sense (best effort)
+                unsafe {
+                    libc::mount(
+                        c"proc".as_ptr(),
+                        c"/proc".as_ptr(),
+                        c"proc".as_ptr(),
+                        libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+                        ptr::null(),
+                    );
+                }
+                Ok(())
+            }
+            child => wait_and_exit(child),
+        }
+    }
+
+    fn remount_read_only(writable_paths: &[CString]) -> io::Result<()> {
+        // Keep mount changes from propagating back to the host
+        check(unsafe {
+            libc::mount(ptr::null(), c"/".as_ptr(), ptr::null(), libc::MS_REC | libc::MS_PRIVATE, ptr::null())
+        })?;
+        for path in writable_paths {
+            check(unsafe {
+                libc::mount(path.as_ptr(), path.as_ptr(), ptr::null(), libc::MS_BIND | libc::MS_REC, ptr::null())
+            })?;
+        }
+        set_mount_attr(c"/", libc::AT_RECURSIVE as c_uint, libc::MOUNT_ATTR_RDONLY, 0)?;
+        for path in writable_paths {
+            set_mount_attr(path, 0, 0, libc::MOUNT_ATTR_RDONLY)?;
+        }
+        Ok(())
+    }
+
+    /// The working directory was entered before the writable bind mounts were made, so
+    /// it still refers to the read-only mount underneath; look it up again by path.
+    fn reenter_working_dir() -> io::Result<()> {
+        let mut buf = [0 as libc::c_char; libc::PATH_MAX as usize];
+        if unsafe { libc::getcwd(buf.as_mut_ptr(), buf.len()) }.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        check(unsafe { libc::chdir(buf.as_ptr()) }).map(|_| ())
+    }
+
+    fn set_mount_attr(path: &CStr, flags: c_uint, set: u64, clear: u64) -> io::Result<()> {
+        let mut attr: libc::mount_attr = unsafe { mem::zeroed() };
+        attr.attr_set = set;
+        attr.attr_clr = clear;
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_mount_setattr,
+                libc::AT_FDCWD,
+                path.as_ptr(),
+                flags,
+                &attr as *const libc::mount_attr,
+                mem::size_of::<libc::mount_attr>(),
+            )
+        };
+        check(result as c_int).map(|_| ())
+    }
+
+    /// A new network namespace starts with loopback down
+    fn loopback_up() -> io::Result<()> {
+        let socket = check(unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) })?;
+        let mut request: libc::ifreq = unsafe { mem::zeroed() };
+        request.ifr_name[0] = b'l' as libc::c_char;
+        request.ifr_name[1] = b'o' as libc::c_char;
+        let result = check(unsafe { libc::ioctl(socket, libc::SIOCGIFFLAGS as _, &mut request) }).and_then(|_| {
+            unsafe { request.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short };
+            check(unsafe { libc::ioctl(socket, libc::SIOCSIFFLAGS as _, &request) })
+        });
+        unsafe { libc::close(socket) };
+        result.map(|_| ())
+    }
+
+    fn wait_and_exit(child: libc::pid_t) -> ! {
+        // Let go of everything but stdio. In particular std's exec-error pipe must only
+        // be held by the command, or spawning would block until the command exits.
+        if unsafe { libc::syscall(libc::SYS_close_range, 3, c_uint::MAX, 0) } == -1 {
+            for fd in 3..1024 {
+                unsafe { libc::close(fd) };
+            }
+        }
+
+        let mut status: c_int = 0;
+        loop {
+            let result = unsafe { libc::waitpid(child, &mut status, 0) };
+            if result == child {
+                break;
+            }
+            if result == -1 && io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+                unsafe { libc::_exit(127) };
+            }
+        }
+
+        let code = if libc::WIFEXITED(status) {
+            libc::WEXITSTATUS(status)
+        } else if libc::WIFSIGNALED(status) {
+            128 + libc::WTERMSIG(status)
+        } else {
+            1
+        };
+        unsafe { libc::_exit(code) }
+    }
+
+    fn write_file(path: &CStr, contents: &[u8]) -> io::Result<()> {
+        let fd = check(unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) })?;
+        let written = unsafe { libc::write(fd, contents.as_ptr().cast(), contents.len()) };
+        let result = if written == contents.len() as isize {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        };
+        unsafe { libc::close(fd) };
+        result
+    }
+
+    fn check(result: c_int) -> io::Result<c_int> {
+        if result == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(result)
+        }
+    }
+}
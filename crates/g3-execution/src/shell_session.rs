@@ -0,0 +1,347 @@
+//! Long-lived bash session, so `cd`, `export` and `source` carry over between commands.
+//!
+//! Commands are written to a single `bash` process over stdin and followed by a
+//! sentinel: a marker on stdout carrying the exit status, the working directory and the
+//! exported environment, then a marker on stderr. Once both markers have been read, all
+//! of the command's output has been too.
+
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
+use tracing::debug;
+
+use crate::{ExecutionResult, OutputReceiver, ProcessGroupGuard, Sandbox};
+
+/// Variables bash maintains itself, left out of `env_changes`
+const VOLATILE_VARS: &[&str] = &["_", "OLDPWD", "PWD", "SHLVL"];
+
+/// A bash process that runs commands one after another in the same shell
+pub struct ShellSession {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    stderr: BufReader<ChildStderr>,
+    process_group: ProcessGroupGuard,
+    marker: String,
+    cwd: PathBuf,
+    initial_env: BTreeMap<String, String>,
+    env: BTreeMap<String, String>,
+    /// A command was started but its sentinel never read (cancelled, timed out or the
+    /// shell exited), so the session can't be trusted with another one
+    broken: bool,
+}
+
+impl ShellSession {
+    /// Start bash in `working_dir` (or the current directory), inside `sandbox` if given
+    pub async fn start(working_dir: Option<&Path>, sandbox: Option<&Sandbox>) -> Result<Self> {
+        let mut cmd = Command::new("bash");
+        cmd.arg("--noprofile")
+            .arg("--norc")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        cmd.process_group(0);
+        if let Some(dir) = working_dir {
+            cmd.current_dir(dir);
+        }
+        if let Some(sandbox) = sandbox {
+            sandbox.apply(cmd.as_std_mut())?;
+        }
+
+        let mut child = cmd.spawn().context("Failed to start bash")?;
+        let nonce = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let mut session = Self {
+            stdin: child.stdin.take().context("bash stdin not captured")?,
+            stdout: BufReader::new(child.stdout.take().context("bash stdout not captured")?),
+            stderr: BufReader::new(child.stderr.take().context("bash stderr not captured")?),
+            process_group: ProcessGroupGuard(child.id()),
+            child,
+            marker: format!("__G3_SESSION_{}_{:x}__", std::process::id(), nonce),
+            cwd: working_dir.map(Path::to_path_buf).unwrap_or_default(),
+            initial_env: BTreeMap::new(),
+            env: BTreeMap::new(),
+            broken: false,
+        };
+
+        // An empty command reports the starting directory and environment
+        let result = session.run(":", &NoOutput, Some(Duration::from_secs(30))).await?;
+        if !result.success {
+            bail!("bash session failed to start: {}", result.stderr);
+        }
+        session.initial_env = session.env.clone();
+        debug!("Started shell session in {}", session.cwd.display());
+        Ok(session)
+    }
+
+    /// Whether another command can run in this session. False once a command was
+    /// interrupted or timed out, or the shell exited (e.g. the command ran `exit`).
+    pub fn is_ready(&mut self) -> bool {
+        !self.broken && matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// The shell's working directory after the last command
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    /// Exported variables set, changed (`Some`) or unset (`None`) since the session started
+    pub fn env_changes(&self) -> Vec<(&str, Option<&str>)> {
+        let changed = self
+            .env
+            .iter()
+            .filter(|(name, value)| self.initial_env.get(*name) != Some(value))
+            .map(|(name, value)| (name.as_str(), Some(value.as_str())));
+        let unset = self
+            .initial_env
+            .keys()
+            .filter(|name| !self.env.contains_key(*name))
+            .map(|name| (name.as_str(), None));
+        changed
+            .chain(unset)
+            .filter(|(name, _)| !VOLATILE_VARS.contains(name))
+            .collect()
+    }
+
+    /// Run `command` in the session, streaming its output to `receiver`.
+    ///
+    /// On timeout the shell is killed along with everything it started, and the session
+    /// stops being ready.
+    pub async fn run<R: OutputReceiver>(
+        &mut self,
+        command: &str,
+        receiver: &R,
+        timeout: Option<Duration>,
+    ) -> Result<ExecutionResult> {
+        if !self.is_ready() {
+            bail!("The shell session is no longer usable; start a new one");
+        }
+
+        // eval keeps a syntax error from taking the whole shell down, and stdin stays
+        // reserved for the session's own input
+        let script = format!(
+            "eval {} < /dev/null\n\
+             printf '%s\\0%d\\0%s\\0' {marker} \"$?\" \"$PWD\"\n\
+             for __g3_var in $(compgen -e); do printf '%s=%s\\0' \"$__g3_var\" \"${{!__g3_var}}\"; done; unset __g3_var\n\
+             printf '%s_END\\n' {marker}\n\
+             printf '%s\\n' {marker} >&2\n",
+            quote(command),
+            marker = self.marker,
+        );
+        self.broken = true;
+        self.stdin.write_all(script.as_bytes()).await?;
+        self.stdin.flush().await?;
+
+        let deadline = async {
+            match timeout {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(deadline);
+
+        let marker = self.marker.as_bytes();
+        let end_marker = format!("{}_END\n", self.marker).into_bytes();
+        let mut stdout_lines = Vec::new();
+        let mut stderr_lines = Vec::new();
+        let mut trailer: Option<Vec<u8>> = None;
+        let mut stderr_done = false;
+        let mut stdout_buf = Vec::new();
+        let mut stderr_buf = Vec::new();
+
+        let finished = loop {
+            let stdout_done = trailer.as_ref().is_some_and(|t| t.ends_with(&end_marker));
+            if stdout_done && stderr_done {
+                break true;
+            }
+            tokio::select! {
+                read = self.stdout.read_until(b'\n', &mut stdout_buf), if !stdout_done => {
+                    if read? == 0 {
+                        break false;
+                    }
+                    if let Some(trailer) = trailer.as_mut() {
+                        trailer.append(&mut stdout_buf);
+                    } else if let Some(pos) = find(&stdout_buf, marker) {
+                        // Output that didn't end in a newline shares a line with the marker
+                        if pos > 0 {
+                            push_line(&stdout_buf[..pos], receiver, &mut stdout_lines);
+                        }
+                        trailer = Some(stdout_buf.split_off(pos));
+                        stdout_buf.clear();
+                    } else {
+                        push_line(&stdout_buf, receiver, &mut stdout_lines);
+                        stdout_buf.clear();
+                    }
+                }
+                read = self.stderr.read_until(b'\n', &mut stderr_buf), if !stderr_done => {
+                    if read? == 0 {
+                        break false;
+                    }
+                    match find(&stderr_buf, marker) {
+                        Some(pos) => {
+                            if pos > 0 {
+                                push_line(&stderr_buf[..pos], receiver, &mut stderr_lines);
+                            }
+                            stderr_done = true;
+                        }
+                        None => push_line(&stderr_buf, receiver, &mut stderr_lines),
+                    }
+                    stderr_buf.clear();
+                }
+                _ = &mut deadline => {
+                    debug!("Shell session command timed out after {:?}; killing the session", timeout);
+                    self.process_group.kill();
+                    let _ = self.child.wait().await;
+                    return Ok(ExecutionResult {
+                        stdout: stdout_lines.join("\n"),
+                        stderr: stderr_lines.join("\n"),
+                        exit_code: -1,
+                        success: false,
+                        timed_out: true,
+                    });
+                }
+            }
+        };
+
+        if !finished {
+            // The command ended the shell (e.g. `exit 3`); report how it went
+            let status = self.child.wait().await?;
+            return Ok(ExecutionResult {
+                stdout: stdout_lines.join("\n"),
+                stderr: stderr_lines.join("\n"),
+                exit_code: status.code().unwrap_or(-1),
+                success: false,
+                timed_out: false,
+            });
+        }
+
+        let trailer = trailer.unwrap_or_default();
+        let trailer = &trailer[marker.len() + 1..trailer.len() - end_marker.len()];
+        let mut fields = trailer
+            .split(|&b| b == 0)
+            .map(|field| String::from_utf8_lossy(field).into_owned());
+        let exit_code = fields.next().and_then(|code| code.parse().ok()).unwrap_or(-1);
+        if let Some(cwd) = fields.next() {
+            self.cwd = PathBuf::from(cwd);
+        }
+        self.env = fields
+            .filter_map(|entry| {
+                let (name, value) = entry.split_once('=')?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect();
+        self.broken = false;
+
+        Ok(ExecutionResult {
+            stdout: stdout_lines.join("\n"),
+            stderr: stderr_lines.join("\n"),
+            exit_code,
+            success: exit_code == 0,
+            timed_out: false,
+        })
+    }
+}
+
+impl Drop for ShellSession {
+    fn drop(&mut self) {
+        // Take down anything the session left running in the background
+        self.process_group.kill();
+    }
+}
+
+/// Receiver for the session's own bookkeeping commands
+struct NoOutput;
+
+impl OutputReceiver for NoOutput {
+    fn on_output_line(&self, _line: &str) {}
+}
+
+fn push_line<R: OutputReceiver>(line: &[u8], receiver: &R, lines: &mut Vec<String>) {
+    let line = String::from_utf8_lossy(line);
+    let line = line.strip_suffix('\n').unwrap_or(&line);
+    receiver.on_output_line(line);
+    lines.push(line.to_string());
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Single-quote `text` for bash
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Collect(std::sync::Mutex<Vec<String>>);
+
+    impl OutputReceiver for Collect {
+        fn on_output_line(&self, line: &str) {
+            self.0.lock().unwrap().push(line.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_state_persists_between_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut session = ShellSession::start(Some(dir.path()), None).await.unwrap();
+        let receiver = Collect(Default::default());
+
+        session.run("mkdir sub && cd sub", &receiver, None).await.unwrap();
+        session.run("export G3_TEST_VAR='a b'; unset HOME", &receiver, None).await.unwrap();
+        let result = session.run("pwd; echo \"$G3_TEST_VAR\"", &receiver, None).await.unwrap();
+
+        let sub = dir.path().canonicalize().unwrap().join("sub");
+        assert_eq!(result.stdout, format!("{}\na b", sub.display()));
+        assert_eq!(session.cwd(), sub);
+        assert_eq!(
+            session.env_changes(),
+            vec![("G3_TEST_VAR", Some("a b")), ("HOME", None)]
+        );
+        assert_eq!(*receiver.0.lock().unwrap(), vec![sub.display().to_string(), "a b".into()]);
+    }
+
+    #[tokio::test]
+    async fn test_exit_codes_stderr_and_partial_lines() {
+        let mut session = ShellSession::start(None, None).await.unwrap();
+
+        let result = session.run("printf 'no newline'; echo oops >&2; false", &NoOutput, None).await.unwrap();
+        assert_eq!(result.stdout, "no newline");
+        assert_eq!(result.stderr, "oops");
+        assert_eq!(result.exit_code, 1);
+        assert!(!result.success);
+
+        // A syntax error fails the command, not the session
+        let result = session.run("if then", &NoOutput, None).await.unwrap();
+        assert_eq!(result.exit_code, 2);
+        assert!(session.is_ready());
+
+        let result = session.run("exit 3", &NoOutput, None).await.unwrap();
+        assert_eq!(result.exit_code, 3);
+        assert!(!session.is_ready());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_breaks_the_session() {
+        let mut session = ShellSession::start(None, None).await.unwrap();
+        let result = session
+            .run("echo before; sleep 30", &NoOutput, Some(Duration::from_millis(500)))
+            .await
+            .unwrap();
+        assert!(result.timed_out);
+        assert_eq!(result.stdout, "before");
+        assert!(!session.is_ready());
+        assert!(session.run("true", &NoOutput, None).await.is_err());
+    }
+}
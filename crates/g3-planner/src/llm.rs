@@ -9,7 +9,7 @@ use anyhow::{anyhow, Context, Result};
 use std::io::Write;
 use g3_config::Config;
 use g3_core::project::Project;
-use g3_core::Agent;
+use g3_core::{Agent, AgentRole};
 use g3_core::error_handling::{classify_error, ErrorType};
 use g3_providers::{CompletionRequest, LLMProvider, Message, MessageRole};
 
@@ -348,6 +348,7 @@ pub async fn call_refinement_llm_with_tools(
         false, // not quiet
     )
     .await?;
+    agent.set_agent_role(AgentRole::Planner);
     
     // Execute the refinement task
     // The agent will have access to tools and execute them
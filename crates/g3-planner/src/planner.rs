@@ -577,7 +577,7 @@ pub async fn run_coach_player_loop(
     use g3_core::project::Project;
     use g3_core::retry::{execute_with_retry, RetryConfig, RetryResult};
     use g3_core::feedback_extraction::{extract_coach_feedback, FeedbackExtractionConfig};
-    use g3_core::Agent;
+    use g3_core::{Agent, AgentRole, CostLedger};
     
     let max_turns = planner_config.max_turns;
     
@@ -599,6 +599,9 @@ pub async fn run_coach_player_loop(
     
     let mut turn = 1;
     let mut coach_feedback = String::new();
+    // Player and coach agents are recreated every turn; they share one ledger for the run
+    let cost_ledger = std::sync::Arc::new(std::sync::Mutex::new(CostLedger::new()));
+    let run_started = std::time::Instant::now();
     
     while turn <= max_turns {
         print_msg(&format!("\n=== Turn {}/{} ===", turn, max_turns));
@@ -614,6 +617,12 @@ pub async fn run_coach_player_loop(
             None,
             planner_config.quiet,
         ).await?;
+        player_agent.set_cost_ledger(cost_ledger.clone());
+        player_agent.set_budget_started(run_started);
+        if let Err(e) = player_agent.check_budget() {
+            print_msg(&format!("🛑 {}", e));
+            return Ok(());
+        }
         
         let player_prompt = if coach_feedback.is_empty() || turn == 1 {
             format!(
@@ -672,6 +681,13 @@ pub async fn run_coach_player_loop(
             None,
             planner_config.quiet,
         ).await?;
+        coach_agent.set_agent_role(AgentRole::Coach);
+        coach_agent.set_cost_ledger(cost_ledger.clone());
+        coach_agent.set_budget_started(run_started);
+        if let Err(e) = coach_agent.check_budget() {
+            print_msg(&format!("🛑 {}", e));
+            return Ok(());
+        }
         
         let coach_prompt = format!(
             "You are G3 in coach mode. Review the implementation against these requirements:\n\n{}\n\nCheck:\n1. Are requirements implemented correctly?\n2. Does the code compile?\n3. What's missing?\n\nProvide your feedback as a summary.\nIf implementation is COMPLETE, include 'IMPLEMENTATION_APPROVED' in your feedback.\nOtherwise, provide specific feedback for the player to fix.",
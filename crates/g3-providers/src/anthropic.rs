@@ -459,12 +459,7 @@ impl AnthropicProvider {
                                             // Extract usage data from message_start event
                                             if let Some(message) = event.message {
                                                 if let Some(usage) = message.usage {
-                                                    accumulated_usage = Some(Usage {
-                                                        prompt_tokens: usage.input_tokens,
-                                                        completion_tokens: usage.output_tokens,
-                                                        total_tokens: usage.input_tokens
-                                                            + usage.output_tokens,
-                                                    });
+                                                    accumulated_usage = Some(usage.to_usage());
                                                     debug!(
                                                         "Captured usage from message_start: {:?}",
                                                         accumulated_usage
@@ -618,7 +613,15 @@ impl AnthropicProvider {
                                                     stop_reason = Some(reason.clone());
                                                 }
                                             }
-                                            // Usage is also in message_delta but we get it from message_start
+                                            // message_start only carries a placeholder output count;
+                                            // the final count arrives here
+                                            if let (Some(delta_usage), Some(usage)) =
+                                                (&event.usage, accumulated_usage.as_mut())
+                                            {
+                                                usage.completion_tokens = delta_usage.output_tokens;
+                                                usage.total_tokens =
+                                                    usage.prompt_tokens + usage.completion_tokens;
+                                            }
                                         }
                                         "message_stop" => {
                                             debug!("Received message stop event");
@@ -734,12 +737,7 @@ impl LLMProvider for AnthropicProvider {
             .collect::<Vec<_>>()
             .join("");
 
-        let usage = Usage {
-            prompt_tokens: anthropic_response.usage.input_tokens,
-            completion_tokens: anthropic_response.usage.output_tokens,
-            total_tokens: anthropic_response.usage.input_tokens
-                + anthropic_response.usage.output_tokens,
-        };
+        let usage = anthropic_response.usage.to_usage();
 
         debug!(
             "Anthropic completion successful: {} tokens generated",
@@ -831,6 +829,11 @@ impl LLMProvider for AnthropicProvider {
         true
     }
 
+    fn supports_vision(&self) -> bool {
+        // Claude 3 and later accept image content blocks
+        true
+    }
+
     fn supports_cache_control(&self) -> bool {
         // Anthropic supports cache control
         true
@@ -943,8 +946,25 @@ struct AnthropicResponse {
 
 #[derive(Debug, Deserialize)]
 struct AnthropicUsage {
+    #[serde(default)]
     input_tokens: u32,
     output_tokens: u32,
+    #[serde(default)]
+    cache_creation_input_tokens: u32,
+    #[serde(default)]
+    cache_read_input_tokens: u32,
+}
+
+impl AnthropicUsage {
+    fn to_usage(&self) -> Usage {
+        Usage {
+            prompt_tokens: self.input_tokens,
+            completion_tokens: self.output_tokens,
+            total_tokens: self.input_tokens + self.output_tokens,
+            cache_creation_tokens: self.cache_creation_input_tokens,
+            cache_read_tokens: self.cache_read_input_tokens,
+        }
+    }
 }
 
 // Streaming response structures
@@ -961,6 +981,9 @@ struct AnthropicStreamEvent {
     content_block: Option<AnthropicContent>,
     #[serde(default)]
     message: Option<AnthropicStreamMessage>,
+    /// Final usage, sent with message_delta
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -1255,4 +1278,25 @@ mod tests {
         assert_eq!(text_content.len(), 1);
         assert_eq!(text_content[0], "Here is my response.");
     }
+
+    #[test]
+    fn test_usage_includes_cache_tokens() {
+        let usage: AnthropicUsage = serde_json::from_str(
+            r#"{"input_tokens": 12, "output_tokens": 40, "cache_creation_input_tokens": 2000, "cache_read_input_tokens": 9000}"#,
+        )
+        .unwrap();
+        let usage = usage.to_usage();
+        assert_eq!(usage.prompt_tokens, 12);
+        assert_eq!(usage.completion_tokens, 40);
+        assert_eq!(usage.total_tokens, 52);
+        assert_eq!(usage.cache_creation_tokens, 2000);
+        assert_eq!(usage.cache_read_tokens, 9000);
+
+        // message_delta only reports the output count
+        let delta: AnthropicStreamEvent = serde_json::from_str(
+            r#"{"type": "message_delta", "delta": {"stop_reason": "end_turn"}, "usage": {"output_tokens": 321}}"#,
+        )
+        .unwrap();
+        assert_eq!(delta.usage.unwrap().output_tokens, 321);
+    }
 }
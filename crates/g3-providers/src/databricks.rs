@@ -355,8 +355,9 @@ impl DatabricksProvider {
                 MessageRole::Assistant => "assistant",
             };
 
-            // Always use simple string format (Databricks doesn't support cache_control)
-            let content = serde_json::Value::String(message.content.clone());
+            // No cache_control blocks (Databricks doesn't support them); images become
+            // OpenAI-style content parts for models that can see them
+            let content = crate::openai::message_content(message, self.supports_vision());
 
             databricks_messages.push(DatabricksMessage {
                 role: role.to_string(),
@@ -763,6 +764,7 @@ impl LLMProvider for DatabricksProvider {
             prompt_tokens: databricks_response.usage.prompt_tokens,
             completion_tokens: databricks_response.usage.completion_tokens,
             total_tokens: databricks_response.usage.total_tokens,
+            ..Default::default()
         };
 
         debug!(
@@ -910,6 +912,11 @@ impl LLMProvider for DatabricksProvider {
         true
     }
 
+    fn supports_vision(&self) -> bool {
+        // Databricks serves Claude, GPT, Gemini and Llama models under one API
+        crate::model_supports_vision(&self.model)
+    }
+
     fn supports_cache_control(&self) -> bool {
         false
     }
@@ -1055,6 +1062,37 @@ mod tests {
         assert_eq!(databricks_messages[2].role, "assistant");
     }
 
+    #[test]
+    fn test_message_conversion_with_images() {
+        let provider = |model: &str| {
+            DatabricksProvider::from_token(
+                "https://test.databricks.com".to_string(),
+                "test-token".to_string(),
+                model.to_string(),
+                None,
+                None,
+            )
+            .unwrap()
+        };
+        let mut message = Message::new(MessageRole::User, "Describe this".to_string());
+        message
+            .images
+            .push(crate::ImageContent::new("image/jpeg", "/9j/4AAQ".to_string()));
+
+        let claude = provider("databricks-claude-sonnet-4");
+        assert!(claude.supports_vision());
+        let converted = claude.convert_messages(&[message.clone()]).unwrap();
+        let parts = converted[0].content.as_ref().unwrap().as_array().unwrap();
+        assert_eq!(parts[0]["text"], "Describe this");
+        assert_eq!(parts[1]["type"], "image_url");
+        assert_eq!(parts[1]["image_url"]["url"], "data:image/jpeg;base64,/9j/4AAQ");
+
+        let llama = provider("databricks-meta-llama-3-3-70b-instruct");
+        assert!(!llama.supports_vision());
+        let converted = llama.convert_messages(&[message]).unwrap();
+        assert_eq!(converted[0].content.as_ref().unwrap(), "Describe this");
+    }
+
     #[test]
     fn test_request_body_creation() {
         let provider = DatabricksProvider::from_token(
@@ -531,6 +531,7 @@ impl LLMProvider for EmbeddedProvider {
                 prompt_tokens,
                 completion_tokens,
                 total_tokens: prompt_tokens + completion_tokens,
+                ..Default::default()
             },
             model: self.model_name.clone(),
         })
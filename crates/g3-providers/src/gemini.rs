@@ -0,0 +1,699 @@
+//! Google Gemini provider implementation for the g3-providers crate.
+//!
+//! Uses the Generative Language REST API: `models/{model}:generateContent` for completions and
+//! `models/{model}:streamGenerateContent?alt=sse` for streaming. g3 `Tool`s are sent as function
+//! declarations and message images as inline data parts.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::StreamExt;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, error};
+
+use crate::{
+    streaming::{decode_utf8_streaming, make_final_chunk_with_reason, make_text_chunk},
+    CompletionChunk, CompletionRequest, CompletionResponse, CompletionStream, LLMProvider, Message,
+    MessageRole, Tool, ToolCall, Usage,
+};
+
+const GEMINI_API_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// JSON Schema keywords that Gemini's OpenAPI-subset schema rejects
+const UNSUPPORTED_SCHEMA_KEYS: &[&str] = &["$schema", "additionalProperties", "$id", "$ref"];
+
+#[derive(Clone)]
+pub struct GeminiProvider {
+    client: Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+    max_tokens: Option<u32>,
+    temperature: Option<f32>,
+    name: String,
+}
+
+impl GeminiProvider {
+    pub fn new(
+        api_key: String,
+        model: Option<String>,
+        base_url: Option<String>,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+    ) -> Result<Self> {
+        Self::new_with_name(
+            "gemini".to_string(),
+            api_key,
+            model,
+            base_url,
+            max_tokens,
+            temperature,
+        )
+    }
+
+    pub fn new_with_name(
+        name: String,
+        api_key: String,
+        model: Option<String>,
+        base_url: Option<String>,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+    ) -> Result<Self> {
+        Ok(Self {
+            client: Client::new(),
+            api_key,
+            model: model.unwrap_or_else(|| "gemini-2.5-pro".to_string()),
+            base_url: base_url
+                .unwrap_or_else(|| GEMINI_API_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            max_tokens,
+            temperature,
+            name,
+        })
+    }
+
+    fn endpoint(&self, streaming: bool) -> String {
+        if streaming {
+            format!(
+                "{}/models/{}:streamGenerateContent?alt=sse",
+                self.base_url, self.model
+            )
+        } else {
+            format!("{}/models/{}:generateContent", self.base_url, self.model)
+        }
+    }
+
+    fn create_request_body(
+        &self,
+        messages: &[Message],
+        tools: Option<&[Tool]>,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+    ) -> Result<serde_json::Value> {
+        let (system, contents) = convert_messages(messages);
+
+        if contents.is_empty() {
+            return Err(anyhow::anyhow!(
+                "At least one user or assistant message is required"
+            ));
+        }
+
+        let mut generation_config = json!({});
+        if let Some(max_tokens) = max_tokens.or(self.max_tokens) {
+            generation_config["maxOutputTokens"] = json!(max_tokens);
+        }
+        if let Some(temperature) = temperature.or(self.temperature) {
+            generation_config["temperature"] = json!(temperature);
+        }
+
+        let mut body = json!({
+            "contents": contents,
+            "generationConfig": generation_config,
+        });
+
+        if let Some(system) = system {
+            body["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+        }
+
+        if let Some(tools) = tools {
+            if !tools.is_empty() {
+                body["tools"] = json!([{ "functionDeclarations": convert_tools(tools) }]);
+            }
+        }
+
+        Ok(body)
+    }
+
+    async fn post(&self, streaming: bool, body: &serde_json::Value) -> Result<reqwest::Response> {
+        let response = self
+            .client
+            .post(self.endpoint(streaming))
+            .header("x-goog-api-key", &self.api_key)
+            .json(body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(anyhow::anyhow!(
+                "Gemini API error {}: {}",
+                status,
+                error_text
+            ));
+        }
+
+        Ok(response)
+    }
+
+    async fn parse_streaming_response(
+        &self,
+        mut stream: impl futures_util::Stream<Item = reqwest::Result<Bytes>> + Unpin,
+        tx: mpsc::Sender<Result<CompletionChunk>>,
+    ) -> Option<Usage> {
+        let mut byte_buffer: Vec<u8> = Vec::new();
+        let mut buffer = String::new();
+        let mut tool_calls: Vec<ToolCall> = Vec::new();
+        let mut usage: Option<Usage> = None;
+        let mut stop_reason: Option<String> = None;
+
+        while let Some(chunk_result) = stream.next().await {
+            let chunk = match chunk_result {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    error!("Stream error: {}", e);
+                    let _ = tx.send(Err(anyhow::anyhow!("Stream error: {}", e))).await;
+                    return usage;
+                }
+            };
+
+            byte_buffer.extend_from_slice(&chunk);
+            if let Some(decoded) = decode_utf8_streaming(&mut byte_buffer) {
+                buffer.push_str(&decoded);
+            }
+
+            while let Some(line_end) = buffer.find('\n') {
+                let line = buffer[..line_end].trim().to_string();
+                buffer.drain(..line_end + 1);
+
+                let Some(data) = line.strip_prefix("data:") else {
+                    continue;
+                };
+
+                let response = match serde_json::from_str::<GeminiResponse>(data.trim()) {
+                    Ok(response) => response,
+                    Err(e) => {
+                        debug!("Failed to parse stream chunk: {} - Data: {}", e, data);
+                        continue;
+                    }
+                };
+
+                if let Some(error) = response.error {
+                    let _ = tx
+                        .send(Err(anyhow::anyhow!("Gemini API error: {}", error.message)))
+                        .await;
+                    return usage;
+                }
+
+                if let Some(candidate) = response.candidates.first() {
+                    let text = candidate.text();
+                    if !text.is_empty() && tx.send(Ok(make_text_chunk(text))).await.is_err() {
+                        debug!("Receiver dropped, stopping stream");
+                        return usage;
+                    }
+
+                    // Function calls arrive whole, never split across events
+                    for function_call in candidate.function_calls() {
+                        tool_calls.push(function_call.to_tool_call(tool_calls.len()));
+                    }
+
+                    if candidate.finish_reason.is_some() {
+                        stop_reason = candidate.stop_reason();
+                    }
+                }
+
+                if let Some(metadata) = &response.usage_metadata {
+                    usage = Some(metadata.to_usage());
+                }
+            }
+        }
+
+        let final_chunk = make_final_chunk_with_reason(tool_calls, usage.clone(), stop_reason);
+        let _ = tx.send(Ok(final_chunk)).await;
+
+        usage
+    }
+}
+
+#[async_trait]
+impl LLMProvider for GeminiProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        debug!(
+            "Processing Gemini completion request with {} messages",
+            request.messages.len()
+        );
+
+        let body = self.create_request_body(
+            &request.messages,
+            request.tools.as_deref(),
+            request.max_tokens,
+            request.temperature,
+        )?;
+
+        debug!("Sending request to Gemini API: model={}", self.model);
+
+        let response: GeminiResponse = self.post(false, &body).await?.json().await?;
+
+        if let Some(error) = response.error {
+            return Err(anyhow::anyhow!("Gemini API error: {}", error.message));
+        }
+
+        let content = response
+            .candidates
+            .first()
+            .map(|candidate| candidate.text())
+            .unwrap_or_default();
+
+        let usage = response
+            .usage_metadata
+            .as_ref()
+            .map(|metadata| metadata.to_usage())
+            .unwrap_or(Usage {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+                ..Default::default()
+            });
+
+        debug!(
+            "Gemini completion successful: {} tokens generated",
+            usage.completion_tokens
+        );
+
+        Ok(CompletionResponse {
+            content,
+            usage,
+            model: self.model.clone(),
+        })
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        debug!(
+            "Processing Gemini streaming request with {} messages",
+            request.messages.len()
+        );
+
+        let body = self.create_request_body(
+            &request.messages,
+            request.tools.as_deref(),
+            request.max_tokens,
+            request.temperature,
+        )?;
+
+        debug!(
+            "Sending streaming request to Gemini API: model={}",
+            self.model
+        );
+
+        let stream = self.post(true, &body).await?.bytes_stream();
+        let (tx, rx) = mpsc::channel(100);
+
+        let provider = self.clone();
+        tokio::spawn(async move {
+            let usage = provider.parse_streaming_response(stream, tx).await;
+            if let Some(usage) = usage {
+                debug!(
+                    "Stream completed with usage - prompt: {}, completion: {}, total: {}",
+                    usage.prompt_tokens, usage.completion_tokens, usage.total_tokens
+                );
+            }
+        });
+
+        Ok(ReceiverStream::new(rx))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn has_native_tool_calling(&self) -> bool {
+        true
+    }
+
+    fn supports_vision(&self) -> bool {
+        true
+    }
+
+    fn max_tokens(&self) -> u32 {
+        self.max_tokens.unwrap_or(16000)
+    }
+
+    fn temperature(&self) -> f32 {
+        self.temperature.unwrap_or(0.1)
+    }
+}
+
+/// Convert g3 messages into Gemini `contents`, returning the system instruction separately.
+///
+/// Gemini expects alternating `user`/`model` turns, so consecutive messages with the same
+/// role are merged into one content entry.
+fn convert_messages(messages: &[Message]) -> (Option<String>, Vec<serde_json::Value>) {
+    let mut system: Option<String> = None;
+    let mut contents: Vec<serde_json::Value> = Vec::new();
+
+    for message in messages {
+        let role = match message.role {
+            MessageRole::System => {
+                system = Some(match system {
+                    Some(existing) => format!("{}\n\n{}", existing, message.content),
+                    None => message.content.clone(),
+                });
+                continue;
+            }
+            MessageRole::User => "user",
+            MessageRole::Assistant => "model",
+        };
+
+        let mut parts: Vec<serde_json::Value> = message
+            .images
+            .iter()
+            .map(|image| {
+                json!({
+                    "inline_data": {
+                        "mime_type": image.media_type,
+                        "data": image.data,
+                    }
+                })
+            })
+            .collect();
+        if !message.content.is_empty() {
+            parts.push(json!({ "text": message.content }));
+        }
+        if parts.is_empty() {
+            continue;
+        }
+
+        match contents.last_mut() {
+            Some(last) if last["role"] == role => {
+                last["parts"].as_array_mut().unwrap().extend(parts);
+            }
+            _ => contents.push(json!({ "role": role, "parts": parts })),
+        }
+    }
+
+    (system, contents)
+}
+
+fn convert_tools(tools: &[Tool]) -> Vec<serde_json::Value> {
+    tools
+        .iter()
+        .map(|tool| {
+            let mut parameters = tool.input_schema.clone();
+            sanitize_schema(&mut parameters);
+            json!({
+                "name": tool.name,
+                "description": tool.description,
+                "parameters": parameters,
+            })
+        })
+        .collect()
+}
+
+/// Strip JSON Schema keywords that Gemini rejects, recursively
+fn sanitize_schema(schema: &mut serde_json::Value) {
+    match schema {
+        serde_json::Value::Object(map) => {
+            for key in UNSUPPORTED_SCHEMA_KEYS {
+                map.remove(*key);
+            }
+            for value in map.values_mut() {
+                sanitize_schema(value);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                sanitize_schema(item);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Gemini API response structures
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiResponse {
+    #[serde(default)]
+    candidates: Vec<GeminiCandidate>,
+    #[serde(default)]
+    usage_metadata: Option<GeminiUsageMetadata>,
+    #[serde(default)]
+    error: Option<GeminiError>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCandidate {
+    #[serde(default)]
+    content: Option<GeminiContent>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+impl GeminiCandidate {
+    fn parts(&self) -> impl Iterator<Item = &GeminiPart> {
+        self.content.iter().flat_map(|content| content.parts.iter())
+    }
+
+    /// Visible text of this candidate (thought summaries are skipped)
+    fn text(&self) -> String {
+        self.parts()
+            .filter(|part| !part.thought)
+            .filter_map(|part| part.text.as_deref())
+            .collect()
+    }
+
+    fn function_calls(&self) -> impl Iterator<Item = &GeminiFunctionCall> {
+        self.parts().filter_map(|part| part.function_call.as_ref())
+    }
+
+    /// Map Gemini's `finishReason` onto the stop reasons g3-core understands
+    fn stop_reason(&self) -> Option<String> {
+        match self.finish_reason.as_deref() {
+            Some("MAX_TOKENS") => Some("max_tokens".to_string()),
+            Some("STOP") => Some("end_turn".to_string()),
+            other => other.map(|reason| reason.to_lowercase()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiContent {
+    #[serde(default)]
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiPart {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    thought: bool,
+    #[serde(default)]
+    function_call: Option<GeminiFunctionCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiFunctionCall {
+    name: String,
+    #[serde(default)]
+    args: serde_json::Value,
+}
+
+impl GeminiFunctionCall {
+    /// Gemini does not assign call ids, so number them in response order
+    fn to_tool_call(&self, index: usize) -> ToolCall {
+        ToolCall {
+            id: format!("gemini_call_{}", index),
+            tool: self.name.clone(),
+            args: self.args.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiUsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+    #[serde(default)]
+    total_token_count: u32,
+}
+
+impl GeminiUsageMetadata {
+    fn to_usage(&self) -> Usage {
+        Usage {
+            prompt_tokens: self.prompt_token_count,
+            completion_tokens: self.candidates_token_count,
+            total_tokens: self.total_token_count,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiError {
+    message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImageContent;
+
+    fn provider() -> GeminiProvider {
+        GeminiProvider::new(
+            "test-key".to_string(),
+            Some("gemini-2.5-flash".to_string()),
+            None,
+            Some(2048),
+            Some(0.3),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_message_conversion() {
+        let mut user = Message::new(MessageRole::User, "Describe this".to_string());
+        user.images.push(ImageContent::new("image/png", "aGVsbG8=".to_string()));
+
+        let messages = vec![
+            Message::new(MessageRole::System, "You are helpful.".to_string()),
+            user,
+            Message::new(MessageRole::User, "Tool result: ok".to_string()),
+            Message::new(MessageRole::Assistant, "It is a cat.".to_string()),
+        ];
+
+        let (system, contents) = convert_messages(&messages);
+
+        assert_eq!(system.as_deref(), Some("You are helpful."));
+        assert_eq!(contents.len(), 2, "consecutive user messages are merged");
+        assert_eq!(contents[0]["role"], "user");
+        assert_eq!(contents[0]["parts"][0]["inline_data"]["mime_type"], "image/png");
+        assert_eq!(contents[0]["parts"][0]["inline_data"]["data"], "aGVsbG8=");
+        assert_eq!(contents[0]["parts"][1]["text"], "Describe this");
+        assert_eq!(contents[0]["parts"][2]["text"], "Tool result: ok");
+        assert_eq!(contents[1]["role"], "model");
+    }
+
+    #[test]
+    fn test_request_body_creation() {
+        let provider = provider();
+        let messages = vec![
+            Message::new(MessageRole::System, "Be brief.".to_string()),
+            Message::new(MessageRole::User, "Hello".to_string()),
+        ];
+
+        let body = provider
+            .create_request_body(&messages, None, None, None)
+            .unwrap();
+
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Be brief.");
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 2048);
+        assert!((body["generationConfig"]["temperature"].as_f64().unwrap() - 0.3).abs() < 1e-6);
+        assert!(body.get("tools").is_none());
+
+        let only_system = vec![Message::new(MessageRole::System, "Be brief.".to_string())];
+        assert!(provider
+            .create_request_body(&only_system, None, None, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_tool_conversion() {
+        let tools = vec![Tool {
+            name: "read_file".to_string(),
+            description: "Read a file".to_string(),
+            input_schema: serde_json::json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "file_path": { "type": "string" },
+                    "options": {
+                        "type": "object",
+                        "additionalProperties": false,
+                        "properties": { "start": { "type": "integer" } }
+                    }
+                },
+                "required": ["file_path"]
+            }),
+        }];
+
+        let declarations = convert_tools(&tools);
+
+        assert_eq!(declarations[0]["name"], "read_file");
+        let parameters = &declarations[0]["parameters"];
+        assert!(parameters.get("$schema").is_none());
+        assert!(parameters.get("additionalProperties").is_none());
+        assert!(parameters["properties"]["options"]
+            .get("additionalProperties")
+            .is_none());
+        assert_eq!(parameters["required"][0], "file_path");
+    }
+
+    #[test]
+    fn test_endpoints() {
+        let provider = provider();
+        assert_eq!(
+            provider.endpoint(false),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash:generateContent"
+        );
+        assert!(provider
+            .endpoint(true)
+            .ends_with("/models/gemini-2.5-flash:streamGenerateContent?alt=sse"));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_text_and_function_calls() {
+        let events = [
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"thinking...\",\"thought\":true},{\"text\":\"Let me \"}]}}]}\r\n\r\n",
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"look.\"}]}}]}\r\n\r\n",
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"functionCall\":{\"name\":\"shell\",\"args\":{\"command\":\"ls\"}}}]},\"finishReason\":\"STOP\"}],",
+            "\"usageMetadata\":{\"promptTokenCount\":50,\"candidatesTokenCount\":7,\"totalTokenCount\":57}}\r\n\r\n",
+        ];
+        let stream = futures_util::stream::iter(
+            events
+                .iter()
+                .map(|event| Ok(Bytes::from(event.to_string())))
+                .collect::<Vec<reqwest::Result<Bytes>>>(),
+        );
+
+        let (tx, mut rx) = mpsc::channel(10);
+        let usage = provider().parse_streaming_response(stream, tx).await;
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            chunks.push(chunk.unwrap());
+        }
+
+        let text: String = chunks.iter().map(|c| c.content.as_str()).collect();
+        assert_eq!(text, "Let me look.");
+
+        let final_chunk = chunks.last().unwrap();
+        assert!(final_chunk.finished);
+        assert_eq!(final_chunk.stop_reason.as_deref(), Some("end_turn"));
+        let tool_calls = final_chunk.tool_calls.as_ref().unwrap();
+        assert_eq!(tool_calls[0].id, "gemini_call_0");
+        assert_eq!(tool_calls[0].tool, "shell");
+        assert_eq!(tool_calls[0].args["command"], "ls");
+        assert_eq!(usage.unwrap().total_tokens, 57);
+    }
+
+    #[test]
+    fn test_max_tokens_finish_reason() {
+        let response: GeminiResponse = serde_json::from_str(
+            r#"{"candidates":[{"content":{"parts":[{"text":"cut"}]},"finishReason":"MAX_TOKENS"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            response.candidates[0].stop_reason().as_deref(),
+            Some("max_tokens")
+        );
+    }
+}
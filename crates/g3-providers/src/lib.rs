@@ -31,6 +31,16 @@ pub trait LLMProvider: Send + Sync {
         false
     }
 
+    /// Check if the model accepts images attached to messages
+    fn supports_vision(&self) -> bool {
+        false
+    }
+
+    /// The model's context window in tokens, when the provider knows it
+    fn context_length(&self) -> Option<u32> {
+        None
+    }
+
     /// Get the configured max_tokens for this provider
     fn max_tokens(&self) -> u32;
 
@@ -179,6 +189,54 @@ impl ImageContent {
 
         None
     }
+
+    /// Encode as a `data:` URL, as used by OpenAI-style `image_url` content parts
+    pub fn to_data_url(&self) -> String {
+        format!("data:{};base64,{}", self.media_type, self.data)
+    }
+}
+
+/// Best-effort check whether a model name belongs to a vision-capable model family.
+/// Used by providers that serve many models through one API (OpenAI, Databricks, Ollama).
+pub fn model_supports_vision(model: &str) -> bool {
+    let model = model.to_lowercase();
+
+    // Text-only members of otherwise vision-capable families
+    const TEXT_ONLY: &[&str] = &["claude-2", "claude-instant", "o1-mini", "o3-mini", "gpt-4o-audio"];
+    if TEXT_ONLY.iter().any(|m| model.contains(m)) {
+        return false;
+    }
+
+    const VISION_FAMILIES: &[&str] = &[
+        "claude",
+        "gpt-4o",
+        "gpt-4.1",
+        "gpt-4.5",
+        "gpt-4-turbo",
+        "gpt-4-vision",
+        "gpt-5",
+        "gemini",
+        "gemma3",
+        "gemma-3",
+        "llama-4",
+        "llama4",
+        "llava",
+        "pixtral",
+        "moondream",
+        "minicpm-v",
+        "qwen2.5vl",
+        "qwen2.5-vl",
+        "qwen-vl",
+        "vision",
+    ];
+    if VISION_FAMILIES.iter().any(|m| model.contains(m)) {
+        return true;
+    }
+
+    // OpenAI reasoning models: o1, o3, o4-mini, ...
+    ["o1", "o3", "o4"]
+        .iter()
+        .any(|m| model == *m || model.starts_with(&format!("{}-", m)))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -188,11 +246,17 @@ pub struct CompletionResponse {
     pub model: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    /// Prompt tokens written to the provider's prompt cache (not included in prompt_tokens)
+    #[serde(default)]
+    pub cache_creation_tokens: u32,
+    /// Prompt tokens served from the provider's prompt cache (not included in prompt_tokens)
+    #[serde(default)]
+    pub cache_read_tokens: u32,
 }
 
 pub type CompletionStream = tokio_stream::wrappers::ReceiverStream<Result<CompletionChunk>>;
@@ -226,13 +290,19 @@ pub struct Tool {
 pub mod anthropic;
 pub mod databricks;
 pub mod embedded;
+pub mod gemini;
 pub mod oauth;
+pub mod ollama;
 pub mod openai;
+pub mod replay;
 
 pub use anthropic::AnthropicProvider;
 pub use databricks::DatabricksProvider;
 pub use embedded::EmbeddedProvider;
+pub use gemini::GeminiProvider;
+pub use ollama::OllamaProvider;
 pub use openai::OpenAIProvider;
+pub use replay::ReplayProvider;
 
 impl Message {
     /// Generate a unique message ID in format HHMMSS-XXX
@@ -362,6 +432,12 @@ impl ProviderRegistry {
             .ok_or_else(|| anyhow::anyhow!("Provider '{}' not found", name))
     }
 
+    /// Remove a provider from the registry, handing ownership to the caller.
+    /// Used to wrap an already-constructed provider (e.g. for cassette recording).
+    pub fn take(&mut self, provider_name: &str) -> Option<Box<dyn LLMProvider>> {
+        self.providers.remove(provider_name)
+    }
+
     pub fn list_providers(&self) -> Vec<&str> {
         self.providers.keys().map(|s| s.as_str()).collect()
     }
@@ -377,6 +453,40 @@ impl Default for ProviderRegistry {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_model_supports_vision() {
+        for model in [
+            "gpt-4o",
+            "gpt-4o-mini",
+            "gpt-5",
+            "o4-mini",
+            "o3",
+            "databricks-claude-sonnet-4",
+            "databricks-llama-4-maverick",
+            "gemini-2.5-pro",
+            "llava:13b",
+            "qwen2.5vl:7b",
+        ] {
+            assert!(model_supports_vision(model), "{} should support vision", model);
+        }
+        for model in [
+            "gpt-3.5-turbo",
+            "o3-mini",
+            "databricks-meta-llama-3-3-70b-instruct",
+            "qwen2.5-coder:14b",
+            "claude-2.1",
+            "llama3.1-8b",
+        ] {
+            assert!(!model_supports_vision(model), "{} should not support vision", model);
+        }
+    }
+
+    #[test]
+    fn test_image_data_url() {
+        let image = ImageContent::new("image/png", "aGVsbG8=".to_string());
+        assert_eq!(image.to_data_url(), "data:image/png;base64,aGVsbG8=");
+    }
+
     #[test]
     fn test_message_serialization_without_cache_control() {
         let msg = Message::new(MessageRole::User, "Hello".to_string());
@@ -0,0 +1,619 @@
+//! Native Ollama provider.
+//!
+//! Talks to Ollama's own `/api/chat` endpoint rather than its OpenAI-compatible shim, so the
+//! context size (`num_ctx`) is set explicitly on every request and tool calls are delivered
+//! as structured `tool_calls`. Streaming responses are newline-delimited JSON objects, not SSE.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::StreamExt;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, error};
+
+use crate::{
+    streaming::{decode_utf8_streaming, make_final_chunk_with_reason, make_text_chunk},
+    CompletionChunk, CompletionRequest, CompletionResponse, CompletionStream, LLMProvider, Message,
+    MessageRole, Tool, ToolCall, Usage,
+};
+
+/// Default Ollama server address
+pub const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+/// Context size sent as `num_ctx` when none is configured and the model's own context
+/// length can't be read from `/api/show`.
+///
+/// Ollama silently truncates prompts to its own (small) default, so g3 always sends an
+/// explicit value and sizes its `ContextWindow` from the same number.
+pub const DEFAULT_NUM_CTX: u32 = 8192;
+
+#[derive(Clone)]
+pub struct OllamaProvider {
+    client: Client,
+    model: String,
+    base_url: String,
+    num_ctx: u32,
+    max_tokens: Option<u32>,
+    temperature: Option<f32>,
+    name: String,
+}
+
+/// A locally available model, as reported by `/api/tags`
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaModel {
+    pub name: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub modified_at: Option<String>,
+    #[serde(default)]
+    pub details: Option<OllamaModelDetails>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaModelDetails {
+    #[serde(default)]
+    pub family: Option<String>,
+    #[serde(default)]
+    pub parameter_size: Option<String>,
+    #[serde(default)]
+    pub quantization_level: Option<String>,
+}
+
+impl OllamaProvider {
+    pub fn new(
+        model: String,
+        base_url: Option<String>,
+        num_ctx: Option<u32>,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+    ) -> Result<Self> {
+        Self::new_with_name(
+            "ollama".to_string(),
+            model,
+            base_url,
+            num_ctx,
+            max_tokens,
+            temperature,
+        )
+    }
+
+    pub fn new_with_name(
+        name: String,
+        model: String,
+        base_url: Option<String>,
+        num_ctx: Option<u32>,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+    ) -> Result<Self> {
+        Ok(Self {
+            client: Client::new(),
+            model,
+            base_url: normalize_base_url(base_url.as_deref().unwrap_or(DEFAULT_BASE_URL)),
+            num_ctx: num_ctx.unwrap_or(DEFAULT_NUM_CTX),
+            max_tokens,
+            temperature,
+            name,
+        })
+    }
+
+    /// List the models installed on an Ollama server (`GET /api/tags`)
+    pub async fn list_models(base_url: Option<&str>) -> Result<Vec<OllamaModel>> {
+        let base_url = normalize_base_url(base_url.unwrap_or(DEFAULT_BASE_URL));
+        let response = Client::new()
+            .get(format!("{}/api/tags", base_url))
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to reach Ollama at {}: {}", base_url, e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(anyhow::anyhow!(
+                "Ollama API error {}: {}",
+                status,
+                error_text
+            ));
+        }
+
+        let tags: OllamaTagsResponse = response.json().await?;
+        Ok(tags.models)
+    }
+
+    /// Read a model's trained context length from its metadata (`POST /api/show`).
+    /// Returns `None` if the server doesn't report one.
+    pub async fn model_context_length(base_url: Option<&str>, model: &str) -> Result<Option<u32>> {
+        let base_url = normalize_base_url(base_url.unwrap_or(DEFAULT_BASE_URL));
+        let response = Client::new()
+            .post(format!("{}/api/show", base_url))
+            .json(&json!({ "model": model }))
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to reach Ollama at {}: {}", base_url, e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(anyhow::anyhow!(
+                "Ollama API error {}: {}",
+                status,
+                error_text
+            ));
+        }
+
+        let show: OllamaShowResponse = response.json().await?;
+        // Keys are prefixed with the model architecture, e.g. "llama.context_length"
+        let architecture = show.model_info.get("general.architecture").and_then(|a| a.as_str());
+        let context_length = architecture
+            .and_then(|arch| show.model_info.get(&format!("{}.context_length", arch)))
+            .or_else(|| {
+                show.model_info
+                    .iter()
+                    .find(|(key, _)| key.ends_with(".context_length"))
+                    .map(|(_, value)| value)
+            })
+            .and_then(|value| value.as_u64())
+            .map(|length| length.min(u32::MAX as u64) as u32);
+        Ok(context_length)
+    }
+
+    fn create_request_body(
+        &self,
+        messages: &[Message],
+        tools: Option<&[Tool]>,
+        stream: bool,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+    ) -> serde_json::Value {
+        let mut options = json!({ "num_ctx": self.num_ctx });
+        if let Some(max_tokens) = max_tokens.or(self.max_tokens) {
+            options["num_predict"] = json!(max_tokens);
+        }
+        if let Some(temperature) = temperature.or(self.temperature) {
+            options["temperature"] = json!(temperature);
+        }
+
+        let mut body = json!({
+            "model": self.model,
+            "messages": convert_messages(messages),
+            "stream": stream,
+            "options": options,
+        });
+
+        if let Some(tools) = tools {
+            if !tools.is_empty() {
+                body["tools"] = json!(convert_tools(tools));
+            }
+        }
+
+        body
+    }
+
+    async fn post_chat(&self, body: &serde_json::Value) -> Result<reqwest::Response> {
+        let response = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to reach Ollama at {}: {}", self.base_url, e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(anyhow::anyhow!(
+                "Ollama API error {}: {}",
+                status,
+                error_text
+            ));
+        }
+
+        Ok(response)
+    }
+
+    async fn parse_streaming_response(
+        &self,
+        mut stream: impl futures_util::Stream<Item = reqwest::Result<Bytes>> + Unpin,
+        tx: mpsc::Sender<Result<CompletionChunk>>,
+    ) -> Option<Usage> {
+        let mut byte_buffer: Vec<u8> = Vec::new();
+        let mut buffer = String::new();
+        let mut tool_calls: Vec<ToolCall> = Vec::new();
+
+        while let Some(chunk_result) = stream.next().await {
+            let chunk = match chunk_result {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    error!("Stream error: {}", e);
+                    let _ = tx.send(Err(anyhow::anyhow!("Stream error: {}", e))).await;
+                    return None;
+                }
+            };
+
+            byte_buffer.extend_from_slice(&chunk);
+            if let Some(decoded) = decode_utf8_streaming(&mut byte_buffer) {
+                buffer.push_str(&decoded);
+            }
+
+            // Each complete line is one JSON object
+            while let Some(line_end) = buffer.find('\n') {
+                let line = buffer[..line_end].trim().to_string();
+                buffer.drain(..line_end + 1);
+
+                if line.is_empty() {
+                    continue;
+                }
+
+                let chunk_data = match serde_json::from_str::<OllamaChatResponse>(&line) {
+                    Ok(chunk_data) => chunk_data,
+                    Err(e) => {
+                        debug!("Failed to parse stream chunk: {} - Data: {}", e, line);
+                        continue;
+                    }
+                };
+
+                if let Some(error) = chunk_data.error {
+                    let _ = tx.send(Err(anyhow::anyhow!("Ollama error: {}", error))).await;
+                    return None;
+                }
+
+                if let Some(message) = &chunk_data.message {
+                    if !message.content.is_empty() {
+                        let chunk = make_text_chunk(message.content.clone());
+                        if tx.send(Ok(chunk)).await.is_err() {
+                            debug!("Receiver dropped, stopping stream");
+                            return None;
+                        }
+                    }
+
+                    // Ollama sends each tool call whole (arguments already parsed), usually in
+                    // a single chunk just before `done`
+                    for tool_call in message.tool_calls.iter().flatten() {
+                        tool_calls.push(tool_call.to_tool_call(tool_calls.len()));
+                    }
+                }
+
+                if chunk_data.done {
+                    let usage = chunk_data.usage();
+                    let final_chunk = make_final_chunk_with_reason(
+                        tool_calls,
+                        Some(usage.clone()),
+                        chunk_data.stop_reason(),
+                    );
+                    let _ = tx.send(Ok(final_chunk)).await;
+                    return Some(usage);
+                }
+            }
+        }
+
+        // Connection closed without a `done` message
+        let _ = tx
+            .send(Ok(make_final_chunk_with_reason(tool_calls, None, None)))
+            .await;
+        None
+    }
+}
+
+#[async_trait]
+impl LLMProvider for OllamaProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        debug!(
+            "Processing Ollama completion request with {} messages",
+            request.messages.len()
+        );
+
+        let body = self.create_request_body(
+            &request.messages,
+            request.tools.as_deref(),
+            false,
+            request.max_tokens,
+            request.temperature,
+        );
+
+        debug!("Sending request to Ollama API: model={}", self.model);
+
+        let response = self.post_chat(&body).await?;
+        let ollama_response: OllamaChatResponse = response.json().await?;
+
+        if let Some(error) = ollama_response.error {
+            return Err(anyhow::anyhow!("Ollama error: {}", error));
+        }
+
+        let usage = ollama_response.usage();
+        let content = ollama_response
+            .message
+            .map(|message| message.content)
+            .unwrap_or_default();
+
+        debug!(
+            "Ollama completion successful: {} tokens generated",
+            usage.completion_tokens
+        );
+
+        Ok(CompletionResponse {
+            content,
+            usage,
+            model: self.model.clone(),
+        })
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        debug!(
+            "Processing Ollama streaming request with {} messages",
+            request.messages.len()
+        );
+
+        let body = self.create_request_body(
+            &request.messages,
+            request.tools.as_deref(),
+            true,
+            request.max_tokens,
+            request.temperature,
+        );
+
+        debug!(
+            "Sending streaming request to Ollama API: model={}, num_ctx={}",
+            self.model, self.num_ctx
+        );
+
+        let response = self.post_chat(&body).await?;
+        let stream = response.bytes_stream();
+        let (tx, rx) = mpsc::channel(100);
+
+        let provider = self.clone();
+        tokio::spawn(async move {
+            let usage = provider.parse_streaming_response(stream, tx).await;
+            if let Some(usage) = usage {
+                debug!(
+                    "Stream completed with usage - prompt: {}, completion: {}, total: {}",
+                    usage.prompt_tokens, usage.completion_tokens, usage.total_tokens
+                );
+            }
+        });
+
+        Ok(ReceiverStream::new(rx))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn has_native_tool_calling(&self) -> bool {
+        true
+    }
+
+    fn supports_vision(&self) -> bool {
+        crate::model_supports_vision(&self.model)
+    }
+
+    /// The context size sent to Ollama as `num_ctx`
+    fn context_length(&self) -> Option<u32> {
+        Some(self.num_ctx)
+    }
+
+    fn max_tokens(&self) -> u32 {
+        self.max_tokens.unwrap_or(4096)
+    }
+
+    fn temperature(&self) -> f32 {
+        self.temperature.unwrap_or(0.1)
+    }
+}
+
+fn normalize_base_url(base_url: &str) -> String {
+    base_url.trim_end_matches('/').to_string()
+}
+
+fn convert_messages(messages: &[Message]) -> Vec<serde_json::Value> {
+    messages
+        .iter()
+        .map(|msg| {
+            let mut message = json!({
+                "role": match msg.role {
+                    MessageRole::System => "system",
+                    MessageRole::User => "user",
+                    MessageRole::Assistant => "assistant",
+                },
+                "content": msg.content,
+            });
+            // Ollama takes raw base64 images alongside the text
+            if !msg.images.is_empty() {
+                message["images"] = json!(msg
+                    .images
+                    .iter()
+                    .map(|image| image.data.clone())
+                    .collect::<Vec<_>>());
+            }
+            message
+        })
+        .collect()
+}
+
+fn convert_tools(tools: &[Tool]) -> Vec<serde_json::Value> {
+    tools
+        .iter()
+        .map(|tool| {
+            json!({
+                "type": "function",
+                "function": {
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool.input_schema,
+                }
+            })
+        })
+        .collect()
+}
+
+// Ollama API response structures
+#[derive(Debug, Deserialize)]
+struct OllamaTagsResponse {
+    #[serde(default)]
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaShowResponse {
+    #[serde(default)]
+    model_info: serde_json::Map<String, serde_json::Value>,
+}
+
+/// One `/api/chat` response object; streamed responses are a sequence of these
+#[derive(Debug, Deserialize)]
+struct OllamaChatResponse {
+    #[serde(default)]
+    message: Option<OllamaMessage>,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    done_reason: Option<String>,
+    #[serde(default)]
+    prompt_eval_count: u32,
+    #[serde(default)]
+    eval_count: u32,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl OllamaChatResponse {
+    fn usage(&self) -> Usage {
+        Usage {
+            prompt_tokens: self.prompt_eval_count,
+            completion_tokens: self.eval_count,
+            total_tokens: self.prompt_eval_count + self.eval_count,
+            ..Default::default()
+        }
+    }
+
+    /// Map Ollama's `done_reason` onto the stop reasons g3-core understands
+    fn stop_reason(&self) -> Option<String> {
+        match self.done_reason.as_deref() {
+            Some("length") => Some("max_tokens".to_string()),
+            Some("stop") => Some("end_turn".to_string()),
+            other => other.map(str::to_string),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaMessage {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tool_calls: Option<Vec<OllamaToolCall>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunction,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaFunction {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+impl OllamaToolCall {
+    /// Ollama does not assign tool call ids, so number them in stream order
+    fn to_tool_call(&self, index: usize) -> ToolCall {
+        ToolCall {
+            id: format!("ollama_call_{}", index),
+            tool: self.function.name.clone(),
+            args: self.function.arguments.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImageContent;
+
+    #[test]
+    fn test_request_body_sets_num_ctx() {
+        let provider = OllamaProvider::new(
+            "qwen2.5-coder:7b".to_string(),
+            Some("http://localhost:11434/".to_string()),
+            Some(32768),
+            Some(2048),
+            Some(0.2),
+        )
+        .unwrap();
+
+        assert_eq!(provider.base_url, "http://localhost:11434");
+        assert_eq!(provider.context_length(), Some(32768));
+
+        let messages = vec![Message::new(MessageRole::User, "Hello".to_string())];
+        let body = provider.create_request_body(&messages, None, true, None, None);
+
+        assert_eq!(body["model"], "qwen2.5-coder:7b");
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["options"]["num_ctx"], 32768);
+        assert_eq!(body["options"]["num_predict"], 2048);
+        assert!(body.get("tools").is_none());
+    }
+
+    #[test]
+    fn test_default_num_ctx() {
+        let provider = OllamaProvider::new("llama3.1".to_string(), None, None, None, None).unwrap();
+        let body = provider.create_request_body(&[], None, false, Some(100), None);
+        assert_eq!(body["options"]["num_ctx"], DEFAULT_NUM_CTX);
+        assert_eq!(body["options"]["num_predict"], 100);
+        assert_eq!(provider.base_url, DEFAULT_BASE_URL);
+    }
+
+    #[test]
+    fn test_message_conversion_with_images() {
+        let mut message = Message::new(MessageRole::User, "What is this?".to_string());
+        message.images.push(ImageContent::new("image/png", "aGVsbG8=".to_string()));
+
+        let converted = convert_messages(&[
+            Message::new(MessageRole::System, "Be brief.".to_string()),
+            message,
+        ]);
+
+        assert_eq!(converted[0]["role"], "system");
+        assert!(converted[0].get("images").is_none());
+        assert_eq!(converted[1]["role"], "user");
+        assert_eq!(converted[1]["images"][0], "aGVsbG8=");
+    }
+
+    #[test]
+    fn test_tool_call_parsing() {
+        let line = r#"{"model":"llama3.1","message":{"role":"assistant","content":"","tool_calls":[{"function":{"name":"shell","arguments":{"command":"ls"}}}]},"done":false}"#;
+        let chunk: OllamaChatResponse = serde_json::from_str(line).unwrap();
+        let tool_call = chunk.message.unwrap().tool_calls.unwrap()[0].to_tool_call(0);
+
+        assert_eq!(tool_call.id, "ollama_call_0");
+        assert_eq!(tool_call.tool, "shell");
+        assert_eq!(tool_call.args["command"], "ls");
+    }
+
+    #[test]
+    fn test_done_reason_mapping() {
+        let line = r#"{"done":true,"done_reason":"length","prompt_eval_count":12,"eval_count":30}"#;
+        let chunk: OllamaChatResponse = serde_json::from_str(line).unwrap();
+
+        assert_eq!(chunk.stop_reason().as_deref(), Some("max_tokens"));
+        assert_eq!(chunk.usage().total_tokens, 42);
+    }
+}
@@ -24,6 +24,7 @@ pub struct OpenAIProvider {
     max_tokens: Option<u32>,
     _temperature: Option<f32>,
     name: String,
+    vision: bool,
 }
 
 impl OpenAIProvider {
@@ -52,10 +53,12 @@ impl OpenAIProvider {
         max_tokens: Option<u32>,
         temperature: Option<f32>,
     ) -> Result<Self> {
+        let model = model.unwrap_or_else(|| "gpt-4o".to_string());
         Ok(Self {
             client: Client::new(),
             api_key,
-            model: model.unwrap_or_else(|| "gpt-4o".to_string()),
+            vision: crate::model_supports_vision(&model),
+            model,
             base_url: base_url.unwrap_or_else(|| "https://api.openai.com/v1".to_string()),
             max_tokens,
             _temperature: temperature,
@@ -63,6 +66,14 @@ impl OpenAIProvider {
         })
     }
 
+    /// Override whether the model accepts images, for models the built-in list doesn't know
+    pub fn with_vision(mut self, vision: Option<bool>) -> Self {
+        if let Some(vision) = vision {
+            self.vision = vision;
+        }
+        self
+    }
+
     fn create_request_body(
         &self,
         messages: &[Message],
@@ -73,7 +84,7 @@ impl OpenAIProvider {
     ) -> serde_json::Value {
         let mut body = json!({
             "model": self.model,
-            "messages": convert_messages(messages),
+            "messages": convert_messages(messages, self.vision),
             "stream": stream,
         });
 
@@ -220,6 +231,7 @@ impl OpenAIProvider {
                                             prompt_tokens: usage.prompt_tokens,
                                             completion_tokens: usage.completion_tokens,
                                             total_tokens: usage.total_tokens,
+                                            ..Default::default()
                                         });
                                     }
                                 }
@@ -306,6 +318,7 @@ impl LLMProvider for OpenAIProvider {
             prompt_tokens: openai_response.usage.prompt_tokens,
             completion_tokens: openai_response.usage.completion_tokens,
             total_tokens: openai_response.usage.total_tokens,
+            ..Default::default()
         };
 
         debug!(
@@ -392,6 +405,10 @@ impl LLMProvider for OpenAIProvider {
         true
     }
 
+    fn supports_vision(&self) -> bool {
+        self.vision
+    }
+
     fn max_tokens(&self) -> u32 {
         self.max_tokens.unwrap_or(16000)
     }
@@ -401,7 +418,7 @@ impl LLMProvider for OpenAIProvider {
     }
 }
 
-fn convert_messages(messages: &[Message]) -> Vec<serde_json::Value> {
+fn convert_messages(messages: &[Message], vision: bool) -> Vec<serde_json::Value> {
     messages
         .iter()
         .map(|msg| {
@@ -411,12 +428,29 @@ fn convert_messages(messages: &[Message]) -> Vec<serde_json::Value> {
                     MessageRole::User => "user",
                     MessageRole::Assistant => "assistant",
                 },
-                "content": msg.content,
+                "content": message_content(msg, vision),
             })
         })
         .collect()
 }
 
+/// Message content in the chat completions format: a plain string, or a multimodal
+/// array of text and `image_url` parts when images are attached and the model can see them.
+pub(crate) fn message_content(msg: &Message, vision: bool) -> serde_json::Value {
+    if !vision || msg.images.is_empty() {
+        return json!(msg.content);
+    }
+
+    let mut parts = vec![json!({ "type": "text", "text": msg.content })];
+    parts.extend(msg.images.iter().map(|image| {
+        json!({
+            "type": "image_url",
+            "image_url": { "url": image.to_data_url() },
+        })
+    }));
+    serde_json::Value::Array(parts)
+}
+
 fn convert_tools(tools: &[Tool]) -> Vec<serde_json::Value> {
     tools
         .iter()
@@ -528,3 +562,42 @@ struct OpenAIDeltaFunction {
     name: Option<String>,
     arguments: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImageContent;
+
+    #[test]
+    fn test_images_sent_as_content_parts() {
+        let mut user = Message::new(MessageRole::User, "What is in this screenshot?".to_string());
+        user.images.push(ImageContent::new("image/png", "aGVsbG8=".to_string()));
+        let messages = vec![
+            Message::new(MessageRole::System, "You are helpful".to_string()),
+            user,
+        ];
+
+        let converted = convert_messages(&messages, true);
+        assert_eq!(converted[0]["content"], "You are helpful");
+        let parts = converted[1]["content"].as_array().unwrap();
+        assert_eq!(parts[0]["type"], "text");
+        assert_eq!(parts[0]["text"], "What is in this screenshot?");
+        assert_eq!(parts[1]["type"], "image_url");
+        assert_eq!(parts[1]["image_url"]["url"], "data:image/png;base64,aGVsbG8=");
+
+        // Text-only models get the text alone
+        let converted = convert_messages(&messages, false);
+        assert_eq!(converted[1]["content"], "What is in this screenshot?");
+    }
+
+    #[test]
+    fn test_vision_capability() {
+        let provider = |model: &str| {
+            OpenAIProvider::new("key".to_string(), Some(model.to_string()), None, None, None).unwrap()
+        };
+        assert!(provider("gpt-4o").supports_vision());
+        assert!(!provider("gpt-3.5-turbo").supports_vision());
+        assert!(provider("my-local-vlm").with_vision(Some(true)).supports_vision());
+        assert!(!provider("gpt-4o").with_vision(Some(false)).supports_vision());
+    }
+}
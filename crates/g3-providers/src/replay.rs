@@ -0,0 +1,411 @@
+//! Record/replay provider for deterministic, offline sessions.
+//!
+//! In record mode the provider wraps a real provider, forwards every request to it and
+//! appends the resulting `CompletionChunk` stream (or `CompletionResponse`) to a cassette
+//! file. In replay mode the cassette is loaded from disk and interactions are served back
+//! without any network access.
+//!
+//! Interactions are keyed by a SHA-256 hash of the request messages (role, content and
+//! images) and the tool definitions. Sampling parameters such as `max_tokens` are not part
+//! of the key, so a cassette survives config tweaks that don't change the conversation.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tracing::{debug, error, warn};
+
+use crate::{
+    CompletionChunk, CompletionRequest, CompletionResponse, CompletionStream, LLMProvider,
+};
+
+/// Current cassette file format version
+const CASSETTE_VERSION: u32 = 1;
+
+/// On-disk cassette: provider metadata plus the recorded interactions, in recording order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cassette {
+    pub version: u32,
+    /// Name of the provider the cassette was recorded from (e.g. "anthropic.default")
+    pub provider: String,
+    pub model: String,
+    pub native_tool_calling: bool,
+    pub supports_cache_control: bool,
+    /// Absent in cassettes written before image support was recorded
+    #[serde(default)]
+    pub supports_vision: bool,
+    pub max_tokens: u32,
+    pub temperature: f32,
+    #[serde(default)]
+    pub interactions: Vec<Interaction>,
+}
+
+/// A single recorded request/response pair.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Hash of the request messages and tools (see [`request_hash`])
+    pub request_hash: String,
+    /// Recorded stream chunks, for `stream` interactions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunks: Option<Vec<CompletionChunk>>,
+    /// Recorded response, for `complete` interactions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<CompletionResponse>,
+}
+
+impl Cassette {
+    /// Create an empty cassette describing the given provider.
+    pub fn for_provider(provider: &dyn LLMProvider) -> Self {
+        Self {
+            version: CASSETTE_VERSION,
+            provider: provider.name().to_string(),
+            model: provider.model().to_string(),
+            native_tool_calling: provider.has_native_tool_calling(),
+            supports_cache_control: provider.supports_cache_control(),
+            supports_vision: provider.supports_vision(),
+            max_tokens: provider.max_tokens(),
+            temperature: provider.temperature(),
+            interactions: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cassette {}", path.display()))?;
+        let cassette: Cassette = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse cassette {}", path.display()))?;
+        if cassette.version != CASSETTE_VERSION {
+            anyhow::bail!(
+                "Unsupported cassette version {} in {} (expected {})",
+                cassette.version,
+                path.display(),
+                CASSETTE_VERSION
+            );
+        }
+        Ok(cassette)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Compute the cassette key for a request.
+///
+/// Only the parts of the request that describe the conversation are hashed: message
+/// roles, contents and attached images, plus tool names, descriptions and schemas.
+/// Message IDs and cache-control annotations are ignored.
+pub fn request_hash(request: &CompletionRequest) -> String {
+    let messages: Vec<serde_json::Value> = request
+        .messages
+        .iter()
+        .map(|msg| {
+            json!({
+                "role": msg.role,
+                "content": msg.content,
+                "images": msg
+                    .images
+                    .iter()
+                    .map(|img| json!([img.media_type, img.data]))
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    let tools: Vec<serde_json::Value> = request
+        .tools
+        .iter()
+        .flatten()
+        .map(|tool| json!([tool.name, tool.description, tool.input_schema]))
+        .collect();
+
+    let canonical = json!({ "messages": messages, "tools": tools }).to_string();
+    let digest = Sha256::digest(canonical.as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Mutable cassette state shared between the provider and its recording tasks.
+struct ReplayState {
+    cassette: Cassette,
+    /// Which interactions have already been served (replay mode)
+    consumed: Vec<bool>,
+}
+
+impl ReplayState {
+    /// Claim the next unserved interaction matching `hash`, falling back to the next
+    /// unserved interaction in recording order when `strict` is false.
+    fn claim(&mut self, hash: &str, strict: bool, streaming: bool) -> Option<Interaction> {
+        let kind_matches = |i: &Interaction| {
+            if streaming {
+                i.chunks.is_some()
+            } else {
+                i.response.is_some()
+            }
+        };
+
+        let interactions = &self.cassette.interactions;
+        let index = interactions
+            .iter()
+            .enumerate()
+            .position(|(idx, i)| !self.consumed[idx] && i.request_hash == hash && kind_matches(i))
+            .or_else(|| {
+                if strict {
+                    return None;
+                }
+                interactions
+                    .iter()
+                    .enumerate()
+                    .position(|(idx, i)| !self.consumed[idx] && kind_matches(i))
+            })?;
+
+        self.consumed[index] = true;
+        Some(self.cassette.interactions[index].clone())
+    }
+
+    fn record(&mut self, interaction: Interaction, path: &Path) {
+        self.cassette.interactions.push(interaction);
+        self.consumed.push(true);
+        if let Err(e) = self.cassette.save(path) {
+            error!("Failed to save cassette {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Provider that records interactions to, or replays them from, a cassette file.
+pub struct ReplayProvider {
+    name: String,
+    cassette_path: PathBuf,
+    /// Source provider in record mode; `None` in replay mode
+    inner: Option<Arc<dyn LLMProvider>>,
+    strict: bool,
+    state: Arc<Mutex<ReplayState>>,
+    model: String,
+    native_tool_calling: bool,
+    supports_cache_control: bool,
+    supports_vision: bool,
+    max_tokens: u32,
+    temperature: f32,
+}
+
+impl ReplayProvider {
+    /// Create a provider that serves interactions from an existing cassette.
+    ///
+    /// With `strict` set, a request whose hash is not in the cassette is an error. Without
+    /// it, the next unserved interaction in recording order is returned instead, which
+    /// is useful when requests contain incidental data such as temp paths or timestamps.
+    pub fn from_cassette(name: String, cassette_path: impl Into<PathBuf>, strict: bool) -> Result<Self> {
+        let cassette_path = cassette_path.into();
+        let cassette = Cassette::load(&cassette_path)?;
+        debug!(
+            "Loaded cassette {} with {} interactions (recorded from {})",
+            cassette_path.display(),
+            cassette.interactions.len(),
+            cassette.provider
+        );
+
+        Ok(Self {
+            name,
+            cassette_path,
+            inner: None,
+            strict,
+            model: cassette.model.clone(),
+            native_tool_calling: cassette.native_tool_calling,
+            supports_cache_control: cassette.supports_cache_control,
+            supports_vision: cassette.supports_vision,
+            max_tokens: cassette.max_tokens,
+            temperature: cassette.temperature,
+            state: Arc::new(Mutex::new(ReplayState {
+                consumed: vec![false; cassette.interactions.len()],
+                cassette,
+            })),
+        })
+    }
+
+    /// Create a provider that forwards requests to `inner` and records every interaction
+    /// into a fresh cassette at `cassette_path`. Any existing file is only overwritten
+    /// once the first interaction is recorded.
+    pub fn recording(
+        name: String,
+        cassette_path: impl Into<PathBuf>,
+        inner: Box<dyn LLMProvider>,
+    ) -> Result<Self> {
+        let cassette_path = cassette_path.into();
+        let inner: Arc<dyn LLMProvider> = Arc::from(inner);
+        let cassette = Cassette::for_provider(inner.as_ref());
+
+        Ok(Self {
+            name,
+            cassette_path,
+            strict: true,
+            model: cassette.model.clone(),
+            native_tool_calling: cassette.native_tool_calling,
+            supports_cache_control: cassette.supports_cache_control,
+            supports_vision: cassette.supports_vision,
+            max_tokens: cassette.max_tokens,
+            temperature: cassette.temperature,
+            inner: Some(inner),
+            state: Arc::new(Mutex::new(ReplayState {
+                cassette,
+                consumed: Vec::new(),
+            })),
+        })
+    }
+
+    /// Whether this provider is recording (as opposed to replaying).
+    pub fn is_recording(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Path of the cassette file this provider reads from or writes to.
+    pub fn cassette_path(&self) -> &Path {
+        &self.cassette_path
+    }
+
+    fn claim(&self, request: &CompletionRequest, streaming: bool) -> Result<Interaction> {
+        let hash = request_hash(request);
+        let mut state = self.state.lock().unwrap();
+        let interaction = state.claim(&hash, self.strict, streaming).ok_or_else(|| {
+            anyhow::anyhow!(
+                "No recorded interaction for request hash {} in cassette {}",
+                hash,
+                self.cassette_path.display()
+            )
+        })?;
+        if interaction.request_hash != hash {
+            warn!(
+                "Replaying interaction {} for unmatched request hash {} (non-strict mode)",
+                interaction.request_hash, hash
+            );
+        }
+        Ok(interaction)
+    }
+}
+
+#[async_trait::async_trait]
+impl LLMProvider for ReplayProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        let Some(inner) = &self.inner else {
+            return self
+                .claim(&request, false)?
+                .response
+                .ok_or_else(|| anyhow::anyhow!("Recorded interaction has no response"));
+        };
+
+        let request_hash = request_hash(&request);
+        let response = inner.complete(request).await?;
+        self.state.lock().unwrap().record(
+            Interaction {
+                request_hash,
+                chunks: None,
+                response: Some(response.clone()),
+            },
+            &self.cassette_path,
+        );
+        Ok(response)
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        let Some(inner) = &self.inner else {
+            let chunks = self.claim(&request, true)?.chunks.unwrap_or_default();
+            let (tx, rx) = mpsc::channel(chunks.len().max(1));
+            for chunk in chunks {
+                // Capacity covers every chunk, so this never fails while rx is alive
+                let _ = tx.try_send(Ok(chunk));
+            }
+            return Ok(ReceiverStream::new(rx));
+        };
+
+        let request_hash = request_hash(&request);
+        let mut inner_stream = inner.stream(request).await?;
+        let (tx, rx) = mpsc::channel(100);
+        let state = self.state.clone();
+        let cassette_path = self.cassette_path.clone();
+
+        tokio::spawn(async move {
+            let mut chunks = Vec::new();
+            let mut recorded = false;
+
+            while let Some(chunk_result) = inner_stream.next().await {
+                match chunk_result {
+                    Ok(chunk) => {
+                        let finished = chunk.finished;
+                        chunks.push(chunk.clone());
+                        // Save before forwarding the final chunk so the cassette is complete
+                        // by the time the consumer sees the end of the stream
+                        if finished && !recorded {
+                            state.lock().unwrap().record(
+                                Interaction {
+                                    request_hash: request_hash.clone(),
+                                    chunks: Some(std::mem::take(&mut chunks)),
+                                    response: None,
+                                },
+                                &cassette_path,
+                            );
+                            recorded = true;
+                        }
+                        if tx.send(Ok(chunk)).await.is_err() {
+                            debug!("Receiver dropped, stopping recording stream");
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        // Errors are not recorded; a replay ends where the recording failed
+                        let _ = tx.send(Err(e)).await;
+                        break;
+                    }
+                }
+            }
+
+            if !recorded && !chunks.is_empty() {
+                state.lock().unwrap().record(
+                    Interaction {
+                        request_hash,
+                        chunks: Some(chunks),
+                        response: None,
+                    },
+                    &cassette_path,
+                );
+            }
+        });
+
+        Ok(ReceiverStream::new(rx))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn has_native_tool_calling(&self) -> bool {
+        self.native_tool_calling
+    }
+
+    fn supports_cache_control(&self) -> bool {
+        self.supports_cache_control
+    }
+
+    fn supports_vision(&self) -> bool {
+        self.supports_vision
+    }
+
+    fn max_tokens(&self) -> u32 {
+        self.max_tokens
+    }
+
+    fn temperature(&self) -> f32 {
+        self.temperature
+    }
+}
@@ -0,0 +1,250 @@
+//! Tests for the native Ollama provider against a stub HTTP server
+//!
+//! The stub speaks just enough HTTP/1.1 to answer `/api/tags` and `/api/chat` with canned
+//! bodies and records each request so the tests can inspect what the provider sent.
+
+use g3_providers::{CompletionRequest, LLMProvider, Message, MessageRole, OllamaProvider, Tool};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_stream::StreamExt;
+
+#[derive(Debug, Clone)]
+struct RecordedRequest {
+    method: String,
+    path: String,
+    body: serde_json::Value,
+}
+
+/// Start a stub server that answers every request with `response_body` (written in
+/// `pieces` separate writes) and returns its base URL plus the recorded requests.
+async fn start_stub_server(
+    response_body: &'static str,
+    pieces: usize,
+) -> (String, Arc<Mutex<Vec<RecordedRequest>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let recorded = Arc::new(Mutex::new(Vec::new()));
+    let recorded_clone = recorded.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                return;
+            };
+            let recorded = recorded_clone.clone();
+            tokio::spawn(async move {
+                let request = read_request(&mut socket).await;
+                recorded.lock().unwrap().push(request);
+
+                let header = "HTTP/1.1 200 OK\r\ncontent-type: application/x-ndjson\r\nconnection: close\r\n\r\n";
+                socket.write_all(header.as_bytes()).await.unwrap();
+
+                let bytes = response_body.as_bytes();
+                let piece_len = bytes.len().div_ceil(pieces);
+                for piece in bytes.chunks(piece_len) {
+                    socket.write_all(piece).await.unwrap();
+                    socket.flush().await.unwrap();
+                    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                }
+                let _ = socket.shutdown().await;
+            });
+        }
+    });
+
+    (format!("http://{}", addr), recorded)
+}
+
+async fn read_request(socket: &mut tokio::net::TcpStream) -> RecordedRequest {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    let header_end = loop {
+        let n = socket.read(&mut buf).await.unwrap();
+        data.extend_from_slice(&buf[..n]);
+        if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+    let content_length = head
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("content-length")
+                .then(|| value.trim().parse::<usize>().ok())
+                .flatten()
+        })
+        .unwrap_or(0);
+    while data.len() < header_end + content_length {
+        let n = socket.read(&mut buf).await.unwrap();
+        data.extend_from_slice(&buf[..n]);
+    }
+
+    let mut request_line = head.lines().next().unwrap().split_whitespace();
+    RecordedRequest {
+        method: request_line.next().unwrap().to_string(),
+        path: request_line.next().unwrap().to_string(),
+        body: serde_json::from_slice(&data[header_end..]).unwrap_or(serde_json::Value::Null),
+    }
+}
+
+fn request(stream: bool, tools: Option<Vec<Tool>>) -> CompletionRequest {
+    CompletionRequest {
+        messages: vec![
+            Message::new(MessageRole::System, "You are a test.".to_string()),
+            Message::new(MessageRole::User, "List the files".to_string()),
+        ],
+        max_tokens: Some(512),
+        temperature: Some(0.3),
+        stream,
+        tools,
+        disable_thinking: false,
+    }
+}
+
+fn shell_tool() -> Tool {
+    Tool {
+        name: "shell".to_string(),
+        description: "Run a shell command".to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": { "command": { "type": "string" } },
+            "required": ["command"]
+        }),
+    }
+}
+
+const STREAM_WITH_TOOL_CALL: &str = concat!(
+    r#"{"model":"qwen2.5-coder:7b","message":{"role":"assistant","content":"Let me "},"done":false}"#,
+    "\n",
+    r#"{"model":"qwen2.5-coder:7b","message":{"role":"assistant","content":"check."},"done":false}"#,
+    "\n",
+    r#"{"model":"qwen2.5-coder:7b","message":{"role":"assistant","content":"","tool_calls":[{"function":{"name":"shell","arguments":{"command":"ls -la"}}}]},"done":false}"#,
+    "\n",
+    r#"{"model":"qwen2.5-coder:7b","message":{"role":"assistant","content":""},"done":true,"done_reason":"stop","prompt_eval_count":120,"eval_count":18}"#,
+    "\n",
+);
+
+#[tokio::test]
+async fn test_stream_text_and_tool_calls() {
+    // Split the body into uneven pieces so lines straddle network reads
+    let (base_url, recorded) = start_stub_server(STREAM_WITH_TOOL_CALL, 7).await;
+    let provider = OllamaProvider::new_with_name(
+        "ollama.default".to_string(),
+        "qwen2.5-coder:7b".to_string(),
+        Some(base_url),
+        Some(16384),
+        None,
+        None,
+    )
+    .unwrap();
+
+    let stream = provider
+        .stream(request(true, Some(vec![shell_tool()])))
+        .await
+        .unwrap();
+    let chunks: Vec<_> = stream.map(|c| c.unwrap()).collect().await;
+
+    let text: String = chunks.iter().map(|c| c.content.as_str()).collect();
+    assert_eq!(text, "Let me check.");
+
+    let final_chunk = chunks.last().unwrap();
+    assert!(final_chunk.finished);
+    assert_eq!(final_chunk.stop_reason.as_deref(), Some("end_turn"));
+    let tool_calls = final_chunk.tool_calls.as_ref().unwrap();
+    assert_eq!(tool_calls.len(), 1);
+    assert_eq!(tool_calls[0].tool, "shell");
+    assert_eq!(tool_calls[0].args["command"], "ls -la");
+    let usage = final_chunk.usage.as_ref().unwrap();
+    assert_eq!(usage.prompt_tokens, 120);
+    assert_eq!(usage.completion_tokens, 18);
+    assert_eq!(usage.total_tokens, 138);
+
+    let requests = recorded.lock().unwrap();
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].path, "/api/chat");
+    let body = &requests[0].body;
+    assert_eq!(body["model"], "qwen2.5-coder:7b");
+    assert_eq!(body["stream"], true);
+    assert_eq!(body["options"]["num_ctx"], 16384);
+    assert_eq!(body["options"]["num_predict"], 512);
+    assert_eq!(body["messages"][1]["content"], "List the files");
+    assert_eq!(body["tools"][0]["function"]["name"], "shell");
+}
+
+#[tokio::test]
+async fn test_complete() {
+    let body = r#"{"model":"llama3.1","message":{"role":"assistant","content":"Hello there"},"done":true,"done_reason":"stop","prompt_eval_count":9,"eval_count":3}"#;
+    let (base_url, recorded) = start_stub_server(body, 1).await;
+    let provider =
+        OllamaProvider::new("llama3.1".to_string(), Some(base_url), None, None, None).unwrap();
+
+    let response = provider.complete(request(false, None)).await.unwrap();
+    assert_eq!(response.content, "Hello there");
+    assert_eq!(response.usage.total_tokens, 12);
+    assert_eq!(response.model, "llama3.1");
+
+    let requests = recorded.lock().unwrap();
+    assert_eq!(requests[0].body["stream"], false);
+    assert!(requests[0].body.get("tools").is_none());
+}
+
+#[tokio::test]
+async fn test_stream_error_line() {
+    let body = "{\"error\":\"model 'missing' not found\"}\n";
+    let (base_url, _) = start_stub_server(body, 1).await;
+    let provider =
+        OllamaProvider::new("missing".to_string(), Some(base_url), None, None, None).unwrap();
+
+    let mut stream = provider.stream(request(true, None)).await.unwrap();
+    let err = stream.next().await.unwrap().unwrap_err();
+    assert!(err.to_string().contains("not found"), "unexpected error: {}", err);
+}
+
+#[tokio::test]
+async fn test_list_models() {
+    let body = r#"{"models":[{"name":"qwen2.5-coder:7b","size":4683087332,"modified_at":"2025-01-10T12:00:00Z","details":{"family":"qwen2","parameter_size":"7.6B","quantization_level":"Q4_K_M"}},{"name":"llama3.1:8b"}]}"#;
+    let (base_url, recorded) = start_stub_server(body, 1).await;
+
+    let models = OllamaProvider::list_models(Some(&base_url)).await.unwrap();
+    assert_eq!(models.len(), 2);
+    assert_eq!(models[0].name, "qwen2.5-coder:7b");
+    assert_eq!(
+        models[0].details.as_ref().unwrap().parameter_size.as_deref(),
+        Some("7.6B")
+    );
+    assert_eq!(models[1].name, "llama3.1:8b");
+    assert_eq!(models[1].size, 0);
+
+    let requests = recorded.lock().unwrap();
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[0].path, "/api/tags");
+}
+
+#[tokio::test]
+async fn test_model_context_length() {
+    let body = r#"{"details":{"family":"llama"},"model_info":{"general.architecture":"llama","general.parameter_count":8030261248,"llama.context_length":131072,"llama.embedding_length":4096}}"#;
+    let (base_url, recorded) = start_stub_server(body, 1).await;
+
+    let context_length = OllamaProvider::model_context_length(Some(&base_url), "llama3.1:8b")
+        .await
+        .unwrap();
+    assert_eq!(context_length, Some(131072));
+
+    let requests = recorded.lock().unwrap();
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].path, "/api/show");
+    assert_eq!(requests[0].body["model"], "llama3.1:8b");
+}
+
+#[tokio::test]
+async fn test_model_context_length_unreported() {
+    let body = r#"{"details":{"family":"llama"}}"#;
+    let (base_url, _) = start_stub_server(body, 1).await;
+
+    let context_length = OllamaProvider::model_context_length(Some(&base_url), "llama3.1:8b")
+        .await
+        .unwrap();
+    assert_eq!(context_length, None);
+}
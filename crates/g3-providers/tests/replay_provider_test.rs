@@ -0,0 +1,259 @@
+//! Tests for the record/replay cassette provider
+//!
+//! These tests record interactions from a scripted in-process provider, then replay the
+//! cassette and verify that the same streams come back without touching the source.
+
+use anyhow::Result;
+use g3_providers::replay::{request_hash, Cassette};
+use g3_providers::{
+    make_final_chunk, make_text_chunk, CompletionChunk, CompletionRequest, CompletionResponse,
+    CompletionStream, LLMProvider, Message, MessageRole, ReplayProvider, Tool, ToolCall, Usage,
+};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+
+/// Provider that answers every request with a fixed script and counts calls
+struct ScriptedProvider {
+    calls: Arc<AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl LLMProvider for ScriptedProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(CompletionResponse {
+            content: format!("echo: {}", request.messages.last().unwrap().content),
+            usage: usage(),
+            model: "scripted-model".to_string(),
+        })
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let last = request.messages.last().unwrap().content.clone();
+        let (tx, rx) = mpsc::channel(10);
+        tokio::spawn(async move {
+            let _ = tx.send(Ok(make_text_chunk("Reply to ".to_string()))).await;
+            let _ = tx.send(Ok(make_text_chunk(last))).await;
+            let tool_call = ToolCall {
+                id: "call_1".to_string(),
+                tool: "shell".to_string(),
+                args: serde_json::json!({ "command": "ls" }),
+            };
+            let _ = tx.send(Ok(make_final_chunk(vec![tool_call], Some(usage())))).await;
+        });
+        Ok(ReceiverStream::new(rx))
+    }
+
+    fn name(&self) -> &str {
+        "scripted.default"
+    }
+
+    fn model(&self) -> &str {
+        "scripted-model"
+    }
+
+    fn has_native_tool_calling(&self) -> bool {
+        true
+    }
+
+    fn max_tokens(&self) -> u32 {
+        4096
+    }
+
+    fn temperature(&self) -> f32 {
+        0.2
+    }
+}
+
+fn usage() -> Usage {
+    Usage {
+        prompt_tokens: 10,
+        completion_tokens: 5,
+        total_tokens: 15,
+        ..Default::default()
+    }
+}
+
+fn request(text: &str) -> CompletionRequest {
+    CompletionRequest {
+        messages: vec![
+            Message::new(MessageRole::System, "You are a test.".to_string()),
+            Message::new(MessageRole::User, text.to_string()),
+        ],
+        max_tokens: Some(1000),
+        temperature: Some(0.1),
+        stream: true,
+        tools: None,
+        disable_thinking: false,
+    }
+}
+
+fn cassette_path(test_name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("g3_replay_{}_{}", test_name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("cassette.json")
+}
+
+async fn collect(stream: CompletionStream) -> Vec<CompletionChunk> {
+    stream.map(|c| c.unwrap()).collect().await
+}
+
+#[test]
+fn test_request_hash_ignores_ids_and_sampling() {
+    let a = request("hello");
+    let mut b = request("hello");
+    b.max_tokens = Some(5);
+    b.temperature = Some(0.9);
+    assert_ne!(a.messages[1].id, "", "messages should have ids");
+    assert_eq!(request_hash(&a), request_hash(&b));
+
+    assert_ne!(request_hash(&a), request_hash(&request("goodbye")));
+
+    let mut with_tools = request("hello");
+    with_tools.tools = Some(vec![Tool {
+        name: "shell".to_string(),
+        description: "Run a command".to_string(),
+        input_schema: serde_json::json!({ "type": "object" }),
+    }]);
+    assert_ne!(request_hash(&a), request_hash(&with_tools));
+}
+
+#[tokio::test]
+async fn test_record_then_replay_stream() {
+    let path = cassette_path("stream");
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let recorder = ReplayProvider::recording(
+        "replay.rec".to_string(),
+        &path,
+        Box::new(ScriptedProvider { calls: calls.clone() }),
+    )
+    .unwrap();
+    assert!(recorder.is_recording());
+    assert_eq!(recorder.model(), "scripted-model");
+
+    let recorded = collect(recorder.stream(request("first")).await.unwrap()).await;
+    let recorded_second = collect(recorder.stream(request("second")).await.unwrap()).await;
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert_eq!(recorded.len(), 3);
+
+    let cassette = Cassette::load(&path).unwrap();
+    assert_eq!(cassette.provider, "scripted.default");
+    assert_eq!(cassette.interactions.len(), 2);
+    assert!(cassette.native_tool_calling);
+
+    // Replay out of order: lookups are by request hash, not position
+    let player = ReplayProvider::from_cassette("replay.ci".to_string(), &path, true).unwrap();
+    assert_eq!(player.name(), "replay.ci");
+    assert_eq!(player.model(), "scripted-model");
+    assert_eq!(player.max_tokens(), 4096);
+    assert!(player.has_native_tool_calling());
+
+    let replayed_second = collect(player.stream(request("second")).await.unwrap()).await;
+    let replayed = collect(player.stream(request("first")).await.unwrap()).await;
+    assert_eq!(calls.load(Ordering::SeqCst), 2, "replay must not call the source");
+
+    let contents = |chunks: &[CompletionChunk]| {
+        chunks.iter().map(|c| c.content.clone()).collect::<Vec<_>>()
+    };
+    assert_eq!(contents(&replayed), contents(&recorded));
+    assert_eq!(contents(&replayed_second), contents(&recorded_second));
+
+    let final_chunk = replayed.last().unwrap();
+    assert!(final_chunk.finished);
+    let tool_calls = final_chunk.tool_calls.as_ref().unwrap();
+    assert_eq!(tool_calls[0].tool, "shell");
+    assert_eq!(final_chunk.usage.as_ref().unwrap().total_tokens, 15);
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[tokio::test]
+async fn test_record_then_replay_complete() {
+    let path = cassette_path("complete");
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let recorder = ReplayProvider::recording(
+        "replay.rec".to_string(),
+        &path,
+        Box::new(ScriptedProvider { calls: calls.clone() }),
+    )
+    .unwrap();
+    let recorded = recorder.complete(request("summarize")).await.unwrap();
+
+    let player = ReplayProvider::from_cassette("replay.ci".to_string(), &path, true).unwrap();
+    let replayed = player.complete(request("summarize")).await.unwrap();
+    assert_eq!(replayed.content, recorded.content);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    // A streamed request with the same messages was never recorded
+    assert!(player.stream(request("summarize")).await.is_err());
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[tokio::test]
+async fn test_strict_replay_rejects_unknown_requests() {
+    let path = cassette_path("strict");
+    let recorder = ReplayProvider::recording(
+        "replay.rec".to_string(),
+        &path,
+        Box::new(ScriptedProvider {
+            calls: Arc::new(AtomicUsize::new(0)),
+        }),
+    )
+    .unwrap();
+    collect(recorder.stream(request("known")).await.unwrap()).await;
+
+    let strict = ReplayProvider::from_cassette("replay.ci".to_string(), &path, true).unwrap();
+    let err = strict.stream(request("unknown")).await.unwrap_err();
+    assert!(
+        err.to_string().contains("No recorded interaction"),
+        "unexpected error: {}",
+        err
+    );
+
+    // Each interaction is served once; replaying it a second time is an error
+    collect(strict.stream(request("known")).await.unwrap()).await;
+    assert!(strict.stream(request("known")).await.is_err());
+
+    // Non-strict mode falls back to recording order
+    let lenient = ReplayProvider::from_cassette("replay.ci".to_string(), &path, false).unwrap();
+    let chunks = collect(lenient.stream(request("unknown")).await.unwrap()).await;
+    assert_eq!(chunks[1].content, "known");
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[tokio::test]
+async fn test_recording_leaves_the_cassette_until_the_first_interaction() {
+    let path = cassette_path("untouched");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "previous recording").unwrap();
+
+    let recorder = ReplayProvider::recording(
+        "replay.rec".to_string(),
+        &path,
+        Box::new(ScriptedProvider {
+            calls: Arc::new(AtomicUsize::new(0)),
+        }),
+    )
+    .unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "previous recording");
+
+    collect(recorder.stream(request("first")).await.unwrap()).await;
+    assert_eq!(Cassette::load(&path).unwrap().interactions.len(), 1);
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[test]
+fn test_missing_cassette_is_an_error() {
+    let path = cassette_path("missing");
+    assert!(ReplayProvider::from_cassette("replay.ci".to_string(), &path, true).is_err());
+}